@@ -82,6 +82,10 @@ def _common_crates(std):
             ],
             version = "0.16.9",
         ),
+        "ed25519-dalek": crate.spec(
+            default_features = False,
+            version = "2.1.1",
+        ),
         "elf": crate.spec(
             default_features = False,
             version = "0.7.4",
@@ -271,6 +275,15 @@ OAK_NO_STD_ANNOTATIONS = {
 # Crates for the no_std crates index. Crates that are used in all crate indexes
 # should instead be added to _common_crates.
 OAK_NO_STD_CRATES = _common_crates(std = False) | {
+    # Restricted Kernel and the applications running on it get their randomness
+    # from the kernel RNG, and register it as a custom getrandom backend. Note
+    # that the rdrand backend takes precedence over custom backends, so it must
+    # not be enabled here.
+    "getrandom": crate.spec(
+        default_features = False,
+        features = ["custom"],
+        version = "0.2.12",
+    ),
     "virtio-drivers": crate.spec(version = "0.7.3"),
 }
 
@@ -284,6 +297,11 @@ OAK_NO_STD_NO_AVX_ANNOTATIONS = {
 
 # Crates for the no_std no-AVX (no-alloc) crates index. Crates that are used in
 # all crate indexes should instead be added to _common_crates.
+#
+# Unlike the no_std index, this keeps the rdrand backend of getrandom: the index
+# is only used for the firmware (Stage 0), which runs before any kernel RNG
+# exists and has no custom backend to register, so RDRAND is its only source of
+# randomness.
 OAK_NO_STD_NO_AVX_CRATES = _common_crates(std = False)
 
 # Annotations for the std crates index.
@@ -22,7 +22,7 @@ extern crate alloc;
 
 use alloc::{boxed::Box, sync::Arc};
 
-use oak_functions_service::wasm::{WasmConfig, WasmHandler};
+use oak_functions_service::wasm::WasmHandler;
 use oak_restricted_kernel_sdk::{
     attestation::InstanceAttester,
     channel::{start_blocking_server, FileDescriptorChannel},
@@ -44,12 +44,14 @@ fn main() -> ! {
     let encryption_key_handle =
         InstanceEncryptionKeyHandle::create().expect("couldn't encryption key");
     let attester = InstanceAttester::create().expect("couldn't create attester");
+    let clock = oak_functions_enclave_service::RestrictedKernelClock::create()
+        .expect("couldn't create clock");
     let service = oak_functions_enclave_service::OakFunctionsService::<_, _, WasmHandler>::new(
         attester,
         Arc::new(encryption_key_handle),
         None,
-        WasmConfig::default(),
-    );
+    )
+    .with_clock(Arc::new(clock));
     let server = oak_micro_rpc::oak::functions::OakFunctionsServer::new(service);
     start_blocking_server(Box::<FileDescriptorChannel>::default(), server, &mut invocation_stats)
         .expect("server encountered an unrecoverable error");
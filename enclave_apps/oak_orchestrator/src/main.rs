@@ -112,9 +112,15 @@ fn entrypoint() {
         AttestedApp::load_and_attest(channel, stage0_dice_data, encoded_event_log)
     };
 
-    syscall::write(DERIVED_KEY_FD, attested_app.derived_key.as_bytes())
-        .expect("failed to write derived key");
-    attested_app.derived_key.as_bytes_mut().zeroize();
+    // If there is no derived key, the application can't read one from the
+    // derived key file descriptor, so it can't seal data.
+    if let Some(derived_key) = attested_app.derived_key.as_mut() {
+        syscall::write(DERIVED_KEY_FD, derived_key.as_bytes())
+            .expect("failed to write derived key");
+        derived_key.as_bytes_mut().zeroize();
+    } else {
+        log::warn!("no derived key available, the application won't be able to seal data");
+    }
     syscall::write(DICE_DATA_FD, attested_app.dice_data.as_bytes())
         .expect("failed to write dice data");
     attested_app.dice_data.as_bytes_mut().zeroize();
@@ -28,6 +28,8 @@ import com.google.oak.util.Result;
 import io.grpc.ManagedChannel;
 import io.grpc.ManagedChannelBuilder;
 import java.net.URL;
+import java.nio.ByteBuffer;
+import java.nio.ByteOrder;
 import java.nio.charset.StandardCharsets;
 import java.time.Clock;
 import java.util.Arrays;
@@ -37,6 +39,9 @@ import java.util.logging.Logger;
 public class Main {
   private static Logger logger = Logger.getLogger(Main.class.getName());
   private static final String EMPTY_API_KEY = "";
+  // As defined in oak_functions_abi/REQUEST_RESPONSE_ENCODING.md.
+  private static final int RESPONSE_STATUS_SUCCESS = 1;
+  private static final int RESPONSE_BODY_OFFSET = 12;
 
   public static void main(String[] args) throws Exception {
     // Create a gRPC channel.
@@ -60,7 +65,7 @@ public class Main {
     byte[] responseWrapperBytes = oakClientInvokeResult.unwrap("invoking client");
     ResponseWrapper responseWrapper = ResponseWrapper.parseFrom(responseWrapperBytes);
     logger.log(Level.INFO, "Client received response wrapper: " + responseWrapper.toString());
-    byte[] responseBody = responseWrapper.getBody().toByteArray();
+    byte[] responseBody = decodeResponse(responseWrapper.getBody().toByteArray());
 
     if (Arrays.equals(responseBody, requestBody)) {
       logger.log(
@@ -72,4 +77,27 @@ public class Main {
       System.exit(1);
     }
   }
+
+  // Strips the status code, the length and the padding from a response encoded
+  // as described in oak_functions_abi/REQUEST_RESPONSE_ENCODING.md.
+  private static byte[] decodeResponse(byte[] encodedResponse) {
+    if (encodedResponse.length < RESPONSE_BODY_OFFSET) {
+      logger.log(Level.INFO, "Client received truncated response");
+      System.exit(1);
+    }
+    ByteBuffer buffer = ByteBuffer.wrap(encodedResponse).order(ByteOrder.LITTLE_ENDIAN);
+    int status = buffer.getInt();
+    long length = buffer.getLong();
+    if (status != RESPONSE_STATUS_SUCCESS) {
+      logger.log(Level.INFO, "Client received response with status " + status);
+      System.exit(1);
+    }
+    // The length is untrusted, so it must fit within the received body.
+    if (length < 0 || length > encodedResponse.length - RESPONSE_BODY_OFFSET) {
+      logger.log(Level.INFO, "Client received response with invalid length " + length);
+      System.exit(1);
+    }
+    return Arrays.copyOfRange(
+        encodedResponse, RESPONSE_BODY_OFFSET, RESPONSE_BODY_OFFSET + (int) length);
+  }
 }
@@ -19,13 +19,14 @@ use alloc::{vec, vec::Vec};
 use anyhow::{anyhow, Context};
 use ciborium::Value;
 use coset::{cwt::ClaimName, CborSerializable, CoseKey};
+use hkdf::Hkdf;
 use oak_dice::{
     cert::{
         cose_key_to_verifying_key, derive_verifying_key_id, generate_ecdsa_key_pair,
         generate_kem_certificate, generate_signing_certificate,
         get_claims_set_from_certificate_bytes, SHA2_256_ID,
     },
-    evidence::Stage0DiceData,
+    evidence::{Stage0DiceData, CDI_SIZE},
 };
 use oak_proto_rust::oak::{
     attestation::v1::{
@@ -37,7 +38,7 @@ use oak_proto_rust::oak::{
 use p256::ecdsa::{SigningKey, VerifyingKey};
 use prost::Message;
 use sha2::Digest;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::attester::{Attester, Serializable};
 
@@ -62,6 +63,22 @@ pub struct LayerData {
     pub encoded_event: Vec<u8>,
 }
 
+/// HKDF info string used when deriving the sealing CDI for the next layer.
+const SEALING_CDI_INFO: &[u8] = b"CDI_Seal";
+
+/// Derives the sealing compound device identifier (CDI) for the next layer.
+///
+/// The CDI is bound to both the CDI of the current layer and the encoded event
+/// of the next layer, so it can only be reproduced by an identical next layer
+/// running on top of an identical chain of previous layers.
+pub fn derive_sealing_cdi(parent_cdi: &[u8], layer_data: &LayerData) -> Zeroizing<[u8; CDI_SIZE]> {
+    let mut cdi = Zeroizing::new([0u8; CDI_SIZE]);
+    Hkdf::<sha2::Sha256>::new(Some(&layer_data.encoded_event), parent_cdi)
+        .expand(SEALING_CDI_INFO, cdi.as_mut())
+        .expect("CDI size is a valid HKDF output length");
+    cdi
+}
+
 /// Builds the DICE evidence and certificate authority for the next DICE layer.
 pub struct DiceAttester {
     evidence: Evidence,
@@ -405,7 +422,14 @@ fn root_layer_evidence_to_proto(
     let remote_attestation_report =
         value.get_remote_attestation_report().map_err(anyhow::Error::msg)?.to_vec();
     let eca_public_key = value.get_eca_public_key().map_err(anyhow::Error::msg)?;
-    Ok(RootLayerEvidence { platform: platform as i32, remote_attestation_report, eca_public_key })
+    // The TEE certificate is not part of the DICE data struct, so it has to be
+    // attached separately.
+    Ok(RootLayerEvidence {
+        platform: platform as i32,
+        remote_attestation_report,
+        eca_public_key,
+        tee_certificate: Vec::new(),
+    })
 }
 
 fn layer_evidence_to_proto(
@@ -119,10 +119,38 @@ pub fn serialize_intel_tdx_attestation_report(
     // all fields. If a new field is added to the struct, this code won't
     // compile unless this destructuring operation is updated, thereby reminding us
     // to keep the serialization in sync manually.
-    let IntelTdxAttestationReport { report_data } = instance;
+    let IntelTdxAttestationReport {
+        report_data,
+        tee_tcb_svn,
+        mr_seam,
+        td_attributes,
+        xfam,
+        mr_td,
+        mr_config_id,
+        mr_owner,
+        mr_owner_config,
+        rtmr0,
+        rtmr1,
+        rtmr2,
+        rtmr3,
+        debug,
+    } = instance;
 
     json!({
         "report_data": hex::encode(report_data),
+        "tee_tcb_svn": hex::encode(tee_tcb_svn),
+        "mr_seam": hex::encode(mr_seam),
+        "td_attributes": td_attributes,
+        "xfam": xfam,
+        "mr_td": hex::encode(mr_td),
+        "mr_config_id": hex::encode(mr_config_id),
+        "mr_owner": hex::encode(mr_owner),
+        "mr_owner_config": hex::encode(mr_owner_config),
+        "rtmr0": hex::encode(rtmr0),
+        "rtmr1": hex::encode(rtmr1),
+        "rtmr2": hex::encode(rtmr2),
+        "rtmr3": hex::encode(rtmr3),
+        "debug": debug,
     })
 }
 
@@ -434,6 +462,7 @@ pub fn serialize_endorsement_reference_value(
         endorser,
         required_claims,
         rekor,
+        rekor_witnesses,
     } = instance;
     json!({
         "endorser_public_key": hex::encode(endorser_public_key),
@@ -441,6 +470,7 @@ pub fn serialize_endorsement_reference_value(
         "endorser": endorser.as_ref().map(serialize_verifying_key_set),
         "required_claims": required_claims.as_ref().map(serialize_claim_reference_value),
         "rekor": rekor.as_ref().map(serialize_verifying_key_reference_value),
+        "rekor_witnesses": rekor_witnesses.as_ref().map(serialize_verifying_key_set),
     })
 }
 
@@ -600,19 +630,85 @@ pub fn serialize_amd_sev_reference_values(instance: &AmdSevReferenceValues) -> s
     // all fields. If a new field is added to the struct, this code won't
     // compile unless this destructuring operation is updated, thereby reminding us
     // to keep the serialization in sync manually.
-    let AmdSevReferenceValues { min_tcb_version, allow_debug, stage0 } = instance;
-    json!({
+    let AmdSevReferenceValues {
+        min_tcb_version,
+        allow_debug,
+        stage0,
+        certificate_revocation_lists,
+        additional_product_roots,
+        product_min_tcb_versions,
+    } = instance;
+    let mut value = json!({
         "min_tcb_version": min_tcb_version.as_ref().map(serialize_tcb_version),
         "allow_debug": allow_debug,
         "stage0": stage0.as_ref().map(serialize_binary_reference_value),
+    });
+    // The optional trust configuration is only shown when it is present.
+    if !certificate_revocation_lists.is_empty() {
+        value["certificate_revocation_lists"] =
+            json!(certificate_revocation_lists.iter().map(hex::encode).collect::<Vec<String>>());
+    }
+    if !additional_product_roots.is_empty() {
+        value["additional_product_roots"] = json!(additional_product_roots
+            .iter()
+            .map(serialize_amd_product_root_certificates)
+            .collect::<Vec<serde_json::Value>>());
+    }
+    if !product_min_tcb_versions.is_empty() {
+        value["product_min_tcb_versions"] = json!(product_min_tcb_versions
+            .iter()
+            .map(|(product_name, tcb_version)| {
+                (product_name.clone(), serialize_tcb_version(tcb_version))
+            })
+            .collect::<serde_json::Map<String, serde_json::Value>>());
+    }
+    value
+}
+
+pub fn serialize_amd_product_root_certificates(
+    instance: &AmdProductRootCertificates,
+) -> serde_json::Value {
+    // Exhaustive destructuring (e.g., without ", ..") ensures this function handles
+    // all fields. If a new field is added to the struct, this code won't
+    // compile unless this destructuring operation is updated, thereby reminding us
+    // to keep the serialization in sync manually.
+    let AmdProductRootCertificates { product_name, ark_certificate, ask_certificate } = instance;
+    json!({
+        "product_name": product_name,
+        "ark_certificate": hex::encode(ark_certificate),
+        "ask_certificate": hex::encode(ask_certificate),
     })
 }
 
 pub fn serialize_intel_tdx_reference_values(
     instance: &IntelTdxReferenceValues,
 ) -> serde_json::Value {
-    let IntelTdxReferenceValues {} = instance;
-    json!({})
+    // Exhaustive destructuring (e.g., without ", ..") ensures this function handles
+    // all fields. If a new field is added to the struct, this code won't
+    // compile unless this destructuring operation is updated, thereby reminding us
+    // to keep the serialization in sync manually.
+    let IntelTdxReferenceValues {
+        min_tee_tcb_svn,
+        allow_debug,
+        mr_td,
+        rtmr0,
+        rtmr1,
+        rtmr2,
+        rtmr3,
+        td_attributes_mask,
+        td_attributes,
+    } = instance;
+    json!({
+        "min_tee_tcb_svn": hex::encode(min_tee_tcb_svn),
+        "allow_debug": allow_debug,
+        "mr_td": mr_td.as_ref().map(serialize_binary_reference_value),
+        "rtmr0": rtmr0.as_ref().map(serialize_binary_reference_value),
+        "rtmr1": rtmr1.as_ref().map(serialize_binary_reference_value),
+        "rtmr2": rtmr2.as_ref().map(serialize_binary_reference_value),
+        "rtmr3": rtmr3.as_ref().map(serialize_binary_reference_value),
+        "td_attributes_mask": td_attributes_mask,
+        "td_attributes": td_attributes,
+    })
 }
 
 pub fn serialize_insecure_reference_values(
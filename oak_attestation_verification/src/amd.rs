@@ -16,18 +16,26 @@
 
 //! Contains code related to AMD hardware certificates and attestation report.
 
-use alloc::string::String;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 
+use anyhow::Context;
 use oak_sev_snp_attestation_report::{AttestationReport, SigningAlgorithm, TcbVersion};
 use p256::pkcs8::ObjectIdentifier;
 use rsa::{pss::Signature, signature::Verifier, RsaPublicKey};
 use sha2::Sha384;
 use x509_cert::{
-    der::{referenced::OwnedToRef, Encode},
+    crl::CertificateList,
+    der::{asn1::Ia5StringRef, referenced::OwnedToRef, Decode, DecodePem, Encode},
+    spki::AlgorithmIdentifierOwned,
     Certificate,
 };
 use zerocopy::{AsBytes, FromZeroes};
 
+use crate::util::{is_revoked, verify_cert_validity, verify_time_window};
+
 // The keys in the key-value map of X509 certificates are Object Identifiers
 // (OIDs) which have a global registry. The present OIDs are taken from
 // Table 8 of
@@ -40,11 +48,129 @@ const SNP_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.3704
 const CHIP_ID_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.3704.1.4");
 const UCODE_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.3704.1.3.8");
 
+const ARK_MILAN_CERT_PEM: &str = include_str!("../data/ark_milan.pem");
+const ASK_MILAN_CERT_PEM: &str = include_str!("../data/ask_milan.pem");
+const ARK_GENOA_CERT_PEM: &str = include_str!("../data/ark_genoa.pem");
+const ASK_GENOA_CERT_PEM: &str = include_str!("../data/ask_genoa.pem");
+
+/// The ARK and ASK certificates of an AMD product line.
+struct ProductRoots {
+    product_name: String,
+    ark: Certificate,
+    ask: Certificate,
+}
+
+/// The trusted root certificates for verifying VCEK certificates.
+///
+/// Each AMD product line (e.g. Milan or Genoa) has its own AMD Root Key (ARK)
+/// and AMD SEV Key (ASK). A VCEK is matched to a product line by the product
+/// name in its certificate.
+#[derive(Default)]
+pub struct RootStore {
+    products: Vec<ProductRoots>,
+}
+
+impl RootStore {
+    /// Creates a root store containing the roots of the product lines that are
+    /// built into this crate.
+    pub fn with_builtin_roots() -> anyhow::Result<Self> {
+        let mut store = Self::default();
+        for (product_name, ark_pem, ask_pem) in [
+            ("Milan", ARK_MILAN_CERT_PEM, ASK_MILAN_CERT_PEM),
+            ("Genoa", ARK_GENOA_CERT_PEM, ASK_GENOA_CERT_PEM),
+        ] {
+            let ark = Certificate::from_pem(ark_pem)
+                .map_err(|_err| anyhow::anyhow!("could not parse {product_name} ARK cert"))?;
+            let ask = Certificate::from_pem(ask_pem)
+                .map_err(|_err| anyhow::anyhow!("could not parse {product_name} ASK cert"))?;
+            store.add_product(product_name, ark, ask);
+        }
+        Ok(store)
+    }
+
+    /// Adds the roots of a product line. VCEK certificates whose product name
+    /// contains `product_name` are verified against these roots.
+    pub fn add_product(&mut self, product_name: &str, ark: Certificate, ask: Certificate) {
+        self.products.push(ProductRoots { product_name: product_name.to_string(), ark, ask });
+    }
+
+    fn find(&self, vcek: &Certificate) -> anyhow::Result<&ProductRoots> {
+        let vcek_product_name = product_name(vcek)?;
+        self.products
+            .iter()
+            .find(|roots| vcek_product_name.contains(&roots.product_name))
+            .context("unsupported AMD product")
+    }
+}
+
+/// Verifies the ARK -> ASK -> VCEK certificate chain for the product line of
+/// the VCEK, including the validity period of each certificate.
+///
+/// CRLs issued by the ARK of the product line are verified and used to check
+/// that the ASK has not been revoked. CRLs of other issuers are ignored.
+pub fn verify_vcek_cert_chain(
+    now_utc_millis: i64,
+    root_store: &RootStore,
+    crls: &[CertificateList],
+    vcek: &Certificate,
+) -> anyhow::Result<()> {
+    let ProductRoots { ark, ask, .. } = root_store.find(vcek)?;
+
+    verify_cert_validity(now_utc_millis, ark).context("verifying ARK cert validity")?;
+    verify_cert_signature(ark, ark).context("verifying ARK cert")?;
+    verify_cert_validity(now_utc_millis, ask).context("verifying ASK cert validity")?;
+    verify_cert_signature(ark, ask).context("verifying ASK cert")?;
+    verify_cert_validity(now_utc_millis, vcek).context("verifying VCEK cert validity")?;
+    verify_cert_signature(ask, vcek).context("verifying VCEK cert")?;
+
+    for crl in crls.iter().filter(|crl| crl.tbs_cert_list.issuer == ark.tbs_certificate.subject) {
+        verify_crl(now_utc_millis, ark, crl).context("verifying ARK CRL")?;
+        anyhow::ensure!(!is_revoked(crl, ask), "ASK cert has been revoked");
+    }
+    Ok(())
+}
+
+/// Verifies that the CRL is signed by the issuer and that it is current.
+fn verify_crl(
+    now_utc_millis: i64,
+    issuer: &Certificate,
+    crl: &CertificateList,
+) -> anyhow::Result<()> {
+    let message = crl
+        .tbs_cert_list
+        .to_der()
+        .map_err(|_err| anyhow::anyhow!("could not extract message to verify RSA signature"))?;
+    verify_rsa_pss_signature(issuer, &crl.signature_algorithm, &message, crl.signature.raw_bytes())
+        .context("verifying CRL signature")?;
+    verify_time_window(now_utc_millis, crl.tbs_cert_list.this_update, crl.tbs_cert_list.next_update)
+        .context("verifying CRL validity")
+}
+
 pub fn verify_cert_signature(signer: &Certificate, signee: &Certificate) -> anyhow::Result<()> {
+    let message = signee
+        .tbs_certificate
+        .to_der()
+        .map_err(|_err| anyhow::anyhow!("could not extract message to verify RSA signature"))?;
+    verify_rsa_pss_signature(
+        signer,
+        &signee.signature_algorithm,
+        &message,
+        signee.signature.raw_bytes(),
+    )
+}
+
+/// Verifies an RSA-PSS SHA-384 signature, as used by AMD for certificates and
+/// CRLs, with the public key of the signer.
+fn verify_rsa_pss_signature(
+    signer: &Certificate,
+    signature_algorithm: &AlgorithmIdentifierOwned,
+    message: &[u8],
+    signature: &[u8],
+) -> anyhow::Result<()> {
     anyhow::ensure!(
-        signee.signature_algorithm.oid == RSA_SSA_PSS_OID,
+        signature_algorithm.oid == RSA_SSA_PSS_OID,
         "unsupported signature algorithm: {:?}",
-        signee.signature_algorithm
+        signature_algorithm
     );
 
     let verifying_key = {
@@ -54,15 +180,11 @@ pub fn verify_cert_signature(signer: &Certificate, signee: &Certificate) -> anyh
         rsa::pss::VerifyingKey::<Sha384>::new(pubkey)
     };
 
-    let message = signee
-        .tbs_certificate
-        .to_der()
-        .map_err(|_err| anyhow::anyhow!("could not extract message to verify RSA signature"))?;
-    let signature = Signature::try_from(signee.signature.raw_bytes())
+    let signature = Signature::try_from(signature)
         .map_err(|_err| anyhow::anyhow!("could not extract RSA signature"))?;
 
     verifying_key
-        .verify(&message, &signature)
+        .verify(message, &signature)
         .map_err(|_err| anyhow::anyhow!("signature verification failed"))
 }
 
@@ -80,6 +202,26 @@ pub fn product_name(cert: &Certificate) -> anyhow::Result<String> {
         .map_err(|_utf8_err| anyhow::anyhow!("failed to read product name"))
 }
 
+/// Returns the AMD product line of the VCEK, e.g. "Milan". The product name
+/// extension holds a DER-encoded IA5String such as "Milan-B0", where the part
+/// after the hyphen, if any, is the stepping.
+pub fn product_line(cert: &Certificate) -> anyhow::Result<String> {
+    let exts = cert
+        .tbs_certificate
+        .extensions
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("could not get extensions from cert"))?;
+    let pn_ext = exts
+        .iter()
+        .find(|&ext| ext.extn_id == PRODUCT_NAME_OID)
+        .ok_or_else(|| anyhow::anyhow!("no product name found in cert"))?;
+    let product_name = Ia5StringRef::from_der(pn_ext.extn_value.as_bytes())
+        .map_err(|_err| anyhow::anyhow!("failed to decode product name"))?;
+    let product_line = product_name.as_str().split('-').next().unwrap_or_default();
+    anyhow::ensure!(!product_line.is_empty(), "empty product name in cert");
+    Ok(product_line.to_string())
+}
+
 fn chip_id(cert: &Certificate) -> anyhow::Result<[u8; 64]> {
     let exts = cert
         .tbs_certificate
@@ -100,26 +242,24 @@ fn chip_id(cert: &Certificate) -> anyhow::Result<[u8; 64]> {
 }
 
 fn tcb_version(vcek: &Certificate) -> anyhow::Result<TcbVersion> {
-    let mut tcb = TcbVersion::new_zeroed();
-    for ext in vcek
+    let exts = vcek
         .tbs_certificate
         .extensions
         .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("could not get extensions from VCEK cert"))?
-    {
-        // What's the appropriate way of extracting u8 from OctetString?
-        let arr = ext.extn_value.as_bytes();
-        let last = arr.len() - 1;
-        if ext.extn_id == BL_OID {
-            tcb.boot_loader = arr[last];
-        } else if ext.extn_id == TEE_OID {
-            tcb.tee = arr[last];
-        } else if ext.extn_id == SNP_OID {
-            tcb.snp = arr[last];
-        } else if ext.extn_id == UCODE_OID {
-            tcb.microcode = arr[last];
-        }
-    }
+        .ok_or_else(|| anyhow::anyhow!("could not get extensions from VCEK cert"))?;
+    // The TCB components are encoded as DER integers, which fit into a single
+    // byte, so the value is the last byte of the encoding.
+    let component = |oid: ObjectIdentifier, name: &str| {
+        exts.iter()
+            .find(|ext| ext.extn_id == oid)
+            .and_then(|ext| ext.extn_value.as_bytes().last().copied())
+            .ok_or_else(|| anyhow::anyhow!("no {name} TCB version found in VCEK cert"))
+    };
+    let mut tcb = TcbVersion::new_zeroed();
+    tcb.boot_loader = component(BL_OID, "boot loader")?;
+    tcb.tee = component(TEE_OID, "tee")?;
+    tcb.snp = component(SNP_OID, "snp")?;
+    tcb.microcode = component(UCODE_OID, "microcode")?;
     Ok(tcb)
 }
 
@@ -18,9 +18,16 @@ extern crate std;
 
 use std::eprintln;
 
-use x509_cert::{certificate::Version, der::DecodePem, Certificate};
+use x509_cert::{
+    certificate::Version,
+    crl::CertificateList,
+    der::{Decode, DecodePem},
+    Certificate,
+};
 
-use crate::amd::verify_cert_signature;
+use crate::amd::{
+    product_line, tcb_version, verify_cert_signature, verify_vcek_cert_chain, RootStore,
+};
 
 const ARK_MILAN_CERT_PEM: &str = include_str!("../../data/ark_milan.pem");
 const ARK_GENOA_CERT_PEM: &str = include_str!("../../data/ark_genoa.pem");
@@ -28,6 +35,23 @@ const ASK_MILAN_CERT_PEM: &str = include_str!("../../data/ask_milan.pem");
 const ASK_GENOA_CERT_PEM: &str = include_str!("../../data/ask_genoa.pem");
 const VCEK_MILAN_CERT_PEM: &str = include_str!("../../testdata/oc_vcek_milan.pem");
 
+// A synthetic ARK -> ASK -> VCEK chain for the product "Test". The VCEK is
+// valid from 2024-01-01 until 2031-01-01. Both CRLs are issued by the test ARK
+// and are valid from 2024-06-01 until 2034-06-01; the second one revokes the
+// test ASK.
+const TEST_ARK_CERT_PEM: &str = include_str!("../../testdata/amd_test_ark.pem");
+const TEST_ASK_CERT_PEM: &str = include_str!("../../testdata/amd_test_ask.pem");
+const TEST_VCEK_CERT_PEM: &str = include_str!("../../testdata/amd_test_vcek.pem");
+const TEST_CRL: &[u8] = include_bytes!("../../testdata/amd_test_crl.der");
+const TEST_CRL_REVOKED_ASK: &[u8] = include_bytes!("../../testdata/amd_test_crl_revoked_ask.der");
+
+// 2025-01-01T00:00:00Z
+const NOW_UTC_MILLIS: i64 = 1735689600000;
+// 2032-01-01T00:00:00Z
+const AFTER_VCEK_EXPIRY_UTC_MILLIS: i64 = 1956528000000;
+// 2023-01-01T00:00:00Z
+const BEFORE_VCEK_ISSUANCE_UTC_MILLIS: i64 = 1672531200000;
+
 // Verifies validity of a matching ARK, ASK certificate pair.
 //
 // Validate at least a subset of Appendix B.3 of
@@ -126,3 +150,116 @@ fn validate_genoa() {
     let ask = Certificate::from_pem(ASK_GENOA_CERT_PEM).expect("could not parse cert");
     assert!(validate_ark_ask_certs(&ark, &ask).is_ok());
 }
+
+fn test_root_store() -> RootStore {
+    let mut root_store = RootStore::default();
+    root_store.add_product(
+        "Test",
+        Certificate::from_pem(TEST_ARK_CERT_PEM).expect("could not parse cert"),
+        Certificate::from_pem(TEST_ASK_CERT_PEM).expect("could not parse cert"),
+    );
+    root_store
+}
+
+fn test_vcek() -> Certificate {
+    Certificate::from_pem(TEST_VCEK_CERT_PEM).expect("could not parse cert")
+}
+
+fn parse_crl(der: &[u8]) -> CertificateList {
+    CertificateList::from_der(der).expect("could not parse CRL")
+}
+
+#[test]
+fn builtin_roots_verify_milan_vcek_chain() {
+    let root_store = RootStore::with_builtin_roots().expect("could not create root store");
+    let vcek = Certificate::from_pem(VCEK_MILAN_CERT_PEM).expect("could not parse cert");
+    assert!(verify_vcek_cert_chain(NOW_UTC_MILLIS, &root_store, &[], &vcek).is_ok());
+}
+
+#[test]
+fn builtin_roots_reject_unknown_product() {
+    let root_store = RootStore::with_builtin_roots().expect("could not create root store");
+    assert!(verify_vcek_cert_chain(NOW_UTC_MILLIS, &root_store, &[], &test_vcek()).is_err());
+}
+
+#[test]
+fn verify_vcek_cert_chain_succeeds() {
+    let crls = [parse_crl(TEST_CRL)];
+    assert!(verify_vcek_cert_chain(NOW_UTC_MILLIS, &test_root_store(), &crls, &test_vcek()).is_ok());
+}
+
+#[test]
+fn verify_vcek_cert_chain_rejects_expired_vcek() {
+    assert!(verify_vcek_cert_chain(
+        AFTER_VCEK_EXPIRY_UTC_MILLIS,
+        &test_root_store(),
+        &[],
+        &test_vcek()
+    )
+    .is_err());
+}
+
+#[test]
+fn verify_vcek_cert_chain_rejects_vcek_before_issuance() {
+    assert!(verify_vcek_cert_chain(
+        BEFORE_VCEK_ISSUANCE_UTC_MILLIS,
+        &test_root_store(),
+        &[],
+        &test_vcek()
+    )
+    .is_err());
+}
+
+#[test]
+fn verify_vcek_cert_chain_rejects_revoked_ask() {
+    let crls = [parse_crl(TEST_CRL_REVOKED_ASK)];
+    let result = verify_vcek_cert_chain(NOW_UTC_MILLIS, &test_root_store(), &crls, &test_vcek());
+    assert!(result.is_err_and(|err| err.to_string().contains("revoked")));
+}
+
+#[test]
+fn verify_vcek_cert_chain_rejects_tampered_crl() {
+    let mut der = TEST_CRL_REVOKED_ASK.to_vec();
+    // Flip a bit in the signature, which is at the end of the CRL.
+    let last = der.len() - 1;
+    der[last] ^= 1;
+    let crls = [parse_crl(&der)];
+    let result = verify_vcek_cert_chain(NOW_UTC_MILLIS, &test_root_store(), &crls, &test_vcek());
+    assert!(result.is_err_and(|err| !err.to_string().contains("revoked")));
+}
+
+#[test]
+fn verify_vcek_cert_chain_ignores_crls_of_other_issuers() {
+    let root_store = RootStore::with_builtin_roots().expect("could not create root store");
+    let vcek = Certificate::from_pem(VCEK_MILAN_CERT_PEM).expect("could not parse cert");
+    let crls = [parse_crl(TEST_CRL_REVOKED_ASK)];
+    assert!(verify_vcek_cert_chain(NOW_UTC_MILLIS, &root_store, &crls, &vcek).is_ok());
+}
+
+#[test]
+fn tcb_version_from_milan_vcek() {
+    let vcek = Certificate::from_pem(VCEK_MILAN_CERT_PEM).expect("could not parse cert");
+    let tcb = tcb_version(&vcek).expect("could not get TCB version");
+    assert_eq!(tcb.boot_loader, 3);
+    assert_eq!(tcb.tee, 0);
+    assert_eq!(tcb.snp, 20);
+    assert_eq!(tcb.microcode, 209);
+}
+
+#[test]
+fn tcb_version_requires_all_components() {
+    // The synthetic test VCEK has no TCB extensions.
+    assert!(tcb_version(&test_vcek()).is_err());
+}
+
+#[test]
+fn product_line_strips_stepping() {
+    let vcek = Certificate::from_pem(VCEK_MILAN_CERT_PEM).expect("could not parse cert");
+    assert_eq!(product_line(&vcek).expect("no product line"), "Milan");
+}
+
+#[test]
+fn product_line_of_test_vcek() {
+    let vcek = Certificate::from_pem(TEST_VCEK_CERT_PEM).expect("could not parse cert");
+    assert_eq!(product_line(&vcek).expect("no product line"), "Test");
+}
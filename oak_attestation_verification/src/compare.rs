@@ -42,21 +42,25 @@ use crate::{
     util::is_raw_digest_match,
 };
 
+/// Validates the values extracted from the evidence against the expected
+/// values. `vcek_product_line` is the AMD product line of the verified VCEK, if
+/// the evidence comes from AMD SEV-SNP, and selects the minimum TCB version.
 pub fn compare_expected_values(
     extracted_evidence: &ExtractedEvidence,
     expected_values: &ExpectedValues,
+    vcek_product_line: Option<&str>,
 ) -> anyhow::Result<()> {
     match (extracted_evidence.evidence_values.as_ref(), expected_values.r#type.as_ref()) {
         (
             Some(EvidenceValues::OakRestrictedKernel(values)),
             Some(expected_values::Type::OakRestrictedKernel(expected)),
-        ) => compare_oak_restricted_kernel_measurement_digests(values, expected),
+        ) => compare_oak_restricted_kernel_measurement_digests(values, expected, vcek_product_line),
         (
             Some(EvidenceValues::OakContainers(values)),
             Some(expected_values::Type::OakContainers(expected)),
-        ) => compare_oak_containers_measurement_digests(values, expected),
+        ) => compare_oak_containers_measurement_digests(values, expected, vcek_product_line),
         (Some(EvidenceValues::Cb(values)), Some(expected_values::Type::Cb(expected))) => {
-            compare_cb_measurement_digests(values, expected)
+            compare_cb_measurement_digests(values, expected, vcek_product_line)
         }
         // Evidence, endorsements and reference values must exist and reflect the same chain type.
         (None, _) => anyhow::bail!("Reference values are empty"),
@@ -72,10 +76,12 @@ pub fn compare_expected_values(
 fn compare_oak_restricted_kernel_measurement_digests(
     values: &OakRestrictedKernelData,
     expected: &OakRestrictedKernelExpectedValues,
+    vcek_product_line: Option<&str>,
 ) -> anyhow::Result<()> {
     compare_root_layer_measurement_digests(
         values.root_layer.as_ref().context("no root layer evidence values")?,
         expected.root_layer.as_ref().context("no root layer expected values")?,
+        vcek_product_line,
     )
     .context("comparing root layer values")?;
 
@@ -97,10 +103,12 @@ fn compare_oak_restricted_kernel_measurement_digests(
 fn compare_oak_containers_measurement_digests(
     values: &OakContainersData,
     expected: &OakContainersExpectedValues,
+    vcek_product_line: Option<&str>,
 ) -> anyhow::Result<()> {
     compare_root_layer_measurement_digests(
         values.root_layer.as_ref().context("no root layer evidence values")?,
         expected.root_layer.as_ref().context("no root layer expected values")?,
+        vcek_product_line,
     )
     .context("comparing root layer digests")?;
 
@@ -128,10 +136,12 @@ fn compare_oak_containers_measurement_digests(
 fn compare_cb_measurement_digests(
     values: &CbData,
     expected: &CbExpectedValues,
+    vcek_product_line: Option<&str>,
 ) -> anyhow::Result<()> {
     compare_root_layer_measurement_digests(
         values.root_layer.as_ref().context("no root layer evidence values")?,
         expected.root_layer.as_ref().context("no root layer expected values")?,
+        vcek_product_line,
     )
     .context("root layer verification failed")?;
 
@@ -157,6 +167,7 @@ fn compare_cb_measurement_digests(
 fn compare_root_layer_measurement_digests(
     values: &RootLayerData,
     expected_values: &RootLayerExpectedValues,
+    vcek_product_line: Option<&str>,
 ) -> anyhow::Result<()> {
     match (
         values.report.as_ref(),
@@ -177,9 +188,26 @@ fn compare_root_layer_measurement_digests(
                     .context("no stage0 expected value provided")?,
             )
             .context("stage0 measurement values failed verification")?;
-            verify_amd_sev_attestation_report(report_values, amd_sev_values)
+            verify_amd_sev_attestation_report(report_values, amd_sev_values, vcek_product_line)
         }
         (Some(Report::Tdx(report_values)), _, Some(intel_tdx_values), _) => {
+            let measurements = [
+                ("MRTD", &report_values.mr_td, &intel_tdx_values.mr_td_expected),
+                ("RTMR0", &report_values.rtmr0, &intel_tdx_values.rtmr0_expected),
+                ("RTMR1", &report_values.rtmr1, &intel_tdx_values.rtmr1_expected),
+                ("RTMR2", &report_values.rtmr2, &intel_tdx_values.rtmr2_expected),
+                ("RTMR3", &report_values.rtmr3, &intel_tdx_values.rtmr3_expected),
+            ];
+            for (name, value, expected) in measurements {
+                let measurement = RawDigest { sha2_384: value.to_vec(), ..Default::default() };
+                compare_measurement_digest(
+                    &measurement,
+                    expected
+                        .as_ref()
+                        .with_context(|| format!("no {name} expected value provided"))?,
+                )
+                .with_context(|| format!("{name} measurement values failed verification"))?;
+            }
             verify_intel_tdx_attestation_report(report_values, intel_tdx_values)
         }
         (_, _, _, Some(insecure_values)) => {
@@ -361,3 +389,6 @@ fn verify_regex(actual: &str, regex: &str) -> anyhow::Result<()> {
 fn verify_regex(_actual: &str, _regex: &str) -> anyhow::Result<()> {
     Err(anyhow::anyhow!("verification of regex values not supported"))
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,155 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use alloc::{vec, vec::Vec};
+
+use oak_proto_rust::oak::{
+    attestation::v1::{
+        expected_digests, root_layer_data::Report, ExpectedDigests, IntelTdxAttestationReport,
+        IntelTdxExpectedValues, RawDigests, RootLayerData, RootLayerExpectedValues,
+        VerificationSkipped,
+    },
+    RawDigest,
+};
+
+use crate::compare::compare_root_layer_measurement_digests;
+
+// SEPT_VE_DISABLE
+const TD_ATTRIBUTES: u64 = 1 << 28;
+
+fn measurement(value: u8) -> Vec<u8> {
+    vec![value; 48]
+}
+
+fn expected_digest(value: u8) -> Option<ExpectedDigests> {
+    Some(ExpectedDigests {
+        r#type: Some(expected_digests::Type::Digests(RawDigests {
+            digests: vec![RawDigest { sha2_384: measurement(value), ..Default::default() }],
+            ..Default::default()
+        })),
+    })
+}
+
+fn tdx_root_layer_data() -> RootLayerData {
+    RootLayerData {
+        report: Some(Report::Tdx(IntelTdxAttestationReport {
+            tee_tcb_svn: vec![3, 1, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            td_attributes: TD_ATTRIBUTES,
+            mr_td: measurement(1),
+            rtmr0: measurement(2),
+            rtmr1: measurement(3),
+            rtmr2: measurement(4),
+            rtmr3: measurement(5),
+            ..Default::default()
+        })),
+    }
+}
+
+fn tdx_expected_values() -> IntelTdxExpectedValues {
+    IntelTdxExpectedValues {
+        mr_td_expected: expected_digest(1),
+        rtmr0_expected: expected_digest(2),
+        rtmr1_expected: expected_digest(3),
+        rtmr2_expected: expected_digest(4),
+        rtmr3_expected: expected_digest(5),
+        min_tee_tcb_svn: vec![3, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        allow_debug: false,
+        td_attributes_mask: TD_ATTRIBUTES,
+        td_attributes: TD_ATTRIBUTES,
+    }
+}
+
+fn root_layer_expected_values(intel_tdx: IntelTdxExpectedValues) -> RootLayerExpectedValues {
+    RootLayerExpectedValues { intel_tdx: Some(intel_tdx), ..Default::default() }
+}
+
+#[test]
+fn compare_tdx_root_layer_succeeds() {
+    let result = compare_root_layer_measurement_digests(
+        &tdx_root_layer_data(),
+        &root_layer_expected_values(tdx_expected_values()),
+        None,
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn compare_tdx_root_layer_with_skipped_rtmr_succeeds() {
+    let expected_values = IntelTdxExpectedValues {
+        rtmr3_expected: Some(ExpectedDigests {
+            r#type: Some(expected_digests::Type::Skipped(VerificationSkipped {})),
+        }),
+        ..tdx_expected_values()
+    };
+    let result = compare_root_layer_measurement_digests(
+        &tdx_root_layer_data(),
+        &root_layer_expected_values(expected_values),
+        None,
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn compare_tdx_root_layer_with_mismatched_measurement_fails() {
+    let mismatches = [
+        IntelTdxExpectedValues { mr_td_expected: expected_digest(9), ..tdx_expected_values() },
+        IntelTdxExpectedValues { rtmr0_expected: expected_digest(9), ..tdx_expected_values() },
+        IntelTdxExpectedValues { rtmr1_expected: expected_digest(9), ..tdx_expected_values() },
+        IntelTdxExpectedValues { rtmr2_expected: expected_digest(9), ..tdx_expected_values() },
+        IntelTdxExpectedValues { rtmr3_expected: expected_digest(9), ..tdx_expected_values() },
+    ];
+    for expected_values in mismatches {
+        assert!(compare_root_layer_measurement_digests(
+            &tdx_root_layer_data(),
+            &root_layer_expected_values(expected_values),
+            None,
+        )
+        .is_err());
+    }
+}
+
+#[test]
+fn compare_tdx_root_layer_without_expected_measurement_fails() {
+    let expected_values = IntelTdxExpectedValues { rtmr2_expected: None, ..tdx_expected_values() };
+    assert!(compare_root_layer_measurement_digests(
+        &tdx_root_layer_data(),
+        &root_layer_expected_values(expected_values),
+        None,
+    )
+    .is_err());
+}
+
+#[test]
+fn compare_tdx_root_layer_with_debug_fails() {
+    let mut data = tdx_root_layer_data();
+    if let Some(Report::Tdx(report)) = data.report.as_mut() {
+        report.debug = true;
+    }
+    assert!(compare_root_layer_measurement_digests(
+        &data,
+        &root_layer_expected_values(tdx_expected_values()),
+        None,
+    )
+    .is_err());
+}
+
+#[test]
+fn compare_tdx_root_layer_with_amd_expected_values_fails() {
+    let expected_values =
+        RootLayerExpectedValues { amd_sev: Some(Default::default()), ..Default::default() };
+    assert!(compare_root_layer_measurement_digests(&tdx_root_layer_data(), &expected_values, None)
+        .is_err());
+}
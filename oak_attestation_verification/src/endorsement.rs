@@ -202,8 +202,13 @@ pub fn verify_endorsement(
             if log_entry.is_empty() {
                 anyhow::bail!("log entry unavailable but verification was requested");
             }
-            verify_rekor_log_entry(log_entry, key_set, &endorsement.serialized)
-                .context("verifying rekor log entry")?;
+            verify_rekor_log_entry(
+                log_entry,
+                key_set,
+                ref_value.rekor_witnesses.as_ref(),
+                &endorsement.serialized,
+            )
+            .context("verifying rekor log entry")?;
             verify_endorser_public_key(log_entry, signature.key_id, endorser_key_set)?;
             Ok(statement)
         }
@@ -16,18 +16,24 @@
 #[cfg(test)]
 extern crate std;
 
-use alloc::vec::Vec;
+use alloc::{format, string::String, vec::Vec};
 use std::fs;
 
+use base64::{prelude::BASE64_STANDARD, Engine as _};
 use oak_file_utils::data_path;
+use p256::pkcs8::EncodePublicKey;
 
 use crate::{
     endorsement::{
         get_digest, parse_statement, validate_statement, verify_binary_endorsement,
         verify_endorser_public_key_ecdsa,
     },
-    rekor::{verify_rekor_log_entry_ecdsa, verify_rekor_signature},
-    util::convert_pem_to_raw,
+    rekor::{
+        root_from_inclusion_proof, verify_rekor_inclusion_proof, verify_rekor_log_entry_ecdsa,
+        verify_rekor_signature,
+    },
+    test_util::new_random_signing_keypair,
+    util::{convert_pem_to_raw, hash_sha2_256},
 };
 
 const ENDORSEMENT_PATH: &str = "oak_attestation_verification/testdata/endorsement.json";
@@ -36,6 +42,18 @@ const ENDORSER_PUBLIC_KEY_PATH: &str =
     "oak_attestation_verification/testdata/endorser_public_key.pem";
 const LOG_ENTRY_PATH: &str = "oak_attestation_verification/testdata/logentry.json";
 
+const COSIGNED_LOG_ENTRY_PATH: &str =
+    "oak_attestation_verification/testdata/logentry_cosigned.json";
+
+// Note verifier key of the test witness that cosigned the checkpoint in
+// `logentry_cosigned.json`.
+const WITNESS_VERIFIER_KEY: &str =
+    "witness.example.com+f8e2fc87+BDUZFqwuDPbOMSw78/WFsFoVJ8QW+jCXb93rNNIDzZoE";
+
+// Note verifier key of a witness with the same name but a different key.
+const OTHER_WITNESS_VERIFIER_KEY: &str =
+    "witness.example.com+841b5829+BDtEn1tB0oSnB1dw6BhFgiR2+Y6aPqRyJGMkBBbs3x8D";
+
 // Public key of the Rekor instance hosted by sigstore.dev. It is downloaded
 // from https://rekor.sigstore.dev/api/v1/log/publicKey.
 const REKOR_PUBLIC_KEY_PATH: &str = "oak_attestation_verification/testdata/rekor_public_key.pem";
@@ -93,6 +111,177 @@ fn test_verify_rekor_log_entry_success() {
     assert!(result.is_ok(), "{:?}", result);
 }
 
+/// Applies `f` to the inclusion proof of the given serialized log entry.
+fn modify_inclusion_proof(log_entry: &[u8], f: impl FnOnce(&mut serde_json::Value)) -> Vec<u8> {
+    let mut value: serde_json::Value =
+        serde_json::from_slice(log_entry).expect("couldn't parse log entry");
+    let entry = value.as_object_mut().unwrap().values_mut().next().unwrap();
+    f(&mut entry["verification"]["inclusionProof"]);
+    serde_json::to_vec(&value).expect("couldn't serialize log entry")
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_success() {
+    let testdata = load_testdata();
+
+    let result =
+        verify_rekor_inclusion_proof(&testdata.log_entry, &[&testdata.rekor_public_key], &[]);
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_without_proof() {
+    let testdata = load_testdata();
+    let log_entry =
+        modify_inclusion_proof(&testdata.log_entry, |proof| *proof = serde_json::Value::Null);
+
+    let result = verify_rekor_inclusion_proof(&log_entry, &[&testdata.rekor_public_key], &[]);
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_modified_hash() {
+    let testdata = load_testdata();
+    let log_entry = modify_inclusion_proof(&testdata.log_entry, |proof| {
+        proof["hashes"][0] = hex::encode([0u8; 32]).into();
+    });
+
+    let result = verify_rekor_inclusion_proof(&log_entry, &[&testdata.rekor_public_key], &[]);
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_modified_index() {
+    let testdata = load_testdata();
+    let log_entry = modify_inclusion_proof(&testdata.log_entry, |proof| {
+        proof["logIndex"] = (proof["logIndex"].as_u64().unwrap() + 1).into();
+    });
+
+    let result = verify_rekor_inclusion_proof(&log_entry, &[&testdata.rekor_public_key], &[]);
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_mismatched_checkpoint() {
+    let testdata = load_testdata();
+    // A checkpoint for a different tree, even if properly signed, must not be
+    // accepted for this proof.
+    let log_entry = modify_inclusion_proof(&testdata.log_entry, |proof| {
+        let checkpoint = proof["checkpoint"].as_str().unwrap();
+        let tree_size = proof["treeSize"].as_u64().unwrap();
+        let modified: String =
+            checkpoint.replacen(&format!("\n{tree_size}\n"), &format!("\n{}\n", tree_size + 1), 1);
+        proof["checkpoint"] = modified.into();
+    });
+
+    let result = verify_rekor_inclusion_proof(&log_entry, &[&testdata.rekor_public_key], &[]);
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_wrong_key() {
+    let testdata = load_testdata();
+    let (_, public_key) = new_random_signing_keypair();
+    let public_key = public_key.to_public_key_der().unwrap();
+
+    let result = verify_rekor_inclusion_proof(&testdata.log_entry, &[public_key.as_bytes()], &[]);
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_with_witness_success() {
+    let testdata = load_testdata();
+    let log_entry =
+        fs::read(data_path(COSIGNED_LOG_ENTRY_PATH)).expect("couldn't read cosigned log entry");
+
+    let result = verify_rekor_inclusion_proof(
+        &log_entry,
+        &[&testdata.rekor_public_key],
+        &[WITNESS_VERIFIER_KEY.as_bytes()],
+    );
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_without_witness() {
+    let testdata = load_testdata();
+
+    let result = verify_rekor_inclusion_proof(
+        &testdata.log_entry,
+        &[&testdata.rekor_public_key],
+        &[WITNESS_VERIFIER_KEY.as_bytes()],
+    );
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_other_witness() {
+    let testdata = load_testdata();
+    let log_entry =
+        fs::read(data_path(COSIGNED_LOG_ENTRY_PATH)).expect("couldn't read cosigned log entry");
+
+    let result = verify_rekor_inclusion_proof(
+        &log_entry,
+        &[&testdata.rekor_public_key],
+        &[OTHER_WITNESS_VERIFIER_KEY.as_bytes()],
+    );
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_modified_cosignature_timestamp() {
+    let testdata = load_testdata();
+    let log_entry =
+        fs::read(data_path(COSIGNED_LOG_ENTRY_PATH)).expect("couldn't read cosigned log entry");
+    let log_entry = modify_inclusion_proof(&log_entry, |proof| {
+        let checkpoint = proof["checkpoint"].as_str().unwrap();
+        let (rest, cosignature) = checkpoint.trim_end().rsplit_once(' ').unwrap();
+        let mut cosignature = BASE64_STANDARD.decode(cosignature).unwrap();
+        // The timestamp follows the 4-byte key hint.
+        cosignature[11] ^= 1;
+        proof["checkpoint"] = format!("{rest} {}\n", BASE64_STANDARD.encode(cosignature)).into();
+    });
+
+    let result = verify_rekor_inclusion_proof(
+        &log_entry,
+        &[&testdata.rekor_public_key],
+        &[WITNESS_VERIFIER_KEY.as_bytes()],
+    );
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_verify_rekor_inclusion_proof_fails_with_malformed_witness_key() {
+    let testdata = load_testdata();
+    let log_entry =
+        fs::read(data_path(COSIGNED_LOG_ENTRY_PATH)).expect("couldn't read cosigned log entry");
+    // The key hint doesn't match the name and public key.
+    let verifier_key = WITNESS_VERIFIER_KEY.replace("+f8e2fc87+", "+00000000+");
+
+    let result = verify_rekor_inclusion_proof(
+        &log_entry,
+        &[&testdata.rekor_public_key],
+        &[verifier_key.as_bytes()],
+    );
+    assert!(result.is_err(), "{:?}", result);
+}
+
+#[test]
+fn test_root_from_inclusion_proof() {
+    // Tree of three leaves: root = H(H(l0, l1), l2).
+    let leaves: Vec<[u8; 32]> = (0u8..3).map(|i| hash_sha2_256(&[0x00, i])).collect();
+    let node = |l: &[u8; 32], r: &[u8; 32]| hash_sha2_256(&[&[0x01], &l[..], &r[..]].concat());
+    let root = node(&node(&leaves[0], &leaves[1]), &leaves[2]);
+
+    assert_eq!(root_from_inclusion_proof(0, 3, &leaves[0], &[leaves[1], leaves[2]]).unwrap(), root);
+    assert_eq!(
+        root_from_inclusion_proof(2, 3, &leaves[2], &[node(&leaves[0], &leaves[1])]).unwrap(),
+        root
+    );
+    assert!(root_from_inclusion_proof(2, 3, &leaves[2], &[]).is_err());
+    assert!(root_from_inclusion_proof(3, 3, &leaves[2], &[leaves[0]]).is_err());
+}
+
 #[test]
 fn test_validate_endorsement_statement_success() {
     let testdata = load_testdata();
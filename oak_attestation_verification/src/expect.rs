@@ -14,7 +14,7 @@
 // limitations under the License.
 //
 
-use alloc::{string::String, vec::Vec};
+use alloc::{format, string::String, vec::Vec};
 
 use anyhow::Context;
 use oak_proto_rust::oak::{
@@ -232,12 +232,37 @@ pub(crate) fn get_root_layer_expected_values(
             stage0_expected: Some(stage0_expected),
             min_tcb_version: amd_sev_values.min_tcb_version.clone(),
             allow_debug: amd_sev_values.allow_debug,
+            certificate_revocation_lists: amd_sev_values.certificate_revocation_lists.clone(),
+            additional_product_roots: amd_sev_values.additional_product_roots.clone(),
+            product_min_tcb_versions: amd_sev_values.product_min_tcb_versions.clone(),
         })
     } else {
         None
     };
 
-    let intel_tdx = reference_values.intel_tdx.as_ref().map(|_| IntelTdxExpectedValues {});
+    let intel_tdx = if let Some(intel_tdx_values) = reference_values.intel_tdx.as_ref() {
+        let get_expected = |reference_value: Option<&BinaryReferenceValue>, name: &str| {
+            get_expected_measurement_digest(
+                now_utc_millis,
+                None,
+                reference_value.with_context(|| format!("{name} reference values not found"))?,
+            )
+            .with_context(|| format!("getting {name} expected values"))
+        };
+        Some(IntelTdxExpectedValues {
+            mr_td_expected: Some(get_expected(intel_tdx_values.mr_td.as_ref(), "MRTD")?),
+            rtmr0_expected: Some(get_expected(intel_tdx_values.rtmr0.as_ref(), "RTMR0")?),
+            rtmr1_expected: Some(get_expected(intel_tdx_values.rtmr1.as_ref(), "RTMR1")?),
+            rtmr2_expected: Some(get_expected(intel_tdx_values.rtmr2.as_ref(), "RTMR2")?),
+            rtmr3_expected: Some(get_expected(intel_tdx_values.rtmr3.as_ref(), "RTMR3")?),
+            min_tee_tcb_svn: intel_tdx_values.min_tee_tcb_svn.clone(),
+            allow_debug: intel_tdx_values.allow_debug,
+            td_attributes_mask: intel_tdx_values.td_attributes_mask,
+            td_attributes: intel_tdx_values.td_attributes,
+        })
+    } else {
+        None
+    };
     let insecure = reference_values.insecure.as_ref().map(|_| InsecureExpectedValues {});
 
     Ok(RootLayerExpectedValues { amd_sev, intel_tdx, insecure })
@@ -31,9 +31,10 @@ use oak_proto_rust::oak::{
     attestation::v1::{
         extracted_evidence::EvidenceValues, root_layer_data::Report, AmdAttestationReport,
         ApplicationKeys, ApplicationLayerData, CbData, ContainerLayerData, Event, EventData,
-        Evidence, ExtractedEvidence, FakeAttestationReport, KernelLayerData, OakContainersData,
-        OakRestrictedKernelData, OrchestratorMeasurements, RootLayerData, RootLayerEvidence,
-        Stage0Measurements, Stage1Measurements, SystemLayerData, TcbVersion, TeePlatform,
+        Evidence, ExtractedEvidence, FakeAttestationReport, IntelTdxAttestationReport,
+        KernelLayerData, OakContainersData, OakRestrictedKernelData, OrchestratorMeasurements,
+        RootLayerData, RootLayerEvidence, Stage0Measurements, Stage1Measurements, SystemLayerData,
+        TcbVersion, TeePlatform,
     },
     RawDigest,
 };
@@ -42,11 +43,43 @@ use prost::Message;
 use sha2::Digest;
 use zerocopy::FromBytes;
 
+use crate::intel::{parse_quote, parse_td_report, verify_quote_matches_td_report};
+
 pub(crate) struct ApplicationKeyValues {
     pub(crate) encryption_public_key: Vec<u8>,
     pub(crate) signing_public_key: Vec<u8>,
 }
 
+/// Replaces the Intel TDX TD report in the root layer evidence with a quote.
+///
+/// Stage 0 can't fit a quote into its attestation data, so the root layer
+/// evidence that it generates on Intel TDX contains a TD report. This is only
+/// MAC'd for the local platform, so a later layer must upgrade it to a quote
+/// before the evidence can be verified remotely. `get_quote` is called with
+/// the TD report, e.g. to pass it to the GetQuote TDVMCALL, and must return a
+/// quote for the same TD and report data.
+pub fn upgrade_td_report_to_quote<F>(
+    root_layer: &mut RootLayerEvidence,
+    get_quote: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce(&[u8]) -> anyhow::Result<Vec<u8>>,
+{
+    anyhow::ensure!(
+        root_layer.platform() == TeePlatform::IntelTdx,
+        "the root layer evidence wasn't generated by Intel TDX"
+    );
+    let td_report = parse_td_report(&root_layer.remote_attestation_report)
+        .context("the root layer evidence doesn't contain a TD report")?;
+    let quote = get_quote(&root_layer.remote_attestation_report).context("couldn't get quote")?;
+    verify_quote_matches_td_report(
+        &parse_quote(&quote).context("invalid Intel TDX quote")?,
+        &td_report,
+    )?;
+    root_layer.remote_attestation_report = quote;
+    Ok(())
+}
+
 /// Extracts attestation-related values without verificaiton.
 ///
 /// Extracts measurements, public keys, and other attestation-related values
@@ -385,7 +418,36 @@ fn extract_root_values(root_layer: &RootLayerEvidence) -> anyhow::Result<RootLay
                 })),
             })
         }
-        TeePlatform::IntelTdx => Err(anyhow::anyhow!("not supported")),
+        TeePlatform::IntelTdx => {
+            anyhow::ensure!(
+                parse_td_report(&root_layer.remote_attestation_report).is_none(),
+                "the root layer contains an Intel TDX TD report that wasn't upgraded to a quote"
+            );
+            let quote = parse_quote(&root_layer.remote_attestation_report)
+                .context("invalid Intel TDX quote")?;
+            let debug = quote.is_debug();
+            let body = quote.body;
+            let [rtmr0, rtmr1, rtmr2, rtmr3] = body.rtmrs.map(|rtmr| rtmr.to_vec());
+
+            Ok(RootLayerData {
+                report: Some(Report::Tdx(IntelTdxAttestationReport {
+                    report_data: body.report_data.to_vec(),
+                    tee_tcb_svn: body.tee_tcb_svn.to_vec(),
+                    mr_seam: body.mr_seam.to_vec(),
+                    td_attributes: body.td_attributes,
+                    xfam: body.xfam,
+                    mr_td: body.mr_td.to_vec(),
+                    mr_config_id: body.mr_config_id.to_vec(),
+                    mr_owner: body.mr_owner.to_vec(),
+                    mr_owner_config: body.mr_owner_config.to_vec(),
+                    rtmr0,
+                    rtmr1,
+                    rtmr2,
+                    rtmr3,
+                    debug,
+                })),
+            })
+        }
         TeePlatform::None => {
             // We use an unsigned, mostly empty AMD SEV-SNP attestation report as a fake
             // when not running in a TEE.
@@ -0,0 +1,645 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Contains code related to Intel TDX quotes and the PCK certificate chain.
+//!
+//! The quote layout follows the Intel TDX DCAP Quote Generation Library and
+//! Quote Verification Library specification, version 4 of the quote format:
+//! <https://download.01.org/intel-sgx/latest/dcap-latest/linux/docs/Intel_TDX_DCAP_Quoting_Library_API.pdf>
+//!
+//! Verification follows the Intel TDX DCAP Quote Verification Library, using
+//! the collateral published by the Intel Provisioning Certification Service:
+//! the CRLs of the Intel SGX Root CA and of the PCK CA, the TDX TCB info and
+//! the identity of the TDX Quoting Enclave.
+
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use anyhow::Context;
+use ecdsa::signature::Verifier;
+use oak_proto_rust::oak::attestation::v1::IntelTdxCollateral;
+use p256::{
+    ecdsa::{Signature, VerifyingKey},
+    pkcs8::ObjectIdentifier,
+};
+use x509_cert::{
+    crl::CertificateList,
+    der::{referenced::OwnedToRef, Decode, DecodePem, Encode},
+    ext::pkix::BasicConstraints,
+    Certificate,
+};
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
+
+use crate::util::{hash_sha2_256, is_revoked, verify_cert_validity, verify_time_window};
+
+mod collateral;
+
+/// The version of the quote format supported by this implementation.
+const QUOTE_VERSION: u16 = 4;
+
+/// Attestation key type for ECDSA-256-with-P-256 curve.
+const ATTESTATION_KEY_TYPE_ECDSA_P256: u16 = 2;
+
+/// TEE type identifier of Intel TDX.
+const TEE_TYPE_TDX: u32 = 0x81;
+
+/// Certification data type of the PCK certificate chain in PEM format.
+const CERTIFICATION_DATA_TYPE_PCK_CERT_CHAIN: u16 = 5;
+
+/// Certification data type of the QE report certification data.
+const CERTIFICATION_DATA_TYPE_QE_REPORT: u16 = 6;
+
+/// The vendor ID of the Intel Quoting Enclave.
+const INTEL_QE_VENDOR_ID: [u8; 16] = [
+    0x93, 0x9a, 0x72, 0x33, 0xf7, 0x9c, 0x4c, 0xa9, 0x94, 0x0a, 0x0d, 0xb3, 0x95, 0x7f, 0x06, 0x07,
+];
+
+/// The number of certificates in the PCK certificate chain: the PCK
+/// certificate, the PCK Platform or Processor CA and the Intel SGX Root CA.
+const PCK_CERT_CHAIN_LENGTH: usize = 3;
+
+/// The type of a TD report, which is its first byte.
+const TD_REPORT_TYPE_TDX: u8 = 0x81;
+
+/// Bit in the TD attributes which indicates that the TD runs in debug mode.
+pub const TD_ATTRIBUTES_DEBUG: u64 = 1;
+
+/// The number of components in the TEE TCB SVN.
+pub const TEE_TCB_SVN_SIZE: usize = 16;
+
+const ECDSA_WITH_SHA_256_OID: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+
+const INTEL_SGX_ROOT_CA_CERT_PEM: &str = include_str!("../data/intel_sgx_root_ca.pem");
+
+/// The header of a TDX quote.
+#[repr(C)]
+#[derive(Debug, AsBytes, FromZeroes, FromBytes)]
+pub struct QuoteHeader {
+    /// The version of the quote format.
+    pub version: u16,
+    /// The type of the attestation key used to sign the quote.
+    pub attestation_key_type: u16,
+    /// The type of the TEE that generated the quote.
+    pub tee_type: u32,
+    pub reserved: [u8; 4],
+    /// The vendor ID of the Quoting Enclave.
+    pub qe_vendor_id: [u8; 16],
+    /// Custom data provided by the quoting library.
+    pub user_data: [u8; 20],
+}
+
+/// The body of a TDX quote, which mirrors the TD report.
+#[repr(C)]
+#[derive(Debug, AsBytes, FromZeroes, FromBytes)]
+pub struct TdQuoteBody {
+    /// The security version numbers of the TDX module TCB components.
+    pub tee_tcb_svn: [u8; TEE_TCB_SVN_SIZE],
+    /// The measurement of the TDX module.
+    pub mr_seam: [u8; 48],
+    /// The measurement of the signer of the TDX module.
+    pub mr_signer_seam: [u8; 48],
+    /// The attributes of the TDX module.
+    pub seam_attributes: u64,
+    /// The attributes of the TD.
+    pub td_attributes: u64,
+    /// The extended features available mask of the TD.
+    pub xfam: u64,
+    /// The measurement of the initial contents of the TD.
+    pub mr_td: [u8; 48],
+    /// Software-defined ID of the TD configuration.
+    pub mr_config_id: [u8; 48],
+    /// Software-defined ID of the TD owner.
+    pub mr_owner: [u8; 48],
+    /// Software-defined ID of the owner-defined TD configuration.
+    pub mr_owner_config: [u8; 48],
+    /// The runtime-extendable measurement registers.
+    pub rtmrs: [[u8; 48]; 4],
+    /// Custom data provided by the TD when the report was requested.
+    pub report_data: [u8; 64],
+}
+
+/// The body of the SGX report generated by the Quoting Enclave (QE).
+#[repr(C)]
+#[derive(Debug, AsBytes, FromZeroes, FromBytes)]
+pub struct EnclaveReportBody {
+    pub cpu_svn: [u8; 16],
+    pub misc_select: u32,
+    pub reserved_1: [u8; 28],
+    pub attributes: [u8; 16],
+    pub mr_enclave: [u8; 32],
+    pub reserved_2: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub reserved_3: [u8; 96],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub reserved_4: [u8; 60],
+    pub report_data: [u8; 64],
+}
+
+/// A TD report generated by the TDX module.
+///
+/// A TD report is only MAC'd for the local platform, so it must be converted
+/// into a quote by the Quoting Enclave before it can be verified remotely.
+///
+/// See section 3.6.1 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf).
+#[repr(C)]
+#[derive(Debug, AsBytes, FromZeroes, FromBytes)]
+pub struct TdReport {
+    pub report_type: [u8; 4],
+    pub reserved_1: [u8; 12],
+    pub cpu_svn: [u8; 16],
+    pub tee_tcb_info_hash: [u8; 48],
+    pub tee_info_hash: [u8; 48],
+    /// Custom data provided by the TD when the report was requested.
+    pub report_data: [u8; 64],
+    pub reserved_2: [u8; 32],
+    pub mac: [u8; 32],
+    pub tee_tcb_info: [u8; 239],
+    pub reserved_3: [u8; 17],
+    /// The attributes of the TD.
+    pub td_attributes: u64,
+    /// The extended features available mask of the TD.
+    pub xfam: u64,
+    /// The measurement of the initial contents of the TD.
+    pub mr_td: [u8; 48],
+    /// Software-defined ID of the TD configuration.
+    pub mr_config_id: [u8; 48],
+    /// Software-defined ID of the TD owner.
+    pub mr_owner: [u8; 48],
+    /// Software-defined ID of the owner-defined TD configuration.
+    pub mr_owner_config: [u8; 48],
+    /// The runtime-extendable measurement registers.
+    pub rtmrs: [[u8; 48]; 4],
+    pub servtd_hash: [u8; 48],
+    pub reserved_4: [u8; 64],
+}
+
+const _: () = assert!(size_of::<TdReport>() == 1024);
+
+/// Parses a TD report, or returns `None` if `report` isn't a TD report.
+pub fn parse_td_report(report: &[u8]) -> Option<TdReport> {
+    if report.first() != Some(&TD_REPORT_TYPE_TDX) {
+        return None;
+    }
+    TdReport::read_from(report)
+}
+
+/// Checks that the quote reports the same TD and report data as the TD
+/// report.
+pub fn verify_quote_matches_td_report(
+    quote: &TdxQuote<'_>,
+    td_report: &TdReport,
+) -> anyhow::Result<()> {
+    let body = &quote.body;
+    anyhow::ensure!(
+        body.report_data == td_report.report_data,
+        "the report data in the quote doesn't match the TD report"
+    );
+    anyhow::ensure!(
+        body.td_attributes == td_report.td_attributes
+            && body.xfam == td_report.xfam
+            && body.mr_td == td_report.mr_td
+            && body.mr_config_id == td_report.mr_config_id
+            && body.mr_owner == td_report.mr_owner
+            && body.mr_owner_config == td_report.mr_owner_config
+            && body.rtmrs == td_report.rtmrs,
+        "the TD measurements in the quote don't match the TD report"
+    );
+    Ok(())
+}
+
+/// A parsed TDX quote.
+///
+/// Parsing does not imply any verification. Use [`verify_quote`] to check the
+/// signatures and the PCK certificate chain.
+pub struct TdxQuote<'a> {
+    pub header: QuoteHeader,
+    pub body: TdQuoteBody,
+    /// The ECDSA P-256 public key of the attestation key that signs the quote.
+    attestation_key: &'a [u8],
+    /// The signature over the serialized header and body.
+    signature: &'a [u8],
+    /// The serialized report of the Quoting Enclave, which binds the
+    /// attestation key.
+    qe_report: &'a [u8],
+    /// The signature over the QE report by the PCK.
+    qe_report_signature: &'a [u8],
+    /// Additional data that is bound by the QE report.
+    qe_authentication_data: &'a [u8],
+    /// The PEM-encoded PCK certificate chain, starting with the PCK
+    /// certificate and ending with the root CA certificate.
+    pck_cert_chain: &'a [u8],
+    /// The serialized header and body covered by `signature`.
+    signed_data: &'a [u8],
+}
+
+impl TdxQuote<'_> {
+    /// Whether the TD was launched in debug mode.
+    pub fn is_debug(&self) -> bool {
+        self.body.td_attributes & TD_ATTRIBUTES_DEBUG != 0
+    }
+}
+
+/// Sequential reader of the little-endian encoded quote structure.
+struct QuoteReader<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> QuoteReader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        anyhow::ensure!(self.remaining.len() >= len, "TDX quote is truncated");
+        let (head, tail) = self.remaining.split_at(len);
+        self.remaining = tail;
+        Ok(head)
+    }
+
+    fn read<T: FromBytes>(&mut self) -> anyhow::Result<T> {
+        T::read_from(self.take(size_of::<T>())?).context("couldn't read TDX quote structure")
+    }
+
+    fn read_u16(&mut self) -> anyhow::Result<u16> {
+        self.read::<u16>()
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        self.read::<u32>()
+    }
+}
+
+/// Parses a serialized TDX quote without verifying it.
+pub fn parse_quote(quote: &[u8]) -> anyhow::Result<TdxQuote<'_>> {
+    let mut reader = QuoteReader { remaining: quote };
+    let header: QuoteHeader = reader.read()?;
+    anyhow::ensure!(
+        header.version == QUOTE_VERSION,
+        "unsupported TDX quote version: {}",
+        header.version
+    );
+    anyhow::ensure!(
+        header.attestation_key_type == ATTESTATION_KEY_TYPE_ECDSA_P256,
+        "unsupported attestation key type: {}",
+        header.attestation_key_type
+    );
+    anyhow::ensure!(
+        header.tee_type == TEE_TYPE_TDX,
+        "unsupported TEE type: {:#x}",
+        header.tee_type
+    );
+    let body: TdQuoteBody = reader.read()?;
+    let signed_data = &quote[..size_of::<QuoteHeader>() + size_of::<TdQuoteBody>()];
+
+    let signature_data_len = reader.read_u32()? as usize;
+    let mut reader = QuoteReader { remaining: reader.take(signature_data_len)? };
+    let signature = reader.take(64)?;
+    let attestation_key = reader.take(64)?;
+
+    let certification_data_type = reader.read_u16()?;
+    anyhow::ensure!(
+        certification_data_type == CERTIFICATION_DATA_TYPE_QE_REPORT,
+        "unsupported certification data type: {}",
+        certification_data_type
+    );
+    let certification_data_len = reader.read_u32()? as usize;
+    let mut reader = QuoteReader { remaining: reader.take(certification_data_len)? };
+    let qe_report = reader.take(size_of::<EnclaveReportBody>())?;
+    let qe_report_signature = reader.take(64)?;
+    let qe_authentication_data_len = reader.read_u16()? as usize;
+    let qe_authentication_data = reader.take(qe_authentication_data_len)?;
+
+    let qe_certification_data_type = reader.read_u16()?;
+    anyhow::ensure!(
+        qe_certification_data_type == CERTIFICATION_DATA_TYPE_PCK_CERT_CHAIN,
+        "unsupported QE certification data type: {}",
+        qe_certification_data_type
+    );
+    let qe_certification_data_len = reader.read_u32()? as usize;
+    let pck_cert_chain = reader.take(qe_certification_data_len)?;
+
+    Ok(TdxQuote {
+        header,
+        body,
+        attestation_key,
+        signature,
+        qe_report,
+        qe_report_signature,
+        qe_authentication_data,
+        pck_cert_chain,
+        signed_data,
+    })
+}
+
+/// Parses and verifies a TDX quote, and returns the parsed quote.
+///
+/// Verifies that the PCK certificate chain included in the quote is rooted in
+/// the Intel SGX Root CA and that none of its certificates are revoked, that
+/// the PCK signs the report of the Quoting Enclave, that the QE report binds
+/// the attestation key, and that the attestation key signs the quote. The
+/// Quoting Enclave must match the QE identity, and the TCB of the platform
+/// and of the TDX module must be up to date according to the TCB info. Both
+/// documents must be signed by the Intel TCB signing key.
+pub fn verify_quote<'a>(
+    now_utc_millis: i64,
+    quote: &'a [u8],
+    collateral: &IntelTdxCollateral,
+) -> anyhow::Result<TdxQuote<'a>> {
+    let root = Certificate::from_pem(INTEL_SGX_ROOT_CA_CERT_PEM)
+        .map_err(|_err| anyhow::anyhow!("could not parse Intel SGX Root CA cert"))?;
+    verify_quote_with_root(now_utc_millis, quote, collateral, &root)
+}
+
+pub(crate) fn verify_quote_with_root<'a>(
+    now_utc_millis: i64,
+    quote: &'a [u8],
+    collateral: &IntelTdxCollateral,
+    root: &Certificate,
+) -> anyhow::Result<TdxQuote<'a>> {
+    let quote = parse_quote(quote)?;
+    anyhow::ensure!(
+        quote.header.qe_vendor_id == INTEL_QE_VENDOR_ID,
+        "the quote was not generated by the Intel Quoting Enclave"
+    );
+
+    verify_cert_validity(now_utc_millis, root).context("verifying root cert validity")?;
+    verify_ca_constraints(root, 1).context("verifying root cert constraints")?;
+    let root_ca_crl = CertificateList::from_der(&collateral.root_ca_crl)
+        .map_err(|_err| anyhow::anyhow!("could not parse root CA CRL"))?;
+    verify_crl(now_utc_millis, root, &root_ca_crl).context("verifying root CA CRL")?;
+    let pck_crl = CertificateList::from_der(&collateral.pck_crl)
+        .map_err(|_err| anyhow::anyhow!("could not parse PCK CRL"))?;
+
+    let pck =
+        verify_pck_cert_chain(now_utc_millis, quote.pck_cert_chain, root, &root_ca_crl, &pck_crl)
+            .context("verifying PCK certificate chain")?;
+
+    // Ensure that the QE report is signed by the PCK.
+    verify_raw_signature(&p256_verifying_key(&pck)?, quote.qe_report, quote.qe_report_signature)
+        .context("verifying QE report signature")?;
+
+    // Ensure that the QE report binds the attestation key.
+    let qe_report =
+        EnclaveReportBody::read_from(quote.qe_report).context("couldn't read QE report")?;
+    let expected = hash_sha2_256(&[quote.attestation_key, quote.qe_authentication_data].concat());
+    let (bound, padding) = qe_report.report_data.split_at(expected.len());
+    anyhow::ensure!(
+        bound == expected && padding.iter().all(|&byte| byte == 0),
+        "the attestation key is not bound to the QE report"
+    );
+
+    // Ensure that the quote is signed by the attestation key.
+    let attestation_key = VerifyingKey::from_sec1_bytes(&[&[0x04], quote.attestation_key].concat())
+        .map_err(|_err| anyhow::anyhow!("could not parse attestation key"))?;
+    verify_raw_signature(&attestation_key, quote.signed_data, quote.signature)
+        .context("verifying quote signature")?;
+
+    // Ensure that the Quoting Enclave and the TCB are trusted by Intel.
+    let tcb_signing_key = verify_tcb_signing_cert_chain(
+        now_utc_millis,
+        &collateral.tcb_signing_cert_chain,
+        root,
+        &root_ca_crl,
+    )
+    .context("verifying TCB signing certificate chain")?;
+    collateral::verify_qe_identity(
+        now_utc_millis,
+        &collateral.qe_identity,
+        &tcb_signing_key,
+        &qe_report,
+    )
+    .context("verifying QE identity")?;
+    let pck_extensions = collateral::PckExtensions::from_cert(&pck)?;
+    collateral::verify_tcb_info(
+        now_utc_millis,
+        &collateral.tcb_info,
+        &tcb_signing_key,
+        &pck_extensions,
+        &quote.body,
+    )
+    .context("verifying TCB info")?;
+
+    Ok(quote)
+}
+
+/// Verifies the PEM-encoded PCK certificate chain up to the given root
+/// certificate, and returns the PCK certificate.
+///
+/// The intermediate CA certificate is checked against the CRL of the root CA,
+/// and the PCK certificate against the CRL of the intermediate CA.
+fn verify_pck_cert_chain(
+    now_utc_millis: i64,
+    pck_cert_chain: &[u8],
+    root: &Certificate,
+    root_ca_crl: &CertificateList,
+    pck_crl: &CertificateList,
+) -> anyhow::Result<Certificate> {
+    let chain = load_pem_chain(pck_cert_chain)?;
+    anyhow::ensure!(
+        chain.len() == PCK_CERT_CHAIN_LENGTH,
+        "unexpected PCK certificate chain length: {}",
+        chain.len()
+    );
+    verify_rooted_in(&chain, root)?;
+    let (pck, intermediate) = (&chain[0], &chain[1]);
+
+    verify_cert_validity(now_utc_millis, intermediate)
+        .context("verifying intermediate validity")?;
+    verify_cert_signature(root, intermediate).context("verifying intermediate signature")?;
+    verify_ca_constraints(intermediate, 0).context("verifying intermediate constraints")?;
+    anyhow::ensure!(!is_revoked(root_ca_crl, intermediate), "intermediate CA cert is revoked");
+
+    verify_cert_validity(now_utc_millis, pck).context("verifying PCK validity")?;
+    verify_cert_signature(intermediate, pck).context("verifying PCK signature")?;
+    verify_leaf_constraints(pck).context("verifying PCK constraints")?;
+    verify_crl(now_utc_millis, intermediate, pck_crl).context("verifying PCK CRL")?;
+    anyhow::ensure!(!is_revoked(pck_crl, pck), "PCK cert is revoked");
+
+    Ok(pck.clone())
+}
+
+/// Verifies the PEM-encoded TCB signing certificate chain up to the given root
+/// certificate, and returns the TCB signing key.
+fn verify_tcb_signing_cert_chain(
+    now_utc_millis: i64,
+    tcb_signing_cert_chain: &[u8],
+    root: &Certificate,
+    root_ca_crl: &CertificateList,
+) -> anyhow::Result<VerifyingKey> {
+    let chain = load_pem_chain(tcb_signing_cert_chain)?;
+    anyhow::ensure!(
+        chain.len() == 2,
+        "unexpected TCB signing certificate chain length: {}",
+        chain.len()
+    );
+    verify_rooted_in(&chain, root)?;
+    let tcb_signing_cert = &chain[0];
+
+    verify_cert_validity(now_utc_millis, tcb_signing_cert).context("verifying cert validity")?;
+    verify_cert_signature(root, tcb_signing_cert).context("verifying cert signature")?;
+    verify_leaf_constraints(tcb_signing_cert).context("verifying cert constraints")?;
+    anyhow::ensure!(!is_revoked(root_ca_crl, tcb_signing_cert), "TCB signing cert is revoked");
+
+    p256_verifying_key(tcb_signing_cert)
+}
+
+/// Parses a PEM-encoded certificate chain.
+fn load_pem_chain(pem_chain: &[u8]) -> anyhow::Result<Vec<Certificate>> {
+    // The chain is usually terminated with a null byte.
+    let end = pem_chain.iter().rposition(|&byte| byte != 0).map_or(0, |index| index + 1);
+    Certificate::load_pem_chain(&pem_chain[..end])
+        .map_err(|_err| anyhow::anyhow!("could not parse certificate chain"))
+}
+
+/// Verifies that the last certificate of the chain is the trusted root.
+fn verify_rooted_in(chain: &[Certificate], root: &Certificate) -> anyhow::Result<()> {
+    // The chain carries its own copy of the root certificate, which must match the
+    // trusted root exactly.
+    let last = chain.last().context("empty certificate chain")?;
+    anyhow::ensure!(
+        last.to_der().map_err(|_err| anyhow::anyhow!("could not encode root cert"))?
+            == root.to_der().map_err(|_err| anyhow::anyhow!("could not encode root cert"))?,
+        "certificate chain is not rooted in the trusted root CA"
+    );
+    Ok(())
+}
+
+/// Verifies that the certificate belongs to a CA which may issue certificates
+/// to at least `intermediate_count` further CAs below it.
+fn verify_ca_constraints(cert: &Certificate, intermediate_count: u8) -> anyhow::Result<()> {
+    let constraints = basic_constraints(cert)?.context("missing basic constraints")?;
+    anyhow::ensure!(constraints.ca, "not a CA certificate");
+    if let Some(path_len) = constraints.path_len_constraint {
+        anyhow::ensure!(
+            intermediate_count <= path_len,
+            "path length constraint exceeded: {}",
+            path_len
+        );
+    }
+    Ok(())
+}
+
+/// Verifies that the certificate does not belong to a CA.
+fn verify_leaf_constraints(cert: &Certificate) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !basic_constraints(cert)?.is_some_and(|constraints| constraints.ca),
+        "unexpected CA certificate"
+    );
+    Ok(())
+}
+
+fn basic_constraints(cert: &Certificate) -> anyhow::Result<Option<BasicConstraints>> {
+    let constraints = cert
+        .tbs_certificate
+        .get::<BasicConstraints>()
+        .map_err(|_err| anyhow::anyhow!("could not parse basic constraints"))?;
+    Ok(constraints.map(|(_critical, constraints)| constraints))
+}
+
+/// Verifies that the CRL is issued and signed by the given CA, and that it is
+/// current.
+fn verify_crl(
+    now_utc_millis: i64,
+    issuer: &Certificate,
+    crl: &CertificateList,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        crl.tbs_cert_list.issuer == issuer.tbs_certificate.subject,
+        "CRL was not issued by the expected CA"
+    );
+    anyhow::ensure!(
+        crl.signature_algorithm.oid == ECDSA_WITH_SHA_256_OID,
+        "unsupported signature algorithm: {:?}",
+        crl.signature_algorithm
+    );
+    let message = crl
+        .tbs_cert_list
+        .to_der()
+        .map_err(|_err| anyhow::anyhow!("could not extract message to verify ECDSA signature"))?;
+    let signature = Signature::from_der(crl.signature.raw_bytes())
+        .map_err(|_err| anyhow::anyhow!("could not extract ECDSA signature"))?;
+    p256_verifying_key(issuer)?
+        .verify(&message, &signature)
+        .map_err(|_err| anyhow::anyhow!("CRL signature verification failed"))?;
+    verify_time_window(now_utc_millis, crl.tbs_cert_list.this_update, crl.tbs_cert_list.next_update)
+        .context("verifying CRL validity")
+}
+
+/// Verifies the ECDSA P-256 SHA-256 signature of a certificate.
+pub fn verify_cert_signature(signer: &Certificate, signee: &Certificate) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        signee.signature_algorithm.oid == ECDSA_WITH_SHA_256_OID,
+        "unsupported signature algorithm: {:?}",
+        signee.signature_algorithm
+    );
+
+    let verifying_key = p256_verifying_key(signer)?;
+    let message = signee
+        .tbs_certificate
+        .to_der()
+        .map_err(|_err| anyhow::anyhow!("could not extract message to verify ECDSA signature"))?;
+    let signature = Signature::from_der(signee.signature.raw_bytes())
+        .map_err(|_err| anyhow::anyhow!("could not extract ECDSA signature"))?;
+
+    verifying_key
+        .verify(&message, &signature)
+        .map_err(|_err| anyhow::anyhow!("signature verification failed"))
+}
+
+fn p256_verifying_key(cert: &Certificate) -> anyhow::Result<VerifyingKey> {
+    let pubkey_info = cert.tbs_certificate.subject_public_key_info.owned_to_ref();
+    VerifyingKey::from_sec1_bytes(pubkey_info.subject_public_key.raw_bytes())
+        .map_err(|_err| anyhow::anyhow!("could not extract ECDSA P-256 public key"))
+}
+
+/// Verifies a signature that is encoded as the concatenation of the big-endian
+/// `r` and `s` values.
+fn verify_raw_signature(
+    verifying_key: &VerifyingKey,
+    message: &[u8],
+    signature: &[u8],
+) -> anyhow::Result<()> {
+    let signature = Signature::from_slice(signature)
+        .map_err(|_err| anyhow::anyhow!("could not extract ECDSA P-256 signature"))?;
+    verifying_key
+        .verify(message, &signature)
+        .map_err(|_err| anyhow::anyhow!("failed to verify ECDSA P-256 signature"))
+}
+
+/// Verifies that each component of the TEE TCB SVN is at least the
+/// corresponding component of the minimum TEE TCB SVN.
+pub fn verify_tee_tcb_svn(tee_tcb_svn: &[u8], min_tee_tcb_svn: &[u8]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        tee_tcb_svn.len() == TEE_TCB_SVN_SIZE,
+        "invalid TEE TCB SVN length: {}",
+        tee_tcb_svn.len()
+    );
+    anyhow::ensure!(
+        min_tee_tcb_svn.len() == TEE_TCB_SVN_SIZE,
+        "invalid minimum TEE TCB SVN length: {}",
+        min_tee_tcb_svn.len()
+    );
+    for (index, (actual, min)) in tee_tcb_svn.iter().zip(min_tee_tcb_svn).enumerate() {
+        anyhow::ensure!(
+            actual >= min,
+            "unsupported version of component {} in the TEE TCB SVN: {}",
+            index,
+            actual
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
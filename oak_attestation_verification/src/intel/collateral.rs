@@ -0,0 +1,476 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Verification of the TDX TCB info and the TDX Quoting Enclave identity
+//! published by the Intel Provisioning Certification Service.
+//!
+//! The format of both documents is described in
+//! <https://api.portal.trustedservices.intel.com/content/documentation.html>.
+
+use alloc::{format, string::String, vec::Vec};
+
+use anyhow::Context;
+use p256::ecdsa::VerifyingKey;
+use serde::Deserialize;
+use time::OffsetDateTime;
+use x509_cert::{
+    der::{
+        asn1::{Any, ObjectIdentifier},
+        Decode, Reader, SliceReader, Tag, Tagged,
+    },
+    Certificate,
+};
+
+use super::{verify_raw_signature, EnclaveReportBody, TdQuoteBody};
+use crate::util::UnixTimestampMillis;
+
+/// The Intel SGX extensions of the PCK certificate.
+const SGX_EXTENSIONS_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1");
+const SGX_TCB_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.2");
+const SGX_PCE_ID_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.3");
+const SGX_FMSPC_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.4");
+
+/// The last arc of the PCE SVN in the SGX TCB extension. The arcs 1 to 16 are
+/// the SGX TCB components.
+const SGX_TCB_PCE_SVN_ARC: u32 = 17;
+
+/// The number of SGX TCB components in the PCK certificate.
+const SGX_TCB_COMPONENTS_SIZE: usize = 16;
+
+const TCB_INFO_ID: &str = "TDX";
+const TCB_INFO_VERSION: u32 = 3;
+const QE_IDENTITY_ID: &str = "TD_QE";
+const QE_IDENTITY_VERSION: u32 = 2;
+
+/// The only TCB status that is accepted. Any other status, e.g.
+/// `SWHardeningNeeded` or `OutOfDate`, indicates that the platform is
+/// affected by known vulnerabilities.
+const TCB_STATUS_UP_TO_DATE: &str = "UpToDate";
+
+/// The values of the Intel SGX extensions of a PCK certificate which are
+/// needed to find the TCB level of the platform.
+pub(super) struct PckExtensions {
+    pub sgx_tcb_components: [u8; SGX_TCB_COMPONENTS_SIZE],
+    pub pce_svn: u16,
+    pub pce_id: [u8; 2],
+    pub fmspc: [u8; 6],
+}
+
+impl PckExtensions {
+    pub(super) fn from_cert(pck: &Certificate) -> anyhow::Result<Self> {
+        let extension = pck
+            .tbs_certificate
+            .extensions
+            .iter()
+            .flatten()
+            .find(|extension| extension.extn_id == SGX_EXTENSIONS_OID)
+            .context("no SGX extensions in the PCK cert")?;
+
+        let mut tcb = None;
+        let mut pce_id = None;
+        let mut fmspc = None;
+        let extensions = Any::from_der(extension.extn_value.as_bytes())
+            .map_err(|_err| anyhow::anyhow!("could not parse SGX extensions"))?;
+        for (oid, value) in parse_oid_value_sequence(&extensions)? {
+            if oid == SGX_TCB_OID {
+                tcb = Some(parse_sgx_tcb(&value)?);
+            } else if oid == SGX_PCE_ID_OID {
+                pce_id = Some(octet_string(&value)?);
+            } else if oid == SGX_FMSPC_OID {
+                fmspc = Some(octet_string(&value)?);
+            }
+        }
+        let (sgx_tcb_components, pce_svn) = tcb.context("no SGX TCB in the PCK cert")?;
+        Ok(Self {
+            sgx_tcb_components,
+            pce_svn,
+            pce_id: pce_id.context("no PCE ID in the PCK cert")?,
+            fmspc: fmspc.context("no FMSPC in the PCK cert")?,
+        })
+    }
+}
+
+/// Parses the SGX TCB components and the PCE SVN from the SGX TCB extension.
+fn parse_sgx_tcb(value: &Any) -> anyhow::Result<([u8; SGX_TCB_COMPONENTS_SIZE], u16)> {
+    let mut components = [None; SGX_TCB_COMPONENTS_SIZE];
+    let mut pce_svn = None;
+    for (oid, value) in parse_oid_value_sequence(value)? {
+        if oid.parent() != Some(SGX_TCB_OID) {
+            continue;
+        }
+        match oid.arcs().last() {
+            Some(arc @ 1..=16) => {
+                components[arc as usize - 1] = Some(
+                    value
+                        .decode_as::<u8>()
+                        .map_err(|_err| anyhow::anyhow!("invalid SGX TCB component"))?,
+                );
+            }
+            Some(SGX_TCB_PCE_SVN_ARC) => {
+                pce_svn = Some(
+                    value.decode_as::<u16>().map_err(|_err| anyhow::anyhow!("invalid PCE SVN"))?,
+                );
+            }
+            _ => {}
+        }
+    }
+    let mut sgx_tcb_components = [0u8; SGX_TCB_COMPONENTS_SIZE];
+    for (component, value) in sgx_tcb_components.iter_mut().zip(components) {
+        *component = value.context("missing SGX TCB component in the PCK cert")?;
+    }
+    Ok((sgx_tcb_components, pce_svn.context("no PCE SVN in the PCK cert")?))
+}
+
+/// Parses a `SEQUENCE OF SEQUENCE { OBJECT IDENTIFIER, ANY }`, which is how
+/// the SGX extensions are structured.
+fn parse_oid_value_sequence(sequence: &Any) -> anyhow::Result<Vec<(ObjectIdentifier, Any)>> {
+    anyhow::ensure!(sequence.tag() == Tag::Sequence, "invalid SGX extension");
+    let mut entries = SliceReader::new(sequence.value())
+        .map_err(|_err| anyhow::anyhow!("invalid SGX extension"))?;
+    let mut result = Vec::new();
+    while !entries.is_finished() {
+        let entry =
+            Any::decode(&mut entries).map_err(|_err| anyhow::anyhow!("invalid SGX extension"))?;
+        anyhow::ensure!(entry.tag() == Tag::Sequence, "invalid SGX extension entry");
+        let mut reader = SliceReader::new(entry.value())
+            .map_err(|_err| anyhow::anyhow!("invalid SGX extension entry"))?;
+        let oid = ObjectIdentifier::decode(&mut reader)
+            .map_err(|_err| anyhow::anyhow!("invalid SGX extension OID"))?;
+        let value = Any::decode(&mut reader)
+            .map_err(|_err| anyhow::anyhow!("invalid SGX extension value"))?;
+        result.push(
+            reader.finish((oid, value)).map_err(|_err| anyhow::anyhow!("invalid SGX extension"))?,
+        );
+    }
+    Ok(result)
+}
+
+fn octet_string<const N: usize>(value: &Any) -> anyhow::Result<[u8; N]> {
+    anyhow::ensure!(value.tag() == Tag::OctetString, "invalid SGX extension value");
+    value.value().try_into().map_err(|_err| anyhow::anyhow!("invalid SGX extension value length"))
+}
+
+/// The TDX TCB info document, version 3.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbInfo {
+    id: String,
+    version: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    issue_date: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    next_update: OffsetDateTime,
+    fmspc: String,
+    pce_id: String,
+    tdx_module: TdxModule,
+    #[serde(default)]
+    tdx_module_identities: Vec<TdxModuleIdentity>,
+    tcb_levels: Vec<TcbLevel>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModule {
+    mrsigner: String,
+    attributes: String,
+    attributes_mask: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModuleIdentity {
+    id: String,
+    mrsigner: String,
+    attributes: String,
+    attributes_mask: String,
+    tcb_levels: Vec<IsvSvnTcbLevel>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbLevel {
+    tcb: Tcb,
+    tcb_status: String,
+}
+
+#[derive(Deserialize)]
+struct Tcb {
+    sgxtcbcomponents: Vec<TcbComponent>,
+    pcesvn: u16,
+    tdxtcbcomponents: Vec<TcbComponent>,
+}
+
+#[derive(Deserialize)]
+struct TcbComponent {
+    svn: u8,
+}
+
+/// A TCB level of an enclave or of a TDX module, which only depends on its
+/// ISV SVN.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IsvSvnTcbLevel {
+    tcb: IsvSvnTcb,
+    tcb_status: String,
+}
+
+#[derive(Deserialize)]
+struct IsvSvnTcb {
+    isvsvn: u16,
+}
+
+/// The identity of the TDX Quoting Enclave, version 2.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnclaveIdentity {
+    id: String,
+    version: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    issue_date: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    next_update: OffsetDateTime,
+    miscselect: String,
+    miscselect_mask: String,
+    attributes: String,
+    attributes_mask: String,
+    mrsigner: String,
+    isvprodid: u16,
+    tcb_levels: Vec<IsvSvnTcbLevel>,
+}
+
+/// Verifies the signed QE identity document and checks that the report of the
+/// Quoting Enclave matches the identity and has an up-to-date TCB level.
+pub(super) fn verify_qe_identity(
+    now_utc_millis: i64,
+    signed_qe_identity: &[u8],
+    tcb_signing_key: &VerifyingKey,
+    qe_report: &EnclaveReportBody,
+) -> anyhow::Result<()> {
+    let identity: EnclaveIdentity =
+        parse_signed_json(signed_qe_identity, "enclaveIdentity", tcb_signing_key)?;
+    anyhow::ensure!(
+        identity.id == QE_IDENTITY_ID && identity.version == QE_IDENTITY_VERSION,
+        "unsupported QE identity: {} version {}",
+        identity.id,
+        identity.version
+    );
+    verify_issue_window(now_utc_millis, identity.issue_date, identity.next_update)?;
+
+    anyhow::ensure!(
+        qe_report.mr_signer == decode_hex::<32>(&identity.mrsigner)?,
+        "unexpected QE MRSIGNER"
+    );
+    anyhow::ensure!(
+        qe_report.isv_prod_id == identity.isvprodid,
+        "unexpected QE ISV product ID: {}",
+        qe_report.isv_prod_id
+    );
+    anyhow::ensure!(
+        masked_eq(
+            &qe_report.misc_select.to_be_bytes(),
+            &decode_hex::<4>(&identity.miscselect)?,
+            &decode_hex::<4>(&identity.miscselect_mask)?,
+        ),
+        "unexpected QE MISCSELECT: {:#x}",
+        qe_report.misc_select
+    );
+    anyhow::ensure!(
+        masked_eq(
+            &qe_report.attributes,
+            &decode_hex::<16>(&identity.attributes)?,
+            &decode_hex::<16>(&identity.attributes_mask)?,
+        ),
+        "unexpected QE attributes"
+    );
+
+    let level = identity
+        .tcb_levels
+        .iter()
+        .find(|level| qe_report.isv_svn >= level.tcb.isvsvn)
+        .context("unsupported QE ISV SVN")?;
+    verify_tcb_status("QE", &level.tcb_status)
+}
+
+/// Verifies the signed TCB info document and checks that the TCB level of the
+/// platform and of the TDX module is up to date.
+pub(super) fn verify_tcb_info(
+    now_utc_millis: i64,
+    signed_tcb_info: &[u8],
+    tcb_signing_key: &VerifyingKey,
+    pck_extensions: &PckExtensions,
+    body: &TdQuoteBody,
+) -> anyhow::Result<()> {
+    let tcb_info: TcbInfo = parse_signed_json(signed_tcb_info, "tcbInfo", tcb_signing_key)?;
+    anyhow::ensure!(
+        tcb_info.id == TCB_INFO_ID && tcb_info.version == TCB_INFO_VERSION,
+        "unsupported TCB info: {} version {}",
+        tcb_info.id,
+        tcb_info.version
+    );
+    verify_issue_window(now_utc_millis, tcb_info.issue_date, tcb_info.next_update)?;
+    anyhow::ensure!(
+        pck_extensions.fmspc == decode_hex::<6>(&tcb_info.fmspc)?,
+        "TCB info does not match the FMSPC of the PCK cert"
+    );
+    anyhow::ensure!(
+        pck_extensions.pce_id == decode_hex::<2>(&tcb_info.pce_id)?,
+        "TCB info does not match the PCE ID of the PCK cert"
+    );
+
+    // The second component of the TEE TCB SVN is the major version of the TDX
+    // module. Modules from version 1 onwards have their own TCB levels, and the
+    // first two components are not compared against the TCB levels of the
+    // platform.
+    let tdx_module_version = body.tee_tcb_svn[1];
+    let tee_tcb_svn_start = if tdx_module_version == 0 {
+        verify_tdx_module(
+            body,
+            &tcb_info.tdx_module.mrsigner,
+            &tcb_info.tdx_module.attributes,
+            &tcb_info.tdx_module.attributes_mask,
+        )?;
+        0
+    } else {
+        let id = format!("TDX_{:02X}", tdx_module_version);
+        let identity = tcb_info
+            .tdx_module_identities
+            .iter()
+            .find(|identity| identity.id == id)
+            .with_context(|| format!("no TDX module identity for {}", id))?;
+        verify_tdx_module(
+            body,
+            &identity.mrsigner,
+            &identity.attributes,
+            &identity.attributes_mask,
+        )?;
+        let level = identity
+            .tcb_levels
+            .iter()
+            .find(|level| u16::from(body.tee_tcb_svn[0]) >= level.tcb.isvsvn)
+            .context("unsupported TDX module ISV SVN")?;
+        verify_tcb_status("TDX module", &level.tcb_status)?;
+        2
+    };
+
+    let level = tcb_info
+        .tcb_levels
+        .iter()
+        .find(|level| {
+            svns_at_least(&pck_extensions.sgx_tcb_components, &level.tcb.sgxtcbcomponents, 0)
+                && pck_extensions.pce_svn >= level.tcb.pcesvn
+                && svns_at_least(&body.tee_tcb_svn, &level.tcb.tdxtcbcomponents, tee_tcb_svn_start)
+        })
+        .context("unsupported platform TCB")?;
+    verify_tcb_status("platform", &level.tcb_status)
+}
+
+/// Verifies that the TDX module is signed by the expected signer and has the
+/// expected attributes.
+fn verify_tdx_module(
+    body: &TdQuoteBody,
+    mrsigner: &str,
+    attributes: &str,
+    attributes_mask: &str,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        body.mr_signer_seam == decode_hex::<48>(mrsigner)?,
+        "unexpected TDX module MRSIGNER"
+    );
+    anyhow::ensure!(
+        masked_eq(
+            &body.seam_attributes.to_le_bytes(),
+            &decode_hex::<8>(attributes)?,
+            &decode_hex::<8>(attributes_mask)?,
+        ),
+        "unexpected TDX module attributes: {:#x}",
+        body.seam_attributes
+    );
+    Ok(())
+}
+
+/// Whether each SVN from index `start` onwards is at least the SVN of the
+/// corresponding TCB component.
+fn svns_at_least(svns: &[u8], components: &[TcbComponent], start: usize) -> bool {
+    components.len() == svns.len()
+        && svns.iter().zip(components).skip(start).all(|(svn, component)| *svn >= component.svn)
+}
+
+fn verify_tcb_status(component: &str, tcb_status: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        tcb_status == TCB_STATUS_UP_TO_DATE,
+        "unsupported {} TCB status: {}",
+        component,
+        tcb_status
+    );
+    Ok(())
+}
+
+fn verify_issue_window(
+    now_utc_millis: i64,
+    issue_date: OffsetDateTime,
+    next_update: OffsetDateTime,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(now_utc_millis >= issue_date.unix_timestamp_millis(), "not yet valid");
+    anyhow::ensure!(now_utc_millis <= next_update.unix_timestamp_millis(), "expired");
+    Ok(())
+}
+
+/// Parses a signed document of the form `{"<field>":{...},"signature":"..."}`.
+///
+/// The signature is a hex-encoded ECDSA P-256 signature over the exact bytes
+/// of the value of `field`, so the value is parsed from the same bytes that
+/// are verified.
+fn parse_signed_json<'a, T: Deserialize<'a>>(
+    document: &'a [u8],
+    field: &str,
+    signing_key: &VerifyingKey,
+) -> anyhow::Result<T> {
+    const SIGNATURE_PREFIX: &[u8] = b",\"signature\":\"";
+    const SIGNATURE_SUFFIX: &[u8] = b"\"}";
+
+    let end = document.iter().rposition(|byte| !byte.is_ascii_whitespace()).map_or(0, |i| i + 1);
+    let prefix = format!("{{\"{}\":", field);
+    let rest = document[..end]
+        .strip_prefix(prefix.as_bytes())
+        .and_then(|rest| rest.strip_suffix(SIGNATURE_SUFFIX))
+        .with_context(|| format!("invalid signed {} document", field))?;
+    let split = rest
+        .windows(SIGNATURE_PREFIX.len())
+        .rposition(|window| window == SIGNATURE_PREFIX)
+        .with_context(|| format!("no signature in the {} document", field))?;
+    let (signed, signature) = rest.split_at(split);
+    let signature = hex::decode(&signature[SIGNATURE_PREFIX.len()..])
+        .map_err(|_err| anyhow::anyhow!("invalid {} signature encoding", field))?;
+    verify_raw_signature(signing_key, signed, &signature)
+        .with_context(|| format!("verifying {} signature", field))?;
+    serde_json::from_slice(signed)
+        .map_err(|err| anyhow::anyhow!("could not parse {}: {}", field, err))
+}
+
+fn decode_hex<const N: usize>(value: &str) -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(value, &mut bytes)
+        .map_err(|_err| anyhow::anyhow!("invalid hex value: {}", value))?;
+    Ok(bytes)
+}
+
+fn masked_eq(actual: &[u8], expected: &[u8], mask: &[u8]) -> bool {
+    actual
+        .iter()
+        .zip(expected)
+        .zip(mask)
+        .all(|((actual, expected), mask)| actual & mask == expected & mask)
+}
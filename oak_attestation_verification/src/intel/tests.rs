@@ -0,0 +1,290 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(test)]
+extern crate std;
+
+use std::{format, vec::Vec};
+
+use oak_proto_rust::oak::attestation::v1::IntelTdxCollateral;
+use x509_cert::{der::DecodePem, Certificate};
+
+use crate::intel::{
+    collateral::PckExtensions, load_pem_chain, parse_quote, verify_ca_constraints,
+    verify_cert_signature, verify_leaf_constraints, verify_quote, verify_quote_with_root,
+    verify_tee_tcb_svn, INTEL_SGX_ROOT_CA_CERT_PEM, TEE_TCB_SVN_SIZE,
+};
+
+// A synthetic TDX quote which is signed by a test PCK certificate chain rooted
+// in `TEST_ROOT_CA_CERT_PEM`. The PCK certificate is valid from 2024-01-01
+// until 2034-01-01. The quote was generated by a TDX module with major version
+// 1 and ISV SVN 3.
+const TDX_QUOTE: &[u8] = include_bytes!("../../testdata/tdx_quote.bin");
+const TEST_ROOT_CA_CERT_PEM: &str = include_str!("../../testdata/tdx_test_root_ca.pem");
+
+// Collateral for the synthetic quote, signed by the test CAs. The CRLs, the TCB
+// info and the QE identity are valid from 2024-12-01 until 2025-02-01. The
+// platform TCB is up to date according to `TCB_INFO` and out of date according
+// to `TCB_INFO_OUT_OF_DATE`. `PCK_CRL_REVOKED_PCK` revokes the PCK certificate.
+const ROOT_CA_CRL: &[u8] = include_bytes!("../../testdata/tdx_root_ca_crl.der");
+const PCK_CRL: &[u8] = include_bytes!("../../testdata/tdx_pck_crl.der");
+const PCK_CRL_REVOKED_PCK: &[u8] = include_bytes!("../../testdata/tdx_pck_crl_revoked_pck.der");
+const TCB_INFO: &[u8] = include_bytes!("../../testdata/tdx_tcb_info.json");
+const TCB_INFO_OUT_OF_DATE: &[u8] = include_bytes!("../../testdata/tdx_tcb_info_out_of_date.json");
+const QE_IDENTITY: &[u8] = include_bytes!("../../testdata/tdx_qe_identity.json");
+const TCB_SIGNING_CERT_CHAIN: &[u8] = include_bytes!("../../testdata/tdx_tcb_signing_chain.pem");
+
+// 2025-01-01T00:00:00Z
+const NOW_UTC_MILLIS: i64 = 1735689600000;
+// 2025-03-01T00:00:00Z
+const AFTER_COLLATERAL_EXPIRY_UTC_MILLIS: i64 = 1740787200000;
+// 2035-01-01T00:00:00Z
+const AFTER_PCK_EXPIRY_UTC_MILLIS: i64 = 2051222400000;
+// 2023-01-01T00:00:00Z
+const BEFORE_PCK_ISSUANCE_UTC_MILLIS: i64 = 1672531200000;
+
+fn test_root() -> Certificate {
+    Certificate::from_pem(TEST_ROOT_CA_CERT_PEM).expect("could not parse cert")
+}
+
+fn test_collateral() -> IntelTdxCollateral {
+    IntelTdxCollateral {
+        root_ca_crl: ROOT_CA_CRL.to_vec(),
+        pck_crl: PCK_CRL.to_vec(),
+        tcb_info: TCB_INFO.to_vec(),
+        qe_identity: QE_IDENTITY.to_vec(),
+        tcb_signing_cert_chain: TCB_SIGNING_CERT_CHAIN.to_vec(),
+    }
+}
+
+fn test_pck_cert_chain() -> Vec<Certificate> {
+    let quote = parse_quote(TDX_QUOTE).expect("could not parse quote");
+    load_pem_chain(quote.pck_cert_chain).expect("could not parse PCK cert chain")
+}
+
+/// Verifies the synthetic quote and returns the error message, if any.
+fn verify_test_quote(
+    now_utc_millis: i64,
+    quote: &[u8],
+    collateral: &IntelTdxCollateral,
+) -> Result<(), std::string::String> {
+    verify_quote_with_root(now_utc_millis, quote, collateral, &test_root())
+        .map(|_| ())
+        .map_err(|err| format!("{:#}", err))
+}
+
+fn assert_err_contains(result: Result<(), std::string::String>, expected: &str) {
+    let err = result.expect_err("verification unexpectedly succeeded");
+    assert!(err.contains(expected), "unexpected error: {}", err);
+}
+
+#[test]
+fn intel_sgx_root_ca_signs_itself() {
+    let root = Certificate::from_pem(INTEL_SGX_ROOT_CA_CERT_PEM).expect("could not parse cert");
+    assert!(verify_cert_signature(&root, &root).is_ok());
+}
+
+#[test]
+fn parse_quote_succeeds() {
+    let quote = parse_quote(TDX_QUOTE).expect("could not parse quote");
+    // Only SEPT_VE_DISABLE is set in the synthetic quote.
+    assert_eq!(quote.body.td_attributes, 1 << 28);
+    assert!(!quote.is_debug());
+}
+
+#[test]
+fn parse_truncated_quote_fails() {
+    assert!(parse_quote(&TDX_QUOTE[..TDX_QUOTE.len() - 1]).is_err());
+}
+
+#[test]
+fn verify_quote_with_test_root_succeeds() {
+    let result = verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &test_collateral());
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn verify_quote_with_intel_root_fails() {
+    assert!(verify_quote(NOW_UTC_MILLIS, TDX_QUOTE, &test_collateral()).is_err());
+}
+
+#[test]
+fn verify_quote_without_collateral_fails() {
+    assert!(verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &IntelTdxCollateral::default()).is_err());
+}
+
+#[test]
+fn verify_quote_with_expired_pck_fails() {
+    assert!(verify_test_quote(AFTER_PCK_EXPIRY_UTC_MILLIS, TDX_QUOTE, &test_collateral()).is_err());
+}
+
+#[test]
+fn verify_quote_before_pck_issuance_fails() {
+    assert!(
+        verify_test_quote(BEFORE_PCK_ISSUANCE_UTC_MILLIS, TDX_QUOTE, &test_collateral()).is_err()
+    );
+}
+
+#[test]
+fn verify_quote_with_expired_collateral_fails() {
+    assert_err_contains(
+        verify_test_quote(AFTER_COLLATERAL_EXPIRY_UTC_MILLIS, TDX_QUOTE, &test_collateral()),
+        "expired",
+    );
+}
+
+#[test]
+fn verify_quote_with_modified_body_fails() {
+    let mut quote = TDX_QUOTE.to_vec();
+    // Flip a bit in MRTD, which starts at offset 48 + 136 of the quote.
+    quote[48 + 136] ^= 1;
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, &quote, &test_collateral()),
+        "verifying quote signature",
+    );
+}
+
+#[test]
+fn verify_quote_with_other_qe_vendor_fails() {
+    let mut quote = TDX_QUOTE.to_vec();
+    // The QE vendor ID starts at offset 12 of the quote header.
+    quote[12] ^= 1;
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, &quote, &test_collateral()),
+        "Intel Quoting Enclave",
+    );
+}
+
+#[test]
+fn verify_quote_with_revoked_pck_fails() {
+    let collateral =
+        IntelTdxCollateral { pck_crl: PCK_CRL_REVOKED_PCK.to_vec(), ..test_collateral() };
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &collateral),
+        "PCK cert is revoked",
+    );
+}
+
+#[test]
+fn verify_quote_with_swapped_crls_fails() {
+    let collateral = IntelTdxCollateral {
+        root_ca_crl: PCK_CRL.to_vec(),
+        pck_crl: ROOT_CA_CRL.to_vec(),
+        ..test_collateral()
+    };
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &collateral),
+        "not issued by the expected CA",
+    );
+}
+
+#[test]
+fn verify_quote_with_out_of_date_tcb_fails() {
+    let collateral =
+        IntelTdxCollateral { tcb_info: TCB_INFO_OUT_OF_DATE.to_vec(), ..test_collateral() };
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &collateral),
+        "unsupported platform TCB status: OutOfDate",
+    );
+}
+
+#[test]
+fn verify_quote_with_modified_tcb_info_fails() {
+    let tcb_info = std::str::from_utf8(TCB_INFO).expect("invalid TCB info").replacen(
+        "\"OutOfDate\"",
+        "\"UpToDate\"",
+        1,
+    );
+    let collateral = IntelTdxCollateral { tcb_info: tcb_info.into_bytes(), ..test_collateral() };
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &collateral),
+        "verifying tcbInfo signature",
+    );
+}
+
+#[test]
+fn verify_quote_with_swapped_tcb_info_and_qe_identity_fails() {
+    let collateral = IntelTdxCollateral {
+        tcb_info: QE_IDENTITY.to_vec(),
+        qe_identity: TCB_INFO.to_vec(),
+        ..test_collateral()
+    };
+    assert!(verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &collateral).is_err());
+}
+
+#[test]
+fn verify_quote_with_pck_chain_as_tcb_signing_chain_fails() {
+    let quote = parse_quote(TDX_QUOTE).expect("could not parse quote");
+    let collateral = IntelTdxCollateral {
+        tcb_signing_cert_chain: quote.pck_cert_chain.to_vec(),
+        ..test_collateral()
+    };
+    assert_err_contains(
+        verify_test_quote(NOW_UTC_MILLIS, TDX_QUOTE, &collateral),
+        "unexpected TCB signing certificate chain length",
+    );
+}
+
+#[test]
+fn verify_basic_constraints() {
+    let chain = test_pck_cert_chain();
+    let (pck, intermediate, root) = (&chain[0], &chain[1], &chain[2]);
+    assert!(verify_leaf_constraints(pck).is_ok());
+    assert!(verify_ca_constraints(intermediate, 0).is_ok());
+    assert!(verify_ca_constraints(root, 1).is_ok());
+
+    assert!(verify_ca_constraints(pck, 0).is_err());
+    assert!(verify_leaf_constraints(intermediate).is_err());
+    assert!(verify_leaf_constraints(root).is_err());
+}
+
+#[test]
+fn parse_pck_extensions_succeeds() {
+    let chain = test_pck_cert_chain();
+    let extensions = PckExtensions::from_cert(&chain[0]).expect("could not parse extensions");
+    assert_eq!(extensions.sgx_tcb_components, [5, 5, 2, 2, 3, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(extensions.pce_svn, 13);
+    assert_eq!(extensions.pce_id, [0, 0]);
+    assert_eq!(extensions.fmspc, [0x00, 0x80, 0x6f, 0x05, 0x00, 0x00]);
+}
+
+#[test]
+fn parse_pck_extensions_without_extensions_fails() {
+    let chain = test_pck_cert_chain();
+    assert!(PckExtensions::from_cert(&chain[1]).is_err());
+}
+
+#[test]
+fn verify_tee_tcb_svn_succeeds() {
+    let min = [0u8; TEE_TCB_SVN_SIZE];
+    let mut actual = [0u8; TEE_TCB_SVN_SIZE];
+    actual[2] = 5;
+    assert!(verify_tee_tcb_svn(&actual, &min).is_ok());
+    assert!(verify_tee_tcb_svn(&actual, &actual).is_ok());
+}
+
+#[test]
+fn verify_tee_tcb_svn_below_minimum_fails() {
+    let actual = [1u8; TEE_TCB_SVN_SIZE];
+    let mut min = [1u8; TEE_TCB_SVN_SIZE];
+    min[7] = 2;
+    assert!(verify_tee_tcb_svn(&actual, &min).is_err());
+}
+
+#[test]
+fn verify_tee_tcb_svn_with_invalid_length_fails() {
+    assert!(verify_tee_tcb_svn(&[0u8; 4], &[0u8; TEE_TCB_SVN_SIZE]).is_err());
+}
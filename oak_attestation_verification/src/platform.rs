@@ -16,54 +16,45 @@
 
 //! Provides verification based on evidence, endorsements and reference values.
 
-use alloc::format;
+use alloc::{format, string::String, vec::Vec};
 
 use anyhow::Context;
 use oak_proto_rust::oak::attestation::v1::{
     AmdAttestationReport, AmdSevExpectedValues, InsecureExpectedValues, IntelTdxAttestationReport,
-    IntelTdxExpectedValues, RootLayerEvidence, TeePlatform,
+    IntelTdxExpectedValues, RootLayerEndorsements, RootLayerEvidence, TcbVersion, TeePlatform,
 };
 use oak_sev_snp_attestation_report::AttestationReport;
-use x509_cert::{
-    der::{Decode, DecodePem},
-    Certificate,
-};
+use x509_cert::{crl::CertificateList, der::Decode, Certificate};
 use zerocopy::FromBytes;
 
 use crate::{
-    amd::{product_name, verify_attestation_report_signature, verify_cert_signature},
+    amd::{product_line, verify_attestation_report_signature, verify_vcek_cert_chain, RootStore},
+    intel::{verify_quote, verify_tee_tcb_svn, TD_ATTRIBUTES_DEBUG},
     util::hash_sha2_256,
 };
 
-const ASK_MILAN_CERT_PEM: &str = include_str!("../data/ask_milan.pem");
-const ASK_GENOA_CERT_PEM: &str = include_str!("../data/ask_genoa.pem");
-
 /// Verifies the signature chain for the attestation report included in the
 /// root.
+///
+/// For AMD SEV-SNP, the expected values may provide CRLs and roots for
+/// additional product lines that are used when verifying the VCEK certificate
+/// chain. For Intel TDX, the endorsements must provide the collateral that is
+/// needed to verify the quote.
 pub fn verify_root_attestation_signature(
-    _now_utc_millis: i64,
+    now_utc_millis: i64,
     root_layer: &RootLayerEvidence,
-    serialized_certificate: &[u8],
+    endorsements: &RootLayerEndorsements,
+    amd_sev_expected_values: Option<&AmdSevExpectedValues>,
 ) -> anyhow::Result<()> {
     match root_layer.platform() {
         TeePlatform::Unspecified => anyhow::bail!("unspecified TEE platform"),
         TeePlatform::AmdSevSnp => {
-            // We demand that product-specific ASK signs the VCEK.
-            let vcek = Certificate::from_der(serialized_certificate)
+            let vcek = Certificate::from_der(&endorsements.tee_certificate)
                 .map_err(|_err| anyhow::anyhow!("could not parse VCEK cert"))?;
 
-            let ask = if product_name(&vcek)?.contains("Milan") {
-                Certificate::from_pem(ASK_MILAN_CERT_PEM)
-                    .map_err(|_err| anyhow::anyhow!("could not parse Milan ASK cert"))?
-            } else if product_name(&vcek)?.contains("Genoa") {
-                Certificate::from_pem(ASK_GENOA_CERT_PEM)
-                    .map_err(|_err| anyhow::anyhow!("could not parse Genoa ASK cert"))?
-            } else {
-                anyhow::bail!("unsupported AMD product");
-            };
-
-            // TODO(#4747): user current date as part of VCEK verification.
-            verify_cert_signature(&ask, &vcek).context("verifying vcek cert")?;
+            let (root_store, crls) = amd_roots_and_crls(amd_sev_expected_values)?;
+            verify_vcek_cert_chain(now_utc_millis, &root_store, &crls, &vcek)
+                .context("verifying VCEK cert chain")?;
 
             let report = AttestationReport::ref_from(&root_layer.remote_attestation_report)
                 .context("invalid AMD SEV-SNP attestation report")?;
@@ -75,27 +66,95 @@ pub fn verify_root_attestation_signature(
 
             // Check that the root ECA public key for the DICE chain is bound to the
             // attestation report to ensure that the entire chain is valid.
-            let expected = &hash_sha2_256(&root_layer.eca_public_key[..])[..];
-            let actual = report.data.report_data;
-
-            anyhow::ensure!(
-                // The report data contains 64 bytes by default, but we only use the first 32 bytes
-                // at the moment.
-                expected.len() < actual.len() && expected == &actual[..expected.len()],
-                "The root layer's ECA public key is not bound to the attestation report"
-            );
+            verify_eca_public_key_binding(&root_layer.eca_public_key, &report.data.report_data)
+        }
+        TeePlatform::IntelTdx => {
+            // The PCK certificate chain is embedded in the quote, so no separate
+            // TEE certificate is needed.
+            let collateral = endorsements
+                .intel_tdx_collateral
+                .as_ref()
+                .context("no Intel TDX collateral in the root layer endorsements")?;
+            let quote =
+                verify_quote(now_utc_millis, &root_layer.remote_attestation_report, collateral)
+                    .context("verifying TDX quote")?;
 
-            Ok(())
+            verify_eca_public_key_binding(&root_layer.eca_public_key, &quote.body.report_data)
         }
-        TeePlatform::IntelTdx => anyhow::bail!("not supported"),
         TeePlatform::None => Ok(()),
     }
 }
 
-/// Verifies the AMD SEV attestation report.
+/// Builds the AMD root store and parses the CRLs from the expected values.
+fn amd_roots_and_crls(
+    expected_values: Option<&AmdSevExpectedValues>,
+) -> anyhow::Result<(RootStore, Vec<CertificateList>)> {
+    let mut root_store = RootStore::with_builtin_roots()?;
+    let Some(expected_values) = expected_values else {
+        return Ok((root_store, Vec::new()));
+    };
+    for roots in expected_values.additional_product_roots.iter() {
+        let ark = Certificate::from_der(&roots.ark_certificate)
+            .map_err(|_err| anyhow::anyhow!("could not parse {} ARK cert", roots.product_name))?;
+        let ask = Certificate::from_der(&roots.ask_certificate)
+            .map_err(|_err| anyhow::anyhow!("could not parse {} ASK cert", roots.product_name))?;
+        root_store.add_product(&roots.product_name, ark, ask);
+    }
+    let crls = expected_values
+        .certificate_revocation_lists
+        .iter()
+        .map(|crl| {
+            CertificateList::from_der(crl).map_err(|_err| anyhow::anyhow!("could not parse CRL"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok((root_store, crls))
+}
+
+/// Returns the AMD product line of the VCEK in the root layer endorsements,
+/// e.g. "Milan", or `None` if the root layer is not AMD SEV-SNP. The VCEK is
+/// only trustworthy once `verify_root_attestation_signature` has succeeded.
+pub fn amd_vcek_product_line(
+    root_layer: &RootLayerEvidence,
+    endorsements: &RootLayerEndorsements,
+) -> anyhow::Result<Option<String>> {
+    if root_layer.platform() != TeePlatform::AmdSevSnp {
+        return Ok(None);
+    }
+    let vcek = Certificate::from_der(&endorsements.tee_certificate)
+        .map_err(|_err| anyhow::anyhow!("could not parse VCEK cert"))?;
+    product_line(&vcek).map(Some)
+}
+
+/// Selects the minimum TCB version for the AMD product line of the VCEK. A
+/// product-specific minimum TCB version applies if its key equals the product
+/// line exactly; otherwise the general minimum TCB version applies.
+fn min_tcb_version_for_product<'a>(
+    expected_values: &'a AmdSevExpectedValues,
+    vcek_product_line: Option<&str>,
+) -> anyhow::Result<Option<&'a TcbVersion>> {
+    if expected_values.product_min_tcb_versions.is_empty() {
+        return Ok(expected_values.min_tcb_version.as_ref());
+    }
+    anyhow::ensure!(
+        !expected_values.product_min_tcb_versions.keys().any(String::is_empty),
+        "empty AMD product name in the min TCB version reference values"
+    );
+    let product_line = vcek_product_line
+        .context("no AMD product line to select the product-specific min TCB version")?;
+    Ok(expected_values
+        .product_min_tcb_versions
+        .get(product_line)
+        .or(expected_values.min_tcb_version.as_ref()))
+}
+
+/// Verifies the AMD SEV attestation report. `vcek_product_line` is the AMD
+/// product line of the verified VCEK (see `amd_vcek_product_line`); it is
+/// required if the expected values contain product-specific minimum TCB
+/// versions.
 pub fn verify_amd_sev_attestation_report(
     attestation_report_values: &AmdAttestationReport,
     expected_values: &AmdSevExpectedValues,
+    vcek_product_line: Option<&str>,
 ) -> anyhow::Result<()> {
     // Stage 0 only destroys VMPCK0, so we only trust attestation reports that were
     // generated in VMPL0.
@@ -110,38 +169,11 @@ pub fn verify_amd_sev_attestation_report(
     }
 
     match (
-        expected_values.min_tcb_version.as_ref(),
+        min_tcb_version_for_product(expected_values, vcek_product_line)?,
         attestation_report_values.reported_tcb.as_ref(),
     ) {
         (Some(min_tcb_version), Some(reported_tcb_version)) => {
-            anyhow::ensure!(
-                reported_tcb_version.boot_loader >= min_tcb_version.boot_loader,
-                format!(
-                    "unsupported boot loader version in the reported TCB: {}",
-                    reported_tcb_version.boot_loader
-                )
-            );
-            anyhow::ensure!(
-                reported_tcb_version.tee >= min_tcb_version.tee,
-                format!(
-                    "unsupported tee version in the reported TCB: {}",
-                    reported_tcb_version.tee
-                )
-            );
-            anyhow::ensure!(
-                reported_tcb_version.snp >= min_tcb_version.snp,
-                format!(
-                    "unsupported snp version in the reported TCB: {}",
-                    reported_tcb_version.snp
-                )
-            );
-            anyhow::ensure!(
-                reported_tcb_version.microcode >= min_tcb_version.microcode,
-                format!(
-                    "unsupported microcode version in the reported TCB: {}",
-                    reported_tcb_version.microcode
-                )
-            );
+            verify_min_tcb_version(reported_tcb_version, min_tcb_version)?;
         }
         (Some(_), None) => anyhow::bail!("no reported TCB version in the attestation report"),
         (None, _) => anyhow::bail!("no min TCB version reference value"),
@@ -150,15 +182,80 @@ pub fn verify_amd_sev_attestation_report(
     Ok(())
 }
 
+/// Verifies that each component of the reported TCB version is at least the
+/// minimum version.
+fn verify_min_tcb_version(
+    reported_tcb_version: &TcbVersion,
+    min_tcb_version: &TcbVersion,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        reported_tcb_version.boot_loader >= min_tcb_version.boot_loader,
+        format!(
+            "unsupported boot loader version in the reported TCB: {}",
+            reported_tcb_version.boot_loader
+        )
+    );
+    anyhow::ensure!(
+        reported_tcb_version.tee >= min_tcb_version.tee,
+        format!("unsupported tee version in the reported TCB: {}", reported_tcb_version.tee)
+    );
+    anyhow::ensure!(
+        reported_tcb_version.snp >= min_tcb_version.snp,
+        format!("unsupported snp version in the reported TCB: {}", reported_tcb_version.snp)
+    );
+    anyhow::ensure!(
+        reported_tcb_version.microcode >= min_tcb_version.microcode,
+        format!(
+            "unsupported microcode version in the reported TCB: {}",
+            reported_tcb_version.microcode
+        )
+    );
+    Ok(())
+}
+
 /// Verifies the Intel TDX attestation report.
 pub fn verify_intel_tdx_attestation_report(
-    _attestation_report_values: &IntelTdxAttestationReport,
-    _expected_values: &IntelTdxExpectedValues,
+    attestation_report_values: &IntelTdxAttestationReport,
+    expected_values: &IntelTdxExpectedValues,
 ) -> anyhow::Result<()> {
-    anyhow::bail!("needs implementation")
+    if !expected_values.allow_debug && attestation_report_values.debug {
+        anyhow::bail!("debug mode not allowed");
+    }
+
+    // The debug bit is covered by `allow_debug` above.
+    let mask = expected_values.td_attributes_mask & !TD_ATTRIBUTES_DEBUG;
+    anyhow::ensure!(
+        attestation_report_values.td_attributes & mask == expected_values.td_attributes & mask,
+        "unexpected TD attributes: {:#x}",
+        attestation_report_values.td_attributes
+    );
+
+    anyhow::ensure!(
+        !expected_values.min_tee_tcb_svn.is_empty(),
+        "no min TEE TCB SVN reference value"
+    );
+    verify_tee_tcb_svn(&attestation_report_values.tee_tcb_svn, &expected_values.min_tee_tcb_svn)
+}
+
+/// Verifies that the root ECA public key for the DICE chain is bound to the
+/// report data of the attestation report.
+fn verify_eca_public_key_binding(eca_public_key: &[u8], report_data: &[u8]) -> anyhow::Result<()> {
+    let expected = &hash_sha2_256(eca_public_key)[..];
+
+    anyhow::ensure!(
+        // The report data contains 64 bytes by default, but we only use the first 32 bytes
+        // at the moment.
+        expected.len() < report_data.len() && expected == &report_data[..expected.len()],
+        "The root layer's ECA public key is not bound to the attestation report"
+    );
+
+    Ok(())
 }
 
 /// Verifies insecure attestation.
 pub fn verify_insecure(_expected_values: &InsecureExpectedValues) -> anyhow::Result<()> {
     Ok(())
 }
+
+#[cfg(test)]
+mod tests;
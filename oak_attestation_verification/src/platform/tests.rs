@@ -0,0 +1,180 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use alloc::{collections::BTreeMap, string::ToString, vec};
+
+use oak_proto_rust::oak::attestation::v1::{
+    AmdAttestationReport, AmdSevExpectedValues, IntelTdxAttestationReport, IntelTdxExpectedValues,
+    RootLayerEndorsements, RootLayerEvidence, TcbVersion, TeePlatform,
+};
+
+use crate::{
+    intel::TD_ATTRIBUTES_DEBUG,
+    platform::{
+        verify_amd_sev_attestation_report, verify_intel_tdx_attestation_report,
+        verify_root_attestation_signature,
+    },
+};
+
+// SEPT_VE_DISABLE
+const TD_ATTRIBUTES_SEPT_VE_DISABLE: u64 = 1 << 28;
+
+const TDX_QUOTE: &[u8] = include_bytes!("../../testdata/tdx_quote.bin");
+
+// 2025-01-01T00:00:00Z
+const NOW_UTC_MILLIS: i64 = 1735689600000;
+
+fn tdx_report() -> IntelTdxAttestationReport {
+    IntelTdxAttestationReport {
+        tee_tcb_svn: vec![3, 1, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        td_attributes: TD_ATTRIBUTES_SEPT_VE_DISABLE,
+        debug: false,
+        ..Default::default()
+    }
+}
+
+fn tdx_expected_values() -> IntelTdxExpectedValues {
+    IntelTdxExpectedValues {
+        min_tee_tcb_svn: vec![3, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        allow_debug: false,
+        td_attributes_mask: TD_ATTRIBUTES_SEPT_VE_DISABLE,
+        td_attributes: TD_ATTRIBUTES_SEPT_VE_DISABLE,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn verify_intel_tdx_attestation_report_succeeds() {
+    let result = verify_intel_tdx_attestation_report(&tdx_report(), &tdx_expected_values());
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn verify_intel_tdx_attestation_report_with_debug_fails() {
+    let report = IntelTdxAttestationReport {
+        td_attributes: TD_ATTRIBUTES_SEPT_VE_DISABLE | TD_ATTRIBUTES_DEBUG,
+        debug: true,
+        ..tdx_report()
+    };
+    assert!(verify_intel_tdx_attestation_report(&report, &tdx_expected_values()).is_err());
+
+    let expected_values = IntelTdxExpectedValues { allow_debug: true, ..tdx_expected_values() };
+    assert!(verify_intel_tdx_attestation_report(&report, &expected_values).is_ok());
+}
+
+#[test]
+fn verify_intel_tdx_attestation_report_with_unexpected_attributes_fails() {
+    let report = IntelTdxAttestationReport { td_attributes: 0, ..tdx_report() };
+    assert!(verify_intel_tdx_attestation_report(&report, &tdx_expected_values()).is_err());
+
+    // Attributes outside of the mask are ignored.
+    let expected_values = IntelTdxExpectedValues { td_attributes_mask: 0, ..tdx_expected_values() };
+    assert!(verify_intel_tdx_attestation_report(&report, &expected_values).is_ok());
+}
+
+#[test]
+fn verify_intel_tdx_attestation_report_with_old_tee_tcb_fails() {
+    let mut min_tee_tcb_svn = tdx_expected_values().min_tee_tcb_svn;
+    min_tee_tcb_svn[2] = 6;
+    let expected_values = IntelTdxExpectedValues { min_tee_tcb_svn, ..tdx_expected_values() };
+    assert!(verify_intel_tdx_attestation_report(&tdx_report(), &expected_values).is_err());
+}
+
+#[test]
+fn verify_intel_tdx_attestation_report_without_min_tee_tcb_fails() {
+    let expected_values =
+        IntelTdxExpectedValues { min_tee_tcb_svn: vec![], ..tdx_expected_values() };
+    assert!(verify_intel_tdx_attestation_report(&tdx_report(), &expected_values).is_err());
+
+    let report = IntelTdxAttestationReport { tee_tcb_svn: vec![0; 4], ..tdx_report() };
+    assert!(verify_intel_tdx_attestation_report(&report, &tdx_expected_values()).is_err());
+}
+
+#[test]
+fn verify_tdx_root_attestation_signature_without_collateral_fails() {
+    let root_layer = RootLayerEvidence {
+        platform: TeePlatform::IntelTdx.into(),
+        remote_attestation_report: TDX_QUOTE.to_vec(),
+        ..Default::default()
+    };
+    let result = verify_root_attestation_signature(
+        NOW_UTC_MILLIS,
+        &root_layer,
+        &RootLayerEndorsements::default(),
+        None,
+    );
+    assert!(result.is_err());
+}
+
+fn amd_report() -> AmdAttestationReport {
+    AmdAttestationReport {
+        reported_tcb: Some(TcbVersion { boot_loader: 3, tee: 0, snp: 20, microcode: 209 }),
+        ..Default::default()
+    }
+}
+
+fn amd_expected_values(product: &str, min_microcode: u32) -> AmdSevExpectedValues {
+    AmdSevExpectedValues {
+        min_tcb_version: Some(TcbVersion::default()),
+        product_min_tcb_versions: BTreeMap::from([(
+            product.to_string(),
+            TcbVersion { microcode: min_microcode, ..Default::default() },
+        )]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn verify_amd_sev_attestation_report_uses_product_min_tcb_version() {
+    let expected_values = amd_expected_values("Milan", 209);
+    assert!(
+        verify_amd_sev_attestation_report(&amd_report(), &expected_values, Some("Milan")).is_ok()
+    );
+
+    let expected_values = amd_expected_values("Milan", 210);
+    assert!(
+        verify_amd_sev_attestation_report(&amd_report(), &expected_values, Some("Milan")).is_err()
+    );
+}
+
+#[test]
+fn verify_amd_sev_attestation_report_requires_exact_product_match() {
+    // "Mil" is not the product line "Milan", so the general minimum applies.
+    let expected_values = amd_expected_values("Mil", 210);
+    assert!(
+        verify_amd_sev_attestation_report(&amd_report(), &expected_values, Some("Milan")).is_ok()
+    );
+
+    let expected_values =
+        AmdSevExpectedValues { min_tcb_version: None, ..amd_expected_values("Mil", 0) };
+    assert!(
+        verify_amd_sev_attestation_report(&amd_report(), &expected_values, Some("Milan")).is_err()
+    );
+}
+
+#[test]
+fn verify_amd_sev_attestation_report_rejects_empty_product() {
+    let expected_values = amd_expected_values("", 0);
+    assert!(
+        verify_amd_sev_attestation_report(&amd_report(), &expected_values, Some("Milan")).is_err()
+    );
+}
+
+#[test]
+fn verify_amd_sev_attestation_report_requires_product_line() {
+    let expected_values = amd_expected_values("Milan", 0);
+    assert!(verify_amd_sev_attestation_report(&amd_report(), &expected_values, None).is_err());
+}
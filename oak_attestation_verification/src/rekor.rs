@@ -26,7 +26,9 @@ use serde::Deserialize;
 #[cfg(feature = "std")]
 use serde::Serialize;
 
-use crate::util::{convert_pem_to_raw, hash_sha2_256, verify_signature_ecdsa};
+use crate::util::{
+    convert_pem_to_raw, hash_sha2_256, verify_signature_ecdsa, verify_signature_ed25519,
+};
 
 /// Struct representing a Rekor LogEntry.
 /// Based on <https://github.com/sigstore/rekor/blob/2978cdc26fdf8f5bfede8459afd9735f0f231a2a/pkg/generated/models/log_entry.go#L89.>
@@ -114,16 +116,261 @@ pub struct PublicKey {
     pub content: String,
 }
 
-/// Struct representing a verification object in a Rekor LogEntry. The
-/// verification object in Rekor also contains an inclusion proof. Since we
-/// currently don't verify the inclusion proof in the client, it is omitted from
-/// this struct.
+/// Struct representing a verification object in a Rekor LogEntry.
 #[derive(Debug, Deserialize, PartialEq)]
 #[cfg_attr(feature = "std", derive(Serialize))]
 pub struct LogEntryVerification {
     // Base64-encoded signature over the body, integratedTime, logID, and logIndex.
     #[serde(rename = "signedEntryTimestamp")]
     pub signed_entry_timestamp: String,
+
+    /// Proof that the entry is included in the Merkle tree committed to by a
+    /// signed checkpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "inclusionProof")]
+    pub inclusion_proof: Option<InclusionProof>,
+}
+
+/// Struct representing an inclusion proof in a Rekor LogEntry.
+/// Based on <https://github.com/sigstore/rekor/blob/2978cdc26fdf8f5bfede8459afd9735f0f231a2a/pkg/generated/models/inclusion_proof.go#L35.>
+#[derive(Debug, Deserialize, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+pub struct InclusionProof {
+    /// Signed note (checkpoint) committing to the root hash and tree size.
+    pub checkpoint: String,
+
+    /// Hex-encoded hashes of the audit path, ordered from the leaf upwards.
+    pub hashes: Vec<String>,
+
+    /// Index of the entry within the tree of the log shard. This differs from
+    /// the global `logIndex` of the LogEntry once the log has been sharded.
+    #[serde(rename = "logIndex")]
+    pub log_index: u64,
+
+    /// Hex-encoded root hash of the tree the proof was computed for.
+    #[serde(rename = "rootHash")]
+    pub root_hash: String,
+
+    /// Size of the tree the proof was computed for.
+    #[serde(rename = "treeSize")]
+    pub tree_size: u64,
+}
+
+/// Struct representing the body of a checkpoint, i.e. a signed tree head in
+/// the format described in <https://github.com/transparency-dev/formats/blob/main/log/README.md>.
+#[derive(Debug, PartialEq)]
+pub struct Checkpoint {
+    /// Unique identity of the log.
+    pub origin: String,
+    pub tree_size: u64,
+    pub root_hash: Vec<u8>,
+}
+
+impl Checkpoint {
+    /// Parses the text of a signed note into a checkpoint. Any optional
+    /// extension lines following the root hash are ignored.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut lines = text.lines();
+        let origin = lines.next().filter(|l| !l.is_empty()).context("checkpoint has no origin")?;
+        let tree_size =
+            lines.next().context("checkpoint has no tree size")?.parse::<u64>().map_err(
+                |error| anyhow::anyhow!("couldn't parse checkpoint tree size: {}", error),
+            )?;
+        let root_hash = BASE64_STANDARD
+            .decode(lines.next().context("checkpoint has no root hash")?)
+            .map_err(|error| anyhow::anyhow!("couldn't decode checkpoint root hash: {}", error))?;
+        Ok(Self { origin: origin.into(), tree_size, root_hash })
+    }
+}
+
+/// A signature line of a signed note, as described in
+/// <https://pkg.go.dev/golang.org/x/mod/sumdb/note>.
+struct NoteSignature<'a> {
+    name: &'a str,
+    key_hint: [u8; 4],
+    signature: Vec<u8>,
+}
+
+/// Splits a signed note into the signed text (including its final newline)
+/// and its signatures.
+fn parse_signed_note(note: &str) -> anyhow::Result<(&str, Vec<NoteSignature<'_>>)> {
+    let separator = note.find("\n\n").context("signed note has no signature block")?;
+    let (text, signature_block) = note.split_at(separator + 1);
+    let signatures = signature_block[1..]
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, encoded) = line
+                .strip_prefix("\u{2014} ")
+                .and_then(|rest| rest.rsplit_once(' '))
+                .context("malformed signed note signature line")?;
+            let decoded = BASE64_STANDARD
+                .decode(encoded)
+                .map_err(|error| anyhow::anyhow!("couldn't decode note signature: {}", error))?;
+            anyhow::ensure!(decoded.len() > 4, "note signature is too short");
+            let (key_hint, signature) = decoded.split_at(4);
+            Ok(NoteSignature {
+                name,
+                key_hint: key_hint.try_into().expect("key hint has 4 bytes"),
+                signature: signature.to_vec(),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    anyhow::ensure!(!signatures.is_empty(), "signed note has no signatures");
+    Ok((text, signatures))
+}
+
+/// Returns true if any of the signatures was produced with one of the given
+/// ECDSA P-256 keys. The key hint of a signature is the first four bytes of
+/// the SHA2-256 hash of the DER-encoded public key, as used by Rekor.
+fn has_note_signature(text: &str, signatures: &[NoteSignature], public_keys: &[&[u8]]) -> bool {
+    public_keys.iter().any(|public_key| {
+        let key_hint = &hash_sha2_256(public_key)[..4];
+        signatures.iter().any(|s| {
+            s.key_hint == key_hint
+                && verify_signature_ecdsa(&s.signature, text.as_bytes(), public_key).is_ok()
+        })
+    })
+}
+
+/// Signature type identifier of `cosignature/v1` signatures, as assigned in
+/// <https://c2sp.org/signed-note>.
+const COSIGNATURE_V1_TYPE: u8 = 0x04;
+
+/// A witness that cosigns checkpoints with `cosignature/v1` signatures, as
+/// described in <https://c2sp.org/tlog-cosignature>.
+struct Witness<'a> {
+    name: &'a str,
+    key_hint: [u8; 4],
+    public_key: Vec<u8>,
+}
+
+impl<'a> Witness<'a> {
+    /// Parses a witness from its note verifier key, which has the form
+    /// `<name>+<hex key hint>+<base64(0x04 || Ed25519 public key)>`.
+    fn parse(verifier_key: &'a [u8]) -> anyhow::Result<Self> {
+        let verifier_key = core::str::from_utf8(verifier_key)
+            .map_err(|error| anyhow::anyhow!("witness verifier key is not UTF-8: {}", error))?;
+        // The Base64 encoding of the key may itself contain '+'.
+        let mut parts = verifier_key.trim_end().splitn(3, '+');
+        let (Some(name), Some(key_hint), Some(encoded)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("malformed witness verifier key");
+        };
+        anyhow::ensure!(!name.is_empty(), "witness verifier key has no name");
+        let key_hint: [u8; 4] = hex::decode(key_hint)
+            .ok()
+            .and_then(|hint| hint.try_into().ok())
+            .context("malformed witness key hint")?;
+        let decoded_key = BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|error| anyhow::anyhow!("couldn't decode witness public key: {}", error))?;
+        let (&key_type, public_key) =
+            decoded_key.split_first().context("witness verifier key has no public key")?;
+        anyhow::ensure!(
+            key_type == COSIGNATURE_V1_TYPE,
+            "unsupported witness signature type {:#04x}",
+            key_type
+        );
+        anyhow::ensure!(
+            cosignature_key_hint(name, public_key) == key_hint,
+            "witness key hint does not match its name and public key"
+        );
+        Ok(Self { name, key_hint, public_key: public_key.to_vec() })
+    }
+
+    /// Returns true if the signature is a valid `cosignature/v1` signature by
+    /// this witness over the checkpoint text. The signature consists of an
+    /// 8-byte big-endian timestamp followed by an Ed25519 signature over the
+    /// timestamped cosigned message.
+    fn has_signed(&self, text: &str, signature: &NoteSignature) -> bool {
+        if signature.name != self.name || signature.key_hint != self.key_hint {
+            return false;
+        }
+        let Some((timestamp, ed25519_signature)) = signature.signature.split_first_chunk::<8>()
+        else {
+            return false;
+        };
+        let message = format!("cosignature/v1\ntime {}\n{}", u64::from_be_bytes(*timestamp), text);
+        verify_signature_ed25519(ed25519_signature, message.as_bytes(), &self.public_key).is_ok()
+    }
+}
+
+/// Computes the key hint of a `cosignature/v1` key, i.e. the first four bytes
+/// of SHA2-256(name || "\n" || 0x04 || public key).
+fn cosignature_key_hint(name: &str, public_key: &[u8]) -> [u8; 4] {
+    let mut input = Vec::with_capacity(name.len() + public_key.len() + 2);
+    input.extend_from_slice(name.as_bytes());
+    input.push(b'\n');
+    input.push(COSIGNATURE_V1_TYPE);
+    input.extend_from_slice(public_key);
+    let hash = hash_sha2_256(&input);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Returns true if any of the signatures is a valid `cosignature/v1`
+/// signature by one of the witnesses with the given note verifier keys.
+fn has_witness_cosignature(
+    text: &str,
+    signatures: &[NoteSignature],
+    witness_verifier_keys: &[&[u8]],
+) -> anyhow::Result<bool> {
+    for verifier_key in witness_verifier_keys {
+        let witness = Witness::parse(verifier_key)?;
+        if signatures.iter().any(|s| witness.has_signed(text, s)) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Computes the RFC 6962 hash of a leaf of the Merkle tree.
+fn leaf_hash(leaf: &[u8]) -> [u8; 32] {
+    let mut input = Vec::with_capacity(leaf.len() + 1);
+    input.push(0x00);
+    input.extend_from_slice(leaf);
+    hash_sha2_256(&input)
+}
+
+/// Computes the RFC 6962 hash of an interior node of the Merkle tree.
+fn node_hash(left: &[u8], right: &[u8]) -> [u8; 32] {
+    let mut input = Vec::with_capacity(left.len() + right.len() + 1);
+    input.push(0x01);
+    input.extend_from_slice(left);
+    input.extend_from_slice(right);
+    hash_sha2_256(&input)
+}
+
+/// Recomputes the root hash of a tree of size `tree_size` from the hash of the
+/// leaf at `index` and its audit path, following the algorithm in
+/// <https://www.rfc-editor.org/rfc/rfc9162#section-2.1.3.2>.
+pub fn root_from_inclusion_proof(
+    index: u64,
+    tree_size: u64,
+    leaf_hash: &[u8; 32],
+    proof: &[[u8; 32]],
+) -> anyhow::Result<[u8; 32]> {
+    anyhow::ensure!(index < tree_size, "leaf index {} is outside the tree", index);
+    let mut fn_ = index;
+    let mut sn = tree_size - 1;
+    let mut root = *leaf_hash;
+    for p in proof {
+        anyhow::ensure!(sn != 0, "inclusion proof is too long");
+        if fn_ % 2 == 1 || fn_ == sn {
+            root = node_hash(p, &root);
+            while fn_ % 2 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            root = node_hash(&root, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    anyhow::ensure!(sn == 0, "inclusion proof is too short");
+    Ok(root)
 }
 
 /// Convenient struct for verifying the `signedEntryTimestamp` in a Rekor
@@ -181,16 +428,26 @@ impl TryFrom<&LogEntry> for RekorSignatureBundle {
     }
 }
 
-/// Verifies a Rekor log entry by key set.
+/// Verifies a Rekor log entry by key set. If `witness_key_set` is given and
+/// non-empty, the checkpoint in the inclusion proof must additionally be
+/// cosigned by one of the witnesses, whose keys hold their note verifier keys
+/// (see `verify_rekor_inclusion_proof`).
 pub fn verify_rekor_log_entry(
     log_entry: &[u8],
     rekor_key_set: &VerifyingKeySet,
+    witness_key_set: Option<&VerifyingKeySet>,
     serialized_endorsement: &[u8],
 ) -> anyhow::Result<()> {
     if !rekor_key_set.keys.iter().any(|k| verify_rekor_signature(log_entry, &k.raw).is_ok()) {
         anyhow::bail!("could not verify rekor signature");
     }
 
+    let rekor_keys: Vec<&[u8]> = rekor_key_set.keys.iter().map(|k| k.raw.as_slice()).collect();
+    let witness_keys: Vec<&[u8]> = witness_key_set
+        .map(|key_set| key_set.keys.iter().map(|k| k.raw.as_slice()).collect())
+        .unwrap_or_default();
+    verify_rekor_inclusion_proof(log_entry, &rekor_keys, &witness_keys)?;
+
     let body = get_rekor_log_entry_body(log_entry)?;
     verify_rekor_body(&body, serialized_endorsement)
 }
@@ -198,6 +455,7 @@ pub fn verify_rekor_log_entry(
 /// Verifies a Rekor LogEntry. This includes verifying:
 ///
 /// 1. the signature in `signedEntryTimestamp` using Rekor's public key,
+/// 1. the inclusion proof and its checkpoint using Rekor's public key,
 /// 1. the signature in `body.RekordObj.signature` using the endorser's public
 ///    key,
 /// 1. that the content of the body equals `endorsement`.
@@ -207,6 +465,7 @@ pub fn verify_rekor_log_entry_ecdsa(
     serialized_endorsement: &[u8],
 ) -> anyhow::Result<()> {
     verify_rekor_signature(log_entry, rekor_public_key)?;
+    verify_rekor_inclusion_proof(log_entry, &[rekor_public_key], &[])?;
 
     let body = get_rekor_log_entry_body(log_entry)?;
     verify_rekor_body(&body, serialized_endorsement)
@@ -243,6 +502,88 @@ pub fn verify_rekor_signature(log_entry: &[u8], rekor_public_key: &[u8]) -> anyh
     .context("couldn't verify signedEntryTimestamp of the Rekor LogEntry")
 }
 
+/// Parses a blob into a Rekor log entry and verifies that the entry is included
+/// in the log. This includes verifying:
+///
+/// 1. that the audit path in the inclusion proof leads from the hash of the
+///    entry body to the root hash of the proof,
+/// 1. that the checkpoint commits to the same root hash and tree size,
+/// 1. that the checkpoint is signed by one of `rekor_public_keys`,
+/// 1. if `witness_verifier_keys` is non-empty, that the checkpoint is also
+///    cosigned by one of the witnesses.
+///
+/// Rekor keys are DER-encoded ECDSA P-256 public keys. Witness keys are note
+/// verifier keys such as `witness.example.com+f8e2fc87+BDUZ...`, and their
+/// cosignatures are Ed25519 `cosignature/v1` signatures as specified in
+/// <https://c2sp.org/tlog-cosignature>. Verification is done
+/// entirely offline, so it only shows that the log committed to the entry in
+/// the given checkpoint; detecting a log that presents different views to
+/// different clients requires checking the checkpoint for consistency, which
+/// is what the witnesses are for.
+pub fn verify_rekor_inclusion_proof(
+    log_entry: &[u8],
+    rekor_public_keys: &[&[u8]],
+    witness_verifier_keys: &[&[u8]],
+) -> anyhow::Result<()> {
+    let parsed: BTreeMap<String, LogEntry> =
+        serde_json::from_slice(log_entry).map_err(|error| {
+            anyhow::anyhow!("couldn't parse bytes into a LogEntry object: {}", error)
+        })?;
+    let entry = parsed.values().next().context("no entry in the map")?;
+    let proof = entry
+        .verification
+        .as_ref()
+        .context("no verification field in the log entry")?
+        .inclusion_proof
+        .as_ref()
+        .context("no inclusion proof in the log entry")?;
+
+    let body = BASE64_STANDARD
+        .decode(&entry.body)
+        .map_err(|error| anyhow::anyhow!("couldn't decode Base64 body: {}", error))?;
+    let hashes = proof
+        .hashes
+        .iter()
+        .map(|h| decode_hash(h).context("couldn't decode inclusion proof hash"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let root =
+        root_from_inclusion_proof(proof.log_index, proof.tree_size, &leaf_hash(&body), &hashes)
+            .context("couldn't verify inclusion proof")?;
+    let expected_root = decode_hash(&proof.root_hash).context("couldn't decode root hash")?;
+    if root != expected_root {
+        anyhow::bail!(
+            "inclusion proof root hash ({}) does not match the expected root hash ({})",
+            hex::encode(root),
+            proof.root_hash
+        );
+    }
+
+    let (text, signatures) =
+        parse_signed_note(&proof.checkpoint).context("couldn't parse checkpoint")?;
+    let checkpoint = Checkpoint::parse(text)?;
+    if checkpoint.root_hash != root || checkpoint.tree_size != proof.tree_size {
+        anyhow::bail!("checkpoint does not match the inclusion proof");
+    }
+    if !has_note_signature(text, &signatures, rekor_public_keys) {
+        anyhow::bail!("could not verify the checkpoint signature of {}", checkpoint.origin);
+    }
+    if !witness_verifier_keys.is_empty()
+        && !has_witness_cosignature(text, &signatures, witness_verifier_keys)?
+    {
+        let names: Vec<&str> = signatures.iter().map(|s| s.name).collect();
+        anyhow::bail!("checkpoint is not cosigned by a known witness; signed by {:?}", names);
+    }
+
+    Ok(())
+}
+
+fn decode_hash(hash: &str) -> anyhow::Result<[u8; 32]> {
+    hex::decode(hash)
+        .map_err(|error| anyhow::anyhow!("couldn't decode hex hash: {}", error))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("hash is not 32 bytes long"))
+}
+
 /// Verifies the signature in the body over the contents.
 pub fn verify_rekor_body(body: &Body, contents_bytes: &[u8]) -> anyhow::Result<()> {
     if body.spec.signature.format != "x509" {
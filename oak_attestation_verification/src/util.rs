@@ -25,9 +25,10 @@ use oak_proto_rust::oak::{
         reference_values, root_layer_data::Report, text_reference_value, AmdSevReferenceValues,
         ApplicationLayerReferenceValues, BinaryReferenceValue, ContainerLayerReferenceValues,
         Digests, Event, EventEndorsement, ExtractedEvidence, InsecureReferenceValues,
-        KernelBinaryReferenceValue, KernelDigests, KernelLayerData, KernelLayerReferenceValues,
-        KeyType, OakContainersReferenceValues, OakRestrictedKernelReferenceValues, ReferenceValues,
-        RootLayerData, RootLayerReferenceValues, Signature, SkipVerification, StringLiterals,
+        IntelTdxReferenceValues, KernelBinaryReferenceValue, KernelDigests, KernelLayerData,
+        KernelLayerReferenceValues, KeyType, OakContainersReferenceValues,
+        OakRestrictedKernelReferenceValues, ReferenceValues, RootLayerData,
+        RootLayerReferenceValues, Signature, SkipVerification, StringLiterals,
         SystemLayerReferenceValues, TextReferenceValue, Validity, VerifyingKeySet,
     },
     HexDigest, RawDigest,
@@ -37,6 +38,7 @@ use prost::Message;
 use prost_types::Any;
 use sha2::{Digest, Sha256, Sha384, Sha512};
 use time::OffsetDateTime;
+use x509_cert::{crl::CertificateList, time::Time, Certificate};
 
 use crate::endorsement;
 
@@ -117,6 +119,24 @@ pub fn verify_signature_ecdsa(
         .map_err(|error| anyhow::anyhow!("couldn't verify signature: {}", error))
 }
 
+/// Verifies an Ed25519 signature (RFC 8032) over the contents using the raw
+/// 32-byte public key. Small-order public keys and non-canonical signatures
+/// are rejected.
+pub fn verify_signature_ed25519(
+    signature: &[u8],
+    contents: &[u8],
+    public_key: &[u8],
+) -> anyhow::Result<()> {
+    let public_key = ed25519_dalek::VerifyingKey::try_from(public_key)
+        .map_err(|error| anyhow::anyhow!("invalid Ed25519 public key: {}", error))?;
+    let signature = ed25519_dalek::Signature::from_slice(signature)
+        .map_err(|error| anyhow::anyhow!("invalid Ed25519 signature: {}", error))?;
+
+    public_key
+        .verify_strict(contents, &signature)
+        .map_err(|error| anyhow::anyhow!("couldn't verify signature: {}", error))
+}
+
 pub fn hash_sha2_256(input: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(input);
@@ -358,14 +378,31 @@ fn root_layer_reference_values_from_evidence(
                 })),
             }),
             allow_debug: r.debug,
+            ..Default::default()
         }),
         _ => None,
     });
-    let intel_tdx = if let Some(Report::Tdx(_)) = root_layer.report.clone() {
-        panic!("not yet supported");
-    } else {
-        None
-    };
+    let intel_tdx = root_layer.report.clone().and_then(|report| match report {
+        Report::Tdx(r) => {
+            let digests = |value: Vec<u8>| BinaryReferenceValue {
+                r#type: Some(binary_reference_value::Type::Digests(Digests {
+                    digests: vec![RawDigest { sha2_384: value, ..Default::default() }],
+                })),
+            };
+            Some(IntelTdxReferenceValues {
+                min_tee_tcb_svn: r.tee_tcb_svn,
+                allow_debug: r.debug,
+                mr_td: Some(digests(r.mr_td)),
+                rtmr0: Some(digests(r.rtmr0)),
+                rtmr1: Some(digests(r.rtmr1)),
+                rtmr2: Some(digests(r.rtmr2)),
+                rtmr3: Some(digests(r.rtmr3)),
+                td_attributes_mask: u64::MAX,
+                td_attributes: r.td_attributes,
+            })
+        }
+        _ => None,
+    });
     let insecure = root_layer.report.and_then(|report| match report {
         Report::Fake(_) => Some(InsecureReferenceValues {}),
         _ => None,
@@ -484,6 +521,38 @@ impl UnixTimestampMillis for OffsetDateTime {
     }
 }
 
+/// Verifies that the certificate is valid at the given time.
+pub(crate) fn verify_cert_validity(now_utc_millis: i64, cert: &Certificate) -> anyhow::Result<()> {
+    let validity = &cert.tbs_certificate.validity;
+    verify_time_window(now_utc_millis, validity.not_before, Some(validity.not_after))
+        .context("verifying certificate validity")
+}
+
+/// Verifies that the given time lies within the window, which is open-ended
+/// if there is no end time.
+pub(crate) fn verify_time_window(
+    now_utc_millis: i64,
+    start: Time,
+    end: Option<Time>,
+) -> anyhow::Result<()> {
+    let now = u128::try_from(now_utc_millis)
+        .map_err(|_err| anyhow::anyhow!("invalid timestamp: {}", now_utc_millis))?;
+    anyhow::ensure!(now >= start.to_unix_duration().as_millis(), "not yet valid");
+    if let Some(end) = end {
+        anyhow::ensure!(now <= end.to_unix_duration().as_millis(), "expired");
+    }
+    Ok(())
+}
+
+/// Whether the CRL revokes the certificate.
+pub(crate) fn is_revoked(crl: &CertificateList, cert: &Certificate) -> bool {
+    crl.tbs_cert_list
+        .revoked_certificates
+        .iter()
+        .flatten()
+        .any(|revoked| revoked.serial_number == cert.tbs_certificate.serial_number)
+}
+
 impl From<&endorsement::Validity> for Validity {
     fn from(value: &endorsement::Validity) -> Validity {
         Validity {
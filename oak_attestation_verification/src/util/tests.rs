@@ -24,7 +24,7 @@ use oak_proto_rust::oak::HexDigest;
 
 use crate::util::{
     convert_pem_to_raw, convert_raw_to_pem, convert_raw_to_verifying_key, equal_keys,
-    get_hex_digest_match, verify_signature_ecdsa, MatchResult,
+    get_hex_digest_match, verify_signature_ecdsa, verify_signature_ed25519, MatchResult,
 };
 
 const ENDORSEMENT_PATH: &str = "oak_attestation_verification/testdata/endorsement.json";
@@ -113,6 +113,41 @@ fn test_verify_signature_ecdsa() {
     assert!(result.is_ok());
 }
 
+// Test vector 2 from RFC 8032, section 7.1.
+const ED25519_PUBLIC_KEY: &str = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+const ED25519_MESSAGE: &[u8] = &[0x72];
+const ED25519_SIGNATURE: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
+
+#[test]
+fn test_verify_signature_ed25519() {
+    let public_key = hex::decode(ED25519_PUBLIC_KEY).unwrap();
+    let signature = hex::decode(ED25519_SIGNATURE).unwrap();
+
+    let result = verify_signature_ed25519(&signature, ED25519_MESSAGE, &public_key);
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(verify_signature_ed25519(&signature, &[0x73], &public_key).is_err());
+}
+
+#[test]
+fn test_verify_signature_ed25519_rejects_non_canonical_signature() {
+    let public_key = hex::decode(ED25519_PUBLIC_KEY).unwrap();
+    let mut signature = hex::decode(ED25519_SIGNATURE).unwrap();
+    // Adding the group order L to s yields an equivalent, non-canonical scalar.
+    let order: [u8; 32] =
+        hex::decode("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010")
+            .unwrap()
+            .try_into()
+            .unwrap();
+    let mut carry = 0u16;
+    for (s, l) in signature[32..].iter_mut().zip(order) {
+        let sum = *s as u16 + l as u16 + carry;
+        *s = sum as u8;
+        carry = sum >> 8;
+    }
+
+    assert!(verify_signature_ed25519(&signature, ED25519_MESSAGE, &public_key).is_err());
+}
+
 #[test]
 fn test_both_empty_undecidable() {
     let empty = HexDigest { ..Default::default() };
@@ -26,8 +26,9 @@ use oak_attestation_verification_types::{
 };
 use oak_dice::cert::{cose_key_to_verifying_key, get_public_key_from_claims_set};
 use oak_proto_rust::oak::attestation::v1::{
-    attestation_results::Status, endorsements, AttestationResults, Endorsements, EventLog,
-    Evidence, ExpectedValues, ExtractedEvidence, LayerEvidence, ReferenceValues,
+    attestation_results::Status, endorsements, expected_values, AmdSevExpectedValues,
+    AttestationResults, Endorsements, EventLog, Evidence, ExpectedValues, ExtractedEvidence,
+    LayerEvidence, ReferenceValues,
 };
 use p256::ecdsa::VerifyingKey;
 
@@ -35,7 +36,7 @@ use crate::{
     compare::compare_expected_values,
     expect::get_expected_values,
     extract::{claims_set_from_serialized_cert, extract_event_data, extract_evidence},
-    platform::verify_root_attestation_signature,
+    platform::{amd_vcek_product_line, verify_root_attestation_signature},
 };
 
 // We don't use additional authenticated data.
@@ -122,31 +123,25 @@ pub fn verify_with_expected_values(
 ) -> anyhow::Result<ExtractedEvidence> {
     // Ensure the Attestation report is properly signed by the platform and that it
     // includes the root public key used in the DICE chain.
-    {
-        let tee_certificate: &[u8] =
+    let vcek_product_line = {
+        let root_layer_endorsements =
             match endorsements.r#type.as_ref().context("no endorsements")? {
-                endorsements::Type::OakRestrictedKernel(endorsements) => endorsements
-                    .root_layer
-                    .as_ref()
-                    .context("no root layer endorsements")?
-                    .tee_certificate
-                    .as_ref(),
-                endorsements::Type::OakContainers(endorsements) => endorsements
-                    .root_layer
-                    .as_ref()
-                    .context("no root layer endorsements")?
-                    .tee_certificate
-                    .as_ref(),
-                endorsements::Type::Cb(endorsements) => endorsements
-                    .root_layer
-                    .as_ref()
-                    .context("no root layer endorsements")?
-                    .tee_certificate
-                    .as_ref(),
-            };
+                endorsements::Type::OakRestrictedKernel(endorsements) => {
+                    endorsements.root_layer.as_ref()
+                }
+                endorsements::Type::OakContainers(endorsements) => endorsements.root_layer.as_ref(),
+                endorsements::Type::Cb(endorsements) => endorsements.root_layer.as_ref(),
+            }
+            .context("no root layer endorsements")?;
         let root_layer = evidence.root_layer.as_ref().context("no root layer evidence")?;
-        verify_root_attestation_signature(now_utc_millis, root_layer, tee_certificate)
-            .context("verifying root signature")?;
+        verify_root_attestation_signature(
+            now_utc_millis,
+            root_layer,
+            root_layer_endorsements,
+            amd_sev_expected_values(expected_values),
+        )
+        .context("verifying root signature")?;
+        amd_vcek_product_line(root_layer, root_layer_endorsements)?
     };
 
     // Ensure the DICE chain signatures are valid and extract the measurements,
@@ -154,12 +149,22 @@ pub fn verify_with_expected_values(
     let extracted_evidence =
         verify_dice_chain_and_extract_evidence(evidence).context("invalid DICE chain")?;
 
-    compare_expected_values(&extracted_evidence, expected_values)
+    compare_expected_values(&extracted_evidence, expected_values, vcek_product_line.as_deref())
         .context("comparing expected values to evidence")?;
 
     Ok(extracted_evidence)
 }
 
+/// Returns the AMD SEV-SNP expected values of the root layer, if any.
+fn amd_sev_expected_values(expected_values: &ExpectedValues) -> Option<&AmdSevExpectedValues> {
+    let root_layer = match expected_values.r#type.as_ref()? {
+        expected_values::Type::OakRestrictedKernel(values) => values.root_layer.as_ref(),
+        expected_values::Type::OakContainers(values) => values.root_layer.as_ref(),
+        expected_values::Type::Cb(values) => values.root_layer.as_ref(),
+    };
+    root_layer?.amd_sev.as_ref()
+}
+
 /// Verifies signatures of the certificates in the DICE chain and returns last
 /// layer's Certificate Authority key if the verification is successful.
 pub fn verify_dice_chain(evidence: &Evidence) -> anyhow::Result<VerifyingKey> {
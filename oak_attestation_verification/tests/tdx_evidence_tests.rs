@@ -0,0 +1,211 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Tests that evidence generated by Stage 0 on Intel TDX can be extracted once
+//! its TD report has been upgraded to a quote, as Stage 1 of Oak Containers
+//! does.
+
+use std::fs;
+
+use oak_attestation::{
+    attester::{Attester, Serializable},
+    dice::DiceAttester,
+};
+use oak_attestation_verification::extract::{extract_evidence, upgrade_td_report_to_quote};
+use oak_dice::{cert::generate_ecdsa_key_pair, evidence::TeePlatform};
+use oak_file_utils::data_path;
+use oak_proto_rust::oak::attestation::v1::{
+    extracted_evidence::EvidenceValues, root_layer_data::Report, ApplicationLayerData, DiceData,
+    Event, Evidence, Stage0Measurements,
+};
+use prost::Message;
+
+const TDX_QUOTE_PATH: &str = "oak_attestation_verification/testdata/tdx_quote.bin";
+
+const TD_REPORT_SIZE: usize = 1024;
+const TD_REPORT_TYPE_TDX: u8 = 0x81;
+
+/// The report data in a TD report.
+const TD_REPORT_REPORT_DATA: std::ops::Range<usize> = 128..192;
+/// The TD attributes, XFAM, MRTD, MRCONFIGID, MROWNER, MROWNERCONFIG and
+/// RTMRs in a TD report.
+const TD_REPORT_TD_MEASUREMENTS: std::ops::Range<usize> = 512..912;
+/// The report data in a quote.
+const QUOTE_REPORT_DATA: std::ops::Range<usize> = 568..632;
+/// The same fields as [`TD_REPORT_TD_MEASUREMENTS`] in a quote.
+const QUOTE_TD_MEASUREMENTS: std::ops::Range<usize> = 168..568;
+
+fn test_quote() -> Vec<u8> {
+    fs::read(data_path(TDX_QUOTE_PATH)).expect("couldn't read TDX quote")
+}
+
+/// Returns a TD report for the same TD as the test quote.
+fn td_report(report_data: [u8; 64]) -> [u8; TD_REPORT_SIZE] {
+    let mut td_report = [0u8; TD_REPORT_SIZE];
+    td_report[0] = TD_REPORT_TYPE_TDX;
+    td_report[TD_REPORT_REPORT_DATA].copy_from_slice(&report_data);
+    td_report[TD_REPORT_TD_MEASUREMENTS].copy_from_slice(&test_quote()[QUOTE_TD_MEASUREMENTS]);
+    td_report
+}
+
+/// Returns the test quote with the report data of the TD report, as the
+/// Quoting Enclave would generate it. The quote signature is not valid, but
+/// extracting the evidence doesn't check it.
+fn get_quote(td_report: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut quote = test_quote();
+    quote[QUOTE_REPORT_DATA].copy_from_slice(&td_report[TD_REPORT_REPORT_DATA]);
+    Ok(quote)
+}
+
+/// Generates the DICE data that Stage 0 on Intel TDX passes to the next layer.
+fn stage0_tdx_dice_data() -> DiceData {
+    let dice_data = oak_stage0_dice::generate_initial_dice_data(
+        |report_data| Ok(td_report(report_data)),
+        TeePlatform::IntelTdx,
+    )
+    .expect("couldn't generate initial DICE data");
+
+    let mut attester: DiceAttester = dice_data.try_into().expect("couldn't create attester");
+    attester
+        .extend(&oak_stage0_dice::encode_stage0_event(Stage0Measurements::default()))
+        .expect("couldn't extend attester");
+    let dice_data = DiceData::decode_length_delimited(attester.serialize().as_slice())
+        .expect("couldn't decode DICE data");
+
+    // The TD report survives the conversion to the fixed-size Stage 0 DICE data
+    // that is passed to the next layer.
+    let stage0_dice_data = oak_stage0_dice::dice_data_proto_to_stage0_dice_data(&dice_data)
+        .expect("couldn't convert DICE data");
+    assert_eq!(
+        stage0_dice_data.root_layer_evidence.get_remote_attestation_report().unwrap(),
+        &dice_data
+            .evidence
+            .as_ref()
+            .unwrap()
+            .root_layer
+            .as_ref()
+            .unwrap()
+            .remote_attestation_report
+    );
+    dice_data
+}
+
+/// Generates evidence the way Stage 0 on Intel TDX and a Restricted Kernel
+/// application do.
+fn stage0_tdx_evidence() -> Evidence {
+    add_application_keys(stage0_tdx_dice_data().try_into().expect("couldn't create attester"))
+}
+
+/// Adds the application layer of a Restricted Kernel application.
+fn add_application_keys(mut attester: DiceAttester) -> Evidence {
+    let application_event = Event {
+        tag: "oak_restricted_kernel_orchestrator".to_string(),
+        event: Some(prost_types::Any {
+            type_url: "type.googleapis.com/oak.attestation.v1.ApplicationLayerData".to_string(),
+            value: ApplicationLayerData::default().encode_to_vec(),
+        }),
+    }
+    .encode_to_vec();
+    let (_, verifying_key) = generate_ecdsa_key_pair();
+    attester
+        .add_application_keys(
+            oak_attestation::dice::LayerData {
+                additional_claims: vec![],
+                encoded_event: application_event,
+            },
+            &[1; 32],
+            &verifying_key,
+            None,
+            None,
+        )
+        .expect("couldn't add application keys")
+}
+
+#[test]
+fn extract_upgraded_tdx_evidence_succeeds() {
+    let mut evidence = stage0_tdx_evidence();
+    let root_layer = evidence.root_layer.as_mut().unwrap();
+    let report_data = td_report_report_data(&root_layer.remote_attestation_report);
+    upgrade_td_report_to_quote(root_layer, get_quote).expect("couldn't upgrade TD report");
+
+    let extracted = extract_evidence(&evidence).expect("couldn't extract evidence");
+    let Some(EvidenceValues::OakRestrictedKernel(values)) = extracted.evidence_values else {
+        panic!("unexpected evidence values: {:?}", extracted.evidence_values);
+    };
+    let Some(Report::Tdx(report)) = values.root_layer.and_then(|root_layer| root_layer.report)
+    else {
+        panic!("root layer doesn't contain a TDX report");
+    };
+    assert_eq!(report.report_data, report_data);
+}
+
+#[test]
+fn extract_tdx_evidence_upgraded_by_stage1_succeeds() {
+    let dice_data = stage0_tdx_dice_data();
+    let evidence = dice_data.evidence.as_ref().unwrap();
+    let report_data =
+        td_report_report_data(&evidence.root_layer.as_ref().unwrap().remote_attestation_report);
+    let event_log = evidence.event_log.clone().unwrap_or_default();
+    let stage0_dice_data = oak_stage0_dice::dice_data_proto_to_stage0_dice_data(&dice_data)
+        .expect("couldn't convert DICE data");
+
+    let attester = oak_containers_stage1_dice::stage0_dice_data_into_dice_attester(
+        stage0_dice_data,
+        event_log,
+        None,
+        get_quote,
+    )
+    .expect("couldn't create attester");
+    let evidence = add_application_keys(attester);
+
+    let extracted = extract_evidence(&evidence).expect("couldn't extract evidence");
+    let Some(EvidenceValues::OakRestrictedKernel(values)) = extracted.evidence_values else {
+        panic!("unexpected evidence values: {:?}", extracted.evidence_values);
+    };
+    let Some(Report::Tdx(report)) = values.root_layer.and_then(|root_layer| root_layer.report)
+    else {
+        panic!("root layer doesn't contain a TDX report");
+    };
+    assert_eq!(report.report_data, report_data);
+}
+
+#[test]
+fn extract_tdx_evidence_with_td_report_fails() {
+    let evidence = stage0_tdx_evidence();
+    let err = extract_evidence(&evidence).expect_err("extracted evidence with a TD report");
+    assert!(format!("{err:#}").contains("wasn't upgraded to a quote"), "{err:#}");
+}
+
+#[test]
+fn upgrade_td_report_to_quote_for_other_report_data_fails() {
+    let mut evidence = stage0_tdx_evidence();
+    let root_layer = evidence.root_layer.as_mut().unwrap();
+    let td_report = root_layer.remote_attestation_report.clone();
+    assert!(upgrade_td_report_to_quote(root_layer, |_| Ok(test_quote())).is_err());
+    assert_eq!(root_layer.remote_attestation_report, td_report);
+}
+
+#[test]
+fn upgrade_quote_fails() {
+    let mut evidence = stage0_tdx_evidence();
+    let root_layer = evidence.root_layer.as_mut().unwrap();
+    upgrade_td_report_to_quote(root_layer, get_quote).expect("couldn't upgrade TD report");
+    assert!(upgrade_td_report_to_quote(root_layer, get_quote).is_err());
+}
+
+fn td_report_report_data(td_report: &[u8]) -> Vec<u8> {
+    td_report[TD_REPORT_REPORT_DATA].to_vec()
+}
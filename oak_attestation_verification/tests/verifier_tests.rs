@@ -14,7 +14,7 @@
 // limitations under the License.
 //
 
-use std::fs;
+use std::{collections::BTreeMap, fs};
 
 use oak_attestation_verification::{
     expect::get_expected_values,
@@ -69,8 +69,9 @@ const GENOA_OC_REFERENCE_PATH: &str =
 const FAKE_EXPECTED_VALUES_PATH: &str =
     "oak_attestation_verification/testdata/fake_expected_values.binarypb";
 
-// Pretend the tests run at this time: 1 Nov 2023, 9:00 UTC
-const NOW_UTC_MILLIS: i64 = 1698829200000;
+// Pretend the tests run at this time: 1 Jul 2024, 0:00 UTC. This needs to be
+// within the validity period of the VCEK certificates of the test evidence.
+const NOW_UTC_MILLIS: i64 = 1719792000000;
 
 // Creates a valid AMD SEV-SNP evidence instance for a confidential borg
 // application.
@@ -145,7 +146,11 @@ fn create_oc_endorsements() -> Endorsements {
     };
 
     let root_layer =
-        RootLayerEndorsements { tee_certificate: vcek_milan_cert, stage0: Some(tre.clone()) };
+        RootLayerEndorsements {
+        tee_certificate: vcek_milan_cert,
+        stage0: Some(tre.clone()),
+        intel_tdx_collateral: None,
+    };
     #[allow(deprecated)]
     let kernel_layer = KernelLayerEndorsements {
         kernel: Some(tre.clone()),
@@ -177,7 +182,11 @@ fn create_rk_endorsements() -> Endorsements {
     let vcek_milan_cert =
         fs::read(data_path(RK_VCEK_MILAN_CERT_DER)).expect("couldn't read TEE cert");
 
-    let root_layer = RootLayerEndorsements { tee_certificate: vcek_milan_cert, stage0: None };
+    let root_layer = RootLayerEndorsements {
+        tee_certificate: vcek_milan_cert,
+        stage0: None,
+        intel_tdx_collateral: None,
+    };
     #[allow(deprecated)]
     let kernel_layer = KernelLayerEndorsements {
         kernel: None,
@@ -217,6 +226,7 @@ fn create_genoa_oc_endorsements() -> Endorsements {
     let root_layer = oak_proto_rust::oak::attestation::v1::RootLayerEndorsements {
         tee_certificate: vcek_milan_cert,
         stage0: None,
+        intel_tdx_collateral: None,
     };
     let ends = oak_proto_rust::oak::attestation::v1::OakContainersEndorsements {
         root_layer: Some(root_layer),
@@ -248,6 +258,7 @@ fn create_oc_reference_values() -> ReferenceValues {
         allow_debug: false,
         // See b/327069120: Do not skip over stage0.
         stage0: Some(skip.clone()),
+        ..Default::default()
     };
 
     let root_layer = RootLayerReferenceValues { amd_sev: Some(amd_sev), ..Default::default() };
@@ -296,6 +307,7 @@ fn create_rk_reference_values() -> ReferenceValues {
         allow_debug: false,
         // See b/327069120: Do not skip over stage0.
         stage0: Some(skip.clone()),
+        ..Default::default()
     };
 
     let root_layer = RootLayerReferenceValues { amd_sev: Some(amd_sev), ..Default::default() };
@@ -903,6 +915,67 @@ fn containers_invalid_tcb_tee_fails() {
     assert!(verify(NOW_UTC_MILLIS, &evidence, &endorsements, &reference_values).is_err());
 }
 
+// Returns the AMD SEV-SNP reference values of an Oak Containers chain.
+fn oc_amd_sev_reference_values(
+    reference_values: &mut ReferenceValues,
+) -> &mut AmdSevReferenceValues {
+    match reference_values.r#type.as_mut().expect("no reference values") {
+        reference_values::Type::OakContainers(oc) => oc
+            .root_layer
+            .as_mut()
+            .expect("no root layer")
+            .amd_sev
+            .as_mut()
+            .expect("invalid TEE platform"),
+        _ => panic!("wrong reference value type"),
+    }
+}
+
+#[test]
+fn containers_product_min_tcb_version_succeeds() {
+    let evidence = create_oc_evidence();
+    let endorsements = create_oc_endorsements();
+    let mut reference_values = create_oc_reference_values();
+
+    // The test evidence comes from a Milan machine, so only the Milan entry
+    // applies.
+    let amd_sev = oc_amd_sev_reference_values(&mut reference_values);
+    amd_sev.min_tcb_version = None;
+    amd_sev.product_min_tcb_versions = BTreeMap::from([
+        ("Milan".to_string(), TcbVersion { boot_loader: 3, tee: 0, snp: 20, microcode: 209 }),
+        ("Genoa".to_string(), TcbVersion { boot_loader: 256, tee: 256, snp: 256, microcode: 256 }),
+    ]);
+    assert!(verify(NOW_UTC_MILLIS, &evidence, &endorsements, &reference_values).is_ok());
+}
+
+#[test]
+fn containers_invalid_product_min_tcb_version_fails() {
+    let evidence = create_oc_evidence();
+    let endorsements = create_oc_endorsements();
+    let mut reference_values = create_oc_reference_values();
+
+    // The product-specific minimum takes precedence over the general one.
+    let amd_sev = oc_amd_sev_reference_values(&mut reference_values);
+    amd_sev.product_min_tcb_versions = BTreeMap::from([(
+        "Milan".to_string(),
+        TcbVersion { boot_loader: 3, tee: 0, snp: 20, microcode: 210 },
+    )]);
+    assert!(verify(NOW_UTC_MILLIS, &evidence, &endorsements, &reference_values).is_err());
+}
+
+#[test]
+fn containers_missing_product_min_tcb_version_fails() {
+    let evidence = create_oc_evidence();
+    let endorsements = create_oc_endorsements();
+    let mut reference_values = create_oc_reference_values();
+
+    let amd_sev = oc_amd_sev_reference_values(&mut reference_values);
+    amd_sev.min_tcb_version = None;
+    amd_sev.product_min_tcb_versions =
+        BTreeMap::from([("Genoa".to_string(), TcbVersion::default())]);
+    assert!(verify(NOW_UTC_MILLIS, &evidence, &endorsements, &reference_values).is_err());
+}
+
 #[test]
 fn containers_invalid_stage0_fails() {
     let evidence = create_oc_evidence();
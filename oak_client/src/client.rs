@@ -15,10 +15,10 @@
 //
 
 use anyhow::{anyhow, Context};
-use oak_crypto::encryptor::ClientEncryptor;
+use oak_crypto::encryptor::{ClientEncryptor, ResponseChunkDecryptor};
 
 use crate::{
-    transport::{EvidenceProvider, Transport},
+    transport::{EvidenceProvider, StreamingTransport, Transport},
     verifier::AttestationVerifier,
 };
 
@@ -80,3 +80,46 @@ impl<T: Transport + EvidenceProvider> OakClient<T> {
         Ok(response)
     }
 }
+
+impl<T: Transport + StreamingTransport> OakClient<T> {
+    /// Sends a request to a server with a streaming application handler and
+    /// returns the decrypted response chunks in order.
+    ///
+    /// Fails if the chunks are reordered or replayed, or if the response
+    /// stream ends before the final chunk.
+    pub async fn invoke_streaming(&mut self, request_body: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+        // Encrypt request.
+        let mut client_encryptor = ClientEncryptor::create(&self.server_encryption_public_key)
+            .context("couldn't create encryptor")?;
+        let encrypted_request = client_encryptor
+            .encrypt(request_body, EMPTY_ASSOCIATED_DATA)
+            .context("couldn't encrypt request")?;
+
+        // Send request.
+        self.transport
+            .send_request(&encrypted_request)
+            .await
+            .map_err(|error| anyhow!("couldn't send request: {:?}", error))?;
+
+        // Read and decrypt response chunks until the final one.
+        let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+        let mut response_chunks = Vec::new();
+        loop {
+            let encrypted_response = self
+                .transport
+                .receive_response()
+                .await
+                .map_err(|error| anyhow!("couldn't receive response: {:?}", error))?;
+            match decryptor
+                .decrypt(&encrypted_response)
+                .context("client couldn't decrypt response chunk")?
+            {
+                Some(response_chunk) => response_chunks.push(response_chunk),
+                None => break,
+            }
+        }
+        decryptor.finish()?;
+
+        Ok(response_chunks)
+    }
+}
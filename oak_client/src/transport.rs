@@ -31,3 +31,12 @@ pub trait Transport {
 pub trait EvidenceProvider {
     async fn get_endorsed_evidence(&mut self) -> anyhow::Result<EndorsedEvidence>;
 }
+
+/// A transport that can receive several encrypted responses for a single
+/// request, as sent by servers with a streaming application handler.
+#[async_trait::async_trait(?Send)]
+pub trait StreamingTransport {
+    async fn send_request(&mut self, encrypted_request: &EncryptedRequest) -> anyhow::Result<()>;
+
+    async fn receive_response(&mut self) -> anyhow::Result<EncryptedResponse>;
+}
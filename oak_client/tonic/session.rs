@@ -0,0 +1,149 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Async client that drives an [`oak_session::ClientSession`] over a
+//! bidirectional gRPC stream.
+
+use std::future::Future;
+
+use anyhow::Context;
+use futures::{channel::mpsc, SinkExt};
+use oak_proto_rust::oak::session::v1::{PlaintextMessage, SessionRequest, SessionResponse};
+use oak_session::{config::SessionConfig, ClientSession, ProtocolEngine, Session};
+
+/// Client for services that expose the Oak session protocol as a bidirectional
+/// streaming gRPC method.
+///
+/// Attestation and the Noise handshake are performed when the client is
+/// created, after which the client can be used either for request/response
+/// exchanges via [`GrpcSessionClient::send`], or as a stream of messages via
+/// [`GrpcSessionClient::write`] and [`GrpcSessionClient::read`].
+///
+/// The client is `Send`, so it can be moved between tasks of a multi-threaded
+/// runtime.
+pub struct GrpcSessionClient {
+    session: ClientSession,
+    response_stream: tonic::Streaming<SessionResponse>,
+    request_tx_channel: mpsc::Sender<SessionRequest>,
+}
+
+impl GrpcSessionClient {
+    /// Creates a new [GrpcSessionClient] and opens the session.
+    ///
+    /// The provided `stream_creator` will be immediately invoked to create a
+    /// new stream, and all messages of the session will be sent over it. If
+    /// the stream dies, a new client will need to be created.
+    ///
+    /// For example, if you have a gRPC service like:
+    /// ```text
+    /// service {
+    ///     rpc MyMethod(stream SessionRequest) returns (stream SessionResponse);
+    /// }
+    /// ```
+    ///
+    /// You will call:
+    /// ```ignore
+    /// let client =
+    ///   GrpcSessionClient::create(config, |rx| client.my_method(rx)).await?
+    /// ```
+    pub async fn create<Fut>(
+        config: SessionConfig,
+        stream_creator: impl FnOnce(mpsc::Receiver<SessionRequest>) -> Fut,
+    ) -> anyhow::Result<Self>
+    where
+        Fut: Future<Output = tonic::Result<tonic::Response<tonic::Streaming<SessionResponse>>>>,
+    {
+        let session = ClientSession::create(config).context("couldn't create client session")?;
+        let (tx, rx) = mpsc::channel(10);
+        let response_stream =
+            stream_creator(rx).await.context("couldn't send stream request")?.into_inner();
+
+        let mut client = Self { session, response_stream, request_tx_channel: tx };
+        client.open_session().await?;
+        Ok(client)
+    }
+
+    /// Encrypts and sends `request`, then waits for the next message from the
+    /// server and returns its decrypted contents.
+    pub async fn send(&mut self, request: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.write(request).await?;
+        self.read().await?.context("stream closed before a response was received")
+    }
+
+    /// Encrypts and sends `message` without waiting for a response.
+    pub async fn write(&mut self, message: &[u8]) -> anyhow::Result<()> {
+        self.session
+            .write(&PlaintextMessage { plaintext: message.to_vec() })
+            .context("couldn't write message to encrypt")?;
+        while let Some(request) =
+            self.session.get_outgoing_message().context("error getting encrypted request")?
+        {
+            self.send_request(request).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the next message from the server and returns its decrypted
+    /// contents, or `None` if the server closed the stream.
+    pub async fn read(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(message) =
+                self.session.read().context("error reading decrypted response")?
+            {
+                return Ok(Some(message.plaintext));
+            }
+            match self.receive_response().await? {
+                Some(response) => self.put_response(&response)?,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    async fn open_session(&mut self) -> anyhow::Result<()> {
+        loop {
+            // Each step of the session produces at most one message before it
+            // expects a response from the server.
+            if let Some(request) =
+                self.session.get_outgoing_message().context("error getting init message")?
+            {
+                self.send_request(request).await?;
+            }
+            if self.session.is_open() {
+                return Ok(());
+            }
+            let response = self
+                .receive_response()
+                .await?
+                .context("stream closed before the session was opened")?;
+            self.put_response(&response)?;
+        }
+    }
+
+    async fn send_request(&mut self, request: SessionRequest) -> anyhow::Result<()> {
+        self.request_tx_channel.send(request).await.context("couldn't send request")
+    }
+
+    async fn receive_response(&mut self) -> anyhow::Result<Option<SessionResponse>> {
+        self.response_stream.message().await.context("couldn't receive response")
+    }
+
+    fn put_response(&mut self, response: &SessionResponse) -> anyhow::Result<()> {
+        self.session
+            .put_incoming_message(response)
+            .context("error putting response into the session")?
+            .context("session doesn't expect any incoming messages")
+    }
+}
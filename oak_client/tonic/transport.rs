@@ -17,7 +17,7 @@ use std::future::Future;
 
 use anyhow::Context;
 use futures::channel::mpsc;
-use oak_client::transport::{EvidenceProvider, Transport};
+use oak_client::transport::{EvidenceProvider, StreamingTransport, Transport};
 use oak_proto_rust::oak::{
     crypto::v1::{EncryptedRequest, EncryptedResponse},
     session::v1::{
@@ -72,20 +72,44 @@ impl GrpcStreamingTransport {
         Ok(Self { response_stream, request_tx_channel: tx })
     }
 
-    async fn send_and_receive(
-        &mut self,
-        request: request_wrapper::Request,
-    ) -> anyhow::Result<ResponseWrapper> {
+    fn send(&mut self, request: request_wrapper::Request) -> anyhow::Result<()> {
         self.request_tx_channel
             .try_send(RequestWrapper { request: Some(request) })
-            .context("Couldn't send request")?;
+            .context("Couldn't send request")
+    }
+
+    async fn receive(&mut self) -> anyhow::Result<ResponseWrapper> {
         self.response_stream
             .message()
             .await
             .context("received empty response stream")?
             .context("empty response")
     }
+
+    async fn send_and_receive(
+        &mut self,
+        request: request_wrapper::Request,
+    ) -> anyhow::Result<ResponseWrapper> {
+        self.send(request)?;
+        self.receive().await
+    }
 }
+
+fn invoke_request(encrypted_request: &EncryptedRequest) -> request_wrapper::Request {
+    request_wrapper::Request::InvokeRequest(InvokeRequest {
+        encrypted_request: Some(encrypted_request.clone()),
+    })
+}
+
+fn encrypted_response(response_wrapper: ResponseWrapper) -> anyhow::Result<EncryptedResponse> {
+    match response_wrapper.response {
+        Some(response_wrapper::Response::InvokeResponse(InvokeResponse {
+            encrypted_response: Some(encrypted_response),
+        })) => Ok(encrypted_response),
+        _ => Err(anyhow::anyhow!("response_wrapper does not have a valid invoke_response message")),
+    }
+}
+
 #[async_trait::async_trait(?Send)]
 impl Transport for GrpcStreamingTransport {
     async fn invoke(
@@ -93,20 +117,23 @@ impl Transport for GrpcStreamingTransport {
         encrypted_request: &EncryptedRequest,
     ) -> anyhow::Result<EncryptedResponse> {
         let response_wrapper: ResponseWrapper = self
-            .send_and_receive(request_wrapper::Request::InvokeRequest(InvokeRequest {
-                encrypted_request: Some(encrypted_request.clone()),
-            }))
+            .send_and_receive(invoke_request(encrypted_request))
             .await
             .context("Sending invoke request")?;
 
-        match response_wrapper.response {
-            Some(response_wrapper::Response::InvokeResponse(InvokeResponse {
-                encrypted_response: Some(encrypted_response),
-            })) => Ok(encrypted_response),
-            _ => Err(anyhow::anyhow!(
-                "response_wrapper does not have a valid invoke_response message"
-            )),
-        }
+        encrypted_response(response_wrapper)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl StreamingTransport for GrpcStreamingTransport {
+    async fn send_request(&mut self, encrypted_request: &EncryptedRequest) -> anyhow::Result<()> {
+        self.send(invoke_request(encrypted_request)).context("Sending invoke request")
+    }
+
+    async fn receive_response(&mut self) -> anyhow::Result<EncryptedResponse> {
+        let response_wrapper = self.receive().await.context("Receiving invoke response")?;
+        encrypted_response(response_wrapper)
     }
 }
 
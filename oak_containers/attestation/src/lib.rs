@@ -22,13 +22,21 @@ use ciborium::Value;
 use coset::cwt::ClaimName;
 use oak_crypto::{
     encryption_key::{generate_encryption_key_pair, EncryptionKey},
-    encryptor::ServerEncryptor,
+    encryptor::{ClientEncryptor, ServerEncryptor},
 };
 use oak_dice::cert::{generate_ecdsa_key_pair, SHA2_256_ID};
 use oak_proto_rust::oak::{
     crypto::v1::EncryptedRequest, key_provisioning::v1::GroupKeys as GroupKeysProto,
 };
 use prost::Message;
+use zeroize::Zeroizing;
+
+/// Associated data bound to the encrypted group encryption key, so that it
+/// can't be passed off as the group signing key or vice versa.
+const GROUP_ENCRYPTION_KEY_ASSOCIATED_DATA: &[u8] = b"group encryption key";
+
+/// Associated data bound to the encrypted group signing key.
+const GROUP_SIGNING_KEY_ASSOCIATED_DATA: &[u8] = b"group signing key";
 
 /// Measures the downloaded container image bytes and configuration and returns
 /// these as a vector of additional CWT claims.
@@ -86,9 +94,13 @@ pub struct InstancePublicKeys {
 impl InstanceKeys {
     pub fn generate_group_keys(&self) -> (GroupKeys, GroupPublicKeys) {
         let (group_encryption_key, group_encryption_public_key) = generate_encryption_key_pair();
+        let (group_signing_key, group_signing_public_key) = generate_ecdsa_key_pair();
         (
-            GroupKeys { encryption_key: group_encryption_key },
-            GroupPublicKeys { encryption_public_key: group_encryption_public_key },
+            GroupKeys { encryption_key: group_encryption_key, signing_key: group_signing_key },
+            GroupPublicKeys {
+                encryption_public_key: group_encryption_public_key,
+                signing_public_key: group_signing_public_key,
+            },
         )
     }
 
@@ -98,26 +110,45 @@ impl InstanceKeys {
         let encrypted_encryption_private_key = group_keys
             .encrypted_encryption_private_key
             .context("encrypted encryption key wasn't provided")?;
+        let encrypted_signing_private_key = group_keys
+            .encrypted_signing_private_key
+            .context("encrypted signing key wasn't provided")?;
 
         // Decrypt group keys.
-        let (_, mut decrypted_encryption_private_key, _) =
+        let (_, mut decrypted_encryption_private_key, encryption_key_associated_data) =
             ServerEncryptor::decrypt(&encrypted_encryption_private_key, &self.encryption_key)
                 .context("couldn't decrypt the encryption private key")?;
+        anyhow::ensure!(
+            encryption_key_associated_data == GROUP_ENCRYPTION_KEY_ASSOCIATED_DATA,
+            "encrypted encryption key has unexpected associated data"
+        );
+        let (_, decrypted_signing_private_key, signing_key_associated_data) =
+            ServerEncryptor::decrypt(&encrypted_signing_private_key, &self.encryption_key)
+                .context("couldn't decrypt the signing private key")?;
+        let decrypted_signing_private_key = Zeroizing::new(decrypted_signing_private_key);
+        anyhow::ensure!(
+            signing_key_associated_data == GROUP_SIGNING_KEY_ASSOCIATED_DATA,
+            "encrypted signing key has unexpected associated data"
+        );
 
         let group_encryption_key =
             EncryptionKey::deserialize(&mut decrypted_encryption_private_key)
                 .context("couldn't deserialize private key")?;
+        let group_signing_key = p256::ecdsa::SigningKey::from_slice(&decrypted_signing_private_key)
+            .map_err(|err| anyhow::anyhow!("couldn't deserialize signing key: {err}"))?;
 
-        Ok(GroupKeys { encryption_key: group_encryption_key })
+        Ok(GroupKeys { encryption_key: group_encryption_key, signing_key: group_signing_key })
     }
 }
 
 pub struct GroupKeys {
     pub encryption_key: EncryptionKey,
+    pub signing_key: p256::ecdsa::SigningKey,
 }
 
 pub struct GroupPublicKeys {
     pub encryption_public_key: Vec<u8>,
+    pub signing_public_key: p256::ecdsa::VerifyingKey,
 }
 
 impl GroupKeys {
@@ -127,6 +158,127 @@ impl GroupKeys {
         &self,
         peer_public_key: &[u8],
     ) -> anyhow::Result<EncryptedRequest> {
-        self.encryption_key.encrypted_private_key(peer_public_key)
+        self.encryption_key
+            .encrypted_private_key(peer_public_key, GROUP_ENCRYPTION_KEY_ASSOCIATED_DATA)
+    }
+
+    /// Returns group signing private key which was encrypted with the
+    /// `peer_public_key`.
+    pub fn encrypted_group_signing_key(
+        &self,
+        peer_public_key: &[u8],
+    ) -> anyhow::Result<EncryptedRequest> {
+        let mut client_encryptor =
+            ClientEncryptor::create(peer_public_key).context("couldn't create client encryptor")?;
+        let signing_private_key: Zeroizing<[u8; 32]> =
+            Zeroizing::new(self.signing_key.to_bytes().into());
+        client_encryptor.encrypt(signing_private_key.as_slice(), GROUP_SIGNING_KEY_ASSOCIATED_DATA)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oak_crypto::{signer::Signer, EMPTY_ASSOCIATED_DATA};
+    use p256::ecdsa::signature::Verifier;
+
+    use super::*;
+
+    #[test]
+    fn test_group_keys_are_shared_with_followers() {
+        let (leader_instance_keys, _) = generate_instance_keys();
+        let (leader_group_keys, leader_group_public_keys) =
+            leader_instance_keys.generate_group_keys();
+
+        let (follower_instance_keys, follower_instance_public_keys) = generate_instance_keys();
+        let group_keys_proto = GroupKeysProto {
+            encrypted_encryption_private_key: Some(
+                leader_group_keys
+                    .encrypted_group_encryption_key(
+                        &follower_instance_public_keys.encryption_public_key,
+                    )
+                    .unwrap(),
+            ),
+            encrypted_signing_private_key: Some(
+                leader_group_keys
+                    .encrypted_group_signing_key(
+                        &follower_instance_public_keys.encryption_public_key,
+                    )
+                    .unwrap(),
+            ),
+        };
+        let follower_group_keys =
+            follower_instance_keys.provide_group_keys(group_keys_proto).unwrap();
+
+        assert_eq!(
+            follower_group_keys.signing_key.verifying_key(),
+            &leader_group_public_keys.signing_public_key
+        );
+        let message = b"message signed by a follower";
+        let signature =
+            p256::ecdsa::Signature::from_slice(&follower_group_keys.signing_key.sign(message))
+                .unwrap();
+        assert!(leader_group_public_keys.signing_public_key.verify(message, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_provide_group_keys_requires_signing_key() {
+        let (leader_instance_keys, _) = generate_instance_keys();
+        let (leader_group_keys, _) = leader_instance_keys.generate_group_keys();
+        let (follower_instance_keys, follower_instance_public_keys) = generate_instance_keys();
+        let group_keys_proto = GroupKeysProto {
+            encrypted_encryption_private_key: Some(
+                leader_group_keys
+                    .encrypted_group_encryption_key(
+                        &follower_instance_public_keys.encryption_public_key,
+                    )
+                    .unwrap(),
+            ),
+            encrypted_signing_private_key: None,
+        };
+        assert!(follower_instance_keys.provide_group_keys(group_keys_proto).is_err());
+    }
+
+    #[test]
+    fn test_provide_group_keys_rejects_swapped_keys() {
+        let (leader_instance_keys, _) = generate_instance_keys();
+        let (leader_group_keys, _) = leader_instance_keys.generate_group_keys();
+        let (follower_instance_keys, follower_instance_public_keys) = generate_instance_keys();
+        let encrypted_encryption_private_key = leader_group_keys
+            .encrypted_group_encryption_key(&follower_instance_public_keys.encryption_public_key)
+            .unwrap();
+        let encrypted_signing_private_key = leader_group_keys
+            .encrypted_group_signing_key(&follower_instance_public_keys.encryption_public_key)
+            .unwrap();
+        let group_keys_proto = GroupKeysProto {
+            encrypted_encryption_private_key: Some(encrypted_signing_private_key),
+            encrypted_signing_private_key: Some(encrypted_encryption_private_key),
+        };
+        assert!(follower_instance_keys.provide_group_keys(group_keys_proto).is_err());
+    }
+
+    #[test]
+    fn test_provide_group_keys_rejects_unlabelled_keys() {
+        let (leader_instance_keys, _) = generate_instance_keys();
+        let (leader_group_keys, _) = leader_instance_keys.generate_group_keys();
+        let (follower_instance_keys, follower_instance_public_keys) = generate_instance_keys();
+        let group_keys_proto = GroupKeysProto {
+            encrypted_encryption_private_key: Some(
+                leader_group_keys
+                    .encryption_key
+                    .encrypted_private_key(
+                        &follower_instance_public_keys.encryption_public_key,
+                        EMPTY_ASSOCIATED_DATA,
+                    )
+                    .unwrap(),
+            ),
+            encrypted_signing_private_key: Some(
+                leader_group_keys
+                    .encrypted_group_signing_key(
+                        &follower_instance_public_keys.encryption_public_key,
+                    )
+                    .unwrap(),
+            ),
+        };
+        assert!(follower_instance_keys.provide_group_keys(group_keys_proto).is_err());
     }
 }
@@ -16,22 +16,44 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use anyhow::{Context, Result};
-use futures::channel::mpsc;
+use futures::{channel::mpsc, SinkExt};
 use oak_client::{client::OakClient, verifier::InsecureAttestationVerifier};
-use oak_client_tonic::transport::GrpcStreamingTransport;
+use oak_client_tonic::{session::GrpcSessionClient, transport::GrpcStreamingTransport};
 use oak_containers_sdk::{
     standalone::StandaloneOrchestrator, OakSessionContext, OrchestratorInterface,
+    ResponseChunkStream, StreamingApplicationHandler,
 };
 use oak_hello_world_proto::oak::containers::example::trusted_application_client::TrustedApplicationClient;
-use oak_proto_rust::oak::session::v1::{PlaintextMessage, SessionRequest, SessionResponse};
+use oak_proto_rust::oak::session::v1::{session_request::Request, PlaintextMessage};
 use oak_session::{
-    attestation::AttestationType, config::SessionConfig, handshake::HandshakeType, ProtocolEngine,
-    Session,
+    attestation::AttestationType, config::SessionConfig, handshake::HandshakeType, ClientSession,
+    ProtocolEngine, Session,
 };
 use tokio::net::TcpListener;
 use tonic::transport::Channel;
 
+/// Echoes the request back in chunks of at most `CHUNK_SIZE` bytes.
+struct ChunkingApplicationHandler;
+
+const CHUNK_SIZE: usize = 4;
+
+impl StreamingApplicationHandler for ChunkingApplicationHandler {
+    fn handle_streaming<'a>(&'a self, request_bytes: &'a [u8]) -> ResponseChunkStream<'a> {
+        Box::pin(tokio_stream::iter(
+            request_bytes.chunks(CHUNK_SIZE).map(|chunk| anyhow::Ok(chunk.to_vec())),
+        ))
+    }
+}
+
 async fn start_server() -> Result<(SocketAddr, tokio::task::JoinHandle<Result<()>>)> {
+    start_server_with_streaming_handler(None).await
+}
+
+/// Starts the trusted application. If a streaming application handler is
+/// provided, the legacy session serves it instead of the Hello World handler.
+async fn start_server_with_streaming_handler(
+    streaming_application_handler: Option<Box<dyn StreamingApplicationHandler>>,
+) -> Result<(SocketAddr, tokio::task::JoinHandle<Result<()>>)> {
     let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
     let listener = TcpListener::bind(addr).await?;
     let addr = listener.local_addr()?;
@@ -42,19 +64,26 @@ async fn start_server() -> Result<(SocketAddr, tokio::task::JoinHandle<Result<()
     let endorsed_evidence = orchestrator.get_endorsed_evidence();
     let application_config = orchestrator.get_application_config().await?;
 
+    let oak_session_context = match streaming_application_handler {
+        Some(streaming_application_handler) => OakSessionContext::new_streaming(
+            Box::new(encryption_key_handle),
+            endorsed_evidence,
+            streaming_application_handler,
+        ),
+        None => OakSessionContext::new(
+            Box::new(encryption_key_handle),
+            endorsed_evidence,
+            Box::new(oak_containers_hello_world_trusted_app::app::HelloWorldApplicationHandler {
+                application_config: application_config.clone(),
+            }),
+        ),
+    };
+
     Ok((
         addr,
         tokio::spawn(oak_containers_hello_world_trusted_app::app_service::create(
             listener,
-            OakSessionContext::new(
-                Box::new(encryption_key_handle),
-                endorsed_evidence,
-                Box::new(
-                    oak_containers_hello_world_trusted_app::app::HelloWorldApplicationHandler {
-                        application_config: application_config.clone(),
-                    },
-                ),
-            ),
+            oak_session_context,
             Box::new(oak_containers_hello_world_trusted_app::app::HelloWorldApplicationHandler {
                 application_config,
             }),
@@ -90,70 +119,57 @@ async fn test_legacy() {
     // Create client
     let mut oak_client = OakClient::create(transport, &attestation_verifier).await.unwrap();
 
-    // Send single request, see the response
-    assert_eq!(
-        String::from_utf8(oak_client.invoke(b"standalone user").await.unwrap()).unwrap(),
-        "Hello from the trusted side, standalone user! Btw, the Trusted App has a config with a length of 4 bytes."
-    );
+    // Send several requests on the same stream, see the responses
+    for _ in 0..2 {
+        assert_eq!(
+            String::from_utf8(oak_client.invoke(b"standalone user").await.unwrap()).unwrap(),
+            "Hello from the trusted side, standalone user! Btw, the Trusted App has a config with a length of 4 bytes."
+        );
+    }
 }
 
-#[async_trait::async_trait]
-trait ClientSessionHelper {
-    fn encrypt_request(&mut self, request: &[u8]) -> anyhow::Result<SessionRequest>;
-    fn decrypt_response(&mut self, session_response: &SessionResponse) -> anyhow::Result<Vec<u8>>;
-    async fn init_session(
-        &mut self,
-        send_request: &mut mpsc::Sender<SessionRequest>,
-        receive_response: &mut tonic::Streaming<SessionResponse>,
-    ) -> anyhow::Result<()>;
-}
+#[tokio::test]
+async fn test_legacy_streaming() {
+    // Start server
+    let (addr, _join_handle) =
+        start_server_with_streaming_handler(Some(Box::new(ChunkingApplicationHandler)))
+            .await
+            .unwrap();
 
-#[async_trait::async_trait]
-impl ClientSessionHelper for oak_session::ClientSession {
-    fn encrypt_request(&mut self, request: &[u8]) -> anyhow::Result<SessionRequest> {
-        self.write(&PlaintextMessage { plaintext: request.to_vec() })
-            .context("couldn't write message to encrypt")?;
+    let url = format!("http://{addr}");
 
-        self.get_outgoing_message()
-            .context("error getting encrypted request")?
-            .ok_or_else(|| anyhow::anyhow!("no encrypted request"))
-    }
+    println!("Connecting to test server on {}", url);
 
-    fn decrypt_response(&mut self, session_response: &SessionResponse) -> anyhow::Result<Vec<u8>> {
-        self.put_incoming_message(session_response)
-            .context("failed to put response for decryption")?;
+    let channel = Channel::from_shared(url)
+        .context("couldn't create gRPC channel")
+        .unwrap()
+        .connect()
+        .await
+        .context("couldn't connect via gRPC channel")
+        .unwrap();
 
-        self.read()
-            .context("error reading decrypted response")?
-            .ok_or_else(|| anyhow::anyhow!("no encrypted response"))
-            .map(|p| p.plaintext)
-    }
+    let mut client = TrustedApplicationClient::new(channel);
 
-    async fn init_session(
-        &mut self,
-        send_request: &mut mpsc::Sender<SessionRequest>,
-        receive_response: &mut tonic::Streaming<SessionResponse>,
-    ) -> Result<()> {
-        while !self.is_open() {
-            let init_request =
-                self.get_outgoing_message().context("error getting init_message")?.ok_or_else(
-                    || anyhow::anyhow!("no init message provided, but session not initialized"),
-                )?;
-
-            send_request.try_send(init_request).context("failed to send init request")?;
-
-            if let Some(init_response) =
-                receive_response.message().await.context("failed to receive response")?
-            {
-                self.put_incoming_message(&init_response)
-                    .context("error putting init_response response")?;
-            }
-        }
-        Ok(())
+    let transport = GrpcStreamingTransport::new(|rx| client.legacy_session(rx))
+        .await
+        .expect("couldn't create GRPC streaming transport");
+
+    let attestation_verifier = InsecureAttestationVerifier {};
+
+    // Create client
+    let mut oak_client = OakClient::create(transport, &attestation_verifier).await.unwrap();
+
+    // Send several requests on the same stream, see the response chunks
+    for request in [&b"standalone user"[..], b"another request"] {
+        let response_chunks = oak_client.invoke_streaming(request).await.unwrap();
+        assert_eq!(
+            response_chunks,
+            request.chunks(CHUNK_SIZE).map(<[u8]>::to_vec).collect::<Vec<_>>()
+        );
     }
 }
 
-#[tokio::test]
+#[tokio::test(flavor = "multi_thread")]
 async fn test_noise() {
     // Start server
     let (addr, _join_handle) = start_server().await.unwrap();
@@ -172,36 +188,63 @@ async fn test_noise() {
 
     let mut client = TrustedApplicationClient::new(channel);
 
-    let (mut tx, rx) = mpsc::channel(10);
-
-    let mut response_stream =
-        client.oak_session(rx).await.expect("couldn't send stream request").into_inner();
-
-    // We don't have a noise client impl yet, so we need to manage the session
-    // manually.
-    let mut client_session = oak_session::ClientSession::create(
+    let mut session_client = GrpcSessionClient::create(
         SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+        |rx| client.oak_session(rx),
     )
-    .expect("could not create client session");
-
-    client_session.init_session(&mut tx, &mut response_stream).await.expect("failed to handshake");
-
-    let request =
-        client_session.encrypt_request(b"standalone user").expect("failed to encrypt message");
+    .await
+    .expect("couldn't open session");
 
-    tx.try_send(request).expect("Could not send request to server");
-
-    let response = response_stream
-        .message()
+    // The client is moved into a task of the multi-threaded runtime.
+    let response = tokio::spawn(async move { session_client.send(b"standalone user").await })
         .await
-        .expect("error getting response")
-        .expect("didn't get any repsonse");
-
-    let decrypted_response =
-        client_session.decrypt_response(&response).expect("failed to decrypt response");
+        .expect("client task failed")
+        .expect("couldn't send request");
 
     assert_eq!(
-        String::from_utf8(decrypted_response).unwrap(),
+        String::from_utf8(response).unwrap(),
         "Hello from the trusted side, standalone user! Btw, the Trusted App has a config with a length of 4 bytes."
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_noise_rejects_corrupted_request() {
+    let (addr, _join_handle) = start_server().await.unwrap();
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .context("couldn't create gRPC channel")
+        .unwrap()
+        .connect()
+        .await
+        .context("couldn't connect via gRPC channel")
+        .unwrap();
+    let mut client = TrustedApplicationClient::new(channel);
+
+    let mut session = ClientSession::create(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+    )
+    .unwrap();
+    let (mut tx, rx) = mpsc::channel(10);
+    let mut response_stream = client.oak_session(rx).await.unwrap().into_inner();
+    loop {
+        if let Some(request) = session.get_outgoing_message().unwrap() {
+            tx.send(request).await.unwrap();
+        }
+        if session.is_open() {
+            break;
+        }
+        let response = response_stream.message().await.unwrap().expect("stream closed");
+        session.put_incoming_message(&response).unwrap();
+    }
+
+    // The first encrypted message after the handshake fails to decrypt.
+    session.write(&PlaintextMessage { plaintext: b"standalone user".to_vec() }).unwrap();
+    let mut request = session.get_outgoing_message().unwrap().expect("no encrypted request");
+    let Some(Request::EncryptedMessage(encrypted_message)) = request.request.as_mut() else {
+        panic!("request isn't an encrypted message");
+    };
+    encrypted_message.ciphertext[0] ^= 1;
+    tx.send(request).await.unwrap();
+
+    let status = response_stream.message().await.expect_err("corrupted request was accepted");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument, "{status}");
+}
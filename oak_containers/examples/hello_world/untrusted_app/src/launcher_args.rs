@@ -48,6 +48,10 @@ pub fn launcher_args(
             pci_passthrough: None,
             vm_type: oak_containers_launcher::QemuVmType::Default,
         },
+        endorsement_params: oak_containers_launcher::EndorsementParams::default(),
+        telemetry_params: oak_containers_launcher::TelemetryParams::default(),
         communication_channel: oak_containers_launcher::ChannelType::default(),
+        reference_values: None,
+        leader_key_provisioning_uri: None,
     })
 }
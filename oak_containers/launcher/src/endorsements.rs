@@ -0,0 +1,462 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::Parser;
+use oak_proto_rust::oak::attestation::v1::{
+    endorsements, ContainerLayerEndorsements, Endorsements, EventEndorsement, EventEndorsements,
+    Evidence, KernelLayerEndorsements, OakContainersEndorsements, RootLayerEndorsements,
+    SystemLayerEndorsements, TeePlatform, TransparentReleaseEndorsement,
+};
+use oak_sev_snp_attestation_report::AttestationReport;
+use prost::Message;
+use zerocopy::{AsBytes, FromBytes};
+
+use crate::path_exists;
+
+/// Represents the endorsement files that the launcher passes on to clients
+/// alongside the attestation evidence of the enclave.
+///
+/// Each binary is endorsed by an in-toto statement, a signature over the
+/// statement, and optionally a Rekor log entry for the statement. All
+/// endorsements are optional; binaries without an endorsement are left
+/// unendorsed.
+#[derive(Parser, Clone, Debug, Default, PartialEq)]
+pub struct Params {
+    /// Path to the DER-encoded TEE certificate of the host, e.g. the AMD VCEK
+    /// certificate.
+    #[arg(long, value_parser = path_exists, value_name = "FILE")]
+    pub tee_certificate: Option<PathBuf>,
+
+    /// Path to a directory for caching TEE certificates, keyed by the chip ID
+    /// and TCB version from the attestation report.
+    ///
+    /// Certificates that the enclave obtains from the host firmware are stored
+    /// in the cache, and used for enclaves on hosts whose firmware doesn't
+    /// provide them. Ignored if `--tee-certificate` is set.
+    #[arg(long, value_name = "DIR")]
+    pub tee_certificate_cache: Option<PathBuf>,
+
+    /// Path to the endorsement statement for the stage0 firmware.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "stage0_endorsement_signature",
+    )]
+    pub stage0_endorsement: Option<PathBuf>,
+
+    /// Path to the signature over the stage0 endorsement statement.
+    #[arg(long, value_parser = path_exists, value_name = "FILE", requires = "stage0_endorsement")]
+    pub stage0_endorsement_signature: Option<PathBuf>,
+
+    /// Path to the Rekor log entry for the stage0 endorsement statement.
+    #[arg(long, value_parser = path_exists, value_name = "FILE", requires = "stage0_endorsement")]
+    pub stage0_rekor_log_entry: Option<PathBuf>,
+
+    /// Path to the endorsement statement for the kernel.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "kernel_endorsement_signature",
+    )]
+    pub kernel_endorsement: Option<PathBuf>,
+
+    /// Path to the signature over the kernel endorsement statement.
+    #[arg(long, value_parser = path_exists, value_name = "FILE", requires = "kernel_endorsement")]
+    pub kernel_endorsement_signature: Option<PathBuf>,
+
+    /// Path to the Rekor log entry for the kernel endorsement statement.
+    #[arg(long, value_parser = path_exists, value_name = "FILE", requires = "kernel_endorsement")]
+    pub kernel_rekor_log_entry: Option<PathBuf>,
+
+    /// Path to the endorsement statement for the system image.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "system_image_endorsement_signature",
+    )]
+    pub system_image_endorsement: Option<PathBuf>,
+
+    /// Path to the signature over the system image endorsement statement.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "system_image_endorsement",
+    )]
+    pub system_image_endorsement_signature: Option<PathBuf>,
+
+    /// Path to the Rekor log entry for the system image endorsement statement.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "system_image_endorsement",
+    )]
+    pub system_image_rekor_log_entry: Option<PathBuf>,
+
+    /// Path to the endorsement statement for the container bundle.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "container_endorsement_signature",
+    )]
+    pub container_endorsement: Option<PathBuf>,
+
+    /// Path to the signature over the container bundle endorsement statement.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "container_endorsement",
+    )]
+    pub container_endorsement_signature: Option<PathBuf>,
+
+    /// Path to the Rekor log entry for the container bundle endorsement
+    /// statement.
+    #[arg(
+        long,
+        value_parser = path_exists,
+        value_name = "FILE",
+        requires = "container_endorsement",
+    )]
+    pub container_rekor_log_entry: Option<PathBuf>,
+}
+
+/// Reads the endorsement files and assembles them into [`Endorsements`].
+///
+/// Both the per-layer `OakContainersEndorsements` and the `EventEndorsements`
+/// are populated. The event endorsements follow the order in which events are
+/// added to the event log during boot: stage0 (kernel layer), stage1 (system
+/// layer) and the orchestrator (container layer).
+pub fn load(params: &Params) -> anyhow::Result<Endorsements> {
+    let tee_certificate = params
+        .tee_certificate
+        .as_deref()
+        .map(read)
+        .transpose()
+        .context("couldn't read TEE certificate")?
+        .unwrap_or_default();
+    let root_layer = RootLayerEndorsements {
+        tee_certificate,
+        stage0: load_binary_endorsement(
+            &params.stage0_endorsement,
+            &params.stage0_endorsement_signature,
+            &params.stage0_rekor_log_entry,
+        )
+        .context("couldn't load stage0 endorsement")?,
+        intel_tdx_collateral: None,
+    };
+    let kernel_layer = KernelLayerEndorsements {
+        kernel: load_binary_endorsement(
+            &params.kernel_endorsement,
+            &params.kernel_endorsement_signature,
+            &params.kernel_rekor_log_entry,
+        )
+        .context("couldn't load kernel endorsement")?,
+        ..Default::default()
+    };
+    let system_layer = SystemLayerEndorsements {
+        system_image: load_binary_endorsement(
+            &params.system_image_endorsement,
+            &params.system_image_endorsement_signature,
+            &params.system_image_rekor_log_entry,
+        )
+        .context("couldn't load system image endorsement")?,
+    };
+    let container_layer = ContainerLayerEndorsements {
+        binary: load_binary_endorsement(
+            &params.container_endorsement,
+            &params.container_endorsement_signature,
+            &params.container_rekor_log_entry,
+        )
+        .context("couldn't load container endorsement")?,
+        configuration: None,
+    };
+
+    let event_endorsements = EventEndorsements {
+        encoded_event_endorsements: vec![
+            encode_event_endorsement("KernelLayerEndorsements", &kernel_layer),
+            encode_event_endorsement("SystemLayerEndorsements", &system_layer),
+            encode_event_endorsement("ContainerLayerEndorsements", &container_layer),
+        ],
+    };
+
+    Ok(Endorsements {
+        r#type: Some(endorsements::Type::OakContainers(OakContainersEndorsements {
+            root_layer: Some(root_layer),
+            kernel_layer: Some(kernel_layer),
+            system_layer: Some(system_layer),
+            container_layer: Some(container_layer),
+        })),
+        event_endorsements: Some(event_endorsements),
+    })
+}
+
+/// Fills in the TEE certificate endorsement for the given evidence, unless one
+/// was provided explicitly.
+///
+/// The certificate that the enclave obtained from the host firmware is used if
+/// present, and is written to the cache. Otherwise the certificate is looked up
+/// in the cache.
+pub fn attach_tee_certificate(
+    endorsements: &mut Endorsements,
+    evidence: &Evidence,
+    cache: Option<&Path>,
+) -> anyhow::Result<()> {
+    let Some(endorsements::Type::OakContainers(OakContainersEndorsements {
+        root_layer: Some(root_layer_endorsements),
+        ..
+    })) = endorsements.r#type.as_mut()
+    else {
+        anyhow::bail!("no root layer endorsements");
+    };
+    if !root_layer_endorsements.tee_certificate.is_empty() {
+        return Ok(());
+    }
+    let root_layer = evidence.root_layer.as_ref().context("no root layer evidence")?;
+    let cache_path = match cache {
+        Some(cache) if root_layer.platform == TeePlatform::AmdSevSnp as i32 => {
+            Some(cache.join(tee_certificate_cache_key(&root_layer.remote_attestation_report)?))
+        }
+        _ => None,
+    };
+
+    if !root_layer.tee_certificate.is_empty() {
+        root_layer_endorsements.tee_certificate = root_layer.tee_certificate.clone();
+        if let (Some(cache), Some(cache_path)) = (cache, cache_path) {
+            std::fs::create_dir_all(cache)
+                .with_context(|| format!("couldn't create TEE certificate cache {cache:?}"))?;
+            std::fs::write(&cache_path, &root_layer.tee_certificate)
+                .with_context(|| format!("couldn't write TEE certificate to {cache_path:?}"))?;
+        }
+    } else if let Some(cache_path) = cache_path.filter(|path| path.exists()) {
+        root_layer_endorsements.tee_certificate = read(&cache_path)?;
+    }
+    Ok(())
+}
+
+/// Returns the cache file name for the VCEK certificate that signed the given
+/// AMD SEV-SNP attestation report.
+///
+/// A VCEK is specific to a chip and the TCB version it was derived for.
+fn tee_certificate_cache_key(report: &[u8]) -> anyhow::Result<String> {
+    let report =
+        AttestationReport::ref_from(report).context("invalid AMD SEV-SNP attestation report")?;
+    Ok(format!(
+        "{}_{}.der",
+        hex::encode(report.data.chip_id),
+        hex::encode(report.data.reported_tcb.as_bytes())
+    ))
+}
+
+fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("couldn't read {path:?}"))
+}
+
+fn load_binary_endorsement(
+    endorsement: &Option<PathBuf>,
+    signature: &Option<PathBuf>,
+    rekor_log_entry: &Option<PathBuf>,
+) -> anyhow::Result<Option<TransparentReleaseEndorsement>> {
+    let Some(endorsement) = endorsement else {
+        anyhow::ensure!(
+            signature.is_none() && rekor_log_entry.is_none(),
+            "signature or log entry provided without an endorsement"
+        );
+        return Ok(None);
+    };
+    let signature = signature.as_deref().context("endorsement provided without a signature")?;
+    Ok(Some(TransparentReleaseEndorsement {
+        endorsement: read(endorsement)?,
+        subject: vec![],
+        endorsement_signature: read(signature)?,
+        rekor_log_entry: rekor_log_entry.as_deref().map(read).transpose()?.unwrap_or_default(),
+    }))
+}
+
+fn encode_event_endorsement<M: Message>(name: &str, message: &M) -> Vec<u8> {
+    EventEndorsement {
+        event_endorsement: Some(prost_types::Any {
+            type_url: format!("type.googleapis.com/oak.attestation.v1.{name}"),
+            value: message.encode_to_vec(),
+        }),
+    }
+    .encode_to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use oak_proto_rust::oak::attestation::v1::RootLayerEvidence;
+    use zerocopy::FromZeroes;
+
+    use super::*;
+
+    fn temp_file(contents: &[u8]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    fn decode_event_endorsement<M: Message + Default>(encoded: &[u8], name: &str) -> M {
+        let any = EventEndorsement::decode(encoded).unwrap().event_endorsement.unwrap();
+        assert_eq!(any.type_url, format!("type.googleapis.com/oak.attestation.v1.{name}"));
+        M::decode(any.value.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_load_endorsements() {
+        let tee_certificate = temp_file(b"vcek");
+        let kernel_endorsement = temp_file(b"kernel statement");
+        let kernel_signature = temp_file(b"kernel signature");
+        let kernel_log_entry = temp_file(b"kernel log entry");
+        let container_endorsement = temp_file(b"container statement");
+        let container_signature = temp_file(b"container signature");
+        let params = Params {
+            tee_certificate: Some(tee_certificate.path().into()),
+            kernel_endorsement: Some(kernel_endorsement.path().into()),
+            kernel_endorsement_signature: Some(kernel_signature.path().into()),
+            kernel_rekor_log_entry: Some(kernel_log_entry.path().into()),
+            container_endorsement: Some(container_endorsement.path().into()),
+            container_endorsement_signature: Some(container_signature.path().into()),
+            ..Default::default()
+        };
+
+        let endorsements = load(&params).unwrap();
+
+        let Some(endorsements::Type::OakContainers(layers)) = endorsements.r#type else {
+            panic!("expected Oak Containers endorsements");
+        };
+        let root_layer = layers.root_layer.unwrap();
+        assert_eq!(root_layer.tee_certificate, b"vcek");
+        assert_eq!(root_layer.stage0, None);
+        let kernel = layers.kernel_layer.clone().unwrap().kernel.unwrap();
+        assert_eq!(kernel.endorsement, b"kernel statement");
+        assert_eq!(kernel.endorsement_signature, b"kernel signature");
+        assert_eq!(kernel.rekor_log_entry, b"kernel log entry");
+        assert_eq!(layers.system_layer.clone().unwrap().system_image, None);
+        let container = layers.container_layer.clone().unwrap().binary.unwrap();
+        assert_eq!(container.endorsement, b"container statement");
+        assert!(container.rekor_log_entry.is_empty());
+
+        let event_endorsements =
+            endorsements.event_endorsements.unwrap().encoded_event_endorsements;
+        assert_eq!(event_endorsements.len(), 3);
+        assert_eq!(
+            decode_event_endorsement::<KernelLayerEndorsements>(
+                &event_endorsements[0],
+                "KernelLayerEndorsements"
+            ),
+            layers.kernel_layer.unwrap()
+        );
+        assert_eq!(
+            decode_event_endorsement::<SystemLayerEndorsements>(
+                &event_endorsements[1],
+                "SystemLayerEndorsements"
+            ),
+            layers.system_layer.unwrap()
+        );
+        assert_eq!(
+            decode_event_endorsement::<ContainerLayerEndorsements>(
+                &event_endorsements[2],
+                "ContainerLayerEndorsements"
+            ),
+            layers.container_layer.unwrap()
+        );
+    }
+
+    fn snp_evidence(chip_id: u8, tee_certificate: &[u8]) -> Evidence {
+        let mut report = AttestationReport::new_zeroed();
+        report.data.chip_id = [chip_id; 64];
+        Evidence {
+            root_layer: Some(RootLayerEvidence {
+                platform: TeePlatform::AmdSevSnp as i32,
+                remote_attestation_report: report.as_bytes().to_vec(),
+                tee_certificate: tee_certificate.to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn tee_certificate(endorsements: &Endorsements) -> &[u8] {
+        let Some(endorsements::Type::OakContainers(layers)) = &endorsements.r#type else {
+            panic!("expected Oak Containers endorsements");
+        };
+        &layers.root_layer.as_ref().unwrap().tee_certificate
+    }
+
+    #[test]
+    fn test_attach_tee_certificate_uses_cache() {
+        let cache = tempfile::tempdir().unwrap();
+
+        let mut endorsements = load(&Params::default()).unwrap();
+        attach_tee_certificate(&mut endorsements, &snp_evidence(1, b"vcek"), Some(cache.path()))
+            .unwrap();
+        assert_eq!(tee_certificate(&endorsements), b"vcek");
+
+        // The same chip on a host that doesn't provide the certificate.
+        let mut endorsements = load(&Params::default()).unwrap();
+        attach_tee_certificate(&mut endorsements, &snp_evidence(1, b""), Some(cache.path()))
+            .unwrap();
+        assert_eq!(tee_certificate(&endorsements), b"vcek");
+
+        // A different chip has no cached certificate.
+        let mut endorsements = load(&Params::default()).unwrap();
+        attach_tee_certificate(&mut endorsements, &snp_evidence(2, b""), Some(cache.path()))
+            .unwrap();
+        assert!(tee_certificate(&endorsements).is_empty());
+    }
+
+    #[test]
+    fn test_attach_tee_certificate_keeps_explicit_certificate() {
+        let explicit_certificate = temp_file(b"explicit vcek");
+        let params = Params {
+            tee_certificate: Some(explicit_certificate.path().into()),
+            ..Default::default()
+        };
+
+        let mut endorsements = load(&params).unwrap();
+        attach_tee_certificate(&mut endorsements, &snp_evidence(1, b"vcek"), None).unwrap();
+
+        assert_eq!(tee_certificate(&endorsements), b"explicit vcek");
+    }
+
+    #[test]
+    fn test_params() {
+        use clap::CommandFactory;
+        Params::command().debug_assert();
+    }
+
+    #[test]
+    fn test_load_endorsement_without_signature_fails() {
+        let kernel_endorsement = temp_file(b"kernel statement");
+        let params = Params {
+            kernel_endorsement: Some(kernel_endorsement.path().into()),
+            ..Default::default()
+        };
+
+        assert!(load(&params).is_err());
+    }
+}
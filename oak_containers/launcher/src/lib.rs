@@ -13,23 +13,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod endorsements;
 mod qemu;
 mod server;
+mod telemetry;
 
 use std::{
     fmt::Display,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
 };
 
 use anyhow::Context;
 use clap::{Parser, ValueEnum};
+pub use endorsements::Params as EndorsementParams;
 use oak_grpc::oak::key_provisioning::v1::key_provisioning_client::KeyProvisioningClient;
 use oak_proto_rust::oak::{
-    attestation::v1::{endorsements, Endorsements, Evidence, OakContainersEndorsements},
+    attestation::v1::{Endorsements, Evidence, ReferenceValues},
     key_provisioning::v1::{GetGroupKeysRequest, GetGroupKeysResponse},
     session::v1::EndorsedEvidence,
 };
+use prost::Message;
 pub use qemu::{Params as QemuParams, VmType as QemuVmType};
+pub use telemetry::{OtlpProtocol, Params as TelemetryParams};
 use tokio::{
     net::TcpListener,
     sync::{oneshot, watch},
@@ -76,10 +82,28 @@ pub struct Args {
     pub application_config: Vec<u8>,
     #[command(flatten)]
     pub qemu_params: qemu::Params,
+    #[command(flatten)]
+    pub endorsement_params: endorsements::Params,
+    #[command(flatten)]
+    pub telemetry_params: telemetry::Params,
 
     // Method of communication with the trusted application in the enclave.
     #[arg(long, value_enum, default_value_t = ChannelType::default())]
     pub communication_channel: ChannelType,
+
+    // Binary encoded `oak.attestation.v1.ReferenceValues` that the evidence of
+    // follower enclaves must match before group keys are provisioned to them, in
+    // addition to the measurements of the leader enclave. They must not skip any
+    // verification step.
+    #[arg(long, value_parser = path_exists,)]
+    pub reference_values: Option<std::path::PathBuf>,
+
+    // URI of the leader's `oak.key_provisioning.v1.KeyProvisioning` service, e.g.
+    // the leader launcher's key provisioning address. If set, the enclave runs as
+    // a follower and gets its group keys from the leader instead of generating
+    // them.
+    #[arg(long)]
+    pub leader_key_provisioning_uri: Option<String>,
 }
 
 pub fn path_exists(s: &str) -> Result<std::path::PathBuf, String> {
@@ -138,6 +162,9 @@ pub struct Launcher {
     // Receiver that is used to get the Attestation Evidence from the server implementation.
     evidence_receiver: Option<oneshot::Receiver<Evidence>>,
     app_ready_notifier: Option<oneshot::Receiver<()>>,
+    // Endorsements of the enclave binaries, which are sent along with the evidence. The
+    // server attaches the TEE certificate once it receives the evidence.
+    endorsements: Arc<Mutex<Endorsements>>,
     orchestrator_key_provisioning_client: Option<KeyProvisioningClient<TonicChannel>>,
     trusted_app_channel: Channel,
     shutdown: Option<watch::Sender<()>>,
@@ -158,7 +185,32 @@ impl Launcher {
         let (shutdown_sender, mut shutdown_receiver) = watch::channel::<()>(());
         shutdown_receiver.mark_unchanged(); // Don't immediately notify on the initial value.
         let (app_notifier_sender, app_notifier_receiver) = oneshot::channel::<()>();
-        let endorsements = get_endorsements();
+        let endorsements = Arc::new(Mutex::new(
+            endorsements::load(&args.endorsement_params).context("couldn't load endorsements")?,
+        ));
+        let reference_values = args
+            .reference_values
+            .map(|path| -> anyhow::Result<ReferenceValues> {
+                let bytes = std::fs::read(&path)
+                    .with_context(|| format!("couldn't read reference values from {path:?}"))?;
+                ReferenceValues::decode(bytes.as_slice())
+                    .context("couldn't decode reference values")
+            })
+            .transpose()?;
+        let leader_key_provisioning_client = args
+            .leader_key_provisioning_uri
+            .map(|uri| -> anyhow::Result<KeyProvisioningClient<TonicChannel>> {
+                // Connect lazily, since the follower only needs the leader once the
+                // orchestrator asks for group keys.
+                let channel = TonicChannel::from_shared(uri)
+                    .context("couldn't parse leader key provisioning URI")?
+                    .connect_lazy();
+                Ok(KeyProvisioningClient::new(channel))
+            })
+            .transpose()?;
+        let telemetry_forwarder = telemetry::Forwarder::create(&args.telemetry_params)
+            .await
+            .context("couldn't create telemetry forwarder")?;
         let server = tokio::spawn(server::new(
             listener,
             vsock_listener,
@@ -168,7 +220,11 @@ impl Launcher {
             evidence_sender,
             app_notifier_sender,
             shutdown_receiver,
-            endorsements,
+            endorsements.clone(),
+            args.endorsement_params.tee_certificate_cache,
+            reference_values,
+            leader_key_provisioning_client,
+            telemetry_forwarder,
         ));
 
         let trusted_app_channel = match args.communication_channel {
@@ -209,6 +265,7 @@ impl Launcher {
             endorsed_evidence: None,
             evidence_receiver: Some(evidence_receiver),
             app_ready_notifier: Some(app_notifier_receiver),
+            endorsements,
             orchestrator_key_provisioning_client: None,
             trusted_app_channel,
             shutdown: Some(shutdown_sender),
@@ -260,7 +317,13 @@ impl Launcher {
                 .context("couldn't get attestation evidence before timeout")?
                 .context("no attestation evidence available")?;
 
-            let endorsements = get_endorsements();
+            let endorsements = self
+                .endorsements
+                .lock()
+                .map_err(|err| {
+                    anyhow::anyhow!("couldn't get exclusive access to endorsements: {err}")
+                })?
+                .clone();
 
             let endorsed_evidence =
                 EndorsedEvidence { evidence: Some(evidence), endorsements: Some(endorsements) };
@@ -271,6 +334,14 @@ impl Launcher {
             .ok_or_else(|| anyhow::anyhow!("endorsed evidence is not set"))
     }
 
+    /// Gets the host address of the orchestrator's Key Provisioning service.
+    ///
+    /// Followers can be pointed at this address (or at a service that forwards
+    /// to it) via `--leader-key-provisioning-uri`.
+    pub fn key_provisioning_address(&self) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(PROXY_ADDRESS), self.host_orchestrator_proxy_port)
+    }
+
     // Gets enclave group keys as part of Key Provisioning.
     pub async fn get_group_keys(
         &mut self,
@@ -314,16 +385,3 @@ impl Launcher {
         self.server.abort();
     }
 }
-
-fn get_endorsements() -> Endorsements {
-    Endorsements {
-        r#type: Some(endorsements::Type::OakContainers(OakContainersEndorsements {
-            root_layer: None,
-            kernel_layer: None,
-            system_layer: None,
-            container_layer: None,
-        })),
-        // TODO: b/375137648 - Populate `event_endorsements` proto field.
-        event_endorsements: None,
-    }
-}
@@ -20,16 +20,25 @@ use std::{
 
 use anyhow::anyhow;
 use futures::{FutureExt, Stream};
-use oak_grpc::oak::containers::{
-    launcher_server::{Launcher, LauncherServer},
-    v1::hostlib_key_provisioning_server::{HostlibKeyProvisioning, HostlibKeyProvisioningServer},
+use oak_grpc::oak::{
+    containers::{
+        launcher_server::{Launcher, LauncherServer},
+        v1::hostlib_key_provisioning_server::{
+            HostlibKeyProvisioning, HostlibKeyProvisioningServer,
+        },
+    },
+    key_provisioning::v1::key_provisioning_client::KeyProvisioningClient,
 };
 use oak_proto_rust::oak::{
-    attestation::v1::{Endorsements, Evidence},
+    attestation::v1::{Endorsements, Evidence, ReferenceValues},
     containers::{
-        v1::{GetGroupKeysResponse, GetKeyProvisioningRoleResponse, KeyProvisioningRole},
+        v1::{
+            GetGroupKeysResponse, GetKeyProvisioningRoleResponse, GetReferenceValuesResponse,
+            KeyProvisioningRole,
+        },
         GetApplicationConfigResponse, GetImageResponse, SendAttestationEvidenceRequest,
     },
+    key_provisioning::v1::GetGroupKeysRequest,
 };
 use opentelemetry_proto::tonic::{
     collector::{
@@ -51,7 +60,12 @@ use tokio::{
 };
 use tokio_stream::wrappers::TcpListenerStream;
 use tokio_vsock::VsockListener;
-use tonic::{transport::Server, Request, Response, Status};
+use tonic::{
+    transport::{Channel as TonicChannel, Server},
+    Request, Response, Status,
+};
+
+use crate::{endorsements::attach_tee_certificate, telemetry::Forwarder};
 
 // Most gRPC implementations limit message sizes to 4MiB. Let's stay
 // comfortably below that by limiting responses to 3MiB.
@@ -66,10 +80,39 @@ struct LauncherServerImplementation {
     application_config: Vec<u8>,
     // Will be used to send the Attestation Evidence to the Launcher.
     evidence_sender: Mutex<Option<oneshot::Sender<Evidence>>>,
+    // Attestation Evidence sent by the Orchestrator, kept so that a follower can
+    // present it to the leader when requesting group keys.
+    evidence: Mutex<Option<Evidence>>,
     // Will be used to notify the untrusted application that the trusted application is ready and
     // listening on a socket address.
     app_ready_notifier: Mutex<Option<oneshot::Sender<()>>>,
-    endorsements: Endorsements,
+    // Endorsements served to the orchestrator and relayed to the leader, shared with the
+    // Launcher. The TEE certificate is attached once the evidence has been received.
+    endorsements: Arc<Mutex<Endorsements>>,
+    // Directory for caching the TEE certificates of the host.
+    tee_certificate_cache: Option<std::path::PathBuf>,
+    // Reference values used by the leader orchestrator to verify followers before
+    // provisioning group keys to them.
+    reference_values: Option<ReferenceValues>,
+    // Client for the leader's Key Provisioning service. If set, the enclave is a
+    // follower and gets its group keys from the leader.
+    leader_key_provisioning_client: Option<KeyProvisioningClient<TonicChannel>>,
+    // Forwards metrics and logs of the guest to external sinks, if configured.
+    telemetry_forwarder: Option<Forwarder>,
+}
+
+impl LauncherServerImplementation {
+    fn endorsements(&self) -> Result<Endorsements, tonic::Status> {
+        Ok(self
+            .endorsements
+            .lock()
+            .map_err(|err| {
+                tonic::Status::internal(format!(
+                    "couldn't get exclusive access to endorsements: {err}"
+                ))
+            })?
+            .clone())
+    }
 }
 
 #[tonic::async_trait]
@@ -150,6 +193,31 @@ impl Launcher for LauncherServerImplementation {
         let evidence = request.dice_evidence.ok_or_else(|| {
             tonic::Status::internal("send_attestation_evidence_request doesn't have evidence")
         })?;
+        self.evidence
+            .lock()
+            .map_err(|err| {
+                tonic::Status::internal(format!(
+                    "couldn't get exclusive access to attestation evidence: {err}"
+                ))
+            })?
+            .replace(evidence.clone());
+
+        // Attach the TEE certificate before the Launcher is notified, so that the
+        // endorsements it sends along with the evidence include it.
+        {
+            let mut endorsements = self.endorsements.lock().map_err(|err| {
+                tonic::Status::internal(format!(
+                    "couldn't get exclusive access to endorsements: {err}"
+                ))
+            })?;
+            if let Err(err) = attach_tee_certificate(
+                &mut endorsements,
+                &evidence,
+                self.tee_certificate_cache.as_deref(),
+            ) {
+                log::warn!("couldn't attach TEE certificate: {err:?}");
+            }
+        }
 
         self.evidence_sender
             .lock()
@@ -173,7 +241,7 @@ impl Launcher for LauncherServerImplementation {
         &self,
         _request: Request<()>,
     ) -> Result<Response<Endorsements>, tonic::Status> {
-        Ok(tonic::Response::new(self.endorsements.clone()))
+        Ok(tonic::Response::new(self.endorsements()?))
     }
 
     async fn notify_app_ready(&self, _request: Request<()>) -> Result<Response<()>, tonic::Status> {
@@ -200,19 +268,59 @@ impl HostlibKeyProvisioning for LauncherServerImplementation {
         &self,
         _request: Request<()>,
     ) -> Result<Response<GetKeyProvisioningRoleResponse>, tonic::Status> {
-        // TODO(#4442): Implement setting Hostlib Key Provisioning role via an input
-        // argument.
-        Ok(tonic::Response::new(GetKeyProvisioningRoleResponse {
-            role: KeyProvisioningRole::Leader.into(),
-        }))
+        let role = if self.leader_key_provisioning_client.is_some() {
+            KeyProvisioningRole::Follower
+        } else {
+            KeyProvisioningRole::Leader
+        };
+        Ok(tonic::Response::new(GetKeyProvisioningRoleResponse { role: role.into() }))
     }
 
     async fn get_group_keys(
         &self,
         _request: Request<()>,
     ) -> Result<Response<GetGroupKeysResponse>, tonic::Status> {
-        // TODO(#4442): Implement sending group keys to the orchestrator.
-        Err(tonic::Status::unimplemented("Key Provisioning is not implemented"))
+        let mut leader_client = self.leader_key_provisioning_client.clone().ok_or_else(|| {
+            tonic::Status::failed_precondition("group keys are only provided to followers")
+        })?;
+        let evidence = self
+            .evidence
+            .lock()
+            .map_err(|err| {
+                tonic::Status::internal(format!(
+                    "couldn't get exclusive access to attestation evidence: {err}"
+                ))
+            })?
+            .clone()
+            .ok_or_else(|| {
+                tonic::Status::failed_precondition("app hasn't sent an attestation evidence yet")
+            })?;
+
+        // Request group keys from the leader on behalf of the orchestrator. The keys
+        // are encrypted to the orchestrator's encryption public key, so they
+        // are only relayed.
+        let response = leader_client
+            .get_group_keys(GetGroupKeysRequest {
+                evidence: Some(evidence),
+                endorsements: Some(self.endorsements()?),
+            })
+            .await
+            .map_err(|status| {
+                tonic::Status::unavailable(format!(
+                    "couldn't get group keys from the leader: {status}"
+                ))
+            })?
+            .into_inner();
+        Ok(tonic::Response::new(GetGroupKeysResponse { group_keys: response.group_keys }))
+    }
+
+    async fn get_reference_values(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<GetReferenceValuesResponse>, tonic::Status> {
+        Ok(tonic::Response::new(GetReferenceValuesResponse {
+            reference_values: self.reference_values.clone(),
+        }))
     }
 }
 
@@ -224,6 +332,11 @@ impl MetricsService for LauncherServerImplementation {
     ) -> Result<Response<ExportMetricsServiceResponse>, tonic::Status> {
         let request = request.into_inner();
         log::debug!("metrics: {:?}", request);
+        if let Some(forwarder) = &self.telemetry_forwarder {
+            forwarder.forward_metrics(request.resource_metrics).map_err(|err| {
+                tonic::Status::unavailable(format!("couldn't forward metrics: {err:#}"))
+            })?;
+        }
         Ok(Response::new(ExportMetricsServiceResponse { partial_success: None }))
     }
 }
@@ -263,6 +376,11 @@ impl LogsService for LauncherServerImplementation {
                 }
             }
         }
+        if let Some(forwarder) = &self.telemetry_forwarder {
+            forwarder.forward_logs(request.resource_logs).map_err(|err| {
+                tonic::Status::unavailable(format!("couldn't forward logs: {err:#}"))
+            })?;
+        }
 
         Ok(Response::new(ExportLogsServiceResponse { partial_success: None }))
     }
@@ -280,15 +398,24 @@ pub async fn new(
     evidence_sender: oneshot::Sender<Evidence>,
     app_ready_notifier: oneshot::Sender<()>,
     shutdown: watch::Receiver<()>,
-    endorsements: Endorsements,
+    endorsements: Arc<Mutex<Endorsements>>,
+    tee_certificate_cache: Option<std::path::PathBuf>,
+    reference_values: Option<ReferenceValues>,
+    leader_key_provisioning_client: Option<KeyProvisioningClient<TonicChannel>>,
+    telemetry_forwarder: Option<Forwarder>,
 ) -> Result<(), anyhow::Error> {
     let server_impl = Arc::new(LauncherServerImplementation {
         system_image,
         container_bundle,
         application_config,
         evidence_sender: Mutex::new(Some(evidence_sender)),
+        evidence: Mutex::new(None),
         app_ready_notifier: Mutex::new(Some(app_ready_notifier)),
         endorsements,
+        tee_certificate_cache,
+        reference_values,
+        leader_key_provisioning_client,
+        telemetry_forwarder,
     });
 
     let mut tcp_shutdown = shutdown.clone();
@@ -315,3 +442,220 @@ pub async fn new(
         .map(|((), ())| ())
         .map_err(|error| anyhow!("server error: {:?}", error))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use oak_grpc::oak::{
+        containers::{
+            launcher_client::LauncherClient,
+            v1::hostlib_key_provisioning_client::HostlibKeyProvisioningClient,
+        },
+        key_provisioning::v1::key_provisioning_server::{KeyProvisioning, KeyProvisioningServer},
+    };
+    use oak_proto_rust::oak::{
+        attestation::v1::{
+            endorsements, OakContainersEndorsements, RootLayerEndorsements, RootLayerEvidence,
+        },
+        crypto::v1::EncryptedRequest,
+        key_provisioning::v1::{
+            GetGroupKeysResponse as KeyProvisioningGetGroupKeysResponse, GroupKeys,
+        },
+    };
+
+    use super::*;
+
+    /// Stands in for the leader's orchestrator, which releases group keys to
+    /// followers.
+    #[derive(Default)]
+    struct FakeLeaderEnclave {
+        requests: Mutex<Vec<GetGroupKeysRequest>>,
+    }
+
+    fn fake_group_keys() -> GroupKeys {
+        GroupKeys {
+            encrypted_encryption_private_key: Some(EncryptedRequest {
+                encrypted_message: None,
+                serialized_encapsulated_public_key: Some(b"group key".to_vec()),
+            }),
+            encrypted_signing_private_key: Some(EncryptedRequest {
+                encrypted_message: None,
+                serialized_encapsulated_public_key: Some(b"group signing key".to_vec()),
+            }),
+        }
+    }
+
+    fn fake_evidence() -> Evidence {
+        Evidence {
+            root_layer: Some(RootLayerEvidence {
+                remote_attestation_report: b"follower report".to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tonic::async_trait]
+    impl KeyProvisioning for FakeLeaderEnclave {
+        async fn get_group_keys(
+            &self,
+            request: Request<GetGroupKeysRequest>,
+        ) -> Result<Response<KeyProvisioningGetGroupKeysResponse>, tonic::Status> {
+            self.requests.lock().unwrap().push(request.into_inner());
+            Ok(Response::new(KeyProvisioningGetGroupKeysResponse {
+                group_keys: Some(fake_group_keys()),
+            }))
+        }
+    }
+
+    async fn serve_tcp(router: tonic::transport::server::Router) -> SocketAddr {
+        let listener =
+            TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(router.serve_with_incoming(TcpListenerStream::new(listener)));
+        addr
+    }
+
+    /// Starts the hostlib services of a launcher and returns their address.
+    async fn start_hostlib(
+        leader_key_provisioning_client: Option<KeyProvisioningClient<TonicChannel>>,
+    ) -> (SocketAddr, oneshot::Receiver<Evidence>) {
+        start_hostlib_with_endorsements(leader_key_provisioning_client, Endorsements::default())
+            .await
+    }
+
+    async fn start_hostlib_with_endorsements(
+        leader_key_provisioning_client: Option<KeyProvisioningClient<TonicChannel>>,
+        endorsements: Endorsements,
+    ) -> (SocketAddr, oneshot::Receiver<Evidence>) {
+        let (evidence_sender, evidence_receiver) = oneshot::channel();
+        let server_impl = Arc::new(LauncherServerImplementation {
+            evidence_sender: Mutex::new(Some(evidence_sender)),
+            endorsements: Arc::new(Mutex::new(endorsements)),
+            leader_key_provisioning_client,
+            ..Default::default()
+        });
+        let addr = serve_tcp(
+            Server::builder()
+                .add_service(LauncherServer::from_arc(server_impl.clone()))
+                .add_service(HostlibKeyProvisioningServer::from_arc(server_impl)),
+        )
+        .await;
+        (addr, evidence_receiver)
+    }
+
+    async fn connect(addr: SocketAddr) -> TonicChannel {
+        TonicChannel::from_shared(format!("http://{addr}")).unwrap().connect().await.unwrap()
+    }
+
+    // The relay to a leader launcher is covered by
+    // `tests/key_provisioning_test.rs`.
+    #[tokio::test]
+    async fn test_key_provisioning_roles() {
+        let leader_enclave = Arc::new(FakeLeaderEnclave::default());
+        let leader_enclave_addr = serve_tcp(
+            Server::builder().add_service(KeyProvisioningServer::from_arc(leader_enclave.clone())),
+        )
+        .await;
+        let (leader_addr, _) = start_hostlib(None).await;
+        let (follower_addr, _follower_evidence) =
+            start_hostlib(Some(KeyProvisioningClient::new(connect(leader_enclave_addr).await)))
+                .await;
+
+        let mut leader = HostlibKeyProvisioningClient::new(connect(leader_addr).await);
+        let role = leader.get_key_provisioning_role(()).await.unwrap().into_inner().role;
+        assert_eq!(role, KeyProvisioningRole::Leader as i32);
+        let status = leader.get_group_keys(()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let mut follower = HostlibKeyProvisioningClient::new(connect(follower_addr).await);
+        let role = follower.get_key_provisioning_role(()).await.unwrap().into_inner().role;
+        assert_eq!(role, KeyProvisioningRole::Follower as i32);
+
+        // The follower needs the orchestrator's evidence to request group keys.
+        let status = follower.get_group_keys(()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(leader_enclave.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_follower_relays_tee_certificate_from_evidence() {
+        let leader_enclave = Arc::new(FakeLeaderEnclave::default());
+        let leader_enclave_addr = serve_tcp(
+            Server::builder().add_service(KeyProvisioningServer::from_arc(leader_enclave.clone())),
+        )
+        .await;
+        // No TEE certificate was passed to the launcher explicitly.
+        let endorsements = Endorsements {
+            r#type: Some(endorsements::Type::OakContainers(OakContainersEndorsements {
+                root_layer: Some(RootLayerEndorsements::default()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let (follower_addr, _follower_evidence) = start_hostlib_with_endorsements(
+            Some(KeyProvisioningClient::new(connect(leader_enclave_addr).await)),
+            endorsements,
+        )
+        .await;
+
+        // The enclave got the TEE certificate from the host firmware.
+        let evidence = Evidence {
+            root_layer: Some(RootLayerEvidence {
+                remote_attestation_report: b"follower report".to_vec(),
+                tee_certificate: b"firmware vcek".to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let follower_channel = connect(follower_addr).await;
+        let mut launcher = LauncherClient::new(follower_channel.clone());
+        launcher
+            .send_attestation_evidence(SendAttestationEvidenceRequest {
+                dice_evidence: Some(evidence),
+            })
+            .await
+            .unwrap();
+
+        let tee_certificate = |endorsements: &Endorsements| match &endorsements.r#type {
+            Some(endorsements::Type::OakContainers(OakContainersEndorsements {
+                root_layer: Some(root_layer),
+                ..
+            })) => root_layer.tee_certificate.clone(),
+            _ => panic!("unexpected endorsements: {endorsements:?}"),
+        };
+        let served_endorsements = launcher.get_endorsements(()).await.unwrap().into_inner();
+        assert_eq!(tee_certificate(&served_endorsements), b"firmware vcek");
+
+        HostlibKeyProvisioningClient::new(follower_channel).get_group_keys(()).await.unwrap();
+        let requests = leader_enclave.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(tee_certificate(requests[0].endorsements.as_ref().unwrap()), b"firmware vcek");
+    }
+
+    #[tokio::test]
+    async fn test_follower_fails_if_leader_unavailable() {
+        // Nothing is listening on the leader's address.
+        let listener =
+            TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await.unwrap();
+        let leader_uri = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let leader_channel = TonicChannel::from_shared(leader_uri).unwrap().connect_lazy();
+        let (follower_addr, _follower_evidence) =
+            start_hostlib(Some(KeyProvisioningClient::new(leader_channel))).await;
+
+        let follower_channel = connect(follower_addr).await;
+        LauncherClient::new(follower_channel.clone())
+            .send_attestation_evidence(SendAttestationEvidenceRequest {
+                dice_evidence: Some(fake_evidence()),
+            })
+            .await
+            .unwrap();
+        let status = HostlibKeyProvisioningClient::new(follower_channel)
+            .get_group_keys(())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+}
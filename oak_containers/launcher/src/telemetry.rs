@@ -0,0 +1,965 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Forwarding of the OpenTelemetry metrics and logs that the guest exports to
+//! the launcher.
+//!
+//! Received data is kept in a bounded buffer and exported in batches by a
+//! background task. If the buffer is full, exports from the guest fail, and
+//! the launcher rejects them with `UNAVAILABLE`, which OTLP exporters retry
+//! with a backoff.
+//!
+//! Batches are limited both in the number of resources and in their encoded
+//! size, so that export requests stay below the message size limit of the
+//! collector.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use bytes::Bytes;
+use clap::{Parser, ValueEnum};
+use http_body_util::{BodyExt, Full};
+use hyper::{header, Request, Uri};
+use hyper_util::rt::TokioIo;
+use opentelemetry_proto::tonic::{
+    collector::{
+        logs::v1::{
+            logs_service_client::LogsServiceClient, ExportLogsServiceRequest,
+            ExportLogsServiceResponse,
+        },
+        metrics::v1::{
+            metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest,
+            ExportMetricsServiceResponse,
+        },
+    },
+    common::v1::{any_value, AnyValue, KeyValue},
+    logs::v1::ResourceLogs,
+    metrics::v1::{metric, number_data_point, Metric, NumberDataPoint, ResourceMetrics},
+    resource::v1::Resource,
+};
+use prost::Message;
+use serde_json::{json, Value};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    net::TcpStream,
+    sync::mpsc,
+    time::{timeout, timeout_at, Duration, Instant},
+};
+use tonic::transport::Channel as TonicChannel;
+
+/// Timeout for a single export to the OTLP collector.
+const OTLP_EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Options for forwarding the metrics and logs of the guest.
+///
+/// Nothing is forwarded unless an OTLP endpoint or a JSONL directory is set.
+/// Metrics are only forwarded if their name is in the allowlist, so that
+/// only approved metrics leave the host.
+#[derive(Parser, Clone, Debug, Default, PartialEq)]
+pub struct Params {
+    /// URI of an OTLP collector, e.g. `http://localhost:4317` for gRPC or
+    /// `http://localhost:4318` for HTTP, to which metrics and logs of the
+    /// guest are forwarded.
+    #[arg(long, value_name = "URI")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Protocol used to export to the OTLP collector.
+    #[arg(long, value_enum, default_value_t = OtlpProtocol::default())]
+    pub otlp_protocol: OtlpProtocol,
+
+    /// Directory in which metrics and logs of the guest are written as JSON
+    /// lines, to `metrics.jsonl` and `logs.jsonl` respectively.
+    #[arg(long, value_name = "DIR")]
+    pub telemetry_jsonl_dir: Option<PathBuf>,
+
+    /// Size in bytes after which a JSONL file is rotated.
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    pub telemetry_jsonl_max_file_size: u64,
+
+    /// Number of rotated JSONL files that are kept in addition to the current
+    /// file.
+    #[arg(long, default_value_t = 4)]
+    pub telemetry_jsonl_max_rotated_files: usize,
+
+    /// Names of the metrics that are forwarded. All other metrics are
+    /// dropped; if no names are given, no metrics are forwarded.
+    #[arg(long, value_delimiter = ',', value_name = "NAME")]
+    pub allowed_metric_names: Vec<String>,
+
+    /// Maximum number of export requests of the guest that are buffered.
+    #[arg(long, default_value_t = 256)]
+    pub telemetry_buffer_size: usize,
+
+    /// Number of resources after which a batch is exported.
+    #[arg(long, default_value_t = 512)]
+    pub telemetry_max_batch_size: usize,
+
+    /// Maximum encoded size in bytes of an export request. This must stay
+    /// below the message size limit of the collector, which is 4 MiB for
+    /// OTLP/gRPC collectors by default.
+    #[arg(long, default_value_t = 3 * 1024 * 1024)]
+    pub telemetry_max_batch_bytes: usize,
+
+    /// Maximum time in milliseconds that data is buffered before it is
+    /// exported.
+    #[arg(long, default_value_t = 5000)]
+    pub telemetry_batch_timeout_ms: u64,
+}
+
+/// Protocols for exporting to an OTLP collector.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum OtlpProtocol {
+    /// OTLP/gRPC.
+    #[default]
+    Grpc,
+
+    /// OTLP/HTTP with protobuf payloads, which are posted to `/v1/metrics`
+    /// and `/v1/logs` under the endpoint.
+    #[value(name = "http/protobuf")]
+    HttpProtobuf,
+}
+
+/// Forwards metrics and logs of the guest to the configured sinks.
+pub struct Forwarder {
+    allowed_metric_names: HashSet<String>,
+    metrics: mpsc::Sender<Vec<ResourceMetrics>>,
+    logs: mpsc::Sender<Vec<ResourceLogs>>,
+}
+
+impl Forwarder {
+    /// Creates a forwarder and starts the background export tasks.
+    ///
+    /// Returns `None` if no sink is configured.
+    pub async fn create(params: &Params) -> anyhow::Result<Option<Self>> {
+        let mut metrics_exporters: Vec<Box<dyn Exporter<ResourceMetrics>>> = Vec::new();
+        let mut logs_exporters: Vec<Box<dyn Exporter<ResourceLogs>>> = Vec::new();
+        if let Some(endpoint) = &params.otlp_endpoint {
+            match params.otlp_protocol {
+                OtlpProtocol::Grpc => {
+                    // Connect lazily, so that the launcher can start before the collector.
+                    let channel = TonicChannel::from_shared(endpoint.clone())
+                        .context("couldn't parse OTLP endpoint")?
+                        .timeout(OTLP_EXPORT_TIMEOUT)
+                        .connect_lazy();
+                    metrics_exporters.push(Box::new(MetricsServiceClient::new(channel.clone())));
+                    logs_exporters.push(Box::new(LogsServiceClient::new(channel)));
+                }
+                OtlpProtocol::HttpProtobuf => {
+                    metrics_exporters
+                        .push(Box::new(OtlpHttpExporter::new(endpoint, "v1/metrics")?));
+                    logs_exporters.push(Box::new(OtlpHttpExporter::new(endpoint, "v1/logs")?));
+                }
+            }
+        }
+        if let Some(dir) = &params.telemetry_jsonl_dir {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("couldn't create telemetry directory {dir:?}"))?;
+            metrics_exporters.push(Box::new(JsonlExporter::new(dir.join("metrics.jsonl"), params)));
+            logs_exporters.push(Box::new(JsonlExporter::new(dir.join("logs.jsonl"), params)));
+        }
+        if metrics_exporters.is_empty() {
+            return Ok(None);
+        }
+        if params.allowed_metric_names.is_empty() {
+            log::warn!("no metric names are allowed, so metrics of the guest won't be forwarded");
+        }
+
+        let buffer_size = params.telemetry_buffer_size.max(1);
+        let limits = BatchLimits {
+            max_size: params.telemetry_max_batch_size.max(1),
+            max_bytes: params.telemetry_max_batch_bytes,
+            timeout: Duration::from_millis(params.telemetry_batch_timeout_ms),
+        };
+        let (metrics, metrics_receiver) = mpsc::channel(buffer_size);
+        let (logs, logs_receiver) = mpsc::channel(buffer_size);
+        tokio::spawn(run_batcher(metrics_receiver, metrics_exporters, limits));
+        tokio::spawn(run_batcher(logs_receiver, logs_exporters, limits));
+        Ok(Some(Self {
+            allowed_metric_names: params.allowed_metric_names.iter().cloned().collect(),
+            metrics,
+            logs,
+        }))
+    }
+
+    /// Queues the allowed metrics for export.
+    pub fn forward_metrics(
+        &self,
+        mut resource_metrics: Vec<ResourceMetrics>,
+    ) -> anyhow::Result<()> {
+        retain_allowed_metrics(&mut resource_metrics, &self.allowed_metric_names);
+        if resource_metrics.is_empty() {
+            return Ok(());
+        }
+        enqueue(&self.metrics, resource_metrics)
+    }
+
+    /// Queues the logs for export.
+    pub fn forward_logs(&self, resource_logs: Vec<ResourceLogs>) -> anyhow::Result<()> {
+        if resource_logs.is_empty() {
+            return Ok(());
+        }
+        enqueue(&self.logs, resource_logs)
+    }
+}
+
+fn enqueue<T>(sender: &mpsc::Sender<Vec<T>>, items: Vec<T>) -> anyhow::Result<()> {
+    sender.try_send(items).map_err(|err| match err {
+        mpsc::error::TrySendError::Full(_) => anyhow::anyhow!("telemetry buffer is full"),
+        mpsc::error::TrySendError::Closed(_) => anyhow::anyhow!("telemetry exporter has stopped"),
+    })
+}
+
+/// Removes all metrics whose names aren't allowed, along with the scopes and
+/// resources that are left without metrics.
+fn retain_allowed_metrics(
+    resource_metrics: &mut Vec<ResourceMetrics>,
+    allowed_metric_names: &HashSet<String>,
+) {
+    for resource in resource_metrics.iter_mut() {
+        for scope in resource.scope_metrics.iter_mut() {
+            scope.metrics.retain(|metric| allowed_metric_names.contains(&metric.name));
+        }
+        resource.scope_metrics.retain(|scope| !scope.metrics.is_empty());
+    }
+    resource_metrics.retain(|resource| !resource.scope_metrics.is_empty());
+}
+
+/// Limits of the batches in which telemetry data is exported.
+#[derive(Clone, Copy, Debug)]
+struct BatchLimits {
+    /// Maximum number of items in a batch.
+    max_size: usize,
+    /// Maximum encoded size of the export request for a batch. A single item
+    /// that exceeds it is still exported on its own.
+    max_bytes: usize,
+    /// Maximum time that a batch is held back after its first item was
+    /// received.
+    timeout: Duration,
+}
+
+/// Collects the queued items into batches and exports them to all exporters.
+///
+/// A batch is exported once adding the next item would exceed one of the
+/// `limits`, or after the batch timeout. Failed exports are logged and the
+/// batch is dropped.
+async fn run_batcher<T: Message + Send + Sync>(
+    mut receiver: mpsc::Receiver<Vec<T>>,
+    mut exporters: Vec<Box<dyn Exporter<T>>>,
+    limits: BatchLimits,
+) {
+    // Items that have been received but didn't fit into the previous batch.
+    let mut pending = VecDeque::new();
+    loop {
+        if pending.is_empty() {
+            match receiver.recv().await {
+                Some(items) => pending.extend(items),
+                None => break,
+            }
+        }
+        let deadline = Instant::now() + limits.timeout;
+        let mut batch = Vec::new();
+        let mut batch_bytes = 0;
+        loop {
+            while let Some(item) = pending.front() {
+                let item_bytes = encoded_len_in_request(item);
+                if batch.len() >= limits.max_size
+                    || (!batch.is_empty() && batch_bytes + item_bytes > limits.max_bytes)
+                {
+                    break;
+                }
+                batch_bytes += item_bytes;
+                batch.extend(pending.pop_front());
+            }
+            // Leftover items don't fit into this batch, so it is full.
+            if !pending.is_empty() || batch.len() >= limits.max_size {
+                break;
+            }
+            match timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(items)) => pending.extend(items),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        for exporter in exporters.iter_mut() {
+            if let Err(err) = exporter.export(&batch).await {
+                log::warn!("couldn't export {} telemetry resources: {err:?}", batch.len());
+            }
+        }
+    }
+}
+
+/// Returns the number of bytes that an item adds to an export request, in
+/// which it is an element of the repeated field with tag 1.
+fn encoded_len_in_request<T: Message>(item: &T) -> usize {
+    prost::encoding::message::encoded_len(1, item)
+}
+
+/// A sink for batches of telemetry data.
+#[tonic::async_trait]
+trait Exporter<T>: Send {
+    async fn export(&mut self, batch: &[T]) -> anyhow::Result<()>;
+}
+
+#[tonic::async_trait]
+impl Exporter<ResourceMetrics> for MetricsServiceClient<TonicChannel> {
+    async fn export(&mut self, batch: &[ResourceMetrics]) -> anyhow::Result<()> {
+        let request = ExportMetricsServiceRequest { resource_metrics: batch.to_vec() };
+        let response = MetricsServiceClient::export(self, request)
+            .await
+            .context("couldn't export metrics to the OTLP collector")?
+            .into_inner();
+        log_rejected_metrics(response);
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Exporter<ResourceLogs> for LogsServiceClient<TonicChannel> {
+    async fn export(&mut self, batch: &[ResourceLogs]) -> anyhow::Result<()> {
+        let request = ExportLogsServiceRequest { resource_logs: batch.to_vec() };
+        let response = LogsServiceClient::export(self, request)
+            .await
+            .context("couldn't export logs to the OTLP collector")?
+            .into_inner();
+        log_rejected_logs(response);
+        Ok(())
+    }
+}
+
+/// Exports telemetry data to an OTLP/HTTP collector, encoded as protobuf.
+///
+/// Only plain HTTP is supported. A new connection is opened for every export,
+/// which is cheap compared to the batch timeout.
+struct OtlpHttpExporter {
+    uri: Uri,
+}
+
+impl OtlpHttpExporter {
+    /// Creates an exporter that posts to `path` under `endpoint`.
+    fn new(endpoint: &str, path: &str) -> anyhow::Result<Self> {
+        let uri: Uri = format!("{}/{path}", endpoint.trim_end_matches('/'))
+            .parse()
+            .context("couldn't parse OTLP endpoint")?;
+        if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+            anyhow::bail!("OTLP/HTTP endpoint must be an http:// URI, got {endpoint}");
+        }
+        Ok(Self { uri })
+    }
+
+    /// Posts an encoded export request and returns the encoded response.
+    async fn post(&self, request: Vec<u8>) -> anyhow::Result<Bytes> {
+        let authority = self.uri.authority().context("OTLP endpoint has no authority")?;
+        let address = format!("{}:{}", authority.host(), authority.port_u16().unwrap_or(80));
+        let export = async {
+            let stream = TcpStream::connect(&address)
+                .await
+                .with_context(|| format!("couldn't connect to {address}"))?;
+            let (mut sender, connection) =
+                hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+            tokio::spawn(async move {
+                if let Err(err) = connection.await {
+                    log::warn!("OTLP/HTTP connection failed: {err:?}");
+                }
+            });
+            let request = Request::post(self.uri.path())
+                .header(header::HOST, authority.as_str())
+                .header(header::CONTENT_TYPE, "application/x-protobuf")
+                .body(Full::new(Bytes::from(request)))?;
+            let response = sender.send_request(request).await?;
+            let status = response.status();
+            let body = response.into_body().collect().await?.to_bytes();
+            if !status.is_success() {
+                anyhow::bail!("OTLP collector responded with {status}");
+            }
+            Ok(body)
+        };
+        timeout(OTLP_EXPORT_TIMEOUT, export).await.context("OTLP export timed out")?
+    }
+}
+
+#[tonic::async_trait]
+impl Exporter<ResourceMetrics> for OtlpHttpExporter {
+    async fn export(&mut self, batch: &[ResourceMetrics]) -> anyhow::Result<()> {
+        let request = ExportMetricsServiceRequest { resource_metrics: batch.to_vec() };
+        let response = self
+            .post(request.encode_to_vec())
+            .await
+            .context("couldn't export metrics to the OTLP collector")?;
+        log_rejected_metrics(
+            ExportMetricsServiceResponse::decode(response)
+                .context("couldn't decode OTLP response")?,
+        );
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Exporter<ResourceLogs> for OtlpHttpExporter {
+    async fn export(&mut self, batch: &[ResourceLogs]) -> anyhow::Result<()> {
+        let request = ExportLogsServiceRequest { resource_logs: batch.to_vec() };
+        let response = self
+            .post(request.encode_to_vec())
+            .await
+            .context("couldn't export logs to the OTLP collector")?;
+        log_rejected_logs(
+            ExportLogsServiceResponse::decode(response).context("couldn't decode OTLP response")?,
+        );
+        Ok(())
+    }
+}
+
+fn log_rejected_metrics(response: ExportMetricsServiceResponse) {
+    if let Some(partial_success) = response.partial_success {
+        if partial_success.rejected_data_points > 0 {
+            log::warn!(
+                "OTLP collector rejected {} data points: {}",
+                partial_success.rejected_data_points,
+                partial_success.error_message
+            );
+        }
+    }
+}
+
+fn log_rejected_logs(response: ExportLogsServiceResponse) {
+    if let Some(partial_success) = response.partial_success {
+        if partial_success.rejected_log_records > 0 {
+            log::warn!(
+                "OTLP collector rejected {} log records: {}",
+                partial_success.rejected_log_records,
+                partial_success.error_message
+            );
+        }
+    }
+}
+
+/// Writes telemetry data as JSON lines to a file, which is rotated once it
+/// exceeds the maximum size.
+///
+/// Rotated files get the suffixes `.1`, `.2`, etc., with `.1` being the most
+/// recent one.
+struct JsonlExporter {
+    path: PathBuf,
+    max_file_size: u64,
+    max_rotated_files: usize,
+    file: Option<File>,
+    file_size: u64,
+}
+
+impl JsonlExporter {
+    fn new(path: PathBuf, params: &Params) -> Self {
+        Self {
+            path,
+            max_file_size: params.telemetry_jsonl_max_file_size,
+            max_rotated_files: params.telemetry_jsonl_max_rotated_files,
+            file: None,
+            file_size: 0,
+        }
+    }
+
+    async fn write(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        if self.file_size > 0 && self.file_size + data.len() as u64 > self.max_file_size {
+            self.rotate().await?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+                    .await
+                    .with_context(|| format!("couldn't open {:?}", self.path))?;
+                self.file_size = file.metadata().await?.len();
+                self.file.insert(file)
+            }
+        };
+        file.write_all(data).await.with_context(|| format!("couldn't write to {:?}", self.path))?;
+        file.flush().await?;
+        self.file_size += data.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> anyhow::Result<()> {
+        self.file = None;
+        self.file_size = 0;
+        if self.max_rotated_files == 0 {
+            return tokio::fs::remove_file(&self.path)
+                .await
+                .with_context(|| format!("couldn't remove {:?}", self.path));
+        }
+        for index in (1..self.max_rotated_files).rev() {
+            let from = rotated_path(&self.path, index);
+            if tokio::fs::try_exists(&from).await? {
+                tokio::fs::rename(&from, rotated_path(&self.path, index + 1)).await?;
+            }
+        }
+        tokio::fs::rename(&self.path, rotated_path(&self.path, 1))
+            .await
+            .with_context(|| format!("couldn't rotate {:?}", self.path))
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{index}"));
+    path.into()
+}
+
+#[tonic::async_trait]
+impl<T: ToJsonLines + Sync> Exporter<T> for JsonlExporter {
+    async fn export(&mut self, batch: &[T]) -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        for line in batch.iter().flat_map(ToJsonLines::to_json_lines) {
+            serde_json::to_writer(&mut data, &line)?;
+            data.push(b'\n');
+        }
+        self.write(&data).await
+    }
+}
+
+/// Converts telemetry data into flat JSON objects, one per data point or log
+/// record, which are easy to process with line-based tools.
+trait ToJsonLines {
+    fn to_json_lines(&self) -> Vec<Value>;
+}
+
+impl ToJsonLines for ResourceMetrics {
+    fn to_json_lines(&self) -> Vec<Value> {
+        let resource = resource_json(self.resource.as_ref());
+        let mut lines = Vec::new();
+        for scope_metrics in &self.scope_metrics {
+            let scope = scope_metrics.scope.as_ref().map(|scope| scope.name.as_str());
+            for metric in &scope_metrics.metrics {
+                for mut line in data_points_json(metric) {
+                    line["resource"] = resource.clone();
+                    line["scope"] = json!(scope);
+                    line["name"] = json!(metric.name);
+                    line["unit"] = json!(metric.unit);
+                    lines.push(line);
+                }
+            }
+        }
+        lines
+    }
+}
+
+impl ToJsonLines for ResourceLogs {
+    fn to_json_lines(&self) -> Vec<Value> {
+        let resource = resource_json(self.resource.as_ref());
+        let mut lines = Vec::new();
+        for scope_logs in &self.scope_logs {
+            let scope = scope_logs.scope.as_ref().map(|scope| scope.name.as_str());
+            for log_record in &scope_logs.log_records {
+                lines.push(json!({
+                    "resource": resource,
+                    "scope": scope,
+                    "time_unix_nano": log_record.time_unix_nano,
+                    "observed_time_unix_nano": log_record.observed_time_unix_nano,
+                    "severity_number": log_record.severity_number,
+                    "severity_text": log_record.severity_text,
+                    "body": log_record.body.as_ref().map(any_value_json),
+                    "attributes": attributes_json(&log_record.attributes),
+                }));
+            }
+        }
+        lines
+    }
+}
+
+fn data_points_json(metric: &Metric) -> Vec<Value> {
+    let number_point = |r#type: &str, point: &NumberDataPoint| {
+        json!({
+            "type": r#type,
+            "time_unix_nano": point.time_unix_nano,
+            "attributes": attributes_json(&point.attributes),
+            "value": match point.value {
+                Some(number_data_point::Value::AsDouble(value)) => json!(value),
+                Some(number_data_point::Value::AsInt(value)) => json!(value),
+                None => Value::Null,
+            },
+        })
+    };
+    match &metric.data {
+        Some(metric::Data::Gauge(gauge)) => {
+            gauge.data_points.iter().map(|point| number_point("gauge", point)).collect()
+        }
+        Some(metric::Data::Sum(sum)) => {
+            sum.data_points.iter().map(|point| number_point("sum", point)).collect()
+        }
+        Some(metric::Data::Histogram(histogram)) => histogram
+            .data_points
+            .iter()
+            .map(|point| {
+                json!({
+                    "type": "histogram",
+                    "time_unix_nano": point.time_unix_nano,
+                    "attributes": attributes_json(&point.attributes),
+                    "count": point.count,
+                    "sum": point.sum,
+                    "bucket_counts": point.bucket_counts,
+                    "explicit_bounds": point.explicit_bounds,
+                })
+            })
+            .collect(),
+        Some(metric::Data::ExponentialHistogram(histogram)) => histogram
+            .data_points
+            .iter()
+            .map(|point| {
+                json!({
+                    "type": "exponential_histogram",
+                    "time_unix_nano": point.time_unix_nano,
+                    "attributes": attributes_json(&point.attributes),
+                    "count": point.count,
+                    "sum": point.sum,
+                })
+            })
+            .collect(),
+        Some(metric::Data::Summary(summary)) => summary
+            .data_points
+            .iter()
+            .map(|point| {
+                json!({
+                    "type": "summary",
+                    "time_unix_nano": point.time_unix_nano,
+                    "attributes": attributes_json(&point.attributes),
+                    "count": point.count,
+                    "sum": point.sum,
+                    "quantiles": point
+                        .quantile_values
+                        .iter()
+                        .map(|value| json!({"quantile": value.quantile, "value": value.value}))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn resource_json(resource: Option<&Resource>) -> Value {
+    attributes_json(resource.map(|resource| resource.attributes.as_slice()).unwrap_or_default())
+}
+
+fn attributes_json(attributes: &[KeyValue]) -> Value {
+    Value::Object(
+        attributes
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute.key.clone(),
+                    attribute.value.as_ref().map_or(Value::Null, any_value_json),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn any_value_json(value: &AnyValue) -> Value {
+    match &value.value {
+        Some(any_value::Value::StringValue(value)) => json!(value),
+        Some(any_value::Value::BoolValue(value)) => json!(value),
+        Some(any_value::Value::IntValue(value)) => json!(value),
+        Some(any_value::Value::DoubleValue(value)) => json!(value),
+        Some(any_value::Value::ArrayValue(array)) => {
+            Value::Array(array.values.iter().map(any_value_json).collect())
+        }
+        Some(any_value::Value::KvlistValue(list)) => attributes_json(&list.values),
+        Some(any_value::Value::BytesValue(value)) => json!(hex::encode(value)),
+        None => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        sync::{Arc, Mutex},
+    };
+
+    use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+    use opentelemetry_proto::tonic::{
+        collector::metrics::v1::{
+            metrics_service_server::{MetricsService, MetricsServiceServer},
+            ExportMetricsServiceResponse,
+        },
+        logs::v1::{LogRecord, ScopeLogs},
+        metrics::v1::{Gauge, ScopeMetrics},
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Request, Response};
+
+    use super::*;
+
+    fn gauge(name: &str, value: i64) -> Metric {
+        Metric {
+            name: name.to_string(),
+            data: Some(metric::Data::Gauge(Gauge {
+                data_points: vec![NumberDataPoint {
+                    value: Some(number_data_point::Value::AsInt(value)),
+                    ..Default::default()
+                }],
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn resource_metrics(metrics: Vec<Metric>) -> ResourceMetrics {
+        ResourceMetrics {
+            scope_metrics: vec![ScopeMetrics { metrics, ..Default::default() }],
+            ..Default::default()
+        }
+    }
+
+    fn resource_logs(body: &str) -> ResourceLogs {
+        ResourceLogs {
+            scope_logs: vec![ScopeLogs {
+                log_records: vec![LogRecord {
+                    body: Some(AnyValue {
+                        value: Some(any_value::Value::StringValue(body.to_string())),
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn metric_names(resource_metrics: &[ResourceMetrics]) -> Vec<&str> {
+        resource_metrics
+            .iter()
+            .flat_map(|resource| &resource.scope_metrics)
+            .flat_map(|scope| &scope.metrics)
+            .map(|metric| metric.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_retain_allowed_metrics() {
+        let mut metrics = vec![
+            resource_metrics(vec![gauge("allowed", 1), gauge("secret", 2)]),
+            resource_metrics(vec![gauge("secret", 3)]),
+        ];
+        retain_allowed_metrics(&mut metrics, &HashSet::from(["allowed".to_string()]));
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metric_names(&metrics), vec!["allowed"]);
+    }
+
+    #[test]
+    fn test_full_buffer_rejects_exports() {
+        let (metrics, _metrics_receiver) = mpsc::channel(1);
+        let (logs, _logs_receiver) = mpsc::channel(1);
+        let forwarder = Forwarder { allowed_metric_names: HashSet::new(), metrics, logs };
+
+        assert!(forwarder.forward_logs(vec![resource_logs("first")]).is_ok());
+        assert!(forwarder.forward_logs(vec![resource_logs("second")]).is_err());
+        // Metrics that aren't allowed are dropped without using the buffer.
+        assert!(forwarder.forward_metrics(vec![resource_metrics(vec![gauge("m", 1)])]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_exporter_rotates_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let params = Params {
+            telemetry_jsonl_max_file_size: 100,
+            telemetry_jsonl_max_rotated_files: 2,
+            ..Default::default()
+        };
+        let mut exporter = JsonlExporter::new(dir.path().join("logs.jsonl"), &params);
+        for index in 0..5 {
+            exporter.export(&[resource_logs(&format!("message {index}"))]).await.unwrap();
+        }
+
+        let read_lines = |name: &str| -> Vec<Value> {
+            std::fs::read_to_string(dir.path().join(name))
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        };
+        // Each line is larger than the maximum file size, so every file holds a
+        // single line.
+        assert_eq!(read_lines("logs.jsonl")[0]["body"], "message 4");
+        assert_eq!(read_lines("logs.jsonl.1")[0]["body"], "message 3");
+        assert_eq!(read_lines("logs.jsonl.2")[0]["body"], "message 2");
+        assert!(!dir.path().join("logs.jsonl.3").exists());
+    }
+
+    #[test]
+    fn test_metrics_to_json_lines() {
+        let lines = resource_metrics(vec![gauge("cpu", 42)]).to_json_lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["name"], "cpu");
+        assert_eq!(lines[0]["type"], "gauge");
+        assert_eq!(lines[0]["value"], 42);
+    }
+
+    /// Stands in for an OTLP collector.
+    #[derive(Default)]
+    struct FakeCollector {
+        requests: Mutex<Vec<ExportMetricsServiceRequest>>,
+    }
+
+    #[tonic::async_trait]
+    impl MetricsService for FakeCollector {
+        async fn export(
+            &self,
+            request: Request<ExportMetricsServiceRequest>,
+        ) -> Result<Response<ExportMetricsServiceResponse>, tonic::Status> {
+            self.requests.lock().unwrap().push(request.into_inner());
+            Ok(Response::new(ExportMetricsServiceResponse { partial_success: None }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forwards_allowed_metrics_to_otlp_collector() {
+        let collector = Arc::new(FakeCollector::default());
+        let listener =
+            TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(MetricsServiceServer::from_arc(collector.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let params = Params {
+            otlp_endpoint: Some(format!("http://{addr}")),
+            allowed_metric_names: vec!["allowed".to_string()],
+            telemetry_buffer_size: 8,
+            telemetry_max_batch_size: 2,
+            telemetry_max_batch_bytes: 1024 * 1024,
+            telemetry_batch_timeout_ms: 10_000,
+            ..Default::default()
+        };
+        let forwarder = Forwarder::create(&params).await.unwrap().unwrap();
+        forwarder
+            .forward_metrics(vec![resource_metrics(vec![gauge("allowed", 1), gauge("secret", 2)])])
+            .unwrap();
+        forwarder.forward_metrics(vec![resource_metrics(vec![gauge("allowed", 3)])]).unwrap();
+
+        // The batch is full after the second request, so it is exported before the
+        // batch timeout.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while collector.requests.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let requests = collector.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(metric_names(&requests[0].resource_metrics), vec!["allowed", "allowed"]);
+    }
+
+    /// Records the batches that it exports.
+    struct RecordingExporter<T>(Arc<Mutex<Vec<Vec<T>>>>);
+
+    #[tonic::async_trait]
+    impl<T: Clone + Send + Sync> Exporter<T> for RecordingExporter<T> {
+        async fn export(&mut self, batch: &[T]) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(batch.to_vec());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_limited_by_encoded_size() {
+        let message = "x".repeat(100);
+        let max_bytes = 2 * encoded_len_in_request(&resource_logs(&message));
+        let (sender, receiver) = mpsc::channel(8);
+        sender.send(vec![resource_logs(&message), resource_logs(&message)]).await.unwrap();
+        sender.send(vec![resource_logs(&message)]).await.unwrap();
+        drop(sender);
+
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        run_batcher(
+            receiver,
+            vec![Box::new(RecordingExporter(batches.clone()))],
+            BatchLimits { max_size: 100, max_bytes, timeout: Duration::from_secs(10) },
+        )
+        .await;
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 1]);
+        for batch in batches.iter() {
+            let request = ExportLogsServiceRequest { resource_logs: batch.clone() };
+            assert!(request.encoded_len() <= max_bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forwards_logs_to_otlp_http_collector() {
+        // Stands in for an OTLP/HTTP collector, recording the path, content type
+        // and body of every request.
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let listener =
+            TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let collector_requests = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let requests = collector_requests.clone();
+                let service = service_fn(move |request: hyper::Request<Incoming>| {
+                    let requests = requests.clone();
+                    async move {
+                        let path = request.uri().path().to_string();
+                        let content_type = request.headers()[header::CONTENT_TYPE].clone();
+                        let body = request.into_body().collect().await.unwrap().to_bytes();
+                        requests.lock().unwrap().push((path, content_type, body));
+                        let response = ExportLogsServiceResponse::default().encode_to_vec();
+                        Ok::<_, Infallible>(hyper::Response::new(Full::new(Bytes::from(response))))
+                    }
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+
+        let params = Params {
+            otlp_endpoint: Some(format!("http://{addr}/")),
+            otlp_protocol: OtlpProtocol::HttpProtobuf,
+            telemetry_buffer_size: 8,
+            telemetry_max_batch_size: 1,
+            telemetry_max_batch_bytes: 1024 * 1024,
+            telemetry_batch_timeout_ms: 10_000,
+            ..Default::default()
+        };
+        let forwarder = Forwarder::create(&params).await.unwrap().unwrap();
+        forwarder.forward_logs(vec![resource_logs("hello")]).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while requests.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (path, content_type, body) = &requests[0];
+        assert_eq!(path, "/v1/logs");
+        assert_eq!(content_type, "application/x-protobuf");
+        let request = ExportLogsServiceRequest::decode(body.clone()).unwrap();
+        assert_eq!(request.resource_logs, vec![resource_logs("hello")]);
+    }
+}
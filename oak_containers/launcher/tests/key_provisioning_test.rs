@@ -0,0 +1,100 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integration test that launches a leader and a follower enclave, and checks
+//! that the follower gets its group keys from the leader through the
+//! follower's launcher.
+
+use std::sync::Once;
+
+use oak_containers_launcher::{Args, Launcher};
+use oak_file_utils::data_path;
+
+static INIT_LOGGING: Once = Once::new();
+
+fn init_logging() {
+    INIT_LOGGING.call_once(|| {
+        env_logger::init();
+    });
+}
+
+fn launcher_args(leader_key_provisioning_uri: Option<String>) -> Args {
+    let vmm_binary = which::which("qemu-system-x86_64").expect("could not find qemu path");
+    let system_image = data_path("oak_containers/system_image/oak_containers_system_image.tar.xz");
+    assert!(system_image.exists(), "System image not found at {system_image:?}");
+    let container_bundle = data_path("oak_containers/examples/hello_world/trusted_app/bundle.tar");
+    assert!(container_bundle.exists(), "Couldn't find container bundle at {container_bundle:?}");
+    let stage0_binary = data_path("stage0_bin/stage0_bin");
+    assert!(stage0_binary.exists(), "Stage0 not found at {stage0_binary:?}");
+    let kernel = data_path("oak_containers/kernel/bzImage");
+    assert!(kernel.exists(), "Kernel not found at {kernel:?}");
+    let initrd = data_path("target/stage1.cpio");
+    assert!(
+        initrd.exists(),
+        "\n\nStage1 not found at {initrd:?}\n\nDid you run `just stage1_cpio`?\n"
+    );
+    Args {
+        system_image,
+        container_bundle,
+        application_config: Vec::new(),
+        qemu_params: oak_containers_launcher::QemuParams {
+            vmm_binary,
+            stage0_binary,
+            kernel,
+            initrd,
+            memory_size: Some("8G".to_owned()),
+            num_cpus: 2,
+            ramdrive_size: 3_000_000,
+            telnet_console: None,
+            virtio_guest_cid: None,
+            pci_passthrough: None,
+            vm_type: oak_containers_launcher::QemuVmType::Default,
+        },
+        endorsement_params: oak_containers_launcher::EndorsementParams::default(),
+        telemetry_params: oak_containers_launcher::TelemetryParams::default(),
+        communication_channel: oak_containers_launcher::ChannelType::default(),
+        reference_values: None,
+        leader_key_provisioning_uri,
+    }
+}
+
+#[tokio::test]
+async fn test_follower_gets_group_keys_from_leader() {
+    init_logging();
+    if oak_functions_test_utils::skip_test() {
+        log::info!("skipping test");
+        return;
+    }
+
+    let mut leader = Launcher::create(launcher_args(None)).await.expect("couldn't start leader");
+    // The leader's orchestrator serves group keys once the trusted app is up.
+    leader.get_trusted_app_address().await.expect("leader didn't become ready");
+
+    let leader_key_provisioning_uri = format!("http://{}", leader.key_provisioning_address());
+    let mut follower = Launcher::create(launcher_args(Some(leader_key_provisioning_uri)))
+        .await
+        .expect("couldn't start follower");
+    // The follower's orchestrator only starts the trusted app after the group keys
+    // it requested through its launcher were provisioned by the leader.
+    follower.get_trusted_app_address().await.expect("follower didn't get group keys");
+
+    let leader_evidence = leader.get_endorsed_evidence().await.unwrap().evidence;
+    let follower_evidence = follower.get_endorsed_evidence().await.unwrap().evidence;
+    assert!(follower_evidence.is_some());
+    assert_ne!(follower_evidence, leader_evidence);
+
+    follower.kill().await;
+    leader.kill().await;
+}
@@ -16,17 +16,22 @@
 use std::{
     fs::OpenOptions,
     io::{Read, Seek, Write},
+    path::Path,
 };
 
 use anyhow::Context;
 use oak_attestation::dice::DiceAttester;
+use oak_dice::evidence::CDI_SIZE;
 use oak_proto_rust::oak::attestation::v1::DiceData;
 use prost::Message;
-use zeroize::Zeroize;
+use zeroize::Zeroizing;
 
 /// The path to the file where the DICE data provided by Stage 1 is stored.
 const STAGE1_DICE_DATA_PATH: &str = "/oak/dice";
 
+/// The path to the file where the sealing CDI provided by Stage 1 is stored.
+const STAGE1_SEALING_CDI_PATH: &str = "/oak/sealing_cdi";
+
 /// Loads the DICE data from the file provided by Stage 1.
 ///
 /// The file is also overwritten with zeros to ensure it cannot be reused by
@@ -35,21 +40,51 @@ pub fn load_stage1_dice_data() -> anyhow::Result<DiceAttester> {
     load_stage1_dice_data_from_path(STAGE1_DICE_DATA_PATH)
 }
 
-fn load_stage1_dice_data_from_path(path: &str) -> anyhow::Result<DiceAttester> {
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(path)
-        .context("couldn't open DICE data file")?;
-    let size = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
-
-    let mut buffer = Vec::with_capacity(size);
-    file.read_to_end(&mut buffer).context("couldn't read DICE data from file")?;
+/// Loads the sealing compound device identifier from the file provided by
+/// Stage 1.
+///
+/// The file is also overwritten with zeros to ensure it cannot be reused by
+/// another process. Returns `None` if Stage 1 didn't provide a sealing CDI
+/// because the platform has no secret to derive it from. Fails if the sealing
+/// CDI is all zeros, e.g. because the file was already wiped by an earlier
+/// run of the orchestrator.
+pub fn load_stage1_sealing_cdi() -> anyhow::Result<Option<Zeroizing<[u8; CDI_SIZE]>>> {
+    load_stage1_sealing_cdi_from_path(STAGE1_SEALING_CDI_PATH)
+}
 
+fn load_stage1_dice_data_from_path(path: &str) -> anyhow::Result<DiceAttester> {
+    let buffer = read_and_wipe_file(path).context("couldn't load DICE data")?;
     let result =
         DiceData::decode_length_delimited(&buffer[..]).context("couldn't parse DICE data")?;
+    result.try_into()
+}
+
+fn load_stage1_sealing_cdi_from_path(
+    path: &str,
+) -> anyhow::Result<Option<Zeroizing<[u8; CDI_SIZE]>>> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let buffer = read_and_wipe_file(path).context("couldn't load sealing CDI")?;
+    let mut cdi = Zeroizing::new([0u8; CDI_SIZE]);
+    anyhow::ensure!(buffer.len() == CDI_SIZE, "invalid sealing CDI size: {}", buffer.len());
+    anyhow::ensure!(
+        buffer.iter().any(|&byte| byte != 0),
+        "sealing CDI is all zeros, it was probably already loaded"
+    );
+    cdi.copy_from_slice(&buffer);
+    Ok(Some(cdi))
+}
+
+/// Reads the contents of the file and then overwrites it with zeros.
+fn read_and_wipe_file(path: &str) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    let mut file =
+        OpenOptions::new().read(true).write(true).open(path).context("couldn't open file")?;
+    let size = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+
+    let mut buffer = Zeroizing::new(Vec::with_capacity(size));
+    file.read_to_end(&mut buffer).context("couldn't read file")?;
 
-    buffer.zeroize();
     file.rewind()?;
     let zeros: Vec<u8> = vec![0; size];
     // Write `size` bytes of value zero over the file in an attempt to overwrite
@@ -58,8 +93,8 @@ fn load_stage1_dice_data_from_path(path: &str) -> anyhow::Result<DiceAttester> {
     // memory.
     // Still the following line does not guarantee overwriting the keys as the
     // filesystem might pick other memory pages to write the data on.
-    file.write_all(&zeros).context("couldn't overwrite DICE data file")?;
-    result.try_into()
+    file.write_all(&zeros).context("couldn't overwrite file")?;
+    Ok(buffer)
 }
 
 #[cfg(test)]
@@ -85,4 +120,36 @@ mod tests {
         assert_eq!(buffer.len(), DICE_DATA_SIZE);
         assert_eq!(buffer, vec![0; DICE_DATA_SIZE]);
     }
+
+    #[test]
+    fn test_load_stage1_sealing_cdi() {
+        const SEALING_CDI_PATH: &str = "sealing_cdi";
+        fs::write(SEALING_CDI_PATH, [42u8; CDI_SIZE]).unwrap();
+
+        let cdi = load_stage1_sealing_cdi_from_path(SEALING_CDI_PATH).unwrap().unwrap();
+        assert_eq!(*cdi, [42u8; CDI_SIZE]);
+        assert_eq!(fs::read(SEALING_CDI_PATH).unwrap(), vec![0; CDI_SIZE]);
+    }
+
+    #[test]
+    fn test_load_stage1_sealing_cdi_twice_fails() {
+        const SEALING_CDI_PATH: &str = "reloaded_sealing_cdi";
+        fs::write(SEALING_CDI_PATH, [42u8; CDI_SIZE]).unwrap();
+
+        load_stage1_sealing_cdi_from_path(SEALING_CDI_PATH).unwrap().unwrap();
+        assert!(load_stage1_sealing_cdi_from_path(SEALING_CDI_PATH).is_err());
+    }
+
+    #[test]
+    fn test_load_stage1_sealing_cdi_invalid_size() {
+        const SEALING_CDI_PATH: &str = "invalid_sealing_cdi";
+        fs::write(SEALING_CDI_PATH, [42u8; CDI_SIZE - 1]).unwrap();
+
+        assert!(load_stage1_sealing_cdi_from_path(SEALING_CDI_PATH).is_err());
+    }
+
+    #[test]
+    fn test_load_stage1_sealing_cdi_missing() {
+        assert!(load_stage1_sealing_cdi_from_path("missing_sealing_cdi").unwrap().is_none());
+    }
 }
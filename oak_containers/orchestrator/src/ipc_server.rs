@@ -26,8 +26,8 @@ use oak_proto_rust::oak::{
     attestation::v1::{Endorsements, Evidence},
     containers::{
         v1::{
-            DeriveSessionKeysRequest, DeriveSessionKeysResponse, KeyOrigin, SignRequest,
-            SignResponse,
+            DeriveSessionKeysRequest, DeriveSessionKeysResponse, KeyOrigin, SealRequest,
+            SealResponse, SignRequest, SignResponse, UnsealRequest, UnsealResponse,
         },
         GetApplicationConfigResponse,
     },
@@ -39,16 +39,27 @@ use tokio_stream::wrappers::UnixListenerStream;
 use tokio_util::sync::CancellationToken;
 use tonic::{transport::Server, Request, Response};
 
-use crate::launcher_client::LauncherClient;
+use crate::{launcher_client::LauncherClient, sealing::Sealer};
 
 pub struct CryptoService {
     instance_keys: InstanceKeys,
     group_keys: Arc<GroupKeys>,
+    sealer: Option<Sealer>,
 }
 
 impl CryptoService {
-    pub fn new(instance_keys: InstanceKeys, group_keys: Arc<GroupKeys>) -> Self {
-        Self { instance_keys, group_keys }
+    pub fn new(
+        instance_keys: InstanceKeys,
+        group_keys: Arc<GroupKeys>,
+        sealer: Option<Sealer>,
+    ) -> Self {
+        Self { instance_keys, group_keys, sealer }
+    }
+
+    fn sealer(&self) -> Result<&Sealer, tonic::Status> {
+        self.sealer.as_ref().ok_or_else(|| {
+            tonic::Status::failed_precondition("sealing is not available on this platform")
+        })
     }
 
     fn signing_key(
@@ -60,13 +71,7 @@ impl CryptoService {
                 Err(tonic::Status::invalid_argument("unspecified key origin"))?
             }
             KeyOrigin::Instance => Ok(&self.instance_keys.signing_key),
-            KeyOrigin::Group =>
-            // TODO(#4442): Implement with key provisioning.
-            {
-                Err(tonic::Status::unimplemented(
-                    "signing using the group key is not yet implemented",
-                ))
-            }
+            KeyOrigin::Group => Ok(&self.group_keys.signing_key),
         }
     }
 }
@@ -111,6 +116,31 @@ impl OrchestratorCrypto for CryptoService {
         };
         Ok(tonic::Response::new(SignResponse { signature: Some(signature) }))
     }
+
+    async fn seal(
+        &self,
+        request: Request<SealRequest>,
+    ) -> Result<Response<SealResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let sealed_data = self.sealer()?.seal(&request.plaintext).map_err(|err| {
+            tonic::Status::failed_precondition(format!("couldn't seal data: {err}"))
+        })?;
+        Ok(tonic::Response::new(SealResponse { sealed_data: Some(sealed_data) }))
+    }
+
+    async fn unseal(
+        &self,
+        request: Request<UnsealRequest>,
+    ) -> Result<Response<UnsealResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let sealed_data = request
+            .sealed_data
+            .ok_or_else(|| tonic::Status::invalid_argument("sealed data not specified"))?;
+        let plaintext = self.sealer()?.unseal(&sealed_data).map_err(|err| {
+            tonic::Status::permission_denied(format!("couldn't unseal data: {err}"))
+        })?;
+        Ok(tonic::Response::new(UnsealResponse { plaintext }))
+    }
 }
 
 pub struct ServiceImplementation {
@@ -181,6 +211,7 @@ pub fn create_services(
     endorsements: Endorsements,
     instance_keys: InstanceKeys,
     group_keys: Arc<GroupKeys>,
+    sealer: Option<Sealer>,
     application_config: Vec<u8>,
     launcher_client: Arc<LauncherClient>,
 ) -> (OrchestratorServer<ServiceImplementation>, OrchestratorCryptoServer<CryptoService>) {
@@ -191,7 +222,7 @@ pub fn create_services(
         evidence,
         endorsements,
     };
-    let crypto_service_instance = CryptoService::new(instance_keys, group_keys);
+    let crypto_service_instance = CryptoService::new(instance_keys, group_keys, sealer);
     (
         OrchestratorServer::new(service_instance),
         OrchestratorCryptoServer::new(crypto_service_instance),
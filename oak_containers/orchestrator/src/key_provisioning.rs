@@ -13,15 +13,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use oak_attestation_verification::verifier::verify_dice_chain_and_extract_evidence;
+use anyhow::bail;
+use oak_attestation_verification::verifier::verify;
 use oak_containers_attestation::GroupKeys;
 use oak_grpc::oak::key_provisioning::v1::key_provisioning_server::{
     KeyProvisioning, KeyProvisioningServer,
 };
-use oak_proto_rust::oak::key_provisioning::v1::{
-    GetGroupKeysRequest, GetGroupKeysResponse, GroupKeys as GroupKeysProto,
+use oak_proto_rust::oak::{
+    attestation::v1::{
+        binary_reference_value, kernel_binary_reference_value, reference_values,
+        regex_reference_value, text_reference_value, verifying_key_reference_value,
+        BinaryReferenceValue, EndorsementReferenceValue, KernelBinaryReferenceValue,
+        ReferenceValues, RegexReferenceValue, TextReferenceValue,
+    },
+    key_provisioning::v1::{
+        GetGroupKeysRequest, GetGroupKeysResponse, GroupKeys as GroupKeysProto,
+    },
 };
 use tokio::net::TcpListener;
 use tokio_stream::wrappers::TcpListenerStream;
@@ -30,11 +42,14 @@ use tonic::{transport::Server, Request, Response};
 
 struct KeyProvisioningService {
     group_keys: Arc<GroupKeys>,
+    // Reference values that the evidence of a follower enclave must match, all of
+    // them, before group keys are released to it.
+    reference_values: Vec<ReferenceValues>,
 }
 
 impl KeyProvisioningService {
-    pub fn new(group_keys: Arc<GroupKeys>) -> Self {
-        Self { group_keys }
+    pub fn new(group_keys: Arc<GroupKeys>, reference_values: Vec<ReferenceValues>) -> Self {
+        Self { group_keys, reference_values }
     }
 }
 
@@ -51,15 +66,28 @@ impl KeyProvisioning for KeyProvisioningService {
         let evidence = request
             .evidence
             .ok_or(tonic::Status::invalid_argument("request message doesn't contain evidence"))?;
-        let _endorsements = request.endorsements.ok_or(tonic::Status::invalid_argument(
+        let endorsements = request.endorsements.ok_or(tonic::Status::invalid_argument(
             "request message doesn't contain endorsements",
         ))?;
-        // TODO(#4442): Provide reference values by the hostlib and use `verify`
-        // function.
-        let attestation_results =
-            verify_dice_chain_and_extract_evidence(&evidence).map_err(|err| {
-                tonic::Status::invalid_argument(format!("couldn't verify endorsed evidence: {err}"))
-            })?;
+        let now_utc_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| tonic::Status::internal(format!("couldn't get current time: {err}")))?
+            .as_millis()
+            .try_into()
+            .map_err(|err| tonic::Status::internal(format!("current time out of range: {err}")))?;
+        let mut attestation_results = None;
+        for reference_values in &self.reference_values {
+            attestation_results =
+                Some(verify(now_utc_millis, &evidence, &endorsements, reference_values).map_err(
+                    |err| {
+                        tonic::Status::permission_denied(format!(
+                            "couldn't verify endorsed evidence: {err:?}"
+                        ))
+                    },
+                )?);
+        }
+        let attestation_results = attestation_results
+            .ok_or_else(|| tonic::Status::internal("no reference values to verify against"))?;
 
         // Encrypt group keys.
         let encrypted_encryption_private_key = self
@@ -68,20 +96,127 @@ impl KeyProvisioning for KeyProvisioningService {
             .map_err(|err| {
                 tonic::Status::internal(format!("couldn't encrypt encryption private key: {err}"))
             })?;
+        let encrypted_signing_private_key = self
+            .group_keys
+            .encrypted_group_signing_key(&attestation_results.encryption_public_key)
+            .map_err(|err| {
+                tonic::Status::internal(format!("couldn't encrypt signing private key: {err}"))
+            })?;
         Ok(tonic::Response::new(GetGroupKeysResponse {
             group_keys: Some(GroupKeysProto {
                 encrypted_encryption_private_key: Some(encrypted_encryption_private_key),
+                encrypted_signing_private_key: Some(encrypted_signing_private_key),
             }),
         }))
     }
 }
 
+/// Checks that reference values provided by the Hostlib do not skip any
+/// verification step.
+///
+/// The Hostlib is untrusted, so the reference values it provides are only ever
+/// checked in addition to the measurements of the leader enclave. Skipping
+/// checks there would silently make the additional constraint meaningless.
+pub fn check_host_reference_values(reference_values: &ReferenceValues) -> anyhow::Result<()> {
+    let Some(reference_values::Type::OakContainers(values)) = &reference_values.r#type else {
+        bail!("reference values are not for Oak Containers");
+    };
+    if let Some(root_layer) = &values.root_layer {
+        if root_layer.insecure.is_some() {
+            bail!("root layer allows insecure platforms");
+        }
+        if let Some(amd_sev) = &root_layer.amd_sev {
+            check_binary("stage0", &amd_sev.stage0)?;
+        }
+        if let Some(intel_tdx) = &root_layer.intel_tdx {
+            check_binary("mr_td", &intel_tdx.mr_td)?;
+            check_binary("rtmr0", &intel_tdx.rtmr0)?;
+            check_binary("rtmr1", &intel_tdx.rtmr1)?;
+            check_binary("rtmr2", &intel_tdx.rtmr2)?;
+            check_binary("rtmr3", &intel_tdx.rtmr3)?;
+        }
+    }
+    if let Some(kernel_layer) = &values.kernel_layer {
+        check_kernel_binary("kernel", &kernel_layer.kernel)?;
+        check_text("kernel_cmd_line_text", &kernel_layer.kernel_cmd_line_text)?;
+        #[allow(deprecated)]
+        {
+            check_binary("kernel_setup_data", &kernel_layer.kernel_setup_data)?;
+            check_binary("kernel_image", &kernel_layer.kernel_image)?;
+            check_binary("kernel_cmd_line", &kernel_layer.kernel_cmd_line)?;
+            check_regex("kernel_cmd_line_regex", &kernel_layer.kernel_cmd_line_regex)?;
+        }
+        check_binary("init_ram_fs", &kernel_layer.init_ram_fs)?;
+        check_binary("memory_map", &kernel_layer.memory_map)?;
+        check_binary("acpi", &kernel_layer.acpi)?;
+    }
+    if let Some(system_layer) = &values.system_layer {
+        check_binary("system_image", &system_layer.system_image)?;
+    }
+    if let Some(container_layer) = &values.container_layer {
+        check_binary("container binary", &container_layer.binary)?;
+        check_binary("container configuration", &container_layer.configuration)?;
+    }
+    Ok(())
+}
+
+fn check_binary(name: &str, value: &Option<BinaryReferenceValue>) -> anyhow::Result<()> {
+    match value.as_ref().and_then(|value| value.r#type.as_ref()) {
+        Some(binary_reference_value::Type::Skip(_)) => bail!("{name} skips verification"),
+        Some(binary_reference_value::Type::Endorsement(endorsement)) => {
+            check_endorsement(name, endorsement)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_kernel_binary(
+    name: &str,
+    value: &Option<KernelBinaryReferenceValue>,
+) -> anyhow::Result<()> {
+    match value.as_ref().and_then(|value| value.r#type.as_ref()) {
+        Some(kernel_binary_reference_value::Type::Skip(_)) => bail!("{name} skips verification"),
+        Some(kernel_binary_reference_value::Type::Endorsement(endorsement)) => {
+            check_endorsement(name, endorsement)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_text(name: &str, value: &Option<TextReferenceValue>) -> anyhow::Result<()> {
+    match value.as_ref().and_then(|value| value.r#type.as_ref()) {
+        Some(text_reference_value::Type::Skip(_)) => bail!("{name} skips verification"),
+        Some(text_reference_value::Type::Endorsement(endorsement)) => {
+            check_endorsement(name, endorsement)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[allow(deprecated)]
+fn check_regex(name: &str, value: &Option<RegexReferenceValue>) -> anyhow::Result<()> {
+    match value.as_ref().and_then(|value| value.r#type.as_ref()) {
+        Some(regex_reference_value::Type::Skip(_)) => bail!("{name} skips verification"),
+        _ => Ok(()),
+    }
+}
+
+fn check_endorsement(name: &str, endorsement: &EndorsementReferenceValue) -> anyhow::Result<()> {
+    let rekor = endorsement.rekor.as_ref().and_then(|rekor| rekor.r#type.as_ref());
+    if let Some(verifying_key_reference_value::Type::Skip(_)) = rekor {
+        bail!("{name} skips Rekor verification");
+    }
+    Ok(())
+}
+
 pub async fn create(
     address: &str,
     group_keys: Arc<GroupKeys>,
+    reference_values: Vec<ReferenceValues>,
     cancellation_token: CancellationToken,
 ) -> Result<(), anyhow::Error> {
-    let key_provisioning_service_instance = KeyProvisioningService::new(group_keys);
+    let key_provisioning_service_instance =
+        KeyProvisioningService::new(group_keys, reference_values);
 
     let listener = TcpListener::bind(address).await?;
 
@@ -95,3 +230,110 @@ pub async fn create(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use oak_attestation_verification::{extract::extract_evidence, reference_values_from_evidence};
+    use oak_containers_attestation::generate_instance_keys;
+    use oak_proto_rust::oak::attestation::v1::{
+        endorsements, Endorsements, Evidence, InsecureReferenceValues, OakContainersEndorsements,
+        RootLayerEndorsements, SkipVerification,
+    };
+    use prost::Message;
+
+    use super::*;
+
+    #[cfg(feature = "bazel")]
+    const TESTDATA_DIR: &str = "oak_attestation_verification/testdata";
+    #[cfg(not(feature = "bazel"))]
+    const TESTDATA_DIR: &str = "../../oak_attestation_verification/testdata";
+
+    fn load_evidence(name: &str) -> Evidence {
+        let serialized =
+            fs::read(format!("{TESTDATA_DIR}/{name}")).expect("couldn't read evidence");
+        Evidence::decode(serialized.as_slice()).expect("couldn't decode evidence")
+    }
+
+    fn load_endorsements(vcek_name: &str) -> Endorsements {
+        let tee_certificate =
+            fs::read(format!("{TESTDATA_DIR}/{vcek_name}")).expect("couldn't read VCEK");
+        Endorsements {
+            r#type: Some(endorsements::Type::OakContainers(OakContainersEndorsements {
+                root_layer: Some(RootLayerEndorsements { tee_certificate, ..Default::default() }),
+                ..Default::default()
+            })),
+            event_endorsements: None,
+        }
+    }
+
+    fn create_service(leader_evidence: &Evidence) -> KeyProvisioningService {
+        let (instance_keys, _) = generate_instance_keys();
+        let (group_keys, _) = instance_keys.generate_group_keys();
+        let reference_values = reference_values_from_evidence(
+            extract_evidence(leader_evidence).expect("couldn't extract evidence"),
+        );
+        KeyProvisioningService::new(Arc::new(group_keys), vec![reference_values])
+    }
+
+    #[tokio::test]
+    async fn test_get_group_keys_for_matching_follower() {
+        let evidence = load_evidence("oc_evidence.binarypb");
+        let service = create_service(&evidence);
+
+        let response = service
+            .get_group_keys(Request::new(GetGroupKeysRequest {
+                evidence: Some(evidence),
+                endorsements: Some(load_endorsements("oc_vcek_milan.der")),
+            }))
+            .await
+            .expect("couldn't get group keys");
+
+        let group_keys = response.into_inner().group_keys.expect("no group keys");
+        assert!(group_keys.encrypted_encryption_private_key.is_some());
+        assert!(group_keys.encrypted_signing_private_key.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_group_keys_for_different_follower() {
+        let service = create_service(&load_evidence("oc_evidence.binarypb"));
+
+        let status = service
+            .get_group_keys(Request::new(GetGroupKeysRequest {
+                evidence: Some(load_evidence("genoa_oc_evidence.binarypb")),
+                endorsements: Some(load_endorsements("vcek_genoa.der")),
+            }))
+            .await
+            .expect_err("group keys were released to a different follower");
+
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[test]
+    fn test_check_host_reference_values() {
+        let reference_values = reference_values_from_evidence(
+            extract_evidence(&load_evidence("oc_evidence.binarypb"))
+                .expect("couldn't extract evidence"),
+        );
+        assert!(check_host_reference_values(&reference_values).is_ok());
+
+        let mut skipping = reference_values.clone();
+        let Some(reference_values::Type::OakContainers(ref mut values)) = skipping.r#type else {
+            panic!("unexpected reference values type");
+        };
+        values.container_layer.as_mut().expect("no container layer").binary =
+            Some(BinaryReferenceValue {
+                r#type: Some(binary_reference_value::Type::Skip(SkipVerification {})),
+            });
+        assert!(check_host_reference_values(&skipping).is_err());
+
+        let mut insecure = reference_values;
+        let Some(reference_values::Type::OakContainers(ref mut values)) = insecure.r#type else {
+            panic!("unexpected reference values type");
+        };
+        values.root_layer.as_mut().expect("no root layer").insecure =
+            Some(InsecureReferenceValues {});
+        assert!(check_host_reference_values(&insecure).is_err());
+    }
+}
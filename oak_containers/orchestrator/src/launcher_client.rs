@@ -20,7 +20,7 @@ use oak_grpc::oak::containers::{
     v1::hostlib_key_provisioning_client::HostlibKeyProvisioningClient,
 };
 use oak_proto_rust::oak::{
-    attestation::v1::{Endorsements, Evidence},
+    attestation::v1::{Endorsements, Evidence, ReferenceValues},
     containers::{v1::KeyProvisioningRole, SendAttestationEvidenceRequest},
     key_provisioning::v1::GroupKeys,
 };
@@ -129,6 +129,17 @@ impl LauncherClient {
             .context("get group keys weren't provided")
     }
 
+    pub async fn get_reference_values(&self) -> anyhow::Result<Option<ReferenceValues>> {
+        Ok(self
+            .hostlib_key_provisioning_client
+            .clone()
+            .get_reference_values(tonic::Request::new(()))
+            .await
+            .context("couldn't get reference values")?
+            .into_inner()
+            .reference_values)
+    }
+
     pub fn openmetrics_builder(&self) -> TonicExporterBuilder {
         opentelemetry_otlp::new_exporter().tonic().with_channel(self.channel.clone())
     }
@@ -20,3 +20,4 @@ pub mod ipc_server;
 pub mod key_provisioning;
 pub mod launcher_client;
 pub mod logging;
+pub mod sealing;
@@ -17,9 +17,12 @@ use std::{path::PathBuf, sync::Arc};
 
 use anyhow::{anyhow, Context};
 use clap::Parser;
+use oak_attestation_verification::{extract::extract_evidence, reference_values_from_evidence};
 use oak_containers_agent::{metrics::MetricsConfig, set_error_handler};
 use oak_containers_attestation::generate_instance_keys;
-use oak_containers_orchestrator::launcher_client::LauncherClient;
+use oak_containers_orchestrator::{
+    key_provisioning::check_host_reference_values, launcher_client::LauncherClient, sealing::Sealer,
+};
 use oak_proto_rust::oak::containers::v1::KeyProvisioningRole;
 use tokio_util::sync::CancellationToken;
 
@@ -94,10 +97,12 @@ async fn main() -> anyhow::Result<()> {
 
     // Generate attestation evidence and send it to the Hostlib.
     let attester = oak_containers_orchestrator::dice::load_stage1_dice_data()?;
+    let sealing_cdi = oak_containers_orchestrator::dice::load_stage1_sealing_cdi()?;
     let layer_data = oak_containers_attestation::measure_container_and_config(
         &container_bundle,
         &application_config,
     );
+    let sealer = sealing_cdi.map(|sealing_cdi| Sealer::new(sealing_cdi.as_ref(), &layer_data));
     let evidence = attester.add_application_keys(
         layer_data,
         &instance_public_keys.encryption_public_key,
@@ -107,7 +112,11 @@ async fn main() -> anyhow::Result<()> {
         } else {
             None
         },
-        None,
+        if let Some(ref group_public_keys) = group_public_keys {
+            Some(&group_public_keys.signing_public_key)
+        } else {
+            None
+        },
     )?;
 
     launcher_client
@@ -127,6 +136,21 @@ async fn main() -> anyhow::Result<()> {
         group_keys = Some(Arc::new(provisioned_group_keys));
     }
 
+    // Followers must always match the measurements of this enclave. Reference
+    // values provided by the Hostlib can only constrain the followers further.
+    let mut reference_values = vec![reference_values_from_evidence(
+        extract_evidence(&evidence).context("couldn't extract evidence")?,
+    )];
+    if let Some(host_reference_values) = launcher_client
+        .get_reference_values()
+        .await
+        .map_err(|error| anyhow!("couldn't get reference values: {:?}", error))?
+    {
+        check_host_reference_values(&host_reference_values)
+            .context("invalid reference values provided by the Hostlib")?;
+        reference_values.push(host_reference_values);
+    }
+
     if let Some(path) = args.ipc_socket_path.parent() {
         tokio::fs::create_dir_all(path).await?;
     }
@@ -142,6 +166,7 @@ async fn main() -> anyhow::Result<()> {
             endorsements,
             instance_keys,
             group_keys.clone().context("group keys were not provisioned")?,
+            sealer,
             application_config,
             launcher_client,
         );
@@ -161,6 +186,7 @@ async fn main() -> anyhow::Result<()> {
         oak_containers_orchestrator::key_provisioning::create(
             &args.orchestrator_addr,
             group_keys.context("group keys were not provisioned")?,
+            reference_values,
             cancellation_token.clone(),
         ),
         oak_containers_orchestrator::container_runtime::run(
@@ -0,0 +1,122 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Sealing of application data to the identity of the enclave.
+//!
+//! Data is sealed with a key derived from the sealing CDI of the container
+//! layer. Only an enclave running exactly the same firmware, kernel, system
+//! image, container bundle and application config can derive it.
+
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use anyhow::anyhow;
+use hkdf::Hkdf;
+use oak_attestation::dice::{derive_sealing_cdi, LayerData};
+use oak_proto_rust::oak::containers::v1::SealedData;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+
+const MEASUREMENT_KEY_INFO: &[u8] = b"Oak Containers measurement sealing key";
+
+/// Seals and unseals application data.
+pub struct Sealer {
+    measurement_key: Zeroizing<[u8; KEY_SIZE]>,
+}
+
+impl Sealer {
+    /// Derives the sealing key from the sealing CDI of the system image layer
+    /// and the layer data of the container.
+    pub fn new(system_cdi: &[u8], container_layer_data: &LayerData) -> Self {
+        let container_cdi = derive_sealing_cdi(system_cdi, container_layer_data);
+        let measurement_key = derive_key(None, container_cdi.as_ref(), MEASUREMENT_KEY_INFO);
+        Self { measurement_key }
+    }
+
+    /// Encrypts the plaintext with the sealing key.
+    pub fn seal(&self, plaintext: &[u8]) -> anyhow::Result<SealedData> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher()?
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|err| anyhow!("couldn't encrypt data: {err}"))?;
+        Ok(SealedData { nonce: nonce.to_vec(), ciphertext })
+    }
+
+    /// Decrypts data that was previously sealed with [`Sealer::seal`].
+    ///
+    /// Fails if the data was sealed by an enclave with a different measurement.
+    pub fn unseal(&self, sealed_data: &SealedData) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(
+            sealed_data.nonce.len() == NONCE_SIZE,
+            "invalid nonce size: {}",
+            sealed_data.nonce.len()
+        );
+        self.cipher()?
+            .decrypt(Nonce::from_slice(&sealed_data.nonce), sealed_data.ciphertext.as_ref())
+            .map_err(|err| anyhow!("couldn't decrypt data: {err}"))
+    }
+
+    fn cipher(&self) -> anyhow::Result<Aes256Gcm> {
+        Aes256Gcm::new_from_slice(self.measurement_key.as_ref())
+            .map_err(|err| anyhow!("invalid key: {err}"))
+    }
+}
+
+fn derive_key(salt: Option<&[u8]>, ikm: &[u8], info: &[u8]) -> Zeroizing<[u8; KEY_SIZE]> {
+    let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info, key.as_mut())
+        .expect("key size is a valid HKDF output length");
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYSTEM_CDI: [u8; 32] = [1; 32];
+    const PLAINTEXT: &[u8] = b"secret";
+
+    fn layer_data(encoded_event: &[u8]) -> LayerData {
+        LayerData { additional_claims: vec![], encoded_event: encoded_event.to_vec() }
+    }
+
+    #[test]
+    fn test_measurement_sealing() {
+        let sealer = Sealer::new(&SYSTEM_CDI, &layer_data(b"container"));
+        let sealed_data = sealer.seal(PLAINTEXT).unwrap();
+        assert_ne!(sealed_data.ciphertext, PLAINTEXT);
+        assert_eq!(sealer.unseal(&sealed_data).unwrap(), PLAINTEXT);
+
+        let other_container = Sealer::new(&SYSTEM_CDI, &layer_data(b"other"));
+        assert!(other_container.unseal(&sealed_data).is_err());
+
+        let other_system = Sealer::new(&[2; 32], &layer_data(b"container"));
+        assert!(other_system.unseal(&sealed_data).is_err());
+    }
+
+    #[test]
+    fn test_modified_ciphertext_fails() {
+        let sealer = Sealer::new(&SYSTEM_CDI, &layer_data(b"container"));
+        let mut sealed_data = sealer.seal(PLAINTEXT).unwrap();
+        sealed_data.ciphertext[0] ^= 1;
+        assert!(sealer.unseal(&sealed_data).is_err());
+    }
+}
@@ -24,7 +24,7 @@ use oak_proto_rust::oak::attestation::v1::EventLog;
 use prost::Message;
 use x86_64::PhysAddr;
 use zerocopy::FromBytes;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::try_parse_phys_addr;
 
@@ -180,12 +180,41 @@ impl SensitiveDiceDataMemory {
         EventLog::decode(event_log_bytes).context("Failed to decode EventLog proto")
     }
 
+    /// Reads the compound device identifier that Stage 0 derived for this
+    /// layer.
+    ///
+    /// The CDI is only used to derive sealing keys, so it is not included in
+    /// the attester. Returns `None` if Stage 0 couldn't derive it from a
+    /// secret.
+    pub fn read_cdi(
+        &self,
+    ) -> anyhow::Result<Option<Zeroizing<[u8; oak_dice::evidence::CDI_SIZE]>>> {
+        let mut stage0_dice_data = self.read_stage0_dice_data()?;
+        let cdi = stage0_dice_data
+            .layer_1_cdi
+            .is_available()
+            .then(|| Zeroizing::new(stage0_dice_data.layer_1_cdi.cdi));
+        stage0_dice_data.layer_1_cdi.cdi.zeroize();
+        Ok(cdi)
+    }
+
     /// Extracts the DICE evidence and ECA key from the Stage 0 DICE data
     /// located at the given physical address.
-    pub fn read_into_attester(self) -> anyhow::Result<oak_attestation::dice::DiceAttester> {
+    ///
+    /// The TEE certificate, if any, is attached to the root layer evidence. On
+    /// Intel TDX, the TD report generated by Stage 0 is upgraded to a quote.
+    pub fn read_into_attester(
+        self,
+        tee_certificate: Option<Vec<u8>>,
+    ) -> anyhow::Result<oak_attestation::dice::DiceAttester> {
         let stage0_dice_data = self.read_stage0_dice_data()?;
         let eventlog = self.read_eventlog()?;
-        oak_containers_stage1_dice::stage0_dice_data_into_dice_attester(stage0_dice_data, eventlog)
+        oak_containers_stage1_dice::stage0_dice_data_into_dice_attester(
+            stage0_dice_data,
+            eventlog,
+            tee_certificate,
+            crate::quote::get_quote,
+        )
     }
 }
 
@@ -205,6 +234,54 @@ impl Drop for SensitiveDiceDataMemory {
     }
 }
 
+/// Reads the TEE certificate that Stage 0 wrote to the given physical address.
+///
+/// The certificate is stored as its length in little-endian format as a u64,
+/// followed by the DER encoding, within a single reserved page.
+pub fn read_tee_certificate(start: PhysAddr) -> anyhow::Result<Vec<u8>> {
+    const PAGE_SIZE: usize = 4096;
+    // Linux presents an inclusive end address.
+    let end = start + (PAGE_SIZE as u64 - 1);
+    anyhow::ensure!(
+        read_memory_ranges()?.iter().any(|range| {
+            range.type_description == RESERVED_E820_TYPE
+                && range.contains(&start)
+                && range.contains(&end)
+        }),
+        "TEE certificate is not in reserved memory"
+    );
+
+    let mem_file = OpenOptions::new()
+        .read(true)
+        .open(PHYS_MEM_PATH)
+        .context("couldn't open memory device for reading")?;
+
+    // Safety: we have checked it is within a reserved region, so the Linux kernel
+    // will not use it for anything else.
+    let ptr = unsafe {
+        mmap(
+            None,
+            PAGE_SIZE.try_into()?,
+            ProtFlags::PROT_READ,
+            MapFlags::MAP_SHARED,
+            Some(&mem_file),
+            start.as_u64().try_into()?,
+        )?
+    };
+    // Safety: we have mapped a full page of reserved memory.
+    let page = unsafe { std::slice::from_raw_parts(ptr as *const u8, PAGE_SIZE) };
+    let length = u64::from_le_bytes(page[..8].try_into().unwrap()) as usize;
+    let result = 8usize
+        .checked_add(length)
+        .and_then(|end| page.get(8..end))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow::anyhow!("TEE certificate extends beyond its page"));
+    // Safety: we've mapped the memory and the slice referencing it is no longer
+    // used.
+    unsafe { munmap(ptr, PAGE_SIZE) }.context("failed to unmap TEE certificate memory")?;
+    result
+}
+
 /// Reads the memory ranges which supplied by the firmware to the Linux kernel.
 fn read_memory_ranges() -> anyhow::Result<Vec<MemoryRange>> {
     read_dir(MEMMAP_PATH)
@@ -20,6 +20,7 @@
 mod client;
 mod dice;
 mod image;
+mod quote;
 
 use std::{
     error::Error,
@@ -59,10 +60,15 @@ struct Args {
 
     #[arg(long = "oak-event-log", value_parser = try_parse_phys_addr)]
     event_log: PhysAddr,
+
+    #[arg(long = "oak-tee-certificate", value_parser = try_parse_phys_addr)]
+    tee_certificate: Option<PhysAddr>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // There is no syslog yet, so log warnings and errors to the console.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
     let args = Args::parse();
     if !Path::new("/dev").try_exists()? {
         create_dir("/dev").context("error creating /dev")?;
@@ -77,23 +83,31 @@ async fn main() -> Result<(), Box<dyn Error>> {
     mount(Some("/dev/ram0"), "/rootfs", Some("ext4"), MsFlags::empty(), None::<&str>)
         .context("error mounting ramdrive to /rootfs")?;
 
-    // Mount /sys so that we can read the memory map.
+    // Mount /sys so that we can read the memory map and, on Intel TDX, get a
+    // quote.
     if !Path::new("/sys").try_exists()? {
         create_dir("/sys").context("error creating /sys")?;
     }
     mount(None::<&str>, "/sys", Some("sysfs"), MsFlags::empty(), None::<&str>)
         .context("error mounting /sys")?;
 
-    let mut attester = {
+    let tee_certificate = args
+        .tee_certificate
+        .map(dice::read_tee_certificate)
+        .transpose()
+        .context("error reading the TEE certificate")?;
+
+    let (mut attester, cdi) = {
         // Safety: This will be the only instance of this struct.
-        unsafe {
+        let dice_memory = unsafe {
             dice::SensitiveDiceDataMemory::new(
                 args.dice_addr,
                 args.event_log,
                 args.dice_data_length,
             )
-        }?
-        .read_into_attester()?
+        }?;
+        let cdi = dice_memory.read_cdi()?;
+        (dice_memory.read_into_attester(tee_certificate)?, cdi)
     };
 
     // Unmount /sys and /dev as they are no longer needed.
@@ -122,6 +136,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // compressed system image. This consumes the `DiceBuilder` which also
     // clears the ECA private key provided by Stage 0.
     let layer_data = oak_containers_stage1_dice::get_layer_data(&buf);
+    let sealing_cdi =
+        cdi.map(|cdi| oak_attestation::dice::derive_sealing_cdi(&cdi[..], &layer_data));
     attester.add_layer(layer_data)?;
     let dice_data = attester.serialize();
 
@@ -138,6 +154,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // file.
     create_dir("/oak").context("error creating `oak` directory")?;
     fs::write("/oak/dice", dice_data).context("error writing DICE data")?;
+    // The orchestrator uses the sealing CDI to derive sealing keys for the
+    // application. Without it, the orchestrator refuses to seal data.
+    if let Some(sealing_cdi) = sealing_cdi {
+        fs::write("/oak/sealing_cdi", &sealing_cdi[..]).context("error writing sealing CDI")?;
+    } else {
+        log::warn!("no sealing CDI available, sealing is disabled");
+    }
 
     // Configure eth0 down, as systemd will want to manage it itself and gets
     // confused if it already has an IP address.
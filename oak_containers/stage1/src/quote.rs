@@ -0,0 +1,82 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::{
+    fs::{self, create_dir, read_to_string, remove_dir},
+    ops::Range,
+    path::Path,
+};
+
+use anyhow::Context;
+use nix::mount::{mount, umount, MsFlags};
+
+/// The mount point of the configfs pseudo-filesystem.
+const CONFIGFS_PATH: &str = "/sys/kernel/config";
+
+/// The configfs directory in which Linux creates TSM attestation reports.
+///
+/// See <https://www.kernel.org/doc/Documentation/ABI/testing/configfs-tsm>.
+const TSM_REPORT_PATH: &str = "/sys/kernel/config/tsm/report";
+
+/// The TSM provider that generates Intel TDX quotes.
+const TDX_TSM_PROVIDER: &str = "tdx_guest";
+
+/// The location of the report data in a TD report.
+///
+/// See section 3.6.1 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf).
+const TD_REPORT_REPORT_DATA: Range<usize> = 128..192;
+
+/// Gets an Intel TDX quote for the same TD and report data as the TD report
+/// that Stage 0 generated.
+///
+/// Linux generates a fresh TD report with the report data and has the Quoting
+/// Enclave sign it through the GetQuote TDVMCALL. The quote only matches the
+/// Stage 0 TD report as long as no RTMR has been extended since, which the
+/// caller checks.
+///
+/// Requires `/sys` to be mounted.
+pub fn get_quote(td_report: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let report_data =
+        td_report.get(TD_REPORT_REPORT_DATA).context("TD report is too short")?.to_vec();
+
+    mount(None::<&str>, CONFIGFS_PATH, Some("configfs"), MsFlags::empty(), None::<&str>)
+        .context("error mounting configfs")?;
+    let quote = get_tsm_report(&report_data);
+    umount(CONFIGFS_PATH).context("failed to unmount configfs")?;
+    quote
+}
+
+/// Requests an attestation report with the report data from the TSM report
+/// interface of configfs.
+fn get_tsm_report(report_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let report_dir = Path::new(TSM_REPORT_PATH).join("stage1");
+    create_dir(&report_dir).context("error creating TSM report")?;
+    let quote = read_tsm_report(&report_dir, report_data);
+    remove_dir(&report_dir).context("error removing TSM report")?;
+    quote
+}
+
+fn read_tsm_report(report_dir: &Path, report_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let provider =
+        read_to_string(report_dir.join("provider")).context("error reading TSM provider")?;
+    anyhow::ensure!(
+        provider.trim() == TDX_TSM_PROVIDER,
+        "unexpected TSM provider: {}",
+        provider.trim()
+    );
+    fs::write(report_dir.join("inblob"), report_data).context("error writing report data")?;
+    fs::read(report_dir.join("outblob")).context("error reading quote")
+}
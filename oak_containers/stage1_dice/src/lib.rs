@@ -17,18 +17,43 @@
 #![feature(iterator_try_collect)]
 #![feature(never_type)]
 
+use anyhow::Context;
 use ciborium::Value;
 use coset::cwt::ClaimName;
+use oak_attestation_verification::extract::upgrade_td_report_to_quote;
 use oak_dice::cert::SHA2_256_ID;
-use oak_proto_rust::oak::attestation::v1::{DiceData, Event, SystemLayerData};
+use oak_proto_rust::oak::attestation::v1::{DiceData, Event, SystemLayerData, TeePlatform};
 use prost::Message;
 
-pub fn stage0_dice_data_into_dice_attester(
+/// Converts the Stage 0 DICE data into an attester for the next layers.
+///
+/// On Intel TDX, Stage 0 records a TD report in the root layer evidence, which
+/// can only be verified on the local platform. It is replaced with the quote
+/// returned by `get_quote`, which is called with the TD report and is not
+/// called on other platforms.
+pub fn stage0_dice_data_into_dice_attester<F>(
     stage0_dice_data: oak_dice::evidence::Stage0DiceData,
     eventlog: oak_proto_rust::oak::attestation::v1::EventLog,
-) -> anyhow::Result<oak_attestation::dice::DiceAttester> {
-    let dice_data: DiceData =
+    tee_certificate: Option<Vec<u8>>,
+    get_quote: F,
+) -> anyhow::Result<oak_attestation::dice::DiceAttester>
+where
+    F: FnOnce(&[u8]) -> anyhow::Result<Vec<u8>>,
+{
+    let mut dice_data: DiceData =
         oak_attestation::dice::stage0_dice_data_and_event_log_to_proto(stage0_dice_data, eventlog)?;
+    let root_layer = dice_data
+        .evidence
+        .as_mut()
+        .and_then(|evidence| evidence.root_layer.as_mut())
+        .ok_or_else(|| anyhow::anyhow!("no root layer evidence"))?;
+    if let Some(tee_certificate) = tee_certificate {
+        root_layer.tee_certificate = tee_certificate;
+    }
+    if root_layer.platform() == TeePlatform::IntelTdx {
+        upgrade_td_report_to_quote(root_layer, get_quote)
+            .context("couldn't upgrade the TD report to a quote")?;
+    }
     dice_data.try_into()
 }
 
@@ -18,7 +18,9 @@ use async_trait::async_trait;
 use oak_crypto::{encryption_key::AsyncEncryptionKeyHandle, hpke::RecipientContext};
 use oak_grpc::oak::containers::v1::orchestrator_crypto_client::OrchestratorCryptoClient as GrpcOrchestratorCryptoClient;
 use oak_proto_rust::oak::{
-    containers::v1::{DeriveSessionKeysRequest, KeyOrigin, SignRequest},
+    containers::v1::{
+        DeriveSessionKeysRequest, KeyOrigin, SealRequest, SealedData, SignRequest, UnsealRequest,
+    },
     crypto::v1::{SessionKeys, Signature},
 };
 use tonic::transport::{Endpoint, Uri};
@@ -76,6 +78,28 @@ impl OrchestratorCryptoClient {
             .signature
             .context("signature was not provided by the Orchestrator")
     }
+
+    async fn seal(&self, plaintext: Vec<u8>) -> anyhow::Result<SealedData> {
+        self.inner
+            // TODO(#4477): Remove unnecessary copies of the Orchestrator client.
+            .clone()
+            .seal(SealRequest { plaintext })
+            .await?
+            .into_inner()
+            .sealed_data
+            .context("sealed data was not provided by the Orchestrator")
+    }
+
+    async fn unseal(&self, sealed_data: SealedData) -> anyhow::Result<Vec<u8>> {
+        Ok(self
+            .inner
+            // TODO(#4477): Remove unnecessary copies of the Orchestrator client.
+            .clone()
+            .unseal(UnsealRequest { sealed_data: Some(sealed_data) })
+            .await?
+            .into_inner()
+            .plaintext)
+    }
 }
 
 pub struct InstanceEncryptionKeyHandle {
@@ -141,3 +165,31 @@ impl Signer for InstanceSigner {
         self.orchestrator_crypto_client.sign(KeyOrigin::Instance, message.to_vec()).await
     }
 }
+
+/// Seals data so that it can only be unsealed by the same application.
+///
+/// Data is sealed to the exact measurement of the enclave, including the
+/// container bundle and application config.
+pub struct Sealer {
+    orchestrator_crypto_client: OrchestratorCryptoClient,
+}
+
+impl Sealer {
+    pub async fn create() -> anyhow::Result<Self> {
+        Ok(Self {
+            orchestrator_crypto_client: OrchestratorCryptoClient::create()
+                .await
+                .context("couldn't create Orchestrator crypto client")?,
+        })
+    }
+
+    /// Seals the data to the measurement of the enclave.
+    pub async fn seal(&self, plaintext: &[u8]) -> anyhow::Result<SealedData> {
+        self.orchestrator_crypto_client.seal(plaintext.to_vec()).await
+    }
+
+    /// Unseals data that was previously sealed with this API.
+    pub async fn unseal(&self, sealed_data: SealedData) -> anyhow::Result<Vec<u8>> {
+        self.orchestrator_crypto_client.unseal(sealed_data).await
+    }
+}
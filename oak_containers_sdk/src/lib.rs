@@ -35,5 +35,7 @@ const IPC_SOCKET: &str = "/oak_utils/orchestrator_ipc";
 // Re-export structs so that they are available at the top level of the SDK.
 pub use attestation::{InstanceAttester, InstanceEndorser};
 pub use crypto::InstanceEncryptionKeyHandle;
-pub use oak_session_context::{ApplicationHandler, OakSessionContext};
+pub use oak_session_context::{
+    ApplicationHandler, OakSessionContext, ResponseChunkStream, StreamingApplicationHandler,
+};
 pub use orchestrator_client::{OrchestratorClient, OrchestratorInterface};
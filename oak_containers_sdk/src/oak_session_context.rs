@@ -13,8 +13,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::pin::Pin;
+
 use anyhow::Context;
-use oak_crypto::{encryption_key::AsyncEncryptionKeyHandle, encryptor::ServerEncryptor};
+use oak_crypto::{
+    encryption_key::AsyncEncryptionKeyHandle,
+    encryptor::{response_chunk_associated_data, ServerEncryptor},
+};
 use oak_proto_rust::oak::{
     crypto::v1::{EncryptedRequest, EncryptedResponse},
     session::v1::{
@@ -22,6 +27,7 @@ use oak_proto_rust::oak::{
         InvokeRequest, InvokeResponse, RequestWrapper, ResponseWrapper,
     },
 };
+use tokio_stream::{Stream, StreamExt};
 
 const EMPTY_ASSOCIATED_DATA: &[u8] = b"";
 
@@ -32,6 +38,60 @@ pub trait ApplicationHandler: Send + Sync {
     async fn handle(&self, request_bytes: &[u8]) -> anyhow::Result<Vec<u8>>;
 }
 
+/// A stream of plaintext response chunks produced by a
+/// [`StreamingApplicationHandler`].
+pub type ResponseChunkStream<'a> = Pin<Box<dyn Stream<Item = anyhow::Result<Vec<u8>>> + Send + 'a>>;
+
+/// An Oak trusted application that produces its response incrementally will
+/// write an implementation of `StreamingApplicationHandler`.
+///
+/// Every chunk yielded by the returned stream is encrypted under the session
+/// keys of the request and sent to the client as a separate `InvokeResponse`,
+/// in the order in which the chunks were produced. The associated data of each
+/// chunk holds its sequence number (see
+/// [`oak_crypto::encryptor::response_chunk_associated_data`]), and an empty
+/// final chunk follows the last one, so clients must read the responses with
+/// `OakClient::invoke_streaming` (or
+/// [`oak_crypto::encryptor::ResponseChunkDecryptor`]) rather than
+/// `OakClient::invoke`. An error ends the stream without a final chunk.
+///
+/// The stream is dropped without being polled further when the client
+/// disconnects, so applications can rely on that to stop generating chunks.
+pub trait StreamingApplicationHandler: Send + Sync {
+    fn handle_streaming<'a>(&'a self, request_bytes: &'a [u8]) -> ResponseChunkStream<'a>;
+}
+
+/// The application logic of an [`OakSessionContext`].
+enum Handler {
+    /// Sends a single response per request, encrypted with empty associated
+    /// data, as expected by `OakClient::invoke`.
+    Unary(Box<dyn ApplicationHandler>),
+    /// Sends a stream of response chunks per request.
+    Streaming(Box<dyn StreamingApplicationHandler>),
+}
+
+/// Encrypts a response chunk, binding it to its position in the response
+/// stream via the associated data.
+fn encrypt_response_chunk(
+    server_encryptor: &ServerEncryptor,
+    response_chunk: &[u8],
+    sequence_number: u64,
+    is_final: bool,
+) -> anyhow::Result<ResponseWrapper> {
+    let encrypted_response = server_encryptor
+        .encrypt(response_chunk, &response_chunk_associated_data(sequence_number, is_final))
+        .context("couldn't encrypt response")?;
+    Ok(invoke_response(encrypted_response))
+}
+
+fn invoke_response(encrypted_response: EncryptedResponse) -> ResponseWrapper {
+    ResponseWrapper {
+        response: Some(response_wrapper::Response::InvokeResponse(InvokeResponse {
+            encrypted_response: Some(encrypted_response),
+        })),
+    }
+}
+
 /// The state needed to handle one streaming Oak crypto session.
 ///
 /// This structure contains the server-implementation-independent functionality
@@ -40,7 +100,7 @@ pub trait ApplicationHandler: Send + Sync {
 pub struct OakSessionContext {
     encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
     endorsed_evidence: EndorsedEvidence,
-    application_handler: Box<dyn ApplicationHandler>,
+    application_handler: Handler,
 }
 
 impl OakSessionContext {
@@ -49,7 +109,29 @@ impl OakSessionContext {
         endorsed_evidence: EndorsedEvidence,
         application_handler: Box<dyn ApplicationHandler>,
     ) -> Self {
-        Self { encryption_key_handle, endorsed_evidence, application_handler }
+        Self {
+            encryption_key_handle,
+            endorsed_evidence,
+            application_handler: Handler::Unary(application_handler),
+        }
+    }
+
+    /// Creates a context whose application handler may send several encrypted
+    /// responses for each request.
+    ///
+    /// The responses are framed as described in
+    /// [`StreamingApplicationHandler`], which existing clients that expect a
+    /// single response per request can't read.
+    pub fn new_streaming(
+        encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+        endorsed_evidence: EndorsedEvidence,
+        application_handler: Box<dyn StreamingApplicationHandler>,
+    ) -> Self {
+        Self {
+            encryption_key_handle,
+            endorsed_evidence,
+            application_handler: Handler::Streaming(application_handler),
+        }
     }
 
     pub fn endorsed_evidence(&self) -> &EndorsedEvidence {
@@ -57,58 +139,91 @@ impl OakSessionContext {
     }
 
     /// Decrypts the request, passes it to the application handler, and
-    /// encrypts the resulting response.
-    async fn handle_encrypted_request(
-        &self,
-        encrypted_request: &Option<EncryptedRequest>,
-    ) -> anyhow::Result<EncryptedResponse> {
-        let encrypted_request =
-            encrypted_request.as_ref().context("encrypted request wasn't present")?;
-
-        // Associated data is ignored.
-        let (server_encryptor, name_bytes, _) =
-            ServerEncryptor::decrypt_async(encrypted_request, &*self.encryption_key_handle)
-                .await
-                .context("couldn't decrypt request")?;
-
-        let response = self
-            .application_handler
-            .handle(&name_bytes)
-            .await
-            .context("application handler failed")?;
-
-        server_encryptor
-            .encrypt(&response, EMPTY_ASSOCIATED_DATA)
-            .context("couldn't encrypt response")
+    /// encrypts the resulting response, or each of its chunks for a streaming
+    /// application handler.
+    fn handle_encrypted_request<'a>(
+        &'a self,
+        encrypted_request: &'a Option<EncryptedRequest>,
+    ) -> impl Stream<Item = anyhow::Result<ResponseWrapper>> + Send + 'a {
+        async_stream::try_stream! {
+            let encrypted_request =
+                encrypted_request.as_ref().context("encrypted request wasn't present")?;
+
+            // Associated data is ignored.
+            let (server_encryptor, name_bytes, _) =
+                ServerEncryptor::decrypt_async(encrypted_request, &*self.encryption_key_handle)
+                    .await
+                    .context("couldn't decrypt request")?;
+
+            match &self.application_handler {
+                Handler::Unary(application_handler) => {
+                    let response = application_handler
+                        .handle(&name_bytes)
+                        .await
+                        .context("application handler failed")?;
+                    yield invoke_response(
+                        server_encryptor
+                            .encrypt(&response, EMPTY_ASSOCIATED_DATA)
+                            .context("couldn't encrypt response")?,
+                    );
+                }
+                Handler::Streaming(application_handler) => {
+                    // Each chunk is bound to its position in the stream, and the stream ends
+                    // with an empty final chunk, so that the client can detect reordered or
+                    // truncated responses. An error ends the stream without a final chunk.
+                    let mut sequence_number = 0u64;
+                    let mut response_chunks = application_handler.handle_streaming(&name_bytes);
+                    while let Some(response_chunk) = response_chunks.next().await {
+                        let response_chunk = response_chunk.context("application handler failed")?;
+                        yield encrypt_response_chunk(
+                            &server_encryptor,
+                            &response_chunk,
+                            sequence_number,
+                            false,
+                        )?;
+                        sequence_number += 1;
+                    }
+                    yield encrypt_response_chunk(&server_encryptor, &[], sequence_number, true)?;
+                }
+            }
+        }
     }
 
     /// Handles an incoming Oak request by either handling it internally if it's
     /// an Oak message, or passing it to the provided application handler if
     /// it's an application message.
-    pub(crate) async fn handle_request(
-        &self,
-        request_wrapper: &RequestWrapper,
-    ) -> anyhow::Result<ResponseWrapper> {
-        let request = &request_wrapper
-            .request
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("empty request message"))?;
-
-        let response = match request {
-            request_wrapper::Request::GetEndorsedEvidenceRequest(_) => {
-                response_wrapper::Response::GetEndorsedEvidenceResponse(
-                    GetEndorsedEvidenceResponse {
-                        endorsed_evidence: Some(self.endorsed_evidence().clone()),
-                    },
-                )
-            }
-            request_wrapper::Request::InvokeRequest(InvokeRequest { encrypted_request }) => {
-                let encrypted_response = self.handle_encrypted_request(encrypted_request).await?;
-                response_wrapper::Response::InvokeResponse(InvokeResponse {
-                    encrypted_response: Some(encrypted_response),
-                })
+    ///
+    /// Application messages may result in several responses, one per chunk
+    /// produced by a streaming application handler.
+    pub(crate) fn handle_request<'a>(
+        &'a self,
+        request_wrapper: &'a RequestWrapper,
+    ) -> impl Stream<Item = anyhow::Result<ResponseWrapper>> + Send + 'a {
+        async_stream::try_stream! {
+            let request = request_wrapper
+                .request
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("empty request message"))?;
+
+            match request {
+                request_wrapper::Request::GetEndorsedEvidenceRequest(_) => {
+                    yield ResponseWrapper {
+                        response: Some(response_wrapper::Response::GetEndorsedEvidenceResponse(
+                            GetEndorsedEvidenceResponse {
+                                endorsed_evidence: Some(self.endorsed_evidence().clone()),
+                            },
+                        )),
+                    };
+                }
+                request_wrapper::Request::InvokeRequest(InvokeRequest { encrypted_request }) => {
+                    for await response in self.handle_encrypted_request(encrypted_request) {
+                        yield response?;
+                    }
+                }
             }
-        };
-        Ok(ResponseWrapper { response: Some(response) })
+        }
     }
 }
+
+#[cfg(test)]
+mod tests;
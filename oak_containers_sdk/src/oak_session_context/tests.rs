@@ -0,0 +1,191 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use oak_crypto::{
+    encryption_key::generate_encryption_key_pair,
+    encryptor::{ClientEncryptor, ResponseChunkDecryptor},
+};
+use oak_proto_rust::oak::crypto::v1::EncryptedResponse;
+
+use super::*;
+
+const REQUEST: &[u8] = b"request";
+
+/// Yields the request bytes split into chunks, optionally followed by an
+/// error.
+struct ChunkingHandler {
+    chunk_size: usize,
+    fail_at_end: bool,
+}
+
+impl StreamingApplicationHandler for ChunkingHandler {
+    fn handle_streaming<'a>(&'a self, request_bytes: &'a [u8]) -> ResponseChunkStream<'a> {
+        Box::pin(async_stream::stream! {
+            for chunk in request_bytes.chunks(self.chunk_size) {
+                yield Ok(chunk.to_vec());
+            }
+            if self.fail_at_end {
+                yield Err(anyhow::anyhow!("handler failed"));
+            }
+        })
+    }
+}
+
+/// Sends the request through a streaming session context and returns the
+/// client encryptor together with the encrypted responses, and the error
+/// that ended the response stream, if any.
+async fn invoke_streaming(
+    handler: ChunkingHandler,
+) -> (ClientEncryptor, Vec<EncryptedResponse>, Option<anyhow::Error>) {
+    let (encryption_key, public_key) = generate_encryption_key_pair();
+    let context = OakSessionContext::new_streaming(
+        Box::new(encryption_key),
+        EndorsedEvidence::default(),
+        Box::new(handler),
+    );
+    let mut client_encryptor =
+        ClientEncryptor::create(&public_key).expect("couldn't create client encryptor");
+    let encrypted_request =
+        client_encryptor.encrypt(REQUEST, b"").expect("couldn't encrypt request");
+    let request_wrapper = RequestWrapper {
+        request: Some(request_wrapper::Request::InvokeRequest(InvokeRequest {
+            encrypted_request: Some(encrypted_request),
+        })),
+    };
+
+    let mut encrypted_responses = Vec::new();
+    let mut error = None;
+    let responses = context.handle_request(&request_wrapper);
+    tokio::pin!(responses);
+    while let Some(response) = responses.next().await {
+        match response {
+            Ok(ResponseWrapper {
+                response:
+                    Some(response_wrapper::Response::InvokeResponse(InvokeResponse {
+                        encrypted_response: Some(encrypted_response),
+                    })),
+            }) => encrypted_responses.push(encrypted_response),
+            Ok(response) => panic!("unexpected response: {response:?}"),
+            Err(err) => error = Some(err),
+        }
+    }
+    (client_encryptor, encrypted_responses, error)
+}
+
+#[tokio::test]
+async fn test_streaming_response_chunks_are_decrypted_in_order() {
+    let (client_encryptor, encrypted_responses, error) =
+        invoke_streaming(ChunkingHandler { chunk_size: 3, fail_at_end: false }).await;
+    assert!(error.is_none(), "{error:?}");
+    // "req", "ues", "t" and the final chunk.
+    assert_eq!(encrypted_responses.len(), 4);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    let mut response = Vec::new();
+    for encrypted_response in &encrypted_responses {
+        if let Some(chunk) = decryptor.decrypt(encrypted_response).expect("couldn't decrypt") {
+            response.extend_from_slice(&chunk);
+        }
+    }
+    assert!(decryptor.finish().is_ok());
+    assert_eq!(response, REQUEST);
+}
+
+#[tokio::test]
+async fn test_reordered_streaming_response_chunks_are_rejected() {
+    let (client_encryptor, mut encrypted_responses, _) =
+        invoke_streaming(ChunkingHandler { chunk_size: 3, fail_at_end: false }).await;
+    encrypted_responses.swap(0, 1);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    assert!(decryptor.decrypt(&encrypted_responses[0]).is_err());
+}
+
+#[tokio::test]
+async fn test_truncated_streaming_response_is_detected() {
+    let (client_encryptor, mut encrypted_responses, _) =
+        invoke_streaming(ChunkingHandler { chunk_size: 3, fail_at_end: false }).await;
+    // Drop the final chunk.
+    encrypted_responses.pop();
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    for encrypted_response in &encrypted_responses {
+        assert!(decryptor.decrypt(encrypted_response).is_ok());
+    }
+    assert!(decryptor.finish().is_err());
+}
+
+#[tokio::test]
+async fn test_failed_streaming_response_has_no_final_chunk() {
+    let (client_encryptor, encrypted_responses, error) =
+        invoke_streaming(ChunkingHandler { chunk_size: 3, fail_at_end: true }).await;
+    assert!(error.is_some());
+    assert_eq!(encrypted_responses.len(), 3);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    for encrypted_response in &encrypted_responses {
+        assert!(decryptor.decrypt(encrypted_response).is_ok());
+    }
+    assert!(decryptor.finish().is_err());
+}
+
+#[tokio::test]
+async fn test_unary_handler_sends_single_response() {
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl ApplicationHandler for EchoHandler {
+        async fn handle(&self, request_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+            Ok(request_bytes.to_vec())
+        }
+    }
+
+    let (encryption_key, public_key) = generate_encryption_key_pair();
+    let context = OakSessionContext::new(
+        Box::new(encryption_key),
+        EndorsedEvidence::default(),
+        Box::new(EchoHandler),
+    );
+    let mut client_encryptor =
+        ClientEncryptor::create(&public_key).expect("couldn't create client encryptor");
+    let request_wrapper = RequestWrapper {
+        request: Some(request_wrapper::Request::InvokeRequest(InvokeRequest {
+            encrypted_request: Some(
+                client_encryptor.encrypt(REQUEST, b"").expect("couldn't encrypt request"),
+            ),
+        })),
+    };
+
+    let responses: Vec<ResponseWrapper> = context
+        .handle_request(&request_wrapper)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .await
+        .expect("request failed");
+    // Existing clients read exactly one response per request, encrypted with
+    // empty associated data.
+    let [ResponseWrapper {
+        response:
+            Some(response_wrapper::Response::InvokeResponse(InvokeResponse {
+                encrypted_response: Some(encrypted_response),
+            })),
+    }] = responses.as_slice()
+    else {
+        panic!("unexpected responses: {responses:?}");
+    };
+    let (response, associated_data) =
+        client_encryptor.decrypt(encrypted_response).expect("couldn't decrypt");
+    assert_eq!(response, REQUEST);
+    assert!(associated_data.is_empty());
+}
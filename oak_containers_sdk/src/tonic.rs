@@ -15,10 +15,13 @@
 
 use std::{pin::Pin, sync::Arc};
 
-use oak_proto_rust::oak::session::v1::{RequestWrapper, ResponseWrapper};
+use oak_proto_rust::oak::session::v1::{
+    PlaintextMessage, RequestWrapper, ResponseWrapper, SessionRequest, SessionResponse,
+};
+use oak_session::{config::SessionConfig, ProtocolEngine, ServerSession, Session};
 use tokio_stream::{Stream, StreamExt};
 
-use crate::oak_session_context::OakSessionContext;
+use crate::oak_session_context::{ApplicationHandler, OakSessionContext};
 
 /// Helper for handling streaming requests presented by a tonic server
 /// implementation.
@@ -37,12 +40,114 @@ pub async fn oak_session(
             let request_wrapper = request
                 .map_err(|e| tonic::Status::internal(format!("failed to read request from stream: {e:?}")))?;
 
-            yield session_context
-                .handle_request(&request_wrapper)
-                .await
-                .map_err(|e| tonic::Status::internal(format!("failed to handle request: {e:?}")))?;
+            // Dropping the response stream when the client disconnects also drops
+            // the application's response chunks, which cancels the request.
+            for await response in session_context.handle_request(&request_wrapper) {
+                yield response
+                    .map_err(|e| tonic::Status::internal(format!("failed to handle request: {e:?}")))?;
+            }
         }
     };
 
     Ok(tonic::Response::new(Box::pin(response_stream) as OakSessionStream))
 }
+
+pub type SessionResponseStream =
+    Pin<Box<dyn Stream<Item = Result<SessionResponse, tonic::Status>> + Send + 'static>>;
+
+/// Serves the Oak session protocol over tonic bidirectional streams.
+///
+/// A new [`ServerSession`] is created for every stream with the configuration
+/// returned by the session config factory. Attestation and handshake messages
+/// are handled by the session, and once it is open, every decrypted request is
+/// passed to the [`ApplicationHandler`] and its response is encrypted and sent
+/// back on the same stream.
+pub struct OakSessionStreamHandler {
+    session_config_factory: Arc<dyn Fn() -> SessionConfig + Send + Sync>,
+    application_handler: Arc<dyn ApplicationHandler>,
+}
+
+impl OakSessionStreamHandler {
+    pub fn new(
+        session_config_factory: impl Fn() -> SessionConfig + Send + Sync + 'static,
+        application_handler: Arc<dyn ApplicationHandler>,
+    ) -> Self {
+        Self { session_config_factory: Arc::new(session_config_factory), application_handler }
+    }
+
+    /// Handles a single stream of session messages until the client closes it.
+    ///
+    /// Errors are reported to the client as tonic statuses:
+    /// - `UNAUTHENTICATED` if attestation or the handshake fail
+    /// - `FAILED_PRECONDITION` if a message is not expected in the current
+    ///   state of the session
+    /// - `INVALID_ARGUMENT` if an encrypted message can't be decrypted
+    /// - `INTERNAL` if the application handler or response encryption fail
+    pub async fn handle(
+        &self,
+        request: tonic::Request<tonic::Streaming<SessionRequest>>,
+    ) -> Result<tonic::Response<SessionResponseStream>, tonic::Status> {
+        let mut server_session =
+            ServerSession::create((self.session_config_factory)()).map_err(|e| {
+                tonic::Status::internal(format!("couldn't create server session: {e:?}"))
+            })?;
+        let application_handler = self.application_handler.clone();
+        let mut request_stream = request.into_inner();
+
+        let response_stream = async_stream::try_stream! {
+            while let Some(request) = request_stream.next().await {
+                let session_request = request?;
+
+                server_session
+                    .put_incoming_message(&session_request)
+                    .map_err(|e| {
+                        session_error(&server_session, format!("couldn't process session message: {e:?}"))
+                    })?
+                    .ok_or_else(|| {
+                        tonic::Status::failed_precondition("session message wasn't expected")
+                    })?;
+
+                if server_session.is_open() {
+                    while let Some(plaintext_request) = server_session
+                        .read()
+                        .map_err(|e| {
+                            session_error(&server_session, format!("couldn't decrypt request: {e:?}"))
+                        })?
+                    {
+                        let plaintext_response = application_handler
+                            .handle(&plaintext_request.plaintext)
+                            .await
+                            .map_err(|e| tonic::Status::internal(format!("application failed: {e:?}")))?;
+                        server_session
+                            .write(&PlaintextMessage { plaintext: plaintext_response })
+                            .map_err(|e| {
+                                tonic::Status::internal(format!("couldn't encrypt response: {e:?}"))
+                            })?;
+                    }
+                }
+
+                while let Some(session_response) = server_session
+                    .get_outgoing_message()
+                    .map_err(|e| {
+                        session_error(&server_session, format!("couldn't get session message: {e:?}"))
+                    })?
+                {
+                    yield session_response;
+                }
+            }
+        };
+
+        Ok(tonic::Response::new(Box::pin(response_stream) as SessionResponseStream))
+    }
+}
+
+/// Maps a session error to a status based on the state of the session after
+/// the failed operation: errors before the session is open are attestation or
+/// handshake failures, and errors after it are invalid encrypted messages.
+fn session_error(server_session: &ServerSession, message: String) -> tonic::Status {
+    if server_session.is_open() {
+        tonic::Status::invalid_argument(message)
+    } else {
+        tonic::Status::unauthenticated(message)
+    }
+}
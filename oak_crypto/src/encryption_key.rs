@@ -27,7 +27,6 @@ use crate::{
         generate_kem_key_pair, setup_base_recipient, Deserializable, PrivateKey, RecipientContext,
         Serializable, OAK_HPKE_INFO,
     },
-    EMPTY_ASSOCIATED_DATA,
 };
 
 /// Generates a random encryption key pair and returns an instance of the
@@ -59,15 +58,16 @@ impl EncryptionKey {
         Ok(Self { private_key })
     }
 
-    /// Returns the private key encrypted with the `peer_public_key`.
+    /// Returns the private key encrypted with the `peer_public_key`, with
+    /// `associated_data` bound to the ciphertext.
     pub fn encrypted_private_key(
         &self,
         peer_public_key: &[u8],
+        associated_data: &[u8],
     ) -> anyhow::Result<EncryptedRequest> {
         let mut client_encryptor =
             ClientEncryptor::create(peer_public_key).context("couldn't create client encryptor")?;
-        client_encryptor
-            .encrypt(&Zeroizing::new(self.private_key.to_bytes()), EMPTY_ASSOCIATED_DATA)
+        client_encryptor.encrypt(&Zeroizing::new(self.private_key.to_bytes()), associated_data)
     }
 }
 
@@ -84,6 +84,18 @@ impl From<Payload> for EncryptedMessage {
 pub trait Encryptor: Send {
     fn encrypt(&mut self, plaintext: &Payload) -> anyhow::Result<Payload>;
     fn decrypt(&mut self, ciphertext: &Payload) -> anyhow::Result<Payload>;
+
+    /// Replaces the key used by [`Encryptor::encrypt`]. Must be mirrored by a
+    /// call to [`Encryptor::rekey_decryption`] on the peer at the same position
+    /// in the message stream.
+    fn rekey_encryption(&mut self) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("rekeying is not supported by this encryptor"))
+    }
+
+    /// Replaces the key used by [`Encryptor::decrypt`].
+    fn rekey_decryption(&mut self) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("rekeying is not supported by this encryptor"))
+    }
 }
 
 /// Encryptor object for encrypting requests/decrypting responses.
@@ -164,6 +176,80 @@ impl ClientEncryptor {
     }
 }
 
+/// Returns the associated data that binds a response chunk to its position in
+/// the stream of responses to a single request: the big-endian sequence number
+/// of the chunk followed by 1 for the final chunk and 0 otherwise.
+///
+/// A streaming server encrypts its chunks with consecutive sequence numbers
+/// starting at 0 and ends the stream with an empty final chunk, so that a
+/// client using [`ResponseChunkDecryptor`] detects reordered, replayed and
+/// truncated responses.
+pub fn response_chunk_associated_data(sequence_number: u64, is_final: bool) -> [u8; 9] {
+    let mut associated_data = [0u8; 9];
+    associated_data[..8].copy_from_slice(&sequence_number.to_be_bytes());
+    associated_data[8] = is_final.into();
+    associated_data
+}
+
+/// Decrypts the stream of response chunks sent for a single request.
+///
+/// Each chunk must carry the associated data returned by
+/// [`response_chunk_associated_data`] for the next expected sequence number,
+/// and no chunk may follow the final one. Once the stream ends,
+/// [`ResponseChunkDecryptor::finish`] checks that the final chunk was received.
+pub struct ResponseChunkDecryptor<'a> {
+    client_encryptor: &'a ClientEncryptor,
+    next_sequence_number: u64,
+    finished: bool,
+}
+
+impl<'a> ResponseChunkDecryptor<'a> {
+    pub fn new(client_encryptor: &'a ClientEncryptor) -> Self {
+        Self { client_encryptor, next_sequence_number: 0, finished: false }
+    }
+
+    /// Decrypts the next response chunk. Returns `None` for the final chunk,
+    /// which carries no data.
+    pub fn decrypt(
+        &mut self,
+        encrypted_response: &EncryptedResponse,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        anyhow::ensure!(!self.finished, "response chunk received after the final chunk");
+        let (plaintext, associated_data) = self.client_encryptor.decrypt(encrypted_response)?;
+        let is_final = if associated_data
+            == response_chunk_associated_data(self.next_sequence_number, false)
+        {
+            false
+        } else if associated_data == response_chunk_associated_data(self.next_sequence_number, true)
+        {
+            true
+        } else {
+            anyhow::bail!(
+                "response chunk is out of order, expected chunk {}",
+                self.next_sequence_number
+            );
+        };
+        self.next_sequence_number += 1;
+        if is_final {
+            anyhow::ensure!(plaintext.is_empty(), "final response chunk isn't empty");
+            self.finished = true;
+            return Ok(None);
+        }
+        Ok(Some(plaintext))
+    }
+
+    /// Checks that the final chunk has been received, i.e. that the response
+    /// stream wasn't truncated.
+    pub fn finish(self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.finished,
+            "response stream was truncated after {} chunks",
+            self.next_sequence_number
+        );
+        Ok(())
+    }
+}
+
 /// Encryptor object for encrypting responses/decrypting requests.
 ///
 /// Encryptor object for decrypting client requests that are received by the
@@ -244,9 +330,12 @@ impl ServerEncryptor {
 
     /// Encrypts `plaintext` and authenticates `associated_data` using AEAD.
     /// Returns a [`EncryptedResponse`] proto message.
+    ///
+    /// May be called multiple times in order to send several responses to a
+    /// single request under the same session keys.
     /// <https://datatracker.ietf.org/doc/html/rfc5116>
     pub fn encrypt(
-        self,
+        &self,
         plaintext: &[u8],
         associated_data: &[u8],
     ) -> anyhow::Result<EncryptedResponse> {
@@ -19,76 +19,118 @@ pub use crate::noise_handshake::crypto_wrapper::{
     p256_scalar_mult, rand_bytes, sha256, sha256_two_part, EcdsaKeyPair, P256Scalar, NONCE_LEN,
     P256_SCALAR_LEN, P256_X962_LEN, SHA256_OUTPUT_LEN, SYMMETRIC_KEY_LEN,
 };
-use crate::noise_handshake::{
-    error::Error,
-    noise::{HandshakeType, Noise},
-    IdentityKeyHandle, NoiseMessage, OrderedCrypter,
+use crate::{
+    identity_key::IdentityKey,
+    noise_handshake::{
+        error::Error,
+        noise::{HandshakeType, Noise},
+        HandshakeParams, IdentityKeyHandle, NoiseMessage, OrderedCrypter, P256_PARAMS,
+    },
 };
 
 pub struct HandshakeInitiator {
+    handshake_type: HandshakeType,
+    params: HandshakeParams,
     noise: Noise,
-    peer_identity_pub_key: Option<[u8; P256_X962_LEN]>,
+    peer_identity_pub_key: Option<Vec<u8>>,
     self_identity_priv_key: Option<Box<dyn IdentityKeyHandle>>,
-    ephemeral_priv_key: P256Scalar,
+    ephemeral_key: Box<dyn IdentityKeyHandle>,
 }
 
 impl HandshakeInitiator {
-    pub fn new_nk(peer_public_key: &[u8; P256_X962_LEN]) -> Self {
+    pub(crate) fn new(
+        handshake_type: HandshakeType,
+        peer_identity_pub_key: Option<Vec<u8>>,
+        self_identity_priv_key: Option<Box<dyn IdentityKeyHandle>>,
+    ) -> Self {
         Self {
-            noise: Noise::new(HandshakeType::Nk),
-            peer_identity_pub_key: Some(*peer_public_key),
-            self_identity_priv_key: None,
-            ephemeral_priv_key: P256Scalar::generate(),
+            handshake_type,
+            params: P256_PARAMS,
+            noise: P256_PARAMS.noise(handshake_type),
+            peer_identity_pub_key,
+            self_identity_priv_key,
+            ephemeral_key: Box::new(IdentityKey::generate()),
         }
     }
 
+    pub fn new_nk(peer_public_key: &[u8; P256_X962_LEN]) -> Self {
+        Self::new(HandshakeType::Nk, Some(peer_public_key.to_vec()), None)
+    }
+
     pub fn new_nn() -> Self {
-        Self {
-            noise: Noise::new(HandshakeType::Nn),
-            peer_identity_pub_key: None,
-            self_identity_priv_key: None,
-            ephemeral_priv_key: P256Scalar::generate(),
-        }
+        Self::new(HandshakeType::Nn, None, None)
     }
+
     pub fn new_kk(
         peer_public_key: [u8; P256_X962_LEN],
         self_priv_key: Box<dyn IdentityKeyHandle>,
     ) -> Self {
-        Self {
-            noise: Noise::new(HandshakeType::Kk),
-            peer_identity_pub_key: Some(peer_public_key),
-            self_identity_priv_key: Some(self_priv_key),
-            ephemeral_priv_key: P256Scalar::generate(),
-        }
+        Self::new(HandshakeType::Kk, Some(peer_public_key.to_vec()), Some(self_priv_key))
+    }
+
+    pub fn new_ik(
+        peer_public_key: [u8; P256_X962_LEN],
+        self_priv_key: Box<dyn IdentityKeyHandle>,
+    ) -> Self {
+        Self::new(HandshakeType::Ik, Some(peer_public_key.to_vec()), Some(self_priv_key))
+    }
+
+    pub fn new_xx(self_priv_key: Box<dyn IdentityKeyHandle>) -> Self {
+        Self::new(HandshakeType::Xx, None, Some(self_priv_key))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_params(mut self, params: HandshakeParams) -> Self {
+        self.noise = params.noise(self.handshake_type);
+        self.params = params;
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_ephemeral_key(mut self, ephemeral_key: Box<dyn IdentityKeyHandle>) -> Self {
+        self.ephemeral_key = ephemeral_key;
+        self
+    }
+
+    /// Returns the static public key of the responder, if it was pre-shared
+    /// or has been learned during the handshake.
+    pub fn peer_static_public_key(&self) -> Option<Vec<u8>> {
+        self.peer_identity_pub_key.clone()
     }
 
     pub fn build_initial_message(&mut self) -> Result<NoiseMessage, Error> {
-        self.noise.mix_hash(&[0; 1]);
-        if let Some(peer_identity_pub_key) = self.peer_identity_pub_key {
+        match self.handshake_type {
+            HandshakeType::Ik => return self.build_ik_initial_message(&[]),
+            HandshakeType::Xx => return self.build_xx_initial_message(&[]),
+            HandshakeType::Nk | HandshakeType::Nn | HandshakeType::Kk => {}
+        }
+        if let Some(peer_identity_pub_key) = self.peer_identity_pub_key.as_ref() {
             self.noise.mix_hash_point(peer_identity_pub_key.as_slice());
         }
-        let ephemeral_pub_key = self.ephemeral_priv_key.compute_public_key();
-        let ephemeral_pub_key_bytes = ephemeral_pub_key.as_ref();
+        let ephemeral_pub_key =
+            self.ephemeral_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?;
 
-        self.noise.mix_hash(ephemeral_pub_key_bytes);
-        self.noise.mix_key(ephemeral_pub_key_bytes);
-        if let Some(peer_identity_pub_key) = self.peer_identity_pub_key {
-            let es_ecdh_bytes = p256_scalar_mult(&self.ephemeral_priv_key, &peer_identity_pub_key)
+        self.noise.mix_hash(&ephemeral_pub_key);
+        self.noise.mix_key(&ephemeral_pub_key);
+        if let Some(peer_identity_pub_key) = self.peer_identity_pub_key.as_ref() {
+            let es_ecdh_bytes = self
+                .ephemeral_key
+                .derive_dh_secret(peer_identity_pub_key)
                 .map_err(|_| Error::InvalidHandshake)?;
             self.noise.mix_key(&es_ecdh_bytes);
         }
         if let Some(self_priv_key) = self.self_identity_priv_key.as_ref() {
             let self_static_pub_key =
                 self_priv_key.get_public_key().map_err(|_| Error::InvalidPublicKey)?;
-            if let Some(peer_identity_pub_key) = self.peer_identity_pub_key {
-                let ss_ecdh_bytes = sha256_two_part(&self_static_pub_key, &peer_identity_pub_key);
+            if let Some(peer_identity_pub_key) = self.peer_identity_pub_key.as_ref() {
+                let ss_ecdh_bytes = sha256_two_part(&self_static_pub_key, peer_identity_pub_key);
                 self.noise.mix_key(&ss_ecdh_bytes);
             } else {
                 return Err(Error::MissingPeerPublicKey);
             }
         }
         if let Some(self_priv_key) = self.self_identity_priv_key.as_ref() {
-            if let Some(peer_identity_pub_key) = self.peer_identity_pub_key {
+            if let Some(peer_identity_pub_key) = self.peer_identity_pub_key.as_ref() {
                 let se_ecdh_bytes = self_priv_key
                     .derive_dh_secret(peer_identity_pub_key.as_slice())
                     .map_err(|_| Error::InvalidHandshake)?;
@@ -98,22 +140,189 @@ impl HandshakeInitiator {
             }
         }
         let ciphertext = self.noise.encrypt_and_hash(&[]);
-        Ok(NoiseMessage { ciphertext, ephemeral_public_key: ephemeral_pub_key.to_vec() })
+        Ok(NoiseMessage {
+            ciphertext,
+            static_public_key: Vec::new(),
+            ephemeral_public_key: ephemeral_pub_key,
+        })
+    }
+
+    /// Builds the `-> e, es, s, ss` message of an IK handshake with the given
+    /// payload.
+    pub(crate) fn build_ik_initial_message(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<NoiseMessage, Error> {
+        let peer_identity_pub_key =
+            self.peer_identity_pub_key.clone().ok_or(Error::MissingPeerPublicKey)?;
+        let self_priv_key = self.self_identity_priv_key.as_ref().ok_or(Error::InvalidPrivateKey)?;
+        self.noise.mix_hash_point(peer_identity_pub_key.as_slice());
+
+        // -> e, es, s, ss
+        let ephemeral_pub_key =
+            self.ephemeral_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?;
+        self.noise.mix_hash(&ephemeral_pub_key);
+        let es_ecdh_bytes = self
+            .ephemeral_key
+            .derive_dh_secret(&peer_identity_pub_key)
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&es_ecdh_bytes);
+        let static_public_key = self.noise.encrypt_and_hash(
+            self_priv_key.get_public_key().map_err(|_| Error::InvalidPublicKey)?.as_slice(),
+        );
+        let ss_ecdh_bytes = self_priv_key
+            .derive_dh_secret(peer_identity_pub_key.as_slice())
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&ss_ecdh_bytes);
+        let ciphertext = self.noise.encrypt_and_hash(payload);
+        Ok(NoiseMessage { ephemeral_public_key: ephemeral_pub_key, static_public_key, ciphertext })
+    }
+
+    /// Builds the `-> e` message of an XX handshake with the given payload.
+    pub(crate) fn build_xx_initial_message(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<NoiseMessage, Error> {
+        // -> e
+        let ephemeral_pub_key =
+            self.ephemeral_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?;
+        self.noise.mix_hash(&ephemeral_pub_key);
+        let ciphertext = self.noise.encrypt_and_hash(payload);
+        Ok(NoiseMessage {
+            ephemeral_public_key: ephemeral_pub_key,
+            static_public_key: Vec::new(),
+            ciphertext,
+        })
+    }
+
+    /// Processes the responder's `<- e, ee, s, es` message of an XX handshake
+    /// and builds the final `-> s, se` message that has to be sent back to the
+    /// responder.
+    ///
+    /// The returned handshake hash is the one taken after the responder's
+    /// message, see [`crate::noise_handshake::XxResponder`].
+    pub fn process_xx_response(
+        &mut self,
+        handshake_response: &NoiseMessage,
+    ) -> Result<(NoiseMessage, [u8; SHA256_OUTPUT_LEN], OrderedCrypter), Error> {
+        let (final_message, handshake_hash, crypter, payload) =
+            self.process_xx_response_with_payload(handshake_response, &[])?;
+        if !payload.is_empty() {
+            return Err(Error::InvalidHandshake);
+        }
+        Ok((final_message, handshake_hash, crypter))
+    }
+
+    /// Same as [`HandshakeInitiator::process_xx_response`], but sends the given
+    /// payload in the final message and also returns the payload of the
+    /// responder.
+    pub(crate) fn process_xx_response_with_payload(
+        &mut self,
+        handshake_response: &NoiseMessage,
+        payload: &[u8],
+    ) -> Result<(NoiseMessage, [u8; SHA256_OUTPUT_LEN], OrderedCrypter, Vec<u8>), Error> {
+        if self.handshake_type != HandshakeType::Xx {
+            return Err(Error::InvalidHandshake);
+        }
+        let responder_ephemeral_pub =
+            self.params.public_key(&handshake_response.ephemeral_public_key)?;
+
+        // <- e, ee, s, es
+        self.noise.mix_hash(&responder_ephemeral_pub);
+        let ee_ecdh_bytes = self
+            .ephemeral_key
+            .derive_dh_secret(&responder_ephemeral_pub)
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&ee_ecdh_bytes);
+        let responder_static_pub = self
+            .params
+            .public_key(&self.noise.decrypt_and_hash(&handshake_response.static_public_key)?)?;
+        let es_ecdh_bytes = self
+            .ephemeral_key
+            .derive_dh_secret(&responder_static_pub)
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&es_ecdh_bytes);
+        let responder_payload = self.noise.decrypt_and_hash(&handshake_response.ciphertext)?;
+        self.peer_identity_pub_key = Some(responder_static_pub);
+        let handshake_hash = self.noise.handshake_hash();
+
+        // -> s, se
+        let self_priv_key = self.self_identity_priv_key.as_ref().ok_or(Error::InvalidPrivateKey)?;
+        let static_public_key = self.noise.encrypt_and_hash(
+            self_priv_key.get_public_key().map_err(|_| Error::InvalidPublicKey)?.as_slice(),
+        );
+        let se_ecdh_bytes = self_priv_key
+            .derive_dh_secret(responder_ephemeral_pub.as_slice())
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&se_ecdh_bytes);
+        let ciphertext = self.noise.encrypt_and_hash(payload);
+
+        let (write_key, read_key) = self.noise.traffic_keys();
+        Ok((
+            NoiseMessage { ephemeral_public_key: Vec::new(), static_public_key, ciphertext },
+            handshake_hash,
+            OrderedCrypter::new(&read_key, &write_key),
+            responder_payload,
+        ))
+    }
+
+    /// Processes the responder's `<- e, ee, se` message of an IK handshake
+    /// and returns the payload of the responder.
+    pub(crate) fn process_ik_response(
+        &mut self,
+        handshake_response: &NoiseMessage,
+    ) -> Result<([u8; SHA256_OUTPUT_LEN], OrderedCrypter, Vec<u8>), Error> {
+        let responder_ephemeral_pub =
+            self.params.public_key(&handshake_response.ephemeral_public_key)?;
+
+        // <- e, ee, se
+        self.noise.mix_hash(&responder_ephemeral_pub);
+        let ee_ecdh_bytes = self
+            .ephemeral_key
+            .derive_dh_secret(&responder_ephemeral_pub)
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&ee_ecdh_bytes);
+        let se_ecdh_bytes = self
+            .self_identity_priv_key
+            .as_ref()
+            .ok_or(Error::InvalidPrivateKey)?
+            .derive_dh_secret(&responder_ephemeral_pub)
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&se_ecdh_bytes);
+        let payload = self
+            .noise
+            .decrypt_and_hash(&handshake_response.ciphertext)
+            .map_err(|_| Error::DecryptFailed)?;
+        let (write_key, read_key) = self.noise.traffic_keys();
+        Ok((self.noise.handshake_hash(), OrderedCrypter::new(&read_key, &write_key), payload))
     }
 
     pub fn process_response(
         &mut self,
         handshake_response: &NoiseMessage,
     ) -> Result<([u8; SHA256_OUTPUT_LEN], OrderedCrypter), Error> {
-        let ee_ecdh_bytes = p256_scalar_mult(
-            &self.ephemeral_priv_key,
-            handshake_response
-                .ephemeral_public_key
-                .as_slice()
-                .try_into()
-                .map_err(|_| Error::DecryptFailed)?,
-        )
-        .map_err(|_| Error::InvalidHandshake)?;
+        match self.handshake_type {
+            // XX requires a third message, see `process_xx_response`.
+            HandshakeType::Xx => return Err(Error::InvalidHandshake),
+            HandshakeType::Ik => {
+                let (handshake_hash, crypter, payload) =
+                    self.process_ik_response(handshake_response)?;
+                if !payload.is_empty() {
+                    return Err(Error::InvalidHandshake);
+                }
+                return Ok((handshake_hash, crypter));
+            }
+            HandshakeType::Nk | HandshakeType::Nn | HandshakeType::Kk => {}
+        }
+        let responder_ephemeral_pub: [u8; P256_X962_LEN] = handshake_response
+            .ephemeral_public_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::DecryptFailed)?;
+        let ee_ecdh_bytes = self
+            .ephemeral_key
+            .derive_dh_secret(&responder_ephemeral_pub)
+            .map_err(|_| Error::InvalidHandshake)?;
         self.noise.mix_hash(&handshake_response.ephemeral_public_key);
         self.noise.mix_key(&handshake_response.ephemeral_public_key);
         self.noise.mix_key(&ee_ecdh_bytes);
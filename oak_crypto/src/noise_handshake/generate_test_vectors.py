@@ -0,0 +1,224 @@
+#!/usr/bin/env python3
+#
+# Copyright 2024 The Project Oak Authors
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+#
+"""Generates the XX and IK conformance vectors used in `tests.rs`.
+
+This is an independent implementation of the Noise Protocol Framework
+(revision 34), written from the specification at
+<https://www.noiseprotocol.org/noise.html> on top of pyca/cryptography, and
+instantiated with P-256, AES-GCM and SHA-256 as in `noise.rs`. It follows the
+generic HandshakeState token processing of section 5.3 rather than the
+per-pattern code in this crate.
+
+Usage (tested with Python 3.11 and cryptography 48.0.0):
+
+    pip install cryptography==48.0.0
+    python3 oak_crypto/src/noise_handshake/generate_test_vectors.py
+"""
+
+import hashlib
+import hmac
+
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.ciphers.aead import AESGCM
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+HASHLEN = 32
+PROLOGUE = b"\x00"
+
+INITIATOR_STATIC_PRIV = "1c1e6fc4f3f5b7c5d1a0e8b2a6c4e2f0d8b6a4c2e0f8d6b4a2c0e8f6d4b2a0c1"
+INITIATOR_EPHEMERAL_PRIV = "2a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f70819"
+RESPONDER_STATIC_PRIV = "3f2e1d0c0b0a09080706050403020100f0e0d0c0b0a090807060504030201001"
+RESPONDER_EPHEMERAL_PRIV = "4d5c6b7a69584736251403f2e1d0c0b0a09f8e7d6c5b4a39281706f5e4d3c2b1"
+
+
+class KeyPair:
+    def __init__(self, private_hex):
+        self.private = ec.derive_private_key(int(private_hex, 16), ec.SECP256R1())
+        self.public = self.private.public_key().public_bytes(
+            Encoding.X962, PublicFormat.UncompressedPoint)
+
+
+def dh(key_pair, public_key):
+    peer = ec.EllipticCurvePublicKey.from_encoded_point(ec.SECP256R1(), public_key)
+    return key_pair.private.exchange(ec.ECDH(), peer)
+
+
+def hkdf(chaining_key, input_key_material, num_outputs):
+    temp_key = hmac.new(chaining_key, input_key_material, hashlib.sha256).digest()
+    outputs = []
+    previous = b""
+    for i in range(1, num_outputs + 1):
+        previous = hmac.new(temp_key, previous + bytes([i]), hashlib.sha256).digest()
+        outputs.append(previous)
+    return outputs
+
+
+class CipherState:
+    def __init__(self, key=None):
+        self.k = key
+        self.n = 0
+
+    def encrypt_with_ad(self, ad, plaintext):
+        if self.k is None:
+            return plaintext
+        ciphertext = AESGCM(self.k).encrypt(self.nonce(), plaintext, ad)
+        self.n += 1
+        return ciphertext
+
+    def decrypt_with_ad(self, ad, ciphertext):
+        if self.k is None:
+            return ciphertext
+        plaintext = AESGCM(self.k).decrypt(self.nonce(), ciphertext, ad)
+        self.n += 1
+        return plaintext
+
+    def nonce(self):
+        return b"\x00" * 4 + self.n.to_bytes(8, "big")
+
+
+class SymmetricState:
+    def __init__(self, protocol_name):
+        if len(protocol_name) <= HASHLEN:
+            self.h = protocol_name.ljust(HASHLEN, b"\x00")
+        else:
+            self.h = hashlib.sha256(protocol_name).digest()
+        self.ck = self.h
+        self.cipher = CipherState()
+
+    def mix_key(self, input_key_material):
+        self.ck, temp_k = hkdf(self.ck, input_key_material, 2)
+        self.cipher = CipherState(temp_k)
+
+    def mix_hash(self, data):
+        self.h = hashlib.sha256(self.h + data).digest()
+
+    def encrypt_and_hash(self, plaintext):
+        ciphertext = self.cipher.encrypt_with_ad(self.h, plaintext)
+        self.mix_hash(ciphertext)
+        return ciphertext
+
+    def decrypt_and_hash(self, ciphertext):
+        plaintext = self.cipher.decrypt_with_ad(self.h, ciphertext)
+        self.mix_hash(ciphertext)
+        return plaintext
+
+    def split(self):
+        return hkdf(self.ck, b"", 2)
+
+
+class HandshakeState:
+    def __init__(self, pattern, initiator, s, e, rs=None):
+        self.symmetric_state = SymmetricState(
+            f"Noise_{pattern.name}_P256_AESGCM_SHA256".encode())
+        self.symmetric_state.mix_hash(PROLOGUE)
+        self.initiator = initiator
+        self.s = s
+        self.e = e
+        self.rs = rs
+        self.re = None
+        for token in pattern.responder_pre_message:
+            assert token == "s"
+            self.symmetric_state.mix_hash(s.public if not initiator else rs)
+        self.message_patterns = list(pattern.message_patterns)
+
+    def dh_token(self, token):
+        # The first letter refers to the initiator's key, the second to the
+        # responder's key.
+        local, remote = (token[0], token[1]) if self.initiator else (token[1], token[0])
+        key_pair = self.e if local == "e" else self.s
+        public_key = self.re if remote == "e" else self.rs
+        self.symmetric_state.mix_key(dh(key_pair, public_key))
+
+    def write_message(self, payload=b""):
+        fields = {}
+        for token in self.message_patterns.pop(0):
+            if token == "e":
+                fields["e"] = self.e.public
+                self.symmetric_state.mix_hash(self.e.public)
+            elif token == "s":
+                fields["s"] = self.symmetric_state.encrypt_and_hash(self.s.public)
+            else:
+                self.dh_token(token)
+        fields["payload"] = self.symmetric_state.encrypt_and_hash(payload)
+        return fields
+
+    def read_message(self, fields):
+        for token in self.message_patterns.pop(0):
+            if token == "e":
+                self.re = fields["e"]
+                self.symmetric_state.mix_hash(self.re)
+            elif token == "s":
+                self.rs = self.symmetric_state.decrypt_and_hash(fields["s"])
+            else:
+                self.dh_token(token)
+        return self.symmetric_state.decrypt_and_hash(fields["payload"])
+
+
+class Pattern:
+    def __init__(self, name, responder_pre_message, message_patterns):
+        self.name = name
+        self.responder_pre_message = responder_pre_message
+        self.message_patterns = message_patterns
+
+
+XX = Pattern("XX", [], [["e"], ["e", "ee", "s", "es"], ["s", "se"]])
+IK = Pattern("IK", ["s"], [["e", "es", "s", "ss"], ["e", "ee", "se"]])
+
+
+def print_message(direction, fields):
+    print(f"{direction}")
+    for name in ("e", "s", "payload"):
+        if name in fields:
+            print(f"  {name}: {fields[name].hex()}")
+
+
+def run(pattern, print_hash_after):
+    initiator_static = KeyPair(INITIATOR_STATIC_PRIV)
+    responder_static = KeyPair(RESPONDER_STATIC_PRIV)
+    initiator = HandshakeState(pattern, True, initiator_static,
+                               KeyPair(INITIATOR_EPHEMERAL_PRIV), rs=responder_static.public)
+    responder = HandshakeState(pattern, False, responder_static,
+                               KeyPair(RESPONDER_EPHEMERAL_PRIV))
+    print(f"Noise_{pattern.name}_P256_AESGCM_SHA256")
+    senders = [(initiator, responder, "->"), (responder, initiator, "<-")]
+    for i in range(len(pattern.message_patterns)):
+        sender, receiver, direction = senders[i % 2]
+        fields = sender.write_message()
+        assert receiver.read_message(fields) == b""
+        print_message(direction, fields)
+        if i + 1 == print_hash_after:
+            assert sender.symmetric_state.h == receiver.symmetric_state.h
+            print(f"  handshake hash: {sender.symmetric_state.h.hex()}")
+    initiator_to_responder, responder_to_initiator = initiator.symmetric_state.split()
+    assert [initiator_to_responder, responder_to_initiator] == responder.symmetric_state.split()
+    print(f"  initiator -> responder key: {initiator_to_responder.hex()}")
+    print(f"  responder -> initiator key: {responder_to_initiator.hex()}")
+    print()
+
+
+def main():
+    print(f"initiator ephemeral public key: {KeyPair(INITIATOR_EPHEMERAL_PRIV).public.hex()}")
+    print(f"responder ephemeral public key: {KeyPair(RESPONDER_EPHEMERAL_PRIV).public.hex()}")
+    print()
+    # Oak binds the XX handshake hash after the responder's message, see
+    # `XxResponder`.
+    run(XX, print_hash_after=2)
+    run(IK, print_hash_after=2)
+
+
+if __name__ == "__main__":
+    main()
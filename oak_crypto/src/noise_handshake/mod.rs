@@ -24,15 +24,18 @@ mod error;
 mod noise;
 #[cfg(test)]
 mod tests;
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 
 use anyhow::anyhow;
 use hashbrown::HashSet;
 use oak_proto_rust::oak::{crypto::v1::SessionKeys, session::v1::NoiseHandshakeMessage};
 
-use crate::noise_handshake::{
-    error::Error,
-    noise::{HandshakeType, Noise},
+use crate::{
+    identity_key::IdentityKey,
+    noise_handshake::{
+        error::Error,
+        noise::{HandshakeType, Noise},
+    },
 };
 pub use crate::{
     identity_key::IdentityKeyHandle,
@@ -46,6 +49,40 @@ pub use crate::{
 // This is assumed to be vastly larger than any connection will ever reach.
 const MAX_SEQUENCE: u32 = 1u32 << 24;
 
+/// Parameters of the XX and IK handshakes that aren't determined by the
+/// pattern.
+///
+/// Sessions always use [`P256_PARAMS`]. Tests use other parameters to replay
+/// published test vectors, which use X25519 and a different prologue, through
+/// the same pattern logic.
+#[derive(Clone, Copy)]
+pub(crate) struct HandshakeParams {
+    /// Name of the DH function in the Noise protocol name.
+    pub(crate) dh_name: &'static str,
+    /// Length of the DH public keys.
+    pub(crate) public_key_len: usize,
+    pub(crate) prologue: &'static [u8],
+}
+
+pub(crate) const P256_PARAMS: HandshakeParams =
+    HandshakeParams { dh_name: "P256", public_key_len: P256_X962_LEN, prologue: &[0] };
+
+impl HandshakeParams {
+    /// Initializes the symmetric state and mixes in the prologue.
+    fn noise(&self, handshake_type: HandshakeType) -> Noise {
+        let mut noise = Noise::with_dh_name(handshake_type, self.dh_name);
+        noise.mix_hash(self.prologue);
+        noise
+    }
+
+    fn public_key(&self, public_key: &[u8]) -> Result<Vec<u8>, Error> {
+        if public_key.len() != self.public_key_len {
+            return Err(Error::InvalidPublicKey);
+        }
+        Ok(public_key.to_vec())
+    }
+}
+
 pub struct Nonce {
     pub nonce: u32,
 }
@@ -75,6 +112,8 @@ impl Nonce {
 
 pub struct NoiseMessage {
     pub ephemeral_public_key: Vec<u8>,
+    // Encrypted static public key, for patterns that transmit it (XX and IK).
+    pub static_public_key: Vec<u8>,
     pub ciphertext: Vec<u8>,
 }
 
@@ -82,11 +121,22 @@ impl From<&NoiseHandshakeMessage> for NoiseMessage {
     fn from(value: &NoiseHandshakeMessage) -> Self {
         NoiseMessage {
             ephemeral_public_key: value.ephemeral_public_key.clone(),
+            static_public_key: value.static_public_key.clone(),
             ciphertext: value.ciphertext.clone(),
         }
     }
 }
 
+impl From<NoiseMessage> for NoiseHandshakeMessage {
+    fn from(value: NoiseMessage) -> Self {
+        NoiseHandshakeMessage {
+            ephemeral_public_key: value.ephemeral_public_key,
+            static_public_key: value.static_public_key,
+            ciphertext: value.ciphertext,
+        }
+    }
+}
+
 fn aes_gcm_256_encrypt(
     key: &[u8; SYMMETRIC_KEY_LEN],
     nonce: &[u8; NONCE_LEN],
@@ -170,6 +220,56 @@ impl OrderedCrypter {
     pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
         aes_gcm_256_decrypt(&self.read_key, &self.read_nonce.next_nonce()?, ciphertext)
     }
+
+    /// Same as [`OrderedCrypter::encrypt`], but also returns the nonce that was
+    /// used, so that it can be sent alongside the ciphertext.
+    pub fn encrypt_with_nonce(&mut self, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let nonce = self.write_nonce.next_nonce()?;
+        let encrypted_message = aes_gcm_256_encrypt(&self.write_key, &nonce, plaintext)?;
+        Ok((encrypted_message, nonce.to_vec()))
+    }
+
+    /// Same as [`OrderedCrypter::decrypt`], but first checks that the nonce
+    /// sent alongside the ciphertext is the next expected one, which gives a
+    /// meaningful error for dropped or reordered messages.
+    pub fn decrypt_with_nonce(
+        &mut self,
+        nonce: &[u8; NONCE_LEN],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        if Nonce::get_nonce_value(nonce)? != self.read_nonce.nonce {
+            return Err(Error::InvalidNonce);
+        }
+        self.decrypt(ciphertext)
+    }
+
+    /// Replaces the key used for encrypting outgoing messages with
+    /// `REKEY(write_key)` and resets the write nonce.
+    pub fn rekey_write(&mut self) {
+        self.write_key = rekey(&self.write_key);
+        self.write_nonce = Nonce { nonce: 0 };
+    }
+
+    /// Replaces the key used for decrypting incoming messages with
+    /// `REKEY(read_key)` and resets the read nonce.
+    pub fn rekey_read(&mut self) {
+        self.read_key = rekey(&self.read_key);
+        self.read_nonce = Nonce { nonce: 0 };
+    }
+}
+
+/// Noise `REKEY(k)` function for AESGCM: the first 32 bytes of the encryption
+/// of 32 zero bytes with the maximum nonce `2^64-1`. Transport nonces never
+/// reach this value.
+/// <http://www.noiseprotocol.org/noise.html#cipher-functions>
+fn rekey(key: &[u8; SYMMETRIC_KEY_LEN]) -> [u8; SYMMETRIC_KEY_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[NONCE_LEN - 8..].copy_from_slice(&u64::MAX.to_be_bytes());
+    let mut data = Vec::from([0u8; SYMMETRIC_KEY_LEN]);
+    aes_256_gcm_seal_in_place(key, &nonce, &[], &mut data);
+    let mut new_key = [0u8; SYMMETRIC_KEY_LEN];
+    new_key.copy_from_slice(&data[..SYMMETRIC_KEY_LEN]);
+    new_key
 }
 
 impl From<OrderedCrypter> for SessionKeys {
@@ -208,8 +308,8 @@ pub struct UnorderedCrypter {
     read_key: [u8; SYMMETRIC_KEY_LEN],
     write_key: [u8; SYMMETRIC_KEY_LEN],
     write_nonce: Nonce,
-    // The current furthest read nonce seen so far.
-    furthest_read_nonce: u32,
+    // The current furthest read nonce seen so far, if any.
+    furthest_read_nonce: Option<u32>,
     // Window size to ratchet receiving nonces in order to avoid receiving
     // nonces way too far in the past.
     window_size: u32,
@@ -227,19 +327,20 @@ impl UnorderedCrypter {
         Self {
             read_key: *read_key,
             write_key: *write_key,
-            write_nonce: Nonce { nonce: 1 },
-            furthest_read_nonce: 0,
+            // Nonces start at zero, as for the `OrderedCrypter`. Earlier versions
+            // started at one, so they can't talk to this version.
+            write_nonce: Nonce { nonce: 0 },
+            furthest_read_nonce: None,
             window_size,
             buffered_read_nonces: HashSet::with_capacity(window_size.try_into().unwrap()),
         }
     }
 
     fn get_lowest_acceptable_read_nonce(&self) -> u32 {
-        let mut lowest_acceptable_read_nonce = 1;
-        if self.furthest_read_nonce > self.window_size {
-            lowest_acceptable_read_nonce = self.furthest_read_nonce - self.window_size + 1;
+        match self.furthest_read_nonce {
+            Some(furthest_read_nonce) => (furthest_read_nonce + 1).saturating_sub(self.window_size),
+            None => 0,
         }
-        lowest_acceptable_read_nonce
     }
 
     pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
@@ -260,22 +361,25 @@ impl UnorderedCrypter {
             return Err(Error::InvalidNonce);
         }
         // Nonce is within the window, check for replayed nonces.
-        else if nonce_value >= lowest_acceptable_nonce && nonce_value <= self.furthest_read_nonce
-        {
-            if self.buffered_read_nonces.contains(&nonce_value) {
-                return Err(Error::ReplayedNonce);
-            }
-            self.buffered_read_nonces.insert(nonce_value);
+        if self.buffered_read_nonces.contains(&nonce_value) {
+            return Err(Error::ReplayedNonce);
         }
+        // The window is only updated once the message is authenticated, so that
+        // forged messages can't move it.
+        let plaintext = aes_gcm_256_decrypt(&self.read_key, nonce, ciphertext)?;
+        let is_furthest_nonce = match self.furthest_read_nonce {
+            Some(furthest_read_nonce) => nonce_value > furthest_read_nonce,
+            None => true,
+        };
         // Nonce is greater than the furthest seen so far.
-        else {
-            self.furthest_read_nonce = nonce_value;
+        if is_furthest_nonce {
+            self.furthest_read_nonce = Some(nonce_value);
             // Retain only buffered nonces in the new window span.
             let new_lowest_acceptable_nonce = self.get_lowest_acceptable_read_nonce();
             self.buffered_read_nonces.retain(|&n| n >= new_lowest_acceptable_nonce);
-            self.buffered_read_nonces.insert(nonce_value);
         }
-        aes_gcm_256_decrypt(&self.read_key, nonce, ciphertext)
+        self.buffered_read_nonces.insert(nonce_value);
+        Ok(plaintext)
     }
 }
 
@@ -305,6 +409,9 @@ pub struct Response {
     pub crypter: OrderedCrypter,
     pub handshake_hash: [u8; SHA256_OUTPUT_LEN],
     pub response: NoiseMessage,
+    // Static public key of the initiator, if it was pre-shared (KK) or
+    // transmitted during the handshake (IK).
+    pub initiator_static_public_key: Option<Vec<u8>>,
 }
 
 pub fn respond_nk(
@@ -370,7 +477,194 @@ pub fn respond_kk(
         .derive_dh_secret(initiator_static_pub_bytes.as_slice())
         .map_err(|_| Error::InvalidHandshake)?;
     noise.mix_key(&se_ecdh_bytes);
-    finish_response(&mut noise, in_message)
+    let mut response = finish_response(&mut noise, in_message)?;
+    response.initiator_static_public_key = Some(initiator_static_pub_bytes.to_vec());
+    Ok(response)
+}
+
+pub fn respond_ik(
+    identity_key: &dyn IdentityKeyHandle,
+    in_message: &NoiseMessage,
+) -> Result<Response, Error> {
+    let (response, payload) = respond_ik_with_params(
+        &P256_PARAMS,
+        identity_key,
+        &IdentityKey::generate(),
+        in_message,
+        &[],
+    )?;
+    if !payload.is_empty() {
+        return Err(Error::InvalidHandshake);
+    }
+    Ok(response)
+}
+
+/// Responds to an IK handshake with the given ephemeral key and payload, and
+/// returns the payload of the initiator.
+fn respond_ik_with_params(
+    params: &HandshakeParams,
+    identity_key: &dyn IdentityKeyHandle,
+    ephemeral_key: &dyn IdentityKeyHandle,
+    in_message: &NoiseMessage,
+    payload: &[u8],
+) -> Result<(Response, Vec<u8>), Error> {
+    let mut noise = params.noise(HandshakeType::Ik);
+    noise.mix_hash_point(
+        identity_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?.as_slice(),
+    );
+
+    // -> e, es, s, ss
+    let initiator_ephemeral_pub = params.public_key(&in_message.ephemeral_public_key)?;
+    noise.mix_hash(&initiator_ephemeral_pub);
+    let es_ecdh_bytes = identity_key
+        .derive_dh_secret(&initiator_ephemeral_pub)
+        .map_err(|_| Error::InvalidHandshake)?;
+    noise.mix_key(&es_ecdh_bytes);
+    let initiator_static_pub =
+        params.public_key(&noise.decrypt_and_hash(&in_message.static_public_key)?)?;
+    let ss_ecdh_bytes = identity_key
+        .derive_dh_secret(&initiator_static_pub)
+        .map_err(|_| Error::InvalidHandshake)?;
+    noise.mix_key(&ss_ecdh_bytes);
+    let initiator_payload = noise.decrypt_and_hash(&in_message.ciphertext)?;
+
+    // <- e, ee, se
+    let ephemeral_pub = ephemeral_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?;
+    noise.mix_hash(&ephemeral_pub);
+    let ee_ecdh_bytes = ephemeral_key
+        .derive_dh_secret(&initiator_ephemeral_pub)
+        .map_err(|_| Error::InvalidHandshake)?;
+    noise.mix_key(&ee_ecdh_bytes);
+    let se_ecdh_bytes = ephemeral_key
+        .derive_dh_secret(&initiator_static_pub)
+        .map_err(|_| Error::InvalidHandshake)?;
+    noise.mix_key(&se_ecdh_bytes);
+    let response_ciphertext = noise.encrypt_and_hash(payload);
+
+    let keys = noise.traffic_keys();
+    let response = Response {
+        crypter: OrderedCrypter::new(&keys.0, &keys.1),
+        handshake_hash: noise.handshake_hash(),
+        response: NoiseMessage {
+            ephemeral_public_key: ephemeral_pub,
+            static_public_key: Vec::new(),
+            ciphertext: response_ciphertext,
+        },
+        initiator_static_public_key: Some(initiator_static_pub),
+    };
+    Ok((response, initiator_payload))
+}
+
+/// Responder state of an XX handshake that has sent its `<- e, ee, s, es`
+/// message and is waiting for the initiator's final `-> s, se` message.
+///
+/// The handshake hash is taken after the responder's message, since this is
+/// the last point at which the responder can bind it (e.g. to its attestation)
+/// in a message sent to the initiator. The initiator's static key is still
+/// authenticated by the `se` token of the final message, which feeds into the
+/// traffic keys.
+pub struct XxResponder {
+    params: HandshakeParams,
+    noise: Noise,
+    ephemeral_key: Box<dyn IdentityKeyHandle>,
+    handshake_hash: [u8; SHA256_OUTPUT_LEN],
+}
+
+impl XxResponder {
+    pub fn handshake_hash(&self) -> [u8; SHA256_OUTPUT_LEN] {
+        self.handshake_hash
+    }
+
+    /// Processes the final message of the initiator and returns the traffic
+    /// crypter together with the initiator's static public key.
+    pub fn finish(self, in_message: &NoiseMessage) -> Result<(OrderedCrypter, Vec<u8>), Error> {
+        let (crypter, initiator_static_pub, payload) = self.finish_with_payload(in_message)?;
+        if !payload.is_empty() {
+            return Err(Error::InvalidHandshake);
+        }
+        Ok((crypter, initiator_static_pub))
+    }
+
+    /// Same as [`XxResponder::finish`], but also returns the payload of the
+    /// initiator.
+    fn finish_with_payload(
+        mut self,
+        in_message: &NoiseMessage,
+    ) -> Result<(OrderedCrypter, Vec<u8>, Vec<u8>), Error> {
+        // -> s, se
+        let initiator_static_pub =
+            self.params.public_key(&self.noise.decrypt_and_hash(&in_message.static_public_key)?)?;
+        let se_ecdh_bytes = self
+            .ephemeral_key
+            .derive_dh_secret(&initiator_static_pub)
+            .map_err(|_| Error::InvalidHandshake)?;
+        self.noise.mix_key(&se_ecdh_bytes);
+        let payload = self.noise.decrypt_and_hash(&in_message.ciphertext)?;
+
+        let keys = self.noise.traffic_keys();
+        Ok((OrderedCrypter::new(&keys.0, &keys.1), initiator_static_pub, payload))
+    }
+}
+
+pub fn respond_xx(
+    identity_key: &dyn IdentityKeyHandle,
+    in_message: &NoiseMessage,
+) -> Result<(XxResponder, NoiseMessage), Error> {
+    let (responder, response, payload) = respond_xx_with_params(
+        &P256_PARAMS,
+        identity_key,
+        Box::new(IdentityKey::generate()),
+        in_message,
+        &[],
+    )?;
+    if !payload.is_empty() {
+        return Err(Error::InvalidHandshake);
+    }
+    Ok((responder, response))
+}
+
+/// Responds to the first message of an XX handshake with the given ephemeral
+/// key and payload, and returns the payload of the initiator.
+fn respond_xx_with_params(
+    params: &HandshakeParams,
+    identity_key: &dyn IdentityKeyHandle,
+    ephemeral_key: Box<dyn IdentityKeyHandle>,
+    in_message: &NoiseMessage,
+    payload: &[u8],
+) -> Result<(XxResponder, NoiseMessage, Vec<u8>), Error> {
+    let mut noise = params.noise(HandshakeType::Xx);
+
+    // -> e
+    let initiator_ephemeral_pub = params.public_key(&in_message.ephemeral_public_key)?;
+    noise.mix_hash(&initiator_ephemeral_pub);
+    let initiator_payload = noise.decrypt_and_hash(&in_message.ciphertext)?;
+
+    // <- e, ee, s, es
+    let ephemeral_pub = ephemeral_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?;
+    noise.mix_hash(&ephemeral_pub);
+    let ee_ecdh_bytes = ephemeral_key
+        .derive_dh_secret(&initiator_ephemeral_pub)
+        .map_err(|_| Error::InvalidHandshake)?;
+    noise.mix_key(&ee_ecdh_bytes);
+    let encrypted_static_pub = noise.encrypt_and_hash(
+        identity_key.get_public_key().map_err(|_| Error::InvalidPrivateKey)?.as_slice(),
+    );
+    let es_ecdh_bytes = identity_key
+        .derive_dh_secret(&initiator_ephemeral_pub)
+        .map_err(|_| Error::InvalidHandshake)?;
+    noise.mix_key(&es_ecdh_bytes);
+    let response_ciphertext = noise.encrypt_and_hash(payload);
+
+    let handshake_hash = noise.handshake_hash();
+    Ok((
+        XxResponder { params: *params, noise, ephemeral_key, handshake_hash },
+        NoiseMessage {
+            ephemeral_public_key: ephemeral_pub,
+            static_public_key: encrypted_static_pub,
+            ciphertext: response_ciphertext,
+        },
+        initiator_payload,
+    ))
 }
 
 fn finish_response(noise: &mut Noise, in_message: &NoiseMessage) -> Result<Response, Error> {
@@ -403,7 +697,9 @@ fn finish_response(noise: &mut Noise, in_message: &NoiseMessage) -> Result<Respo
         handshake_hash: noise.handshake_hash(),
         response: NoiseMessage {
             ciphertext: response_ciphertext,
+            static_public_key: Vec::new(),
             ephemeral_public_key: ephemeral_pub_key_bytes.to_vec(),
         },
+        initiator_static_public_key: None,
     })
 }
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
 
 use crypto_wrapper::{NONCE_LEN, SYMMETRIC_KEY_LEN};
 
@@ -23,6 +23,8 @@ pub enum HandshakeType {
     Nk, // https://noiseexplorer.com/patterns/NK/
     Nn, // https://noiseexplorer.com/patterns/NN/
     Kk, // https://noiseexplorer.com/patterns/KK/
+    Xx, // https://noiseexplorer.com/patterns/XX/
+    Ik, // https://noiseexplorer.com/patterns/IK/
 }
 
 // Helper to generate 2 keys.
@@ -45,22 +47,40 @@ pub struct Noise {
     h: [u8; SYMMETRIC_KEY_LEN],
     symmetric_key: [u8; SYMMETRIC_KEY_LEN],
     symmetric_nonce: u32,
+    // Whether `symmetric_key` has been initialized by `mix_key`. Until then
+    // `encrypt_and_hash` and `decrypt_and_hash` pass data through unencrypted.
+    has_key: bool,
 }
 
 impl Noise {
     pub fn new(handshake_type: HandshakeType) -> Self {
-        let mut chaining_key_in = [0; SYMMETRIC_KEY_LEN];
-        let protocol_name = match handshake_type {
-            HandshakeType::Nk => b"Noise_NK_P256_AESGCM_SHA256",
-            HandshakeType::Nn => b"Noise_NN_P256_AESGCM_SHA256",
-            HandshakeType::Kk => b"Noise_KK_P256_AESGCM_SHA256",
+        Self::with_dh_name(handshake_type, "P256")
+    }
+
+    /// Same as [`Noise::new`], but names a DH function other than P-256 in the
+    /// protocol name.
+    pub fn with_dh_name(handshake_type: HandshakeType, dh_name: &str) -> Self {
+        // See https://www.noiseprotocol.org/noise.html#the-symmetricstate-object
+        let pattern_name = match handshake_type {
+            HandshakeType::Nk => "NK",
+            HandshakeType::Nn => "NN",
+            HandshakeType::Kk => "KK",
+            HandshakeType::Xx => "XX",
+            HandshakeType::Ik => "IK",
         };
-        chaining_key_in[..protocol_name.len()].copy_from_slice(protocol_name);
+        let protocol_name = format!("Noise_{pattern_name}_{dh_name}_AESGCM_SHA256");
+        let mut chaining_key_in = [0; SYMMETRIC_KEY_LEN];
+        if protocol_name.len() <= SYMMETRIC_KEY_LEN {
+            chaining_key_in[..protocol_name.len()].copy_from_slice(protocol_name.as_bytes());
+        } else {
+            chaining_key_in = crypto_wrapper::sha256(protocol_name.as_bytes());
+        }
         Noise {
             chaining_key: chaining_key_in,
             h: chaining_key_in,
             symmetric_key: [0; SYMMETRIC_KEY_LEN],
             symmetric_nonce: 0,
+            has_key: false,
         }
     }
 
@@ -89,16 +109,18 @@ impl Noise {
     }
 
     fn next_nonce(&mut self) -> [u8; NONCE_LEN] {
+        // See https://www.noiseprotocol.org/noise.html#the-aesgcm-cipher-functions
         let mut nonce_bytes = [0; NONCE_LEN];
-        nonce_bytes[0] = (self.symmetric_nonce >> 24) as u8;
-        nonce_bytes[1] = (self.symmetric_nonce >> 16) as u8;
-        nonce_bytes[2] = (self.symmetric_nonce >> 8) as u8;
-        nonce_bytes[3] = self.symmetric_nonce as u8;
+        nonce_bytes[4..].copy_from_slice(&u64::from(self.symmetric_nonce).to_be_bytes());
         self.symmetric_nonce += 1;
         nonce_bytes
     }
 
     pub fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        if !self.has_key {
+            self.mix_hash(plaintext);
+            return Vec::from(plaintext);
+        }
         let mut encrypted_data = Vec::from(plaintext);
         let nonce = self.next_nonce();
         crypto_wrapper::aes_256_gcm_seal_in_place(
@@ -112,6 +134,10 @@ impl Noise {
     }
 
     pub fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        if !self.has_key {
+            self.mix_hash(ciphertext);
+            return Ok(Vec::from(ciphertext));
+        }
         let h = self.h;
         self.mix_hash(ciphertext);
 
@@ -141,6 +167,7 @@ impl Noise {
         // See https://www.noiseprotocol.org/noise.html#the-cipherstate-object
         self.symmetric_key.copy_from_slice(key);
         self.symmetric_nonce = 0;
+        self.has_key = true;
     }
 }
 
@@ -177,6 +204,8 @@ mod tests {
         let handshake_types = vec![
             (HandshakeType::Nk, "53741f8d5a69c11d8f6f0865193f15f6d756b0d209fd7116b400a4a8a39439b8"),
             (HandshakeType::Nn, "a701b0aa0ab2100dd2679f3c621e5c63cf705f267fbb8273ee305e754ac24bd4"),
+            (HandshakeType::Xx, "84d0a2172df4b5d3464ff32498427b615881f7fb98255002990a4e636b7e23b7"),
+            (HandshakeType::Ik, "990f6997cd316fbd2beeb9c05fce8d30e45c2914e2f7f040dcd616f2917c7555"),
         ];
 
         for (handshake_type, expected_hash) in handshake_types {
@@ -199,6 +228,16 @@ mod tests {
                 "7134cc2910f616a55807c422a682fa615382ca6ed049b8755d",
                 "13334c68c25a7ccfc252a6b924d913390f9de381f3c1b3a2482ef15e5a1a69fc",
             ),
+            (
+                HandshakeType::Xx,
+                "fc1d9b2f2e3cb52744fb977db151248f252a0d5346e193dda4",
+                "1d00d6f438f042bebbfddff96ddf975d7f62753cc87297405ea9ccf4b53a7a88",
+            ),
+            (
+                HandshakeType::Ik,
+                "cd9b1821d7a46ee9b2c507f618d731712d79c0c77b07ea074a",
+                "4c6927973043306c6aa5af9c360f1667a7f4a3f64ab00bd45544ea75045bf833",
+            ),
         ];
         for (handshake_type, expected_ciphertext, expected_hash) in handshake_types {
             let mut noise = Noise::new(handshake_type);
@@ -245,6 +284,16 @@ mod tests {
                 "7134cc2910f616a55807c422a682fa615382ca6ed049b8755d",
                 "13334c68c25a7ccfc252a6b924d913390f9de381f3c1b3a2482ef15e5a1a69fc",
             ),
+            (
+                HandshakeType::Xx,
+                "fc1d9b2f2e3cb52744fb977db151248f252a0d5346e193dda4",
+                "1d00d6f438f042bebbfddff96ddf975d7f62753cc87297405ea9ccf4b53a7a88",
+            ),
+            (
+                HandshakeType::Ik,
+                "cd9b1821d7a46ee9b2c507f618d731712d79c0c77b07ea074a",
+                "4c6927973043306c6aa5af9c360f1667a7f4a3f64ab00bd45544ea75045bf833",
+            ),
         ];
         for (handshake_type, ciphertext, expected_hash) in handshake_types {
             let mut noise = Noise::new(handshake_type);
@@ -258,6 +307,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encrypt_and_hash_without_key() {
+        let mut noise = Noise::new(HandshakeType::Xx);
+        let ciphertext = noise.encrypt_and_hash("plaintext".as_bytes());
+        assert_eq!(ciphertext, "plaintext".as_bytes());
+
+        let mut other = Noise::new(HandshakeType::Xx);
+        let plaintext = other.decrypt_and_hash(&ciphertext).unwrap();
+        assert_eq!(plaintext, "plaintext".as_bytes());
+        assert_eq!(noise.handshake_hash(), other.handshake_hash());
+    }
+
     #[test]
     fn split() {
         let handshake_types = vec![
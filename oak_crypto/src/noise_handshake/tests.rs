@@ -13,13 +13,78 @@
 // limitations under the License.
 
 #[cfg(test)]
-use alloc::{boxed::Box, vec};
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use oak_proto_rust::oak::crypto::v1::SessionKeys;
 
 use crate::{
     identity_key::{IdentityKey, IdentityKeyHandle},
-    noise_handshake::{client::HandshakeInitiator, respond_kk, respond_nk, respond_nn},
+    noise_handshake::{
+        aes_256_gcm_seal_in_place, client::HandshakeInitiator, noise::HandshakeType,
+        p256_scalar_mult, rekey, respond_ik, respond_ik_with_params, respond_kk, respond_nk,
+        respond_nn, respond_xx, respond_xx_with_params, HandshakeParams, OrderedCrypter,
+        P256Scalar, UnorderedCrypter, NONCE_LEN, P256_PARAMS, SYMMETRIC_KEY_LEN,
+    },
 };
 
+// Conformance vectors for the P-256 XX and IK handshakes, with the prologue
+// `[0]` and empty payloads. They are generated by `generate_test_vectors.py`
+// in this directory, a Python implementation of the Noise Protocol Framework
+// (revision 34) that is independent of this crate:
+//
+//   python3 oak_crypto/src/noise_handshake/generate_test_vectors.py
+const INITIATOR_STATIC_PRIV: &str =
+    "1c1e6fc4f3f5b7c5d1a0e8b2a6c4e2f0d8b6a4c2e0f8d6b4a2c0e8f6d4b2a0c1";
+const INITIATOR_EPHEMERAL_PRIV: &str =
+    "2a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f70819";
+const RESPONDER_STATIC_PRIV: &str =
+    "3f2e1d0c0b0a09080706050403020100f0e0d0c0b0a090807060504030201001";
+const RESPONDER_EPHEMERAL_PRIV: &str =
+    "4d5c6b7a69584736251403f2e1d0c0b0a09f8e7d6c5b4a39281706f5e4d3c2b1";
+const INITIATOR_EPHEMERAL_PUB: &str = "047da106dca6e3d72fd2556297e7d1a02ff6d5b6d0a3887f54442f0e57fdd8a7af1e445f9265bf62be4df5d73d943f07876e909e035a1b46097cd1274629fed4b9";
+const RESPONDER_EPHEMERAL_PUB: &str = "046144f35c194539f05dd346731b38be9c22d4672c47670b5af0c506ad6fa8cc7d246dfdf0a23c1b4e472ef5a34d6ab54884bf99afd6cf2b8606e3f726d9dee7d6";
+
+/// Identity key with a fixed private key, used to replay conformance vectors.
+struct FixedIdentityKey(P256Scalar);
+
+impl IdentityKeyHandle for FixedIdentityKey {
+    fn get_public_key(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.0.compute_public_key().to_vec())
+    }
+
+    fn derive_dh_secret(&self, peer_public_key: &[u8]) -> anyhow::Result<Vec<u8>> {
+        p256_scalar_mult(
+            &self.0,
+            peer_public_key.try_into().map_err(|_| anyhow::anyhow!("invalid peer public key"))?,
+        )
+        .map(|secret| secret.to_vec())
+        .map_err(|_| anyhow::anyhow!("couldn't derive DH secret"))
+    }
+}
+
+fn scalar(hex_scalar: &str) -> P256Scalar {
+    hex::decode(hex_scalar).unwrap().as_slice().try_into().unwrap()
+}
+
+fn assert_crypters_match(mut client_crypter: OrderedCrypter, mut enclave_crypter: OrderedCrypter) {
+    let test_messages = vec![vec![1u8, 2u8, 3u8, 4u8], vec![4u8, 3u8, 2u8, 1u8], vec![]];
+
+    // Client -> Enclave encrypt+decrypt
+    for message in &test_messages {
+        let ciphertext = client_crypter.encrypt(message).unwrap();
+        let plaintext = enclave_crypter.decrypt(&ciphertext).unwrap();
+        assert_eq!(message, &plaintext);
+    }
+
+    // Enclave -> Client encrypt+decrypt
+    for message in &test_messages {
+        let ciphertext = enclave_crypter.encrypt(message).unwrap();
+        let plaintext = client_crypter.decrypt(&ciphertext).unwrap();
+        assert_eq!(message, &plaintext);
+    }
+}
+
 #[test]
 fn process_kk_handshake() {
     let test_messages = vec![vec![1u8, 2u8, 3u8, 4u8], vec![4u8, 3u8, 2u8, 1u8], vec![]];
@@ -113,3 +178,455 @@ fn process_nn_handshake() {
         assert_eq!(message, &plaintext);
     }
 }
+
+#[test]
+fn process_ik_handshake() {
+    let identity_priv = IdentityKey::generate();
+    let identity_pub_bytes = identity_priv
+        .get_public_key()
+        .expect("couldn't get the public key from the generated identity key");
+    let init_priv: Box<dyn IdentityKeyHandle> = Box::new(IdentityKey::generate());
+    let init_pub = init_priv.get_public_key().unwrap();
+    let mut initiator =
+        HandshakeInitiator::new_ik(identity_pub_bytes.as_slice().try_into().unwrap(), init_priv);
+    let message = initiator.build_initial_message().unwrap();
+    let handshake_response = respond_ik(&identity_priv, &message).unwrap();
+    assert_eq!(handshake_response.initiator_static_public_key.unwrap().to_vec(), init_pub);
+
+    let (client_hash, client_crypter) =
+        initiator.process_response(&handshake_response.response).unwrap();
+    assert_eq!(&client_hash, &handshake_response.handshake_hash);
+    assert_eq!(initiator.peer_static_public_key().unwrap().to_vec(), identity_pub_bytes);
+    assert_crypters_match(client_crypter, handshake_response.crypter);
+}
+
+#[test]
+fn ik_handshake_fails_with_wrong_responder_key() {
+    let identity_priv = IdentityKey::generate();
+    let other_identity_pub_bytes = IdentityKey::generate().get_public_key().unwrap();
+    let mut initiator = HandshakeInitiator::new_ik(
+        other_identity_pub_bytes.as_slice().try_into().unwrap(),
+        Box::new(IdentityKey::generate()),
+    );
+    let message = initiator.build_initial_message().unwrap();
+    assert!(respond_ik(&identity_priv, &message).is_err());
+}
+
+#[test]
+fn process_xx_handshake() {
+    let identity_priv = IdentityKey::generate();
+    let identity_pub_bytes = identity_priv.get_public_key().unwrap();
+    let init_priv: Box<dyn IdentityKeyHandle> = Box::new(IdentityKey::generate());
+    let init_pub = init_priv.get_public_key().unwrap();
+    let mut initiator = HandshakeInitiator::new_xx(init_priv);
+    let message = initiator.build_initial_message().unwrap();
+    // The initiator's static key is never sent in the clear.
+    assert!(message.static_public_key.is_empty());
+    let (responder, response) = respond_xx(&identity_priv, &message).unwrap();
+
+    let (final_message, client_hash, client_crypter) =
+        initiator.process_xx_response(&response).unwrap();
+    assert_eq!(client_hash, responder.handshake_hash());
+    assert_eq!(initiator.peer_static_public_key().unwrap().to_vec(), identity_pub_bytes);
+    assert_ne!(final_message.static_public_key, init_pub);
+
+    let (enclave_crypter, initiator_static_public_key) = responder.finish(&final_message).unwrap();
+    assert_eq!(initiator_static_public_key.to_vec(), init_pub);
+    assert_crypters_match(client_crypter, enclave_crypter);
+}
+
+#[test]
+fn xx_handshake_fails_with_tampered_static_key() {
+    let identity_priv = IdentityKey::generate();
+    let mut initiator = HandshakeInitiator::new_xx(Box::new(IdentityKey::generate()));
+    let message = initiator.build_initial_message().unwrap();
+    let (responder, response) = respond_xx(&identity_priv, &message).unwrap();
+    let (mut final_message, _, _) = initiator.process_xx_response(&response).unwrap();
+    final_message.static_public_key[0] ^= 1;
+    assert!(responder.finish(&final_message).is_err());
+}
+
+#[test]
+fn xx_handshake_conformance() {
+    let initiator_static_pub = scalar(INITIATOR_STATIC_PRIV).compute_public_key();
+    let mut initiator =
+        HandshakeInitiator::new_xx(Box::new(FixedIdentityKey(scalar(INITIATOR_STATIC_PRIV))))
+            .with_ephemeral_key(Box::new(FixedIdentityKey(scalar(INITIATOR_EPHEMERAL_PRIV))));
+
+    // -> e
+    let message = initiator.build_initial_message().unwrap();
+    assert_eq!(hex::encode(&message.ephemeral_public_key), INITIATOR_EPHEMERAL_PUB);
+    assert!(message.static_public_key.is_empty());
+    assert!(message.ciphertext.is_empty());
+
+    // <- e, ee, s, es
+    let (responder, response, _) = respond_xx_with_params(
+        &P256_PARAMS,
+        &FixedIdentityKey(scalar(RESPONDER_STATIC_PRIV)),
+        Box::new(FixedIdentityKey(scalar(RESPONDER_EPHEMERAL_PRIV))),
+        &message,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(hex::encode(&response.ephemeral_public_key), RESPONDER_EPHEMERAL_PUB);
+    assert_eq!(
+        hex::encode(&response.static_public_key),
+        "1a99ef402da48afff28613a62e0bd33d94d82b2ef226fd15be886d9859c037a42b24650c2f35a5d7f25f9109b37ad09696602a9ac2f1c0c78c83b70a49dfec8890d0e70dc536d971cb6fc52c39135e1b6f"
+    );
+    assert_eq!(hex::encode(&response.ciphertext), "bac819f7a77f12e994033198f49de44e");
+    assert_eq!(
+        hex::encode(responder.handshake_hash()),
+        "3815d0fbead7666cf0bb42488a75b1e606ab5a5ee86278a121650882970f566e"
+    );
+
+    // -> s, se
+    let (final_message, client_hash, client_crypter) =
+        initiator.process_xx_response(&response).unwrap();
+    assert_eq!(client_hash, responder.handshake_hash());
+    assert_eq!(
+        hex::encode(&final_message.static_public_key),
+        "75f63578b05a37b563b984de9b8392e72098deacd96cff8bf2796cc3a85e83c924b66dbac434db702cba3b915925381e328e8fbc2a864ddf118c4cad864d0625e5a6082d1f505e27cb316c1debc9fec16f"
+    );
+    assert_eq!(hex::encode(&final_message.ciphertext), "83b589f0d0e39f1810be730fbd48cb97");
+
+    let (enclave_crypter, initiator_static_public_key) = responder.finish(&final_message).unwrap();
+    assert_eq!(initiator_static_public_key, initiator_static_pub.to_vec());
+    let session_keys: SessionKeys = client_crypter.into();
+    assert_eq!(
+        hex::encode(&session_keys.request_key),
+        "54b921e3716b04652450191bd358ab1bbcd67021a5e6c1713ee49f940386875e"
+    );
+    assert_eq!(
+        hex::encode(&session_keys.response_key),
+        "03ad813142dedee971fcfccdd47bddb2cac74d6e7e1d9e8c57383da06766459e"
+    );
+    let session_keys: oak_proto_rust::oak::crypto::v1::SessionKeys = enclave_crypter.into();
+    assert_eq!(
+        hex::encode(&session_keys.request_key),
+        "03ad813142dedee971fcfccdd47bddb2cac74d6e7e1d9e8c57383da06766459e"
+    );
+}
+
+#[test]
+fn ik_handshake_conformance() {
+    let responder_static_pub = scalar(RESPONDER_STATIC_PRIV).compute_public_key();
+    let initiator_static_pub = scalar(INITIATOR_STATIC_PRIV).compute_public_key();
+    let mut initiator = HandshakeInitiator::new_ik(
+        responder_static_pub,
+        Box::new(FixedIdentityKey(scalar(INITIATOR_STATIC_PRIV))),
+    )
+    .with_ephemeral_key(Box::new(FixedIdentityKey(scalar(INITIATOR_EPHEMERAL_PRIV))));
+
+    // -> e, es, s, ss
+    let message = initiator.build_initial_message().unwrap();
+    assert_eq!(hex::encode(&message.ephemeral_public_key), INITIATOR_EPHEMERAL_PUB);
+    assert_eq!(
+        hex::encode(&message.static_public_key),
+        "5e2d373827b4e92ab11c0e784524956aefbb9bbf30f88fab8b99628b9d055ec8cb28b21787fee7774e6a1ac178cde2094083fb4a680b39c76f258d666d1970f4682f6a7ea415615f3142199cb4236a3e87"
+    );
+    assert_eq!(hex::encode(&message.ciphertext), "e2563b80b3b7e460efce4732057f4ce9");
+
+    // <- e, ee, se
+    let (handshake_response, _) = respond_ik_with_params(
+        &P256_PARAMS,
+        &FixedIdentityKey(scalar(RESPONDER_STATIC_PRIV)),
+        &FixedIdentityKey(scalar(RESPONDER_EPHEMERAL_PRIV)),
+        &message,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(handshake_response.initiator_static_public_key, Some(initiator_static_pub.to_vec()));
+    assert_eq!(
+        hex::encode(&handshake_response.response.ephemeral_public_key),
+        RESPONDER_EPHEMERAL_PUB
+    );
+    assert_eq!(
+        hex::encode(&handshake_response.response.ciphertext),
+        "a6f9e988a75634bb42f2b625c287d03a"
+    );
+    assert_eq!(
+        hex::encode(handshake_response.handshake_hash),
+        "91683e6dd7a7e961eaa3ca6326d063bf7650ebe6686c44ac50b44dc20bbe0e34"
+    );
+
+    let (client_hash, client_crypter) =
+        initiator.process_response(&handshake_response.response).unwrap();
+    assert_eq!(client_hash, handshake_response.handshake_hash);
+    let session_keys: SessionKeys = client_crypter.into();
+    assert_eq!(
+        hex::encode(&session_keys.request_key),
+        "e50e7ccd4b986ff4ff076f3089ceedde727f9fec049663aeeda25704185ceb29"
+    );
+    assert_eq!(
+        hex::encode(&session_keys.response_key),
+        "fe0fe41f73e9d4d1d042eb143f6c2177707778c6083ac08b77bb1cacd8d79bfc"
+    );
+}
+
+// The Noise_XX_25519_AESGCM_SHA256 and Noise_IK_25519_AESGCM_SHA256 vectors
+// from cacophony, as shipped in `tests/vectors/cacophony.txt` of the snow 0.9.6
+// crate. They are replayed through the same pattern logic as the P-256
+// handshakes, with X25519 in place of P-256.
+const X25519_PARAMS: HandshakeParams =
+    HandshakeParams { dh_name: "25519", public_key_len: 32, prologue: b"John Galt" };
+const CACOPHONY_INITIATOR_STATIC: &str =
+    "e61ef9919cde45dd5f82166404bd08e38bceb5dfdfded0a34c8df7ed542214d1";
+const CACOPHONY_INITIATOR_EPHEMERAL: &str =
+    "893e28b9dc6ca8d611ab664754b8ceb7bac5117349a4439a6b0569da977c464a";
+const CACOPHONY_RESPONDER_STATIC: &str =
+    "4a3acbfdb163dec651dfa3194dece676d437029c62a408b4c5ea9114246e4893";
+const CACOPHONY_RESPONDER_EPHEMERAL: &str =
+    "bbdb4cdbd309f1a1f2e1456967fe288cadd6f712d65dc7b7793d5e63da6b375b";
+// (payload, message) pairs, alternating between initiator and responder.
+const CACOPHONY_XX_MESSAGES: [(&str, &str); 6] = [
+    ("4c756477696720766f6e204d69736573", "ca35def5ae56cec33dc2036731ab14896bc4c75dbb07a61f879f8e3afa4c79444c756477696720766f6e204d69736573"),
+    ("4d757272617920526f746862617264", "95ebc60d2b1fa672c1f46a8aa265ef51bfe38e7ccb39ec5be34069f144808843757117acceb05bd7a45733bc22015c97a9d0cbaf41b80446d5988ff5127235d76b79eade70f473d6a4ef521fdcbeda5340d01e028ba793fc059f2724a83af05f12dda0448a7621a926b379a92477fd"),
+    ("462e20412e20486179656b", "c90f1cf77eba4e50edb038991565e36c9758943a989229b6051244dc4fbecb6946744b401af2ee1a5881b65fbb87fd07cb6a328ececc9ce6ce84c399dc332d4fd521fa4bb7f467ce909395"),
+    ("4361726c204d656e676572", "bc3fa77f6aca3e8466d7dc6bea10013e88a6a29add5132b461806c"),
+    ("4a65616e2d426170746973746520536179", "250b01074cdfe0df2ecf8ccbf1737b15a2ddb5b52fd9a396604e9c793cee3b3bb9"),
+    ("457567656e2042f6686d20766f6e2042617765726b", "449d4d433b3cdc3d02bf6fc881774b9df54366ebcffb9689bb13f14709822cd7ef42bcdb4d"),
+];
+const CACOPHONY_IK_MESSAGES: [(&str, &str); 6] = [
+    ("4c756477696720766f6e204d69736573", "ca35def5ae56cec33dc2036731ab14896bc4c75dbb07a61f879f8e3afa4c79444e417bc55c7a8166c993356c1be41ef67818a292426f301556c7f26b21d25ddb097153891a9a956cff47b83e63ad8d701c1342c209cff1ca5ecd43402762ac249e3bd3a4c0a145fe07cb5dae28ea13a3"),
+    ("4d757272617920526f746862617264", "95ebc60d2b1fa672c1f46a8aa265ef51bfe38e7ccb39ec5be34069f144808843af2ccf9972e22afc67aeafcd25162f7f98c363b7762e3e4cb7d272e39f27a5"),
+    ("462e20412e20486179656b", "66acfc92e3197de166809e6d4d5d003dcc819a84bc3522ca53c9d9"),
+    ("4361726c204d656e676572", "71f89aa6533a6de70b0826864dd75f60806ee40170c16290189eb3"),
+    ("4a65616e2d426170746973746520536179", "4795a3423550c8bf00386bd496a3e2c76c10669d2a75ab8f79b5094c5412a25705"),
+    ("457567656e2042f6686d20766f6e2042617765726b", "aa0bb39097555c918e40be82abc2b909eb79d9eb87adb07e268fc37323a6cf904fd01fb391"),
+];
+
+/// X25519 key, used to replay the cacophony vectors.
+struct X25519Key([u8; 32]);
+
+impl X25519Key {
+    fn from_hex(hex_key: &str) -> Self {
+        Self(hex::decode(hex_key).unwrap().as_slice().try_into().unwrap())
+    }
+}
+
+impl IdentityKeyHandle for X25519Key {
+    fn get_public_key(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(MontgomeryPoint::mul_base_clamped(self.0).to_bytes().to_vec())
+    }
+
+    fn derive_dh_secret(&self, peer_public_key: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let peer_public_key: [u8; 32] =
+            peer_public_key.try_into().map_err(|_| anyhow::anyhow!("invalid peer public key"))?;
+        Ok(MontgomeryPoint(peer_public_key).mul_clamped(self.0).to_bytes().to_vec())
+    }
+}
+
+/// Checks the transport messages of a cacophony vector, starting at
+/// `first_index`. `OrderedCrypter` pads its plaintexts, so the messages are
+/// checked against the traffic keys directly.
+fn assert_cacophony_transport_messages(
+    initiator_crypter: OrderedCrypter,
+    responder_crypter: OrderedCrypter,
+    messages: &[(&str, &str)],
+    first_index: usize,
+) {
+    let initiator_keys: SessionKeys = initiator_crypter.into();
+    let responder_keys: SessionKeys = responder_crypter.into();
+    assert_eq!(initiator_keys.request_key, responder_keys.response_key);
+    assert_eq!(initiator_keys.response_key, responder_keys.request_key);
+
+    let mut initiator_nonce = 0u64;
+    let mut responder_nonce = 0u64;
+    for (index, (payload, expected_message)) in messages.iter().enumerate().skip(first_index) {
+        let (key, counter) = if index % 2 == 0 {
+            (&initiator_keys.request_key, &mut initiator_nonce)
+        } else {
+            (&responder_keys.request_key, &mut responder_nonce)
+        };
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[NONCE_LEN - 8..].copy_from_slice(&counter.to_be_bytes());
+        *counter += 1;
+        let mut message = hex::decode(payload).unwrap();
+        aes_256_gcm_seal_in_place(key.as_slice().try_into().unwrap(), &nonce, &[], &mut message);
+        assert_eq!(hex::encode(message), *expected_message);
+    }
+}
+
+fn cacophony_payload(messages: &[(&str, &str)], index: usize) -> Vec<u8> {
+    hex::decode(messages[index].0).unwrap()
+}
+
+fn cacophony_message(messages: &[(&str, &str)], index: usize) -> Vec<u8> {
+    hex::decode(messages[index].1).unwrap()
+}
+
+#[test]
+fn xx_handshake_cacophony_x25519() {
+    let messages = &CACOPHONY_XX_MESSAGES;
+    let mut initiator =
+        HandshakeInitiator::new_xx(Box::new(X25519Key::from_hex(CACOPHONY_INITIATOR_STATIC)))
+            .with_params(X25519_PARAMS)
+            .with_ephemeral_key(Box::new(X25519Key::from_hex(CACOPHONY_INITIATOR_EPHEMERAL)));
+
+    // -> e
+    let message = initiator.build_xx_initial_message(&cacophony_payload(messages, 0)).unwrap();
+    assert_eq!(
+        [message.ephemeral_public_key.as_slice(), &message.ciphertext].concat(),
+        cacophony_message(messages, 0)
+    );
+
+    // <- e, ee, s, es
+    let (responder, response, initiator_payload) = respond_xx_with_params(
+        &X25519_PARAMS,
+        &X25519Key::from_hex(CACOPHONY_RESPONDER_STATIC),
+        Box::new(X25519Key::from_hex(CACOPHONY_RESPONDER_EPHEMERAL)),
+        &message,
+        &cacophony_payload(messages, 1),
+    )
+    .unwrap();
+    assert_eq!(initiator_payload, cacophony_payload(messages, 0));
+    assert_eq!(
+        [
+            response.ephemeral_public_key.as_slice(),
+            &response.static_public_key,
+            &response.ciphertext
+        ]
+        .concat(),
+        cacophony_message(messages, 1)
+    );
+
+    // -> s, se
+    let (final_message, _, initiator_crypter, responder_payload) = initiator
+        .process_xx_response_with_payload(&response, &cacophony_payload(messages, 2))
+        .unwrap();
+    assert_eq!(responder_payload, cacophony_payload(messages, 1));
+    assert_eq!(
+        initiator.peer_static_public_key(),
+        Some(X25519Key::from_hex(CACOPHONY_RESPONDER_STATIC).get_public_key().unwrap())
+    );
+    assert_eq!(
+        [final_message.static_public_key.as_slice(), &final_message.ciphertext].concat(),
+        cacophony_message(messages, 2)
+    );
+
+    // The published handshake hash is taken after the final message, whereas
+    // `XxResponder` binds the one after the responder's message, so it isn't
+    // compared here. The transport messages cover the final chaining key.
+    let (responder_crypter, initiator_static_public_key, initiator_payload) =
+        responder.finish_with_payload(&final_message).unwrap();
+    assert_eq!(initiator_payload, cacophony_payload(messages, 2));
+    assert_eq!(
+        initiator_static_public_key,
+        X25519Key::from_hex(CACOPHONY_INITIATOR_STATIC).get_public_key().unwrap()
+    );
+    assert_cacophony_transport_messages(initiator_crypter, responder_crypter, messages, 3);
+}
+
+#[test]
+fn ik_handshake_cacophony_x25519() {
+    let messages = &CACOPHONY_IK_MESSAGES;
+    let responder_static_pub =
+        X25519Key::from_hex(CACOPHONY_RESPONDER_STATIC).get_public_key().unwrap();
+    assert_eq!(
+        hex::encode(&responder_static_pub),
+        "31e0303fd6418d2f8c0e78b91f22e8caed0fbe48656dcf4767e4834f701b8f62"
+    );
+    let mut initiator = HandshakeInitiator::new(
+        HandshakeType::Ik,
+        Some(responder_static_pub),
+        Some(Box::new(X25519Key::from_hex(CACOPHONY_INITIATOR_STATIC))),
+    )
+    .with_params(X25519_PARAMS)
+    .with_ephemeral_key(Box::new(X25519Key::from_hex(CACOPHONY_INITIATOR_EPHEMERAL)));
+
+    // -> e, es, s, ss
+    let message = initiator.build_ik_initial_message(&cacophony_payload(messages, 0)).unwrap();
+    assert_eq!(
+        [message.ephemeral_public_key.as_slice(), &message.static_public_key, &message.ciphertext]
+            .concat(),
+        cacophony_message(messages, 0)
+    );
+
+    // <- e, ee, se
+    let (handshake_response, initiator_payload) = respond_ik_with_params(
+        &X25519_PARAMS,
+        &X25519Key::from_hex(CACOPHONY_RESPONDER_STATIC),
+        &X25519Key::from_hex(CACOPHONY_RESPONDER_EPHEMERAL),
+        &message,
+        &cacophony_payload(messages, 1),
+    )
+    .unwrap();
+    assert_eq!(initiator_payload, cacophony_payload(messages, 0));
+    assert_eq!(
+        handshake_response.initiator_static_public_key,
+        Some(X25519Key::from_hex(CACOPHONY_INITIATOR_STATIC).get_public_key().unwrap())
+    );
+    assert_eq!(
+        [
+            handshake_response.response.ephemeral_public_key.as_slice(),
+            &handshake_response.response.ciphertext
+        ]
+        .concat(),
+        cacophony_message(messages, 1)
+    );
+    assert_eq!(
+        hex::encode(handshake_response.handshake_hash),
+        "669c8640d9e42a3cda2f232f78597ceefb01daa6e3df81181ccce6fc6b5026bf"
+    );
+
+    let (client_hash, initiator_crypter, responder_payload) =
+        initiator.process_ik_response(&handshake_response.response).unwrap();
+    assert_eq!(client_hash, handshake_response.handshake_hash);
+    assert_eq!(responder_payload, cacophony_payload(messages, 1));
+    assert_cacophony_transport_messages(initiator_crypter, handshake_response.crypter, messages, 2);
+}
+
+#[test]
+fn rekey_conformance() {
+    let key: [u8; SYMMETRIC_KEY_LEN] = core::array::from_fn(|i| i as u8);
+    assert_eq!(
+        hex::encode(rekey(&key)),
+        "0201675c87335949b909793da5bb4d92fcf6d44b92a6e0792b6ae48b1881259d"
+    );
+}
+
+#[test]
+fn rekeyed_crypters_match() {
+    let identity_priv = IdentityKey::generate();
+    let mut initiator = HandshakeInitiator::new_nk(
+        identity_priv.get_public_key().unwrap().as_slice().try_into().unwrap(),
+    );
+    let message = initiator.build_initial_message().unwrap();
+    let handshake_response = respond_nk(&identity_priv, &message).unwrap();
+    let enclave_keys: SessionKeys = handshake_response.crypter.into();
+    let (_, mut client_crypter) = initiator.process_response(&handshake_response.response).unwrap();
+
+    // Only the Client -> Enclave direction is rekeyed, so the message must not
+    // decrypt until the enclave rekeys its read key too.
+    client_crypter.rekey_write();
+    let ciphertext = client_crypter.encrypt(&[1, 2, 3]).unwrap();
+    let mut stale_crypter: OrderedCrypter = enclave_keys.clone().try_into().unwrap();
+    assert!(stale_crypter.decrypt(&ciphertext).is_err());
+
+    let mut enclave_crypter: OrderedCrypter = enclave_keys.try_into().unwrap();
+    enclave_crypter.rekey_read();
+    assert_eq!(enclave_crypter.decrypt(&ciphertext).unwrap(), vec![1, 2, 3]);
+
+    enclave_crypter.rekey_write();
+    client_crypter.rekey_read();
+    assert_crypters_match(client_crypter, enclave_crypter);
+}
+
+#[test]
+fn unordered_crypter_ignores_forged_nonces() {
+    let key_1 = &[42u8; SYMMETRIC_KEY_LEN];
+    let key_2 = &[52u8; SYMMETRIC_KEY_LEN];
+    let mut sender = UnorderedCrypter::new(key_1, key_2, 2);
+    let mut receiver = UnorderedCrypter::new(key_2, key_1, 2);
+    let (ciphertext, nonce) = sender.encrypt(&[1, 2, 3]).unwrap();
+
+    // A forged message with a far-ahead nonce must not move the window.
+    let mut forged_nonce = [0u8; NONCE_LEN];
+    forged_nonce[NONCE_LEN - 1] = 100;
+    assert!(receiver.decrypt(&forged_nonce, &ciphertext).is_err());
+
+    assert_eq!(
+        receiver.decrypt(nonce.as_slice().try_into().unwrap(), &ciphertext).unwrap(),
+        vec![1, 2, 3]
+    );
+}
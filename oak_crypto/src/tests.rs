@@ -14,9 +14,15 @@
 // limitations under the License.
 //
 
+use alloc::vec::Vec;
+
+use oak_proto_rust::oak::crypto::v1::EncryptedResponse;
+
 use crate::{
     encryption_key::generate_encryption_key_pair,
-    encryptor::{ClientEncryptor, ServerEncryptor},
+    encryptor::{
+        response_chunk_associated_data, ClientEncryptor, ResponseChunkDecryptor, ServerEncryptor,
+    },
     hpke::{
         aead::{AEAD_ALGORITHM_KEY_SIZE_BYTES, AEAD_NONCE_SIZE_BYTES},
         generate_kem_key_pair, generate_random_nonce, setup_base_recipient, setup_base_sender,
@@ -173,6 +179,126 @@ async fn test_async_encryptor() {
     assert_eq!(TEST_RESPONSE_ASSOCIATED_DATA, response_associated_data);
 }
 
+#[test]
+fn test_encryptor_multiple_responses() {
+    let (encryption_key, encryption_public_key) = generate_encryption_key_pair();
+
+    let mut client_encryptor =
+        ClientEncryptor::create(&encryption_public_key).expect("couldn't create client encryptor");
+    let encrypted_request = client_encryptor
+        .encrypt(TEST_REQUEST_MESSAGE, TEST_REQUEST_ASSOCIATED_DATA)
+        .expect("client couldn't encrypt request");
+
+    let (server_encryptor, _, _) = ServerEncryptor::decrypt(&encrypted_request, &encryption_key)
+        .expect("server couldn't decrypt request");
+
+    let response_chunks: [&[u8]; 3] = [b"first", b"second", b"third"];
+    for chunk in response_chunks {
+        let encrypted_response = server_encryptor
+            .encrypt(chunk, TEST_RESPONSE_ASSOCIATED_DATA)
+            .expect("server couldn't encrypt response");
+        let (decrypted_response, _) = client_encryptor
+            .decrypt(&encrypted_response)
+            .expect("client couldn't decrypt response");
+        assert_eq!(chunk, decrypted_response);
+    }
+}
+
+/// Encrypts the response chunks the way a streaming server does, ending with
+/// an empty final chunk.
+fn encrypt_response_chunks(
+    server_encryptor: &ServerEncryptor,
+    chunks: &[&[u8]],
+) -> Vec<EncryptedResponse> {
+    let mut encrypted_responses: Vec<EncryptedResponse> = chunks
+        .iter()
+        .enumerate()
+        .map(|(sequence_number, chunk)| {
+            server_encryptor
+                .encrypt(chunk, &response_chunk_associated_data(sequence_number as u64, false))
+                .expect("server couldn't encrypt response")
+        })
+        .collect();
+    encrypted_responses.push(
+        server_encryptor
+            .encrypt(&[], &response_chunk_associated_data(chunks.len() as u64, true))
+            .expect("server couldn't encrypt final response"),
+    );
+    encrypted_responses
+}
+
+fn create_response_chunk_session() -> (ClientEncryptor, ServerEncryptor) {
+    let (encryption_key, encryption_public_key) = generate_encryption_key_pair();
+    let mut client_encryptor =
+        ClientEncryptor::create(&encryption_public_key).expect("couldn't create client encryptor");
+    let encrypted_request = client_encryptor
+        .encrypt(TEST_REQUEST_MESSAGE, TEST_REQUEST_ASSOCIATED_DATA)
+        .expect("client couldn't encrypt request");
+    let (server_encryptor, _, _) = ServerEncryptor::decrypt(&encrypted_request, &encryption_key)
+        .expect("server couldn't decrypt request");
+    (client_encryptor, server_encryptor)
+}
+
+#[test]
+fn test_response_chunk_decryptor() {
+    let (client_encryptor, server_encryptor) = create_response_chunk_session();
+    let chunks: [&[u8]; 3] = [b"first", b"second", b"third"];
+    let encrypted_responses = encrypt_response_chunks(&server_encryptor, &chunks);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    let (final_response, chunk_responses) = encrypted_responses.split_last().unwrap();
+    for (chunk, encrypted_response) in chunks.iter().zip(chunk_responses) {
+        let decrypted = decryptor.decrypt(encrypted_response).expect("couldn't decrypt chunk");
+        assert_eq!(Some(chunk.to_vec()), decrypted);
+    }
+    assert_eq!(None, decryptor.decrypt(final_response).expect("couldn't decrypt final chunk"));
+    assert!(decryptor.finish().is_ok());
+}
+
+#[test]
+fn test_response_chunk_decryptor_rejects_reordered_chunks() {
+    let (client_encryptor, server_encryptor) = create_response_chunk_session();
+    let encrypted_responses =
+        encrypt_response_chunks(&server_encryptor, &[b"first", b"second", b"third"]);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    assert!(decryptor.decrypt(&encrypted_responses[1]).is_err());
+}
+
+#[test]
+fn test_response_chunk_decryptor_rejects_replayed_chunks() {
+    let (client_encryptor, server_encryptor) = create_response_chunk_session();
+    let encrypted_responses = encrypt_response_chunks(&server_encryptor, &[b"first", b"second"]);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    assert!(decryptor.decrypt(&encrypted_responses[0]).is_ok());
+    assert!(decryptor.decrypt(&encrypted_responses[0]).is_err());
+}
+
+#[test]
+fn test_response_chunk_decryptor_detects_truncation() {
+    let (client_encryptor, server_encryptor) = create_response_chunk_session();
+    let encrypted_responses = encrypt_response_chunks(&server_encryptor, &[b"first", b"second"]);
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    assert!(decryptor.decrypt(&encrypted_responses[0]).is_ok());
+    assert!(decryptor.decrypt(&encrypted_responses[1]).is_ok());
+    assert!(decryptor.finish().is_err());
+}
+
+#[test]
+fn test_response_chunk_decryptor_rejects_chunks_after_final() {
+    let (client_encryptor, server_encryptor) = create_response_chunk_session();
+    let encrypted_responses = encrypt_response_chunks(&server_encryptor, &[]);
+    let extra_response = server_encryptor
+        .encrypt(b"extra", &response_chunk_associated_data(1, false))
+        .expect("server couldn't encrypt response");
+
+    let mut decryptor = ResponseChunkDecryptor::new(&client_encryptor);
+    assert_eq!(None, decryptor.decrypt(&encrypted_responses[0]).unwrap());
+    assert!(decryptor.decrypt(&extra_response).is_err());
+}
+
 const TEST_SIGNATURE_MESSAGE_ONE: &[u8] = b"Dogs are the best";
 const TEST_SIGNATURE_MESSAGE_TWO: &[u8] = b"Cats are even better";
 
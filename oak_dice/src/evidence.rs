@@ -35,6 +35,13 @@ const AMD_SEV_SNP_ATTESTATION_REPORT_SIZE: usize = 1184;
 
 static_assertions::const_assert!(REPORT_SIZE >= AMD_SEV_SNP_ATTESTATION_REPORT_SIZE);
 
+/// The size of the TD report generated by Intel TDX.
+///
+/// See section 3.6.1 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf).
+const INTEL_TDX_TD_REPORT_SIZE: usize = 1024;
+
+static_assertions::const_assert!(REPORT_SIZE >= INTEL_TDX_TD_REPORT_SIZE);
+
 /// The size for compound device identifiers.
 ///
 /// See <https://pigweed.googlesource.com/open-dice/+/HEAD/docs/specification.md>
@@ -70,6 +77,14 @@ pub const DICE_DATA_LENGTH_CMDLINE_PARAM: &str = "oak-dice-length";
 /// physical address of the Stage 0 event log.
 pub const EVENTLOG_CMDLINE_PARAM: &str = "oak-event-log";
 
+/// The name of the kernel command-line parameter that is used to send the
+/// physical address of the TEE certificate (e.g. the AMD VCEK certificate) that
+/// the host provided alongside the attestation report.
+///
+/// The certificate is stored as the length of the DER encoding in little-endian
+/// format as a u64, followed by the DER encoding itself.
+pub const TEE_CERTIFICATE_CMDLINE_PARAM: &str = "oak-tee-certificate";
+
 /// The magic number used to identify the Stage 0 DICE data in memory.
 pub const STAGE0_MAGIC: u64 = u64::from_le_bytes(*b"oak.dice");
 
@@ -125,7 +140,11 @@ impl RootLayerEvidence {
             TeePlatform::AmdSevSnp => {
                 Ok(&self.remote_attestation_report[..AMD_SEV_SNP_ATTESTATION_REPORT_SIZE])
             }
-            TeePlatform::IntelTdx => Ok(&self.remote_attestation_report),
+            // The TD report is only upgraded to a quote after it has been converted to a
+            // protocol buffer, since a quote doesn't fit in the report buffer.
+            TeePlatform::IntelTdx => {
+                Ok(&self.remote_attestation_report[..INTEL_TDX_TD_REPORT_SIZE])
+            }
             TeePlatform::Unspecified => Err("TEE platform not specified"),
         }
     }
@@ -184,6 +203,17 @@ pub struct CompoundDeviceIdentifier {
     pub cdi: [u8; CDI_SIZE],
 }
 
+impl CompoundDeviceIdentifier {
+    /// Returns whether the CDI was derived from a secret.
+    ///
+    /// Stage 0 leaves the CDI zeroed if the platform has no secret to derive
+    /// it from, e.g. on Intel TDX. It must not be used to derive sealing keys
+    /// in that case.
+    pub fn is_available(&self) -> bool {
+        self.cdi.iter().any(|&byte| byte != 0)
+    }
+}
+
 static_assertions::assert_eq_size!([u8; CDI_SIZE], CompoundDeviceIdentifier);
 
 /// Wrapper for passing DICE info from Stage0 to the next layer (Stage 1 or the
@@ -26,43 +26,76 @@ use oak_restricted_kernel_interface::syscalls::{MmapFlags, MmapProtection};
 use rlsf::{FlexSource, FlexTlsf};
 use spinning_top::Spinlock;
 
-struct Source {}
+// Restricted Kernel deals in 2 MiB pages, so that's what we use to request
+// memory.
+const PAGE_SIZE: usize = 0x20_0000usize;
+
+/// Asks Restricted Kernel for at least `min_size` bytes of memory.
+fn map_pages(min_size: usize) -> Option<NonNull<[u8]>> {
+    // Ensure that we're allocating page-sized chunks of memory.
+    let size =
+        if min_size % PAGE_SIZE != 0 { PAGE_SIZE * ((min_size / PAGE_SIZE) + 1) } else { min_size };
+
+    oak_restricted_kernel_interface::syscall::mmap(
+        // TODO(#3864): One we start compiling C++ applications internally using the Oak
+        // Toolchain, we won't need to manually separate Rust and C++ heaps.
+        Some(0x100_0000_0000 as *const core::ffi::c_void),
+        size.try_into().ok()?,
+        MmapProtection::PROT_READ | MmapProtection::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        -1,
+        0,
+    )
+    .map(|r| NonNull::new(r))
+    .ok()
+    .flatten()
+}
 
-impl Source {
-    // Restricted Kernel deals in 2 MiB pages, so that's what we use to request
-    // memory.
-    const PAGE_SIZE: usize = 0x20_0000usize;
+/// Returns memory obtained via [`map_pages`] back to Restricted Kernel.
+///
+/// # Safety
+///
+/// The memory must not be accessed after it has been unmapped.
+unsafe fn unmap_pages(ptr: NonNull<u8>, size: usize) {
+    if let Err(err) = oak_restricted_kernel_interface::syscall::munmap(
+        ptr.as_ptr() as *const core::ffi::c_void,
+        size,
+    ) {
+        log::error!("failed to unmap memory at {:p}: {:?}", ptr, err);
+    }
 }
 
+struct Source {}
+
 unsafe impl FlexSource for Source {
     unsafe fn alloc(&mut self, min_size: usize) -> Option<NonNull<[u8]>> {
-        // Ensure that we're allocating page-sized chunks of memory.
-        let size = if min_size % Self::PAGE_SIZE != 0 {
-            Self::PAGE_SIZE * ((min_size / Self::PAGE_SIZE) + 1)
-        } else {
-            min_size
-        };
+        map_pages(min_size)
+    }
+
+    unsafe fn dealloc(&mut self, ptr: NonNull<[u8]>) {
+        unmap_pages(ptr.cast(), ptr.len())
+    }
 
-        oak_restricted_kernel_interface::syscall::mmap(
-            // TODO(#3864): One we start compiling C++ applications internally using the Oak
-            // Toolchain, we won't need to manually separate Rust and C++ heaps.
-            Some(0x100_0000_0000 as *const core::ffi::c_void),
-            size.try_into().ok()?,
-            MmapProtection::PROT_READ | MmapProtection::PROT_WRITE,
-            MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
-            -1,
-            0,
-        )
-        .map(|r| NonNull::new(r))
-        .ok()
-        .flatten()
+    fn supports_dealloc(&self) -> bool {
+        true
     }
 
     fn min_align(&self) -> usize {
-        Self::PAGE_SIZE
+        PAGE_SIZE
     }
 }
 
+/// Whether an allocation is served directly by Restricted Kernel instead of
+/// the underlying heap.
+///
+/// Memory in the underlying heap is never returned to Restricted Kernel, so
+/// large allocations get their own pages, which are unmapped again when the
+/// allocation is freed. The decision only depends on the layout, so that
+/// `deallocate` knows where the memory came from.
+fn is_large_allocation(layout: &Layout) -> bool {
+    layout.size() >= PAGE_SIZE && layout.align() <= PAGE_SIZE
+}
+
 /// Heap implementation that asks Restricted Kernel for more memory when
 /// allocations fail.
 ///
@@ -84,18 +117,25 @@ impl GrowableHeap {
 
     #[allow(clippy::result_unit_err)]
     pub fn allocate(&mut self, layout: Layout) -> Result<NonNull<u8>, ()> {
-        self.heap
-            .allocate(layout)
-            .ok_or_else(|| log::error!("failed to allocate memory with layout: {:?}", layout))
+        let ptr = if is_large_allocation(&layout) {
+            map_pages(layout.size()).map(NonNull::cast)
+        } else {
+            self.heap.allocate(layout)
+        };
+        ptr.ok_or_else(|| log::error!("failed to allocate memory with layout: {:?}", layout))
     }
 
     /// # Safety
     ///
     ///  - `ptr` must denote a memory block previously allocated via `self`.
-    ///  - The memory block must have been allocated with the same alignment
-    ///    ([`Layout::align`]) as `align`.
-    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, align: usize) {
-        self.heap.deallocate(ptr, align)
+    ///  - The memory block must have been allocated with the same layout as
+    ///    `layout`.
+    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        if is_large_allocation(&layout) {
+            unmap_pages(ptr, layout.size())
+        } else {
+            self.heap.deallocate(ptr, layout.align())
+        }
     }
 }
 
@@ -122,6 +162,6 @@ unsafe impl GlobalAlloc for LockedGrowableHeap {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
-        self.0.lock().deallocate(NonNull::new_unchecked(ptr), layout.align())
+        self.0.lock().deallocate(NonNull::new_unchecked(ptr), layout)
     }
 }
@@ -113,6 +113,9 @@ impl Response {
     }
 
     pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < RESPONSE_BODY_OFFSET {
+            anyhow::bail!("response is shorter than the response header");
+        }
         let status: StatusCode = {
             let mut status_bytes: [u8; RESPONSE_STATUS_CODE_SIZE] = [0; RESPONSE_STATUS_CODE_SIZE];
             status_bytes.copy_from_slice(
@@ -133,6 +136,9 @@ impl Response {
             Vec::with_capacity(bytes.len() - RESPONSE_LENGTH_SIZE - RESPONSE_STATUS_CODE_SIZE);
 
         body.extend_from_slice(&bytes[RESPONSE_BODY_OFFSET..bytes.len()]);
+        if usize::try_from(length).map_or(true, |length| length > body.len()) {
+            anyhow::bail!("response length is larger than the response body");
+        }
 
         Ok(Self { status, body, length })
     }
@@ -16,6 +16,7 @@
 use anyhow::Context;
 use oak_client::{client::OakClient, verifier::AttestationVerifier};
 use oak_client_tonic::transport::GrpcStreamingTransport;
+use oak_functions_abi::{Response, StatusCode};
 use oak_grpc::oak::session::v1::streaming_session_client::StreamingSessionClient;
 use prost::Message;
 use tonic::transport::Channel;
@@ -57,6 +58,32 @@ impl OakFunctionsClient {
                     format!("couldn't deserialize response wrapper: {:?}", err),
                 )
             })?;
-        response.into()
+        let response_result: Result<Vec<u8>, micro_rpc::Status> = response.into();
+        // An error here indicates that the response violated the server policy.
+        decode_response(&response_result?)
+    }
+}
+
+// Extracts the unpadded body from a response encoded as described in
+// `REQUEST_RESPONSE_ENCODING.md` in the `oak_functions_abi` crate.
+fn decode_response(response_bytes: &[u8]) -> Result<Vec<u8>, micro_rpc::Status> {
+    let response = Response::decode(response_bytes).map_err(|err| {
+        micro_rpc::Status::new_with_message(
+            micro_rpc::StatusCode::Internal,
+            format!("couldn't decode Oak Functions response: {:?}", err),
+        )
+    })?;
+    let body = response.body().map_err(|err| {
+        micro_rpc::Status::new_with_message(
+            micro_rpc::StatusCode::Internal,
+            format!("invalid Oak Functions response length: {:?}", err),
+        )
+    })?;
+    match response.status {
+        StatusCode::Success => Ok(body.to_vec()),
+        status => Err(micro_rpc::Status::new_with_message(
+            micro_rpc::StatusCode::Internal,
+            format!("Oak Functions response status {}: {}", status, String::from_utf8_lossy(body)),
+        )),
     }
 }
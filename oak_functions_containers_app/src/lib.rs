@@ -47,7 +47,7 @@ use oak_grpc::oak::functions::oak_functions_server::{OakFunctions, OakFunctionsS
 // Instance of the OakFunctions service for Oak Containers.
 pub struct OakFunctionsContainersService<H: Handler> {
     instance_config: H::HandlerConfig,
-    instance: OnceLock<OakFunctionsInstance<H>>,
+    instance: OnceLock<Arc<OakFunctionsInstance<H>>>,
     encryption_key_handle: Arc<dyn AsyncEncryptionKeyHandle + Send + Sync>,
     observer: Option<Arc<dyn Observer + Send + Sync>>,
 }
@@ -61,7 +61,7 @@ impl<H: Handler> OakFunctionsContainersService<H> {
         Self { instance_config, instance: OnceLock::new(), encryption_key_handle, observer }
     }
 
-    fn get_instance(&self) -> tonic::Result<&OakFunctionsInstance<H>> {
+    fn get_instance(&self) -> tonic::Result<&Arc<OakFunctionsInstance<H>>> {
         self.instance.get().ok_or_else(|| tonic::Status::failed_precondition("not initialized"))
     }
 }
@@ -109,7 +109,7 @@ where
                     self.instance_config.clone(),
                 )
                 .map_err(map_status)?;
-                if self.instance.set(instance).is_err() {
+                if self.instance.set(Arc::new(instance)).is_err() {
                     return Err(tonic::Status::failed_precondition("already initialized"));
                 }
                 Ok(tonic::Response::new(InitializeResponse::default()))
@@ -121,7 +121,7 @@ where
         &self,
         request: tonic::Request<InvokeRequest>,
     ) -> tonic::Result<tonic::Response<InvokeResponse>> {
-        let instance = self.get_instance()?;
+        let instance = self.get_instance()?.clone();
 
         let encrypted_request = request.into_inner().encrypted_request.ok_or_else(|| {
             tonic::Status::invalid_argument(
@@ -129,11 +129,20 @@ where
             )
         })?;
 
-        AsyncEncryptionHandler::create(self.encryption_key_handle.clone(), |r| async {
+        AsyncEncryptionHandler::create(self.encryption_key_handle.clone(), |r| async move {
+            // The Wasm invocation and the constant processing time policy block the
+            // thread, so they must not run on a runtime worker thread.
+            let response_result: Result<Vec<u8>, micro_rpc::Status> =
+                tokio::task::spawn_blocking(move || instance.handle_user_request(r))
+                    .await
+                    .unwrap_or_else(|err| {
+                        Err(micro_rpc::Status::new_with_message(
+                            micro_rpc::StatusCode::Internal,
+                            format!("request handler failed: {:?}", err),
+                        ))
+                    });
             // Wrap the invocation result (which may be an Error) into a micro RPC Response
             // wrapper protobuf, and encode that as bytes.
-            let response_result: Result<Vec<u8>, micro_rpc::Status> =
-                instance.handle_user_request(r);
             let response: micro_rpc::ResponseWrapper = response_result.into();
             response.encode_to_vec()
         })
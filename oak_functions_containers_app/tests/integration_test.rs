@@ -64,7 +64,9 @@ async fn test_lookup() {
     let _ = oak_functions_client
         .initialize(InitializeRequest {
             constant_response_size: 1000,
+            constant_processing_time_ms: 0,
             wasm_module: fs::read(wasm_path).expect("failed to read wasm module"),
+            application_config: None,
         })
         .await
         .expect("failed to initialize Oak Functions");
@@ -74,6 +74,8 @@ async fn main() -> Result<(), anyhow::Error> {
         .initialize_enclave(InitializeRequest {
             wasm_module: wasm_bytes,
             constant_response_size: args.functions_args.constant_response_size,
+            constant_processing_time_ms: args.functions_args.constant_processing_time_ms,
+            application_config: None,
         })
         .await
         .map_err(|error| {
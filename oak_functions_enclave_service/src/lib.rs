@@ -19,18 +19,19 @@
 extern crate alloc;
 
 use alloc::{format, string::ToString, sync::Arc, vec::Vec};
+use core::time::Duration;
 
 use oak_core::sync::OnceCell;
 use oak_crypto::encryption_key::EncryptionKeyHandle;
-use oak_functions_service::{instance::OakFunctionsInstance, Handler, Observer};
+use oak_functions_service::{instance::OakFunctionsInstance, Clock, Handler, Observer};
 use oak_micro_rpc::oak::functions::OakFunctions;
 use oak_proto_rust::oak::functions::{
-    extend_next_lookup_data_request::Data, AbortNextLookupDataResponse, Empty,
-    ExtendNextLookupDataRequest, ExtendNextLookupDataResponse, FinishNextLookupDataRequest,
+    config::ApplicationConfig, extend_next_lookup_data_request::Data, AbortNextLookupDataResponse,
+    Empty, ExtendNextLookupDataRequest, ExtendNextLookupDataResponse, FinishNextLookupDataRequest,
     FinishNextLookupDataResponse, InitializeRequest, InitializeResponse, InvokeRequest,
     InvokeResponse, LookupDataChunk, ReserveRequest, ReserveResponse,
 };
-use oak_restricted_kernel_sdk::{handler::EncryptionHandler, Attester};
+use oak_restricted_kernel_sdk::{clock::SystemClock, handler::EncryptionHandler, Attester};
 use prost::Message;
 
 pub struct OakFunctionsService<EKH, A, H>
@@ -41,9 +42,9 @@ where
 {
     attester: A,
     encryption_key_handle: Arc<EKH>,
-    instance_config: H::HandlerConfig,
     instance: OnceCell<OakFunctionsInstance<H>>,
     observer: Option<Arc<dyn Observer + Send + Sync>>,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl<EKH, A, H> OakFunctionsService<EKH, A, H>
@@ -56,15 +57,15 @@ where
         attester: A,
         encryption_key_handle: Arc<EKH>,
         observer: Option<Arc<dyn Observer + Send + Sync>>,
-        instance_config: H::HandlerConfig,
     ) -> Self {
-        Self {
-            attester,
-            encryption_key_handle,
-            instance_config,
-            instance: OnceCell::new(),
-            observer,
-        }
+        Self { attester, encryption_key_handle, instance: OnceCell::new(), observer, clock: None }
+    }
+
+    /// Uses `clock` to enforce the constant processing time policy. Without a
+    /// clock, initializing an instance with that policy fails.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
     }
     fn get_instance(&self) -> Result<&OakFunctionsInstance<H>, micro_rpc::Status> {
         self.instance.get().ok_or_else(|| {
@@ -81,6 +82,7 @@ where
     EKH: EncryptionKeyHandle + 'static,
     A: Attester,
     H: Handler,
+    H::HandlerConfig: TryFrom<ApplicationConfig, Error = anyhow::Error>,
 {
     fn initialize(
         &self,
@@ -93,11 +95,26 @@ where
                 "already initialized",
             )),
             None => {
-                let instance = OakFunctionsInstance::new(
-                    &request,
-                    self.observer.clone(),
-                    self.instance_config.clone(),
-                )?;
+                let instance_config = H::HandlerConfig::try_from(
+                    request.application_config.clone().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    micro_rpc::Status::new_with_message(
+                        micro_rpc::StatusCode::InvalidArgument,
+                        format!("invalid application config: {:?}", err),
+                    )
+                })?;
+                let instance = match &self.clock {
+                    Some(clock) => OakFunctionsInstance::new_with_clock(
+                        &request,
+                        self.observer.clone(),
+                        instance_config,
+                        clock.clone(),
+                    )?,
+                    None => {
+                        OakFunctionsInstance::new(&request, self.observer.clone(), instance_config)?
+                    }
+                };
                 if self.instance.set(instance).is_err() {
                     return Err(micro_rpc::Status::new_with_message(
                         micro_rpc::StatusCode::FailedPrecondition,
@@ -199,3 +216,34 @@ where
         self.get_instance()?.reserve(request)
     }
 }
+
+/// [`Clock`] backed by the monotonic clock of Restricted Kernel.
+pub struct RestrictedKernelClock {
+    clock: SystemClock,
+}
+
+impl RestrictedKernelClock {
+    /// Creates a clock, failing if Restricted Kernel doesn't provide the time.
+    pub fn create() -> anyhow::Result<Self> {
+        SystemClock::new()
+            .map(|clock| Self { clock })
+            .map_err(|err| anyhow::anyhow!("couldn't read the kernel clock: {:?}", err))
+    }
+}
+
+impl Clock for RestrictedKernelClock {
+    fn now(&self) -> Duration {
+        // `SystemClock::new` checked that the kernel provides the time, after which
+        // reading it doesn't fail.
+        let millis = self.clock.monotonic_millis().expect("couldn't read the kernel clock");
+        Duration::from_millis(millis.try_into().expect("negative monotonic time"))
+    }
+
+    /// Restricted Kernel has no sleep syscall, so this spins on the clock.
+    fn sleep(&self, duration: Duration) {
+        let end = self.now() + duration;
+        while self.now() < end {
+            core::hint::spin_loop();
+        }
+    }
+}
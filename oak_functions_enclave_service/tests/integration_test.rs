@@ -25,6 +25,7 @@ use core::assert_matches::assert_matches;
 
 use oak_client::verifier::extract_encryption_public_key;
 use oak_crypto::encryptor::ClientEncryptor;
+use oak_functions_abi::{Response, StatusCode};
 use oak_functions_enclave_service::OakFunctionsService;
 use oak_micro_rpc::oak::functions::{
     testing::TestModuleClient, OakFunctionsClient, OakFunctionsServer,
@@ -62,7 +63,6 @@ fn new_service_for_testing() -> OakFunctionsService<
                 .expect("failed to create EncryptionKeyHandle"),
         ),
         None,
-        Default::default(),
     )
 }
 
@@ -99,6 +99,8 @@ fn it_should_handle_user_requests_after_initialization() {
     let request = InitializeRequest {
         wasm_module: wasm_bytes,
         constant_response_size: MOCK_CONSTANT_RESPONSE_SIZE,
+        constant_processing_time_ms: 0,
+        application_config: None,
     };
 
     let initialize_response = client.initialize(&request).into_ok().unwrap();
@@ -136,6 +138,8 @@ fn it_should_only_initialize_once() {
     let request = InitializeRequest {
         wasm_module: wasm_bytes,
         constant_response_size: MOCK_CONSTANT_RESPONSE_SIZE,
+        constant_processing_time_ms: 0,
+        application_config: None,
     };
     client.initialize(&request).into_ok().unwrap();
 
@@ -161,6 +165,8 @@ fn it_should_error_on_invalid_wasm_module() {
     let request = InitializeRequest {
         wasm_module: wasm_bytes,
         constant_response_size: MOCK_CONSTANT_RESPONSE_SIZE,
+        constant_processing_time_ms: 0,
+        application_config: None,
     };
 
     let initialize_response = client.initialize(&request).into_ok().unwrap();
@@ -229,6 +235,8 @@ fn it_should_support_lookup_data() {
     let request = InitializeRequest {
         wasm_module: wasm_bytes,
         constant_response_size: MOCK_CONSTANT_RESPONSE_SIZE,
+        constant_processing_time_ms: 0,
+        application_config: None,
     };
 
     let initialize_response = client.initialize(&request).into_ok().unwrap();
@@ -285,7 +293,15 @@ fn it_should_support_lookup_data() {
     let response_result: Result<Vec<u8>, micro_rpc::Status> = response.into();
     log::info!("response_result: {:?}", response_result);
 
-    assert_eq!(LOOKUP_TEST_VALUE, response_result.unwrap());
+    // The response is padded to the constant response size.
+    let response_bytes = response_result.unwrap();
+    assert_eq!(
+        response_bytes.len(),
+        oak_functions_abi::RESPONSE_BODY_OFFSET + MOCK_CONSTANT_RESPONSE_SIZE as usize
+    );
+    let response = Response::decode(&response_bytes).expect("couldn't decode response");
+    assert_eq!(response.status, StatusCode::Success);
+    assert_eq!(LOOKUP_TEST_VALUE, response.body().unwrap());
 }
 
 #[test]
@@ -304,6 +320,8 @@ fn it_should_handle_wasm_panic() {
     let request = InitializeRequest {
         wasm_module: wasm_bytes,
         constant_response_size: MOCK_CONSTANT_RESPONSE_SIZE,
+        constant_processing_time_ms: 0,
+        application_config: None,
     };
 
     let initialize_response = client.initialize(&request).into_ok().unwrap();
@@ -378,7 +396,9 @@ fn it_should_handle_wasm_panic() {
 
             log::debug!("response inner result: {:?}", response_result);
 
-            Ok(response_result.unwrap())
+            let response =
+                Response::decode(&response_result.unwrap()).expect("couldn't decode response");
+            Ok(response.body().unwrap().to_vec())
         }
     }
 
@@ -23,6 +23,7 @@ use std::path::PathBuf;
 
 use oak_client::verifier::extract_encryption_public_key;
 use oak_crypto::encryptor::ClientEncryptor;
+use oak_functions_abi::Response;
 use oak_functions_launcher::LookupDataConfig;
 use oak_launcher_utils::launcher;
 use oak_micro_rpc::oak::functions::OakFunctionsAsyncClient;
@@ -78,6 +79,8 @@ fn run_bench(b: &mut Bencher, config: &OakFunctionsTestConfig) {
             lookup_data_config,
             config.wasm_path.to_path_buf(),
             constant_response_size,
+            0,
+            Default::default(),
         ))
         .expect("Failed to create launcher");
     log::info!("created launcher instance");
@@ -115,7 +118,9 @@ fn run_bench(b: &mut Bencher, config: &OakFunctionsTestConfig) {
             micro_rpc::ResponseWrapper::decode(decrypted_response.as_ref())
                 .expect("could not decode response")
                 .into();
-        assert_eq!(response.unwrap(), config.expected_response);
+        let response =
+            Response::decode(&response.unwrap()).expect("could not decode Oak Functions response");
+        assert_eq!(response.body().unwrap(), config.expected_response);
     }
 
     // We need to make sure to block on the future returned by
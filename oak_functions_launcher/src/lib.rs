@@ -30,7 +30,10 @@ use oak_launcher_utils::{
     launcher,
 };
 use oak_micro_rpc::oak::functions::OakFunctionsAsyncClient;
-use oak_proto_rust::oak::functions::{InitializeRequest, InitializeResponse};
+use oak_proto_rust::oak::functions::{
+    config::{ApplicationConfig, WasmiConfig},
+    InitializeRequest, InitializeResponse,
+};
 use ubyte::ByteUnit;
 
 #[derive(Parser, Debug)]
@@ -40,6 +43,21 @@ pub struct Args {
     #[arg(long, default_value = "1024")]
     pub constant_response_size: u32,
 
+    /// Consistent processing time, in milliseconds, that the enclave should
+    /// apply to every response
+    #[arg(long, default_value = "0")]
+    pub constant_processing_time_ms: u32,
+
+    /// Amount of fuel available to each invocation of the Wasm module. If not
+    /// specified, fuel is not metered.
+    #[arg(long)]
+    pub fuel_per_invocation: Option<u64>,
+
+    /// Maximum size of the linear memory of the Wasm module, in bytes. If not
+    /// specified, the size is only bounded by the Wasm specification.
+    #[arg(long)]
+    pub max_memory_bytes: Option<u64>,
+
     #[arg(long, default_value = "8080")]
     pub port: u16,
 
@@ -59,6 +77,19 @@ pub struct Args {
     pub lookup_data: PathBuf,
 }
 
+impl Args {
+    /// Returns the application config to send to the enclave.
+    pub fn application_config(&self) -> ApplicationConfig {
+        ApplicationConfig {
+            wasmi_config: Some(WasmiConfig {
+                fuel_per_invocation: self.fuel_per_invocation,
+                max_memory_bytes: self.max_memory_bytes,
+            }),
+            ..Default::default()
+        }
+    }
+}
+
 fn path_exists(s: &str) -> Result<PathBuf, String> {
     let path = PathBuf::from(s);
     if !fs::metadata(s).map_err(|err| err.to_string())?.is_file() {
@@ -80,14 +111,22 @@ pub async fn create(
     lookup_data_config: LookupDataConfig,
     wasm_path: PathBuf,
     constant_response_size: u32,
+    constant_processing_time_ms: u32,
+    application_config: ApplicationConfig,
 ) -> Result<
     (Box<dyn launcher::GuestInstance>, channel::ConnectorHandle, InitializeResponse),
     Box<dyn std::error::Error>,
 > {
     log::info!("creating Oak Functions guest instance");
     let (launched_instance, connector_handle) = launcher::launch(params).await?;
-    let intialize_response =
-        intialize_enclave(connector_handle.clone(), &wasm_path, constant_response_size).await?;
+    let intialize_response = intialize_enclave(
+        connector_handle.clone(),
+        &wasm_path,
+        constant_response_size,
+        constant_processing_time_ms,
+        application_config,
+    )
+    .await?;
     setup_lookup_data(connector_handle.clone(), lookup_data_config).await?;
     Ok((launched_instance, connector_handle, intialize_response))
 }
@@ -146,6 +185,8 @@ async fn intialize_enclave(
     connector_handle: channel::ConnectorHandle,
     wasm: &PathBuf,
     constant_response_size: u32,
+    constant_processing_time_ms: u32,
+    application_config: ApplicationConfig,
 ) -> Result<InitializeResponse, Box<dyn std::error::Error>> {
     let wasm_bytes = fs::read(wasm)
         .with_context(|| format!("couldn't read Wasm file {}", wasm.display()))
@@ -156,7 +197,12 @@ async fn intialize_enclave(
         ubyte::ByteUnit::Byte(wasm_bytes.len() as u64)
     );
 
-    let request = InitializeRequest { wasm_module: wasm_bytes, constant_response_size };
+    let request = InitializeRequest {
+        wasm_module: wasm_bytes,
+        constant_response_size,
+        constant_processing_time_ms,
+        application_config: Some(application_config),
+    };
 
     let mut client = OakFunctionsAsyncClient::new(connector_handle);
     log::info!("sending initialize request");
@@ -52,12 +52,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         max_chunk_size: ByteUnit::Gibibyte(2),
     };
 
+    let application_config = cli.functions_params.application_config();
     let (mut launched_instance, connector_handle, initialize_response) =
         oak_functions_launcher::create(
             cli.launcher_params,
             lookup_data_config,
             cli.functions_params.wasm,
             cli.functions_params.constant_response_size,
+            cli.functions_params.constant_processing_time_ms,
+            application_config,
         )
         .await?;
 
@@ -115,10 +115,16 @@ async fn test_load_large_lookup_data() {
         max_chunk_size,
     };
     let wasm_path = oak_functions_test_utils::rust_crate_wasm_out_path("key_value_lookup");
-    let (launched_instance, connector_handle, _) =
-        oak_functions_launcher::create(params, lookup_data_config, wasm_path.into(), 1024)
-            .await
-            .unwrap();
+    let (launched_instance, connector_handle, _) = oak_functions_launcher::create(
+        params,
+        lookup_data_config,
+        wasm_path.into(),
+        1024,
+        0,
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     let mut client = OakFunctionsAsyncClient::new(connector_handle);
 
@@ -187,7 +193,14 @@ async fn test_load_two_gib_lookup_data() {
         max_chunk_size,
     };
     let wasm_path = oak_functions_test_utils::rust_crate_wasm_out_path("key_value_lookup");
-    let status =
-        oak_functions_launcher::create(params, lookup_data_config, wasm_path.into(), 1024).await;
+    let status = oak_functions_launcher::create(
+        params,
+        lookup_data_config,
+        wasm_path.into(),
+        1024,
+        0,
+        Default::default(),
+    )
+    .await;
     assert!(status.is_ok());
 }
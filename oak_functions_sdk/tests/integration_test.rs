@@ -21,7 +21,7 @@ use oak_functions_abi::{Request, Response};
 use oak_functions_service::{
     logger::StandaloneLogger,
     lookup::LookupDataManager,
-    wasm::{api::StdWasmApiFactory, WasmHandler},
+    wasm::{api::StdWasmApiFactory, WasmConfig, WasmHandler},
     Handler,
 };
 
@@ -53,9 +53,14 @@ async fn test_read_write() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"ReadWrite".to_vec() };
     let response: Response = wasm_handler.handle_invoke(request).unwrap();
@@ -69,9 +74,14 @@ async fn test_double_read() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"DoubleRead".to_vec() };
     let response: Response = wasm_handler.handle_invoke(request).unwrap();
@@ -85,9 +95,14 @@ async fn test_double_write() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"DoubleWrite".to_vec() };
     let response: Response = wasm_handler.handle_invoke(request).unwrap();
@@ -101,9 +116,14 @@ async fn test_write_log() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"WriteLog".to_vec() };
     let response: Response = wasm_handler.handle_invoke(request).unwrap();
@@ -118,9 +138,14 @@ async fn test_storage_get_item() {
     let lookup_data_manager = Arc::new(LookupDataManager::<1>::for_test(entries, logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"StorageGet".to_vec() };
     let response: Response = wasm_handler.handle_invoke(request).unwrap();
@@ -136,9 +161,14 @@ async fn test_storage_get_item_not_found() {
     let lookup_data_manager = Arc::new(LookupDataManager::<1>::for_test(entries, logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"StorageGetItemNotFound".to_vec() };
     let response: Response = wasm_handler.handle_invoke(request).unwrap();
@@ -155,9 +185,14 @@ async fn test_storage_get_item_huge_key() {
     let lookup_data_manager = Arc::new(LookupDataManager::<1>::for_test(entries, logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&LOOKUP_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &LOOKUP_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: b"LargeKey".to_vec() };
 
@@ -174,9 +209,14 @@ async fn test_echo() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&TESTING_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &TESTING_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: message_to_echo.as_bytes().to_vec() };
 
@@ -196,9 +236,14 @@ async fn test_blackhole() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&TESTING_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &TESTING_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: message_to_blackhole.as_bytes().to_vec() };
 
@@ -218,9 +263,14 @@ async fn test_huge_response() {
         Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
     let api_factory = StdWasmApiFactory { lookup_data_manager };
 
-    let wasm_handler =
-        WasmHandler::create(&TESTING_WASM_MODULE_BYTES, Arc::new(api_factory), logger, None)
-            .expect("couldn't instantiate WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &TESTING_WASM_MODULE_BYTES,
+        WasmConfig::default(),
+        Arc::new(api_factory),
+        logger,
+        None,
+    )
+    .expect("couldn't instantiate WasmHandler");
 
     let request = Request { body: "HUGE_RESPONSE".as_bytes().to_vec() };
 
@@ -15,9 +15,10 @@
 //
 
 use alloc::{format, sync::Arc};
+use core::time::Duration;
 
 use micro_rpc::{Status, Vec};
-use oak_functions_abi::Request;
+use oak_functions_abi::{Request, Response, StatusCode};
 use oak_proto_rust::oak::functions::{
     extend_next_lookup_data_request::Data, AbortNextLookupDataResponse, Empty,
     ExtendNextLookupDataRequest, ExtendNextLookupDataResponse, FinishNextLookupDataRequest,
@@ -26,19 +27,56 @@ use oak_proto_rust::oak::functions::{
 };
 use prost::Message;
 
-use crate::{logger::StandaloneLogger, lookup::LookupDataManager, Handler, Observer};
+use crate::{logger::StandaloneLogger, lookup::LookupDataManager, Clock, Handler, Observer};
 
 pub struct OakFunctionsInstance<H: Handler> {
     lookup_data_manager: Arc<LookupDataManager<16>>,
     wasm_handler: H::HandlerType,
+    /// Size that the body of every response gets padded to. Zero disables
+    /// padding.
+    constant_response_size: usize,
+    /// Minimum amount of time it takes to return every response, and the clock
+    /// to measure it with. `None` disables the delay.
+    constant_processing_time: Option<(Duration, Arc<dyn Clock>)>,
 }
 
 impl<H: Handler> OakFunctionsInstance<H> {
     /// See [`crate::proto::oak::functions::OakFunctions::initialize`].
+    ///
+    /// With std, the constant processing time is measured with [`StdClock`].
+    /// Without std there is no default clock, so a constant processing time
+    /// policy is rejected; use [`OakFunctionsInstance::new_with_clock`]
+    /// instead.
+    ///
+    /// [`StdClock`]: crate::StdClock
     pub fn new(
         request: &InitializeRequest,
         observer: Option<Arc<dyn Observer + Send + Sync>>,
         config: H::HandlerConfig,
+    ) -> Result<Self, micro_rpc::Status> {
+        #[cfg(feature = "std")]
+        let clock = Some(Arc::new(crate::StdClock::default()) as Arc<dyn Clock>);
+        #[cfg(not(feature = "std"))]
+        let clock = None;
+        Self::create(request, observer, config, clock)
+    }
+
+    /// Same as [`OakFunctionsInstance::new`], but measures the constant
+    /// processing time with the supplied clock.
+    pub fn new_with_clock(
+        request: &InitializeRequest,
+        observer: Option<Arc<dyn Observer + Send + Sync>>,
+        config: H::HandlerConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, micro_rpc::Status> {
+        Self::create(request, observer, config, Some(clock))
+    }
+
+    fn create(
+        request: &InitializeRequest,
+        observer: Option<Arc<dyn Observer + Send + Sync>>,
+        config: H::HandlerConfig,
+        clock: Option<Arc<dyn Clock>>,
     ) -> Result<Self, micro_rpc::Status> {
         let lookup_data_manager =
             Arc::new(LookupDataManager::new_empty(Arc::new(StandaloneLogger)));
@@ -50,13 +88,110 @@ impl<H: Handler> OakFunctionsInstance<H> {
                         format!("couldn't initialize Wasm handler: {:?}", err),
                     )
                 })?;
-        Ok(Self { lookup_data_manager, wasm_handler })
+        let constant_response_size =
+            usize::try_from(request.constant_response_size).map_err(|err| {
+                micro_rpc::Status::new_with_message(
+                    micro_rpc::StatusCode::InvalidArgument,
+                    format!("invalid constant response size: {:?}", err),
+                )
+            })?;
+        let constant_processing_time = match request.constant_processing_time_ms {
+            0 => None,
+            // Without a clock the policy cannot be enforced, so refuse to start rather
+            // than silently leaking the processing time.
+            constant_processing_time_ms => Some((
+                Duration::from_millis(constant_processing_time_ms.into()),
+                clock.ok_or_else(|| {
+                    micro_rpc::Status::new_with_message(
+                        micro_rpc::StatusCode::InvalidArgument,
+                        "constant processing time policy requires a clock",
+                    )
+                })?,
+            )),
+        };
+        Ok(Self {
+            lookup_data_manager,
+            wasm_handler,
+            constant_response_size,
+            constant_processing_time,
+        })
     }
+
     /// See [`crate::proto::oak::functions::OakFunctions::handle_user_request`].
+    ///
+    /// Returns the response encoded as described in
+    /// `REQUEST_RESPONSE_ENCODING.md` in the `oak_functions_abi` crate,
+    /// with the body padded to the constant response size and the reply
+    /// held back until the constant processing time has elapsed, if the
+    /// respective policies are enabled. While the constant response size
+    /// policy is enabled, errors are returned as padded error responses too.
+    ///
+    /// This blocks the calling thread for the duration of the Wasm invocation
+    /// and the constant processing time, so async callers should run it on a
+    /// blocking thread.
     pub fn handle_user_request(&self, request: Vec<u8>) -> Result<Vec<u8>, micro_rpc::Status> {
-        // TODO(#3442): Implement constant response size policy.
-        self.wasm_handler.handle_invoke(Request { body: request }).map(|response| response.body)
+        let deadline = self
+            .constant_processing_time
+            .as_ref()
+            .map(|(duration, clock)| (clock.now() + *duration, clock));
+
+        let result = self.wasm_handler.handle_invoke(Request { body: request });
+
+        // An error status would reveal that the request failed through the size of
+        // the reply, so it is turned into an error response which is padded like
+        // any other response.
+        let result = match result {
+            Err(status) if self.constant_response_size != 0 => Ok(self.error_response(status)),
+            result => result,
+        };
+
+        let result = result.map(|response| match deadline {
+            Some((deadline, clock)) if clock.now() > deadline => Response::create(
+                StatusCode::PolicyTimeViolation,
+                b"response took longer than the constant processing time".to_vec(),
+            ),
+            _ => response,
+        });
+
+        let result = result.map(|response| self.pad(response).encode_to_vec());
+
+        if let Some((deadline, clock)) = deadline {
+            if let Some(remaining) = deadline.checked_sub(clock.now()) {
+                clock.sleep(remaining);
+            }
+        }
+
+        result
+    }
+
+    /// Converts an error status into an error response whose body fits the
+    /// constant response size.
+    fn error_response(&self, status: micro_rpc::Status) -> Response {
+        let status_code = match status.code {
+            micro_rpc::StatusCode::InvalidArgument => StatusCode::BadRequest,
+            _ => StatusCode::InternalServerError,
+        };
+        let mut body = status.message.into_bytes();
+        body.truncate(self.constant_response_size);
+        Response::create(status_code, body)
     }
+
+    /// Pads the body of the response to the constant response size. Responses
+    /// that are too large are replaced with an error response of the same
+    /// size.
+    fn pad(&self, response: Response) -> Response {
+        if self.constant_response_size == 0 {
+            return response;
+        }
+        response.pad(self.constant_response_size).unwrap_or_else(|_| {
+            let mut body = b"response is larger than the constant response size".to_vec();
+            body.truncate(self.constant_response_size);
+            Response::create(StatusCode::PolicySizeViolation, body)
+                .pad(self.constant_response_size)
+                .expect("truncated error message should fit the constant response size")
+        })
+    }
+
     /// See [`crate::proto::oak::functions::OakFunctions::extend_next_lookup_data`].
     pub fn extend_next_lookup_data(
         &self,
@@ -126,10 +261,73 @@ fn to_data(chunk: &LookupDataChunk) -> impl Iterator<Item = (&[u8], &[u8])> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::{string::String, vec};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    #[cfg(feature = "std")]
+    use std::time::Instant;
+
     use bytes::{Bytes, BytesMut};
+    use oak_functions_abi::RESPONSE_BODY_OFFSET;
 
     use super::*;
-    use crate::wasm::{WasmConfig, WasmHandler};
+    use crate::{
+        wasm::{WasmConfig, WasmHandler},
+        Observer,
+    };
+
+    const CONSTANT_RESPONSE_SIZE: u32 = 64;
+    const FAILURE_PREFIX: &[u8] = b"fail:";
+
+    /// A [`Handler`] that echoes the request after the configured delay, so
+    /// that the response policies can be tested without a Wasm module.
+    /// Requests starting with `FAILURE_PREFIX` fail with the rest of the
+    /// request as the error message.
+    struct EchoHandler {
+        delay: Duration,
+    }
+
+    impl Handler for EchoHandler {
+        type HandlerType = EchoHandler;
+        type HandlerConfig = Duration;
+
+        fn new_handler(
+            delay: Duration,
+            _wasm_module_bytes: &[u8],
+            _lookup_data_manager: Arc<LookupDataManager<16>>,
+            _observer: Option<Arc<dyn Observer + Send + Sync>>,
+        ) -> anyhow::Result<EchoHandler> {
+            Ok(EchoHandler { delay })
+        }
+
+        fn handle_invoke(&self, invoke_request: Request) -> Result<Response, micro_rpc::Status> {
+            std::thread::sleep(self.delay);
+            if let Some(message) = invoke_request.body.strip_prefix(FAILURE_PREFIX) {
+                return Err(micro_rpc::Status::new_with_message(
+                    micro_rpc::StatusCode::Internal,
+                    String::from_utf8(message.to_vec()).unwrap(),
+                ));
+            }
+            Ok(Response::create(StatusCode::Success, invoke_request.body))
+        }
+    }
+
+    fn new_echo_instance(
+        constant_response_size: u32,
+        constant_processing_time_ms: u32,
+        delay: Duration,
+    ) -> OakFunctionsInstance<EchoHandler> {
+        OakFunctionsInstance::<EchoHandler>::new(
+            &InitializeRequest {
+                wasm_module: Vec::new(),
+                constant_response_size,
+                constant_processing_time_ms,
+                application_config: None,
+            },
+            None,
+            delay,
+        )
+        .unwrap()
+    }
 
     static ITEMS: [LookupDataEntry; 2] = [
         LookupDataEntry { key: Bytes::from_static(b"key1"), value: Bytes::from_static(b"value1") },
@@ -146,7 +344,12 @@ mod tests {
         let wasm_module = std::fs::read(wasm_module_path).unwrap();
 
         let instance = OakFunctionsInstance::<WasmHandler>::new(
-            &InitializeRequest { wasm_module, constant_response_size: 0 },
+            &InitializeRequest {
+                wasm_module,
+                constant_response_size: 0,
+                constant_processing_time_ms: 0,
+                application_config: None,
+            },
             None,
             WasmConfig::default(),
         )
@@ -175,7 +378,12 @@ mod tests {
         let wasm_module = std::fs::read(wasm_module_path).unwrap();
 
         let instance = OakFunctionsInstance::<WasmHandler>::new(
-            &InitializeRequest { wasm_module, constant_response_size: 0 },
+            &InitializeRequest {
+                wasm_module,
+                constant_response_size: 0,
+                constant_processing_time_ms: 0,
+                application_config: None,
+            },
             None,
             WasmConfig::default(),
         )
@@ -198,4 +406,166 @@ mod tests {
         }
         assert_eq!(None, lookup_data.get(b"key3"));
     }
+
+    #[test]
+    fn test_response_is_padded() {
+        let instance = new_echo_instance(CONSTANT_RESPONSE_SIZE, 0, Duration::ZERO);
+
+        let response_bytes = instance.handle_user_request(b"hello".to_vec()).unwrap();
+
+        assert_eq!(response_bytes.len(), RESPONSE_BODY_OFFSET + CONSTANT_RESPONSE_SIZE as usize);
+        let response = Response::decode(&response_bytes).unwrap();
+        assert_eq!(response.status, StatusCode::Success);
+        assert_eq!(response.body().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_responses_have_constant_size() {
+        let instance = new_echo_instance(CONSTANT_RESPONSE_SIZE, 0, Duration::ZERO);
+
+        let sizes: Vec<usize> = [0, 1, 10, CONSTANT_RESPONSE_SIZE as usize, 1000]
+            .into_iter()
+            .map(|len| instance.handle_user_request(vec![b'x'; len]).unwrap().len())
+            .collect();
+
+        assert!(sizes
+            .iter()
+            .all(|&size| size == RESPONSE_BODY_OFFSET + CONSTANT_RESPONSE_SIZE as usize));
+    }
+
+    #[test]
+    fn test_oversize_response_is_replaced() {
+        let instance = new_echo_instance(CONSTANT_RESPONSE_SIZE, 0, Duration::ZERO);
+
+        let response_bytes =
+            instance.handle_user_request(vec![b'x'; CONSTANT_RESPONSE_SIZE as usize + 1]).unwrap();
+
+        assert_eq!(response_bytes.len(), RESPONSE_BODY_OFFSET + CONSTANT_RESPONSE_SIZE as usize);
+        let response = Response::decode(&response_bytes).unwrap();
+        assert_eq!(response.status, StatusCode::PolicySizeViolation);
+        assert!(!response.body().unwrap().contains(&b'x'));
+    }
+
+    #[test]
+    fn test_errors_are_padded() {
+        let instance = new_echo_instance(CONSTANT_RESPONSE_SIZE, 0, Duration::ZERO);
+
+        let sizes: Vec<usize> = [0, 10, 1000]
+            .into_iter()
+            .map(|len| {
+                let request = [FAILURE_PREFIX, &vec![b'x'; len]].concat();
+                let response_bytes = instance.handle_user_request(request).unwrap();
+                let response = Response::decode(&response_bytes).unwrap();
+                assert_eq!(response.status, StatusCode::InternalServerError);
+                response_bytes.len()
+            })
+            .collect();
+
+        assert!(sizes
+            .iter()
+            .all(|&size| size == RESPONSE_BODY_OFFSET + CONSTANT_RESPONSE_SIZE as usize));
+    }
+
+    #[test]
+    fn test_error_is_returned_when_padding_disabled() {
+        let instance = new_echo_instance(0, 0, Duration::ZERO);
+
+        let request = [FAILURE_PREFIX, b"oops"].concat();
+        let status = instance.handle_user_request(request).unwrap_err();
+
+        assert_eq!(status.code, micro_rpc::StatusCode::Internal);
+        assert_eq!(status.message, "oops");
+    }
+
+    #[test]
+    fn test_response_is_not_padded_when_disabled() {
+        let instance = new_echo_instance(0, 0, Duration::ZERO);
+
+        let response_bytes = instance.handle_user_request(b"hello".to_vec()).unwrap();
+
+        let response = Response::decode(&response_bytes).unwrap();
+        assert_eq!(response.status, StatusCode::Success);
+        assert_eq!(response.body, b"hello");
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn test_constant_processing_time_is_rejected_without_std() {
+        let result = OakFunctionsInstance::<EchoHandler>::new(
+            &InitializeRequest {
+                wasm_module: Vec::new(),
+                constant_response_size: 0,
+                constant_processing_time_ms: 200,
+                application_config: None,
+            },
+            None,
+            Duration::ZERO,
+        );
+
+        assert_eq!(result.err().unwrap().code, micro_rpc::StatusCode::InvalidArgument);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_response_is_delayed() {
+        let instance = new_echo_instance(CONSTANT_RESPONSE_SIZE, 200, Duration::ZERO);
+
+        let start = Instant::now();
+        let response_bytes = instance.handle_user_request(b"hello".to_vec()).unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let response = Response::decode(&response_bytes).unwrap();
+        assert_eq!(response.status, StatusCode::Success);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_slow_response_is_replaced() {
+        let instance = new_echo_instance(CONSTANT_RESPONSE_SIZE, 10, Duration::from_millis(100));
+
+        let response_bytes = instance.handle_user_request(b"hello".to_vec()).unwrap();
+
+        assert_eq!(response_bytes.len(), RESPONSE_BODY_OFFSET + CONSTANT_RESPONSE_SIZE as usize);
+        let response = Response::decode(&response_bytes).unwrap();
+        assert_eq!(response.status, StatusCode::PolicyTimeViolation);
+    }
+
+    /// A [`Clock`] that only advances when it sleeps.
+    #[derive(Default)]
+    struct FakeClock {
+        now_ms: AtomicU64,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            Duration::from_millis(self.now_ms.load(Ordering::SeqCst))
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.now_ms.fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_response_is_delayed_with_clock() {
+        let clock = Arc::new(FakeClock::default());
+        let instance = OakFunctionsInstance::<EchoHandler>::new_with_clock(
+            &InitializeRequest {
+                wasm_module: Vec::new(),
+                constant_response_size: CONSTANT_RESPONSE_SIZE,
+                constant_processing_time_ms: 200,
+                application_config: None,
+            },
+            None,
+            Duration::ZERO,
+            clock.clone(),
+        )
+        .unwrap();
+
+        let response_bytes = instance.handle_user_request(b"hello".to_vec()).unwrap();
+
+        assert_eq!(clock.now(), Duration::from_millis(200));
+        let response = Response::decode(&response_bytes).unwrap();
+        assert_eq!(response.status, StatusCode::Success);
+    }
 }
@@ -29,7 +29,7 @@ use oak_functions_abi::{Request, Response};
 extern crate alloc;
 extern crate rand_core;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
 pub mod instance;
@@ -43,6 +43,40 @@ pub trait Observer {
     fn wasm_invocation(&self, duration: core::time::Duration);
 }
 
+/// Monotonic time source used to enforce the constant processing time policy.
+pub trait Clock: Send + Sync {
+    /// Returns the time elapsed since an unspecified point in the past. The
+    /// returned value never decreases.
+    fn now(&self) -> core::time::Duration;
+
+    /// Blocks the calling thread until at least `duration` has passed.
+    fn sleep(&self, duration: core::time::Duration);
+}
+
+/// [`Clock`] backed by the monotonic clock of the standard library.
+#[cfg(feature = "std")]
+pub struct StdClock {
+    origin: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self { origin: std::time::Instant::now() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&self) -> core::time::Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: core::time::Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
 pub trait Handler {
     type HandlerType: Handler;
     type HandlerConfig: Default + Send + Sync + Clone;
@@ -36,7 +36,8 @@ use api::StdWasmApiFactory;
 use log::Level;
 use micro_rpc::StatusCode;
 use oak_functions_abi::{Request, Response};
-use wasmi::Store;
+use oak_proto_rust::oak::functions::config::ApplicationConfig;
+use wasmi::{core::TrapCode, Store};
 
 use crate::{
     logger::{OakLogger, StandaloneLogger},
@@ -69,6 +70,38 @@ pub type AbiPointerOffset = u32;
 pub struct UserState {
     wasm_api_transport: Box<dyn micro_rpc::Transport<Error = !>>,
     logger: Arc<dyn OakLogger>,
+    memory_limiter: MemoryLimiter,
+}
+
+/// Caps the size of the linear memory of a Wasm module and remembers whether
+/// the module tried to grow the memory beyond the cap.
+struct MemoryLimiter {
+    max_memory_bytes: usize,
+    limit_exceeded: bool,
+}
+
+impl wasmi::ResourceLimiter for MemoryLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool, wasmi::errors::MemoryError> {
+        if desired > self.max_memory_bytes {
+            self.limit_exceeded = true;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> Result<bool, wasmi::errors::TableError> {
+        Ok(true)
+    }
 }
 
 /// Stubs a Wasm imported function in the provided linker.
@@ -102,7 +135,11 @@ impl UserState {
         wasm_api_transport: Box<dyn micro_rpc::Transport<Error = !>>,
         logger: Arc<dyn OakLogger>,
     ) -> Self {
-        UserState { wasm_api_transport, logger }
+        UserState {
+            wasm_api_transport,
+            logger,
+            memory_limiter: MemoryLimiter { max_memory_bytes: usize::MAX, limit_exceeded: false },
+        }
     }
 
     // Use an `OakLogger` to log.
@@ -384,12 +421,41 @@ impl<'a> OakCaller<'a> {
     }
 }
 
+/// Per-invocation limits on the resources used by a Wasm module.
+///
+/// Wasmi cannot interrupt execution based on wall-clock time, so fuel is the
+/// only way to bound the execution time of a module.
 #[derive(Clone, Default)]
-pub struct WasmConfig {}
+pub struct WasmConfig {
+    /// Amount of fuel available to each invocation, including the
+    /// instantiation of the module. Invocations that run out of fuel fail with
+    /// [`StatusCode::ResourceExhausted`]. If unset, fuel is not metered.
+    pub fuel_per_invocation: Option<u64>,
+    /// Maximum size of the linear memory of the module, in bytes. Invocations
+    /// that try to grow the memory beyond this size fail with
+    /// [`StatusCode::ResourceExhausted`]. If unset, the size is only bounded
+    /// by the Wasm specification.
+    pub max_memory_bytes: Option<usize>,
+}
+
+impl TryFrom<ApplicationConfig> for WasmConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(config: ApplicationConfig) -> anyhow::Result<Self> {
+        let wasmi_config = config.wasmi_config.unwrap_or_default();
+        let max_memory_bytes = wasmi_config
+            .max_memory_bytes
+            .map(usize::try_from)
+            .transpose()
+            .map_err(|err| anyhow::anyhow!("invalid maximum memory size: {:?}", err))?;
+        Ok(WasmConfig { fuel_per_invocation: wasmi_config.fuel_per_invocation, max_memory_bytes })
+    }
+}
 
 // A request handler with a Wasm module for handling multiple requests.
 pub struct WasmHandler {
     wasm_module: Arc<wasmi::Module>,
+    config: WasmConfig,
     linker: OakLinker,
     wasm_api_factory: Arc<dyn WasmApiFactory + Send + Sync>,
     logger: Arc<dyn OakLogger>,
@@ -416,11 +482,14 @@ pub trait WasmApi {
 impl WasmHandler {
     pub fn create(
         wasm_module_bytes: &[u8],
+        config: WasmConfig,
         wasm_api_factory: Arc<dyn WasmApiFactory + Send + Sync>,
         logger: Arc<dyn OakLogger>,
         observer: Option<Arc<dyn Observer + Send + Sync>>,
     ) -> anyhow::Result<Self> {
-        let engine = wasmi::Engine::default();
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(config.fuel_per_invocation.is_some());
+        let engine = wasmi::Engine::new(&engine_config);
         let module = wasmi::Module::new(&engine, wasm_module_bytes)
             .map_err(|err| anyhow::anyhow!("couldn't load module from buffer: {:?}", err))?;
 
@@ -428,6 +497,7 @@ impl WasmHandler {
 
         Ok(WasmHandler {
             wasm_module: Arc::new(module),
+            config,
             linker,
             wasm_api_factory,
             logger,
@@ -441,7 +511,7 @@ impl Handler for WasmHandler {
     type HandlerConfig = WasmConfig;
 
     fn new_handler(
-        _config: WasmConfig,
+        config: WasmConfig,
         wasm_module_bytes: &[u8],
         lookup_data_manager: Arc<LookupDataManager<16>>,
         observer: Option<Arc<dyn Observer + Send + Sync>>,
@@ -449,7 +519,7 @@ impl Handler for WasmHandler {
         let logger = Arc::new(StandaloneLogger);
         let wasm_api_factory = Arc::new(StdWasmApiFactory { lookup_data_manager });
 
-        Self::create(wasm_module_bytes, wasm_api_factory, logger, observer)
+        Self::create(wasm_module_bytes, config, wasm_api_factory, logger, observer)
     }
 
     fn handle_invoke(&self, invoke_request: Request) -> Result<Response, micro_rpc::Status> {
@@ -464,7 +534,22 @@ impl Handler for WasmHandler {
             let user_state = UserState::new(wasm_api.transport(), self.logger.clone());
             // For isolated requests we need to create a new store for every request.
             let mut store = wasmi::Store::new(module.engine(), user_state);
-            let instance = self.linker.instantiate(&mut store, module)?;
+            if let Some(fuel) = self.config.fuel_per_invocation {
+                store.add_fuel(fuel).map_err(|err| {
+                    micro_rpc::Status::new_with_message(
+                        micro_rpc::StatusCode::Internal,
+                        format!("couldn't add fuel to the store: {:?}", err),
+                    )
+                })?;
+            }
+            if let Some(max_memory_bytes) = self.config.max_memory_bytes {
+                store.data_mut().memory_limiter.max_memory_bytes = max_memory_bytes;
+                store.limiter(|user_state| &mut user_state.memory_limiter);
+            }
+            let instance = self
+                .linker
+                .instantiate(&mut store, module)
+                .map_err(|err| check_memory_limit(&store).unwrap_or(err))?;
 
             #[cfg(not(feature = "deny_sensitive_logging"))]
             instance.exports(&store).for_each(|export| {
@@ -486,13 +571,28 @@ impl Handler for WasmHandler {
             // included in the metric.
             #[cfg(feature = "std")]
             let now = Instant::now();
-            #[allow(unused)]
             let result = main.call(&mut store, ());
             #[cfg(feature = "std")]
             if let Some(ref observer) = self.observer {
                 observer.wasm_invocation(now.elapsed());
             }
 
+            // Exceeding a limit fails the invocation. Other traps (e.g. panics in
+            // the module) are ignored, and the response is whatever the module
+            // wrote before trapping.
+            if let Some(status) = check_memory_limit(&store) {
+                return Err(status);
+            }
+            if result
+                .as_ref()
+                .is_err_and(|trap| matches!(trap.trap_code(), Some(TrapCode::OutOfFuel)))
+            {
+                return Err(micro_rpc::Status::new_with_message(
+                    micro_rpc::StatusCode::ResourceExhausted,
+                    "Wasm module ran out of fuel",
+                ));
+            }
+
             #[cfg(not(feature = "deny_sensitive_logging"))]
             self.logger.log_sensitive(
                 Level::Info,
@@ -510,6 +610,17 @@ impl Handler for WasmHandler {
     }
 }
 
+/// Returns an error if the Wasm module tried to grow its memory beyond the
+/// configured maximum size.
+fn check_memory_limit(store: &Store<UserState>) -> Option<micro_rpc::Status> {
+    store.data().memory_limiter.limit_exceeded.then(|| {
+        micro_rpc::Status::new_with_message(
+            micro_rpc::StatusCode::ResourceExhausted,
+            "Wasm module exceeded the memory limit",
+        )
+    })
+}
+
 /// A helper function to move between our specific result type `Result<(),
 /// StatusCode>` and the `wasmi` specific result type `Result<i32,
 /// wasmi::Trap>`.
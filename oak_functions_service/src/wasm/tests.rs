@@ -20,10 +20,11 @@ use alloc::{rc::Rc, sync::Arc, vec::Vec};
 use core::cell::Cell;
 
 use oak_functions_abi::Request;
+use oak_proto_rust::oak::functions::config::{ApplicationConfig, WasmiConfig};
 
 use super::{
-    api::StdWasmApiFactory, OakLinker, UserState, WasmApiFactory, WasmHandler, ALLOC_FUNCTION_NAME,
-    MEMORY_NAME,
+    api::StdWasmApiFactory, OakLinker, UserState, WasmApiFactory, WasmConfig, WasmHandler,
+    ALLOC_FUNCTION_NAME, MEMORY_NAME,
 };
 use crate::{
     logger::StandaloneLogger,
@@ -135,6 +136,122 @@ fn test_invoke() {
     assert_eq!(response.body, data.to_vec());
 }
 
+// A module whose `main` never returns:
+//
+// (module
+//   (memory (export "memory") 1)
+//   (func (export "main") (loop (br 0)))
+//   (func (export "alloc") (param i32) (result i32) (i32.const 0)))
+pub(super) const INFINITE_LOOP_MODULE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header.
+    0x01, 0x09, 0x02, 0x60, 0x00, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, // Types.
+    0x03, 0x03, 0x02, 0x00, 0x01, // Functions.
+    0x05, 0x03, 0x01, 0x00, 0x01, // Memory.
+    0x07, 0x19, 0x03, // Exports.
+    0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x00, // "main".
+    0x05, 0x61, 0x6c, 0x6c, 0x6f, 0x63, 0x00, 0x01, // "alloc".
+    0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, // "memory".
+    0x0a, 0x0e, 0x02, // Code.
+    0x07, 0x00, 0x03, 0x40, 0x0c, 0x00, 0x0b, 0x0b, // `main`.
+    0x04, 0x00, 0x41, 0x00, 0x0b, // `alloc`.
+];
+
+// A module whose `main` grows its memory by 100 pages:
+//
+// (module
+//   (memory (export "memory") 1)
+//   (func (export "main") (drop (memory.grow (i32.const 100))))
+//   (func (export "alloc") (param i32) (result i32) (i32.const 0)))
+pub(super) const MEMORY_GROW_MODULE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header.
+    0x01, 0x09, 0x02, 0x60, 0x00, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, // Types.
+    0x03, 0x03, 0x02, 0x00, 0x01, // Functions.
+    0x05, 0x03, 0x01, 0x00, 0x01, // Memory.
+    0x07, 0x19, 0x03, // Exports.
+    0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x00, // "main".
+    0x05, 0x61, 0x6c, 0x6c, 0x6f, 0x63, 0x00, 0x01, // "alloc".
+    0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, // "memory".
+    0x0a, 0x0f, 0x02, // Code.
+    0x08, 0x00, 0x41, 0xe4, 0x00, 0x40, 0x00, 0x1a, 0x0b, // `main`.
+    0x04, 0x00, 0x41, 0x00, 0x0b, // `alloc`.
+];
+
+pub(super) const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+fn create_handler(wasm_module_bytes: &[u8], config: WasmConfig) -> WasmHandler {
+    let logger = Arc::new(StandaloneLogger);
+    let lookup_data_manager =
+        Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger.clone()));
+    let api_factory = Arc::new(StdWasmApiFactory { lookup_data_manager });
+    WasmHandler::create(wasm_module_bytes, config, api_factory, logger, None)
+        .expect("couldn't create WasmHandler")
+}
+
+#[test]
+fn test_invoke_out_of_fuel() {
+    let wasm_handler = create_handler(
+        INFINITE_LOOP_MODULE,
+        WasmConfig { fuel_per_invocation: Some(10_000), ..Default::default() },
+    );
+    let status = wasm_handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
+
+#[test]
+fn test_invoke_within_memory_limit() {
+    let wasm_handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmConfig { max_memory_bytes: Some(101 * WASM_PAGE_SIZE), ..Default::default() },
+    );
+    assert!(wasm_handler.handle_invoke(Request { body: Vec::new() }).is_ok());
+}
+
+#[test]
+fn test_invoke_exceeds_memory_limit() {
+    let wasm_handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmConfig { max_memory_bytes: Some(2 * WASM_PAGE_SIZE), ..Default::default() },
+    );
+    let status = wasm_handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
+
+#[test]
+fn test_instantiate_exceeds_memory_limit() {
+    let wasm_handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmConfig { max_memory_bytes: Some(WASM_PAGE_SIZE / 2), ..Default::default() },
+    );
+    let status = wasm_handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
+
+#[test]
+fn test_config_from_application_config() {
+    let config = WasmConfig::try_from(ApplicationConfig {
+        wasmi_config: Some(WasmiConfig {
+            fuel_per_invocation: Some(10_000),
+            max_memory_bytes: Some(2 * WASM_PAGE_SIZE as u64),
+        }),
+        ..Default::default()
+    })
+    .expect("couldn't convert application config");
+    assert_eq!(config.fuel_per_invocation, Some(10_000));
+    assert_eq!(config.max_memory_bytes, Some(2 * WASM_PAGE_SIZE));
+
+    let wasm_handler = create_handler(MEMORY_GROW_MODULE, config);
+    let status = wasm_handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
+
+#[test]
+fn test_config_from_default_application_config() {
+    let config = WasmConfig::try_from(ApplicationConfig::default())
+        .expect("couldn't convert application config");
+    assert_eq!(config.fuel_per_invocation, None);
+    assert_eq!(config.max_memory_bytes, None);
+}
+
 struct TestState {
     instance: wasmi::Instance,
     store: wasmi::Store<UserState>,
@@ -156,9 +273,14 @@ fn create_test_state() -> TestState {
 
     let wasm_module_bytes = std::fs::read(wasm_module_path).unwrap();
 
-    let wasm_handler =
-        WasmHandler::create(&wasm_module_bytes, api_factory.clone(), logger.clone(), None)
-            .expect("couldn't create WasmHandler");
+    let wasm_handler = WasmHandler::create(
+        &wasm_module_bytes,
+        WasmConfig::default(),
+        api_factory.clone(),
+        logger.clone(),
+        None,
+    )
+    .expect("couldn't create WasmHandler");
 
     let request = Vec::new();
     let response = Rc::new(Cell::new(Vec::new()));
@@ -25,10 +25,17 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "std")]
+use alloc::collections::BTreeMap;
 use alloc::{boxed::Box, format, rc::Rc, string::ToString, sync::Arc, vec::Vec};
-use core::cell::Cell;
 #[cfg(feature = "std")]
-use std::time::Instant;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::{cell::Cell, time::Duration};
+#[cfg(feature = "std")]
+use std::{
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+    time::Instant,
+};
 
 use log::Level;
 use micro_rpc::StatusCode;
@@ -43,6 +50,9 @@ use crate::{
     Handler, Observer,
 };
 
+#[cfg(test)]
+mod tests;
+
 /// Fixed name of the function to start a Wasm. Every Oak Wasm module must
 /// provide this function.
 pub const MAIN_FUNCTION_NAME: &str = "main";
@@ -68,6 +78,100 @@ pub type AbiPointerOffset = u32;
 pub struct UserState {
     wasm_api_transport: Box<dyn micro_rpc::Transport<Error = !>>,
     logger: Arc<dyn OakLogger>,
+    memory_limiter: MemoryLimiter,
+}
+
+/// Caps the size of the linear memory of a Wasm module and remembers whether
+/// the module tried to grow the memory beyond the cap.
+struct MemoryLimiter {
+    max_memory_bytes: usize,
+    limit_exceeded: bool,
+}
+
+impl wasmtime::ResourceLimiter for MemoryLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if desired > self.max_memory_bytes {
+            self.limit_exceeded = true;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Period at which the epoch of an engine with epoch interruption enabled is
+/// incremented. Epoch deadlines are rounded up to a multiple of this period.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Increments the epoch of every registered engine every [`EPOCH_TICK`].
+///
+/// A single thread, started on first use, drives all the engines in the
+/// process, so the number of threads does not grow with the number of
+/// handlers.
+#[cfg(feature = "std")]
+struct EpochTicker {
+    engines: Mutex<BTreeMap<u64, wasmtime::Engine>>,
+    next_id: AtomicU64,
+}
+
+#[cfg(feature = "std")]
+static EPOCH_TICKER: OnceLock<EpochTicker> = OnceLock::new();
+
+#[cfg(feature = "std")]
+impl EpochTicker {
+    /// Registers the engine with the shared ticker. The epoch of the engine is
+    /// incremented until the returned registration is dropped.
+    fn register(engine: wasmtime::Engine) -> EpochRegistration {
+        let ticker = EPOCH_TICKER.get_or_init(|| {
+            std::thread::spawn(|| loop {
+                std::thread::sleep(EPOCH_TICK);
+                if let Some(ticker) = EPOCH_TICKER.get() {
+                    ticker.tick();
+                }
+            });
+            EpochTicker { engines: Mutex::new(BTreeMap::new()), next_id: AtomicU64::new(0) }
+        });
+        let id = ticker.next_id.fetch_add(1, Ordering::Relaxed);
+        ticker.engines().insert(id, engine);
+        EpochRegistration { id }
+    }
+
+    fn tick(&self) {
+        self.engines().values().for_each(wasmtime::Engine::increment_epoch);
+    }
+
+    fn engines(&self) -> MutexGuard<'_, BTreeMap<u64, wasmtime::Engine>> {
+        // The map stays consistent even if a thread panicked while holding the lock.
+        self.engines.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Keeps an engine registered with the shared [`EpochTicker`] until dropped.
+#[cfg(feature = "std")]
+struct EpochRegistration {
+    id: u64,
+}
+
+#[cfg(feature = "std")]
+impl Drop for EpochRegistration {
+    fn drop(&mut self) {
+        if let Some(ticker) = EPOCH_TICKER.get() {
+            ticker.engines().remove(&self.id);
+        }
+    }
 }
 
 /// Stubs a Wasm imported function in the provided linker.
@@ -103,7 +207,11 @@ impl UserState {
         wasm_api_transport: Box<dyn micro_rpc::Transport<Error = !>>,
         logger: Arc<dyn OakLogger>,
     ) -> Self {
-        UserState { wasm_api_transport, logger }
+        UserState {
+            wasm_api_transport,
+            logger,
+            memory_limiter: MemoryLimiter { max_memory_bytes: usize::MAX, limit_exceeded: false },
+        }
     }
 
     // Use an `OakLogger` to log.
@@ -385,6 +493,12 @@ impl<'a> OakCaller<'a> {
 // A request handler with a Wasm module for handling multiple requests.
 pub struct WasmtimeHandler {
     wasm_module: wasmtime::Module,
+    fuel_per_invocation: Option<u64>,
+    epoch_deadline_ticks: Option<u64>,
+    max_memory_bytes: Option<usize>,
+    // Kept alive for as long as the handler, as it drives epoch interruption.
+    #[cfg(feature = "std")]
+    _epoch_registration: Option<EpochRegistration>,
     linker: OakLinker,
     wasm_api_factory: Box<dyn WasmApiFactory + Send + Sync>,
     logger: Arc<dyn OakLogger>,
@@ -460,6 +574,20 @@ impl WasmtimeHandler {
             ]
         );
 
+        // Epochs are incremented by a thread, which needs std.
+        #[cfg(not(feature = "std"))]
+        anyhow::ensure!(
+            config_proto.epoch_deadline_ms.is_none(),
+            "epoch deadlines are only supported with the std feature"
+        );
+        config.consume_fuel(config_proto.fuel_per_invocation.is_some());
+        config.epoch_interruption(config_proto.epoch_deadline_ms.is_some());
+        let max_memory_bytes = config_proto
+            .max_memory_bytes
+            .map(usize::try_from)
+            .transpose()
+            .map_err(|err| anyhow::anyhow!("invalid maximum memory size: {:?}", err))?;
+
         let engine = wasmtime::Engine::new(&config)
             .map_err(|err| anyhow::anyhow!("couldn't create Wasmtime engine: {:?}", err))?;
         let wasm_module = wasmtime::Module::new(&engine, wasm_module_bytes)
@@ -467,8 +595,20 @@ impl WasmtimeHandler {
 
         let linker = OakLinker::new(wasm_module.engine());
 
+        let epoch_deadline_ticks = config_proto
+            .epoch_deadline_ms
+            .map(|deadline_ms| deadline_ms.div_ceil(EPOCH_TICK.as_millis() as u64).max(1));
+        #[cfg(feature = "std")]
+        let epoch_registration =
+            epoch_deadline_ticks.map(|_| EpochTicker::register(engine.clone()));
+
         Ok(WasmtimeHandler {
             wasm_module,
+            fuel_per_invocation: config_proto.fuel_per_invocation,
+            epoch_deadline_ticks,
+            max_memory_bytes,
+            #[cfg(feature = "std")]
+            _epoch_registration: epoch_registration,
             linker,
             wasm_api_factory,
             logger: Arc::from(logger),
@@ -504,7 +644,25 @@ impl Handler for WasmtimeHandler {
             let user_state = UserState::new(wasm_api.transport(), self.logger.clone());
             // For isolated requests we need to create a new store for every request.
             let mut store = wasmtime::Store::new(self.wasm_module.engine(), user_state);
-            let instance = self.linker.instantiate(&mut store, &self.wasm_module)?;
+            if let Some(fuel) = self.fuel_per_invocation {
+                store.set_fuel(fuel).map_err(|err| {
+                    micro_rpc::Status::new_with_message(
+                        micro_rpc::StatusCode::Internal,
+                        format!("couldn't add fuel to the store: {:?}", err),
+                    )
+                })?;
+            }
+            if let Some(ticks) = self.epoch_deadline_ticks {
+                store.set_epoch_deadline(ticks);
+            }
+            if let Some(max_memory_bytes) = self.max_memory_bytes {
+                store.data_mut().memory_limiter.max_memory_bytes = max_memory_bytes;
+                store.limiter(|user_state| &mut user_state.memory_limiter);
+            }
+            let instance = self
+                .linker
+                .instantiate(&mut store, &self.wasm_module)
+                .map_err(|err| check_memory_limit(&store).unwrap_or(err))?;
 
             // Does not work in wasmtime
             // #[cfg(not(feature = "deny_sensitive_logging"))]
@@ -527,13 +685,34 @@ impl Handler for WasmtimeHandler {
             // included in the metric.
             #[cfg(feature = "std")]
             let now = Instant::now();
-            #[allow(unused)]
             let result = main.call(&mut store, ());
             #[cfg(feature = "std")]
             if let Some(ref observer) = self.observer {
                 observer.wasm_invocation(now.elapsed());
             }
 
+            // Exceeding a limit fails the invocation. Other traps (e.g. panics in
+            // the module) are ignored, and the response is whatever the module
+            // wrote before trapping.
+            if let Some(status) = check_memory_limit(&store) {
+                return Err(status);
+            }
+            match result.as_ref().map_err(|err| err.downcast_ref::<wasmtime::Trap>()) {
+                Err(Some(wasmtime::Trap::OutOfFuel)) => {
+                    return Err(micro_rpc::Status::new_with_message(
+                        micro_rpc::StatusCode::ResourceExhausted,
+                        "Wasm module ran out of fuel",
+                    ));
+                }
+                Err(Some(wasmtime::Trap::Interrupt)) => {
+                    return Err(micro_rpc::Status::new_with_message(
+                        micro_rpc::StatusCode::DeadlineExceeded,
+                        "Wasm module exceeded the epoch deadline",
+                    ));
+                }
+                _ => {}
+            }
+
             #[cfg(not(feature = "deny_sensitive_logging"))]
             self.logger.log_sensitive(
                 Level::Info,
@@ -552,6 +731,17 @@ impl Handler for WasmtimeHandler {
     }
 }
 
+/// Returns an error if the Wasm module tried to grow its memory beyond the
+/// configured maximum size.
+fn check_memory_limit(store: &Store<UserState>) -> Option<micro_rpc::Status> {
+    store.data().memory_limiter.limit_exceeded.then(|| {
+        micro_rpc::Status::new_with_message(
+            micro_rpc::StatusCode::ResourceExhausted,
+            "Wasm module exceeded the memory limit",
+        )
+    })
+}
+
 /// A helper function to move between our specific result type `Result<(),
 /// StatusCode>` and the `wasmtime` specific result type `Result<i32,
 /// wasmtime::Trap>`.
@@ -0,0 +1,126 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+
+use oak_functions_abi::Request;
+use oak_proto_rust::oak::functions::config::WasmtimeConfig;
+
+use super::{WasmtimeHandler, EPOCH_TICKER};
+use crate::{
+    logger::StandaloneLogger,
+    lookup::LookupDataManager,
+    wasm::{
+        api::StdWasmApiFactory,
+        tests::{INFINITE_LOOP_MODULE, MEMORY_GROW_MODULE, WASM_PAGE_SIZE},
+    },
+    Handler,
+};
+
+fn create_handler(wasm_module_bytes: &[u8], config: WasmtimeConfig) -> WasmtimeHandler {
+    let logger = Arc::new(StandaloneLogger);
+    let lookup_data_manager = Arc::new(LookupDataManager::<1>::for_test(Vec::default(), logger));
+    let api_factory = Box::new(StdWasmApiFactory { lookup_data_manager });
+    WasmtimeHandler::create(
+        wasm_module_bytes,
+        config,
+        api_factory,
+        Box::new(StandaloneLogger),
+        None,
+    )
+    .expect("couldn't create WasmtimeHandler")
+}
+
+#[test]
+fn test_invoke_out_of_fuel() {
+    let handler = create_handler(
+        INFINITE_LOOP_MODULE,
+        WasmtimeConfig { fuel_per_invocation: Some(10_000), ..Default::default() },
+    );
+    let status = handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
+
+#[test]
+fn test_invoke_exceeds_epoch_deadline() {
+    let handler = create_handler(
+        INFINITE_LOOP_MODULE,
+        WasmtimeConfig { epoch_deadline_ms: Some(50), ..Default::default() },
+    );
+    let status = handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::DeadlineExceeded);
+}
+
+#[test]
+fn test_handlers_share_epoch_ticker() {
+    let config = WasmtimeConfig { epoch_deadline_ms: Some(50), ..Default::default() };
+    let first = create_handler(INFINITE_LOOP_MODULE, config.clone());
+    let second = create_handler(INFINITE_LOOP_MODULE, config);
+
+    // Both handlers are interrupted by the same ticker.
+    for handler in [&first, &second] {
+        let status = handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+        assert_eq!(status.code, micro_rpc::StatusCode::DeadlineExceeded);
+    }
+
+    // Dropping a handler stops ticking its engine.
+    let id = first._epoch_registration.as_ref().expect("no epoch registration").id;
+    let ticker = EPOCH_TICKER.get().expect("epoch ticker not started");
+    assert!(ticker.engines().contains_key(&id));
+    drop(first);
+    assert!(!ticker.engines().contains_key(&id));
+}
+
+#[test]
+fn test_no_epoch_registration_without_deadline() {
+    let handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmtimeConfig { fuel_per_invocation: Some(10_000), ..Default::default() },
+    );
+    assert!(handler._epoch_registration.is_none());
+}
+
+#[test]
+fn test_invoke_within_memory_limit() {
+    let handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmtimeConfig {
+            max_memory_bytes: Some(101 * WASM_PAGE_SIZE as u64),
+            ..Default::default()
+        },
+    );
+    assert!(handler.handle_invoke(Request { body: Vec::new() }).is_ok());
+}
+
+#[test]
+fn test_invoke_exceeds_memory_limit() {
+    let handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmtimeConfig { max_memory_bytes: Some(2 * WASM_PAGE_SIZE as u64), ..Default::default() },
+    );
+    let status = handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
+
+#[test]
+fn test_instantiate_exceeds_memory_limit() {
+    let handler = create_handler(
+        MEMORY_GROW_MODULE,
+        WasmtimeConfig { max_memory_bytes: Some(WASM_PAGE_SIZE as u64 / 2), ..Default::default() },
+    );
+    let status = handler.handle_invoke(Request { body: Vec::new() }).unwrap_err();
+    assert_eq!(status.code, micro_rpc::StatusCode::ResourceExhausted);
+}
@@ -136,6 +136,11 @@ pub struct RootLayerEvidence {
     /// <<https://www.secg.org/sec1-v2.pdf#page=16>>
     #[prost(bytes = "vec", tag = "3")]
     pub eca_public_key: ::prost::alloc::vec::Vec<u8>,
+    /// DER-encoded certificate for the key that signed the remote attestation
+    /// report (e.g. the AMD VCEK certificate), if the host provided it to the
+    /// guest, so that it can be forwarded to verifiers as an endorsement.
+    #[prost(bytes = "vec", tag = "4")]
+    pub tee_certificate: ::prost::alloc::vec::Vec<u8>,
 }
 /// DICE layer evidence containing a certificate signed by the previous layer.
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -383,6 +388,15 @@ pub struct EndorsementReferenceValue {
     /// Verifies the Rekor log entry, if present and requested.
     #[prost(message, optional, tag = "5")]
     pub rekor: ::core::option::Option<VerifyingKeyReferenceValue>,
+    /// Keys of witnesses trusted to cosign Rekor checkpoints. If any are set,
+    /// the checkpoint in the log entry's inclusion proof must additionally be
+    /// cosigned by one of these witnesses with an Ed25519 `cosignature/v1`
+    /// signature (<https://c2sp.org/tlog-cosignature>). The `raw` field of each
+    /// key holds the witness's note verifier key, e.g.
+    /// "witness.example.com+f8e2fc87+BDUZFqwuDPbOMSw78/WFsFoVJ8QW+jCXb93rNNIDzZoE",
+    /// and its `type` is ignored. Only used when `rekor` requests verification.
+    #[prost(message, optional, tag = "6")]
+    pub rekor_witnesses: ::core::option::Option<VerifyingKeySet>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
@@ -544,10 +558,77 @@ pub struct AmdSevReferenceValues {
     /// Verifies the stage0 binary implicitly contained in the root layer.
     #[prost(message, optional, tag = "4")]
     pub stage0: ::core::option::Option<BinaryReferenceValue>,
+    /// DER-encoded certificate revocation lists (CRLs) issued by AMD Root Keys
+    /// (ARKs), as published at <https://kdsintf.amd.com/vcek/v1/{product}/crl.>
+    /// The ASK certificate is rejected if it is revoked by a CRL of its ARK. CRLs
+    /// of other issuers are ignored.
+    #[prost(bytes = "vec", repeated, tag = "6")]
+    pub certificate_revocation_lists: ::prost::alloc::vec::Vec<
+        ::prost::alloc::vec::Vec<u8>,
+    >,
+    /// Root certificates of AMD product lines that are trusted in addition to the
+    /// built-in ones (Milan and Genoa), e.g. Turin.
+    #[prost(message, repeated, tag = "7")]
+    pub additional_product_roots: ::prost::alloc::vec::Vec<AmdProductRootCertificates>,
+    /// Minimum accepted TCB versions for specific AMD product lines, keyed by
+    /// product line (e.g. "Milan", "Genoa" or "Turin"). The product line is taken
+    /// from the product name in the VCEK certificate, without the stepping (e.g.
+    /// "Milan" for "Milan-B0"). If it equals a key exactly, the corresponding
+    /// entry is used instead of `min_tcb_version`. Keys must not be empty.
+    #[prost(btree_map = "string, message", tag = "8")]
+    pub product_min_tcb_versions: ::prost::alloc::collections::BTreeMap<
+        ::prost::alloc::string::String,
+        TcbVersion,
+    >,
 }
+/// The root certificates of an AMD product line, as published at
+/// <https://kdsintf.amd.com/vcek/v1/{product}/cert_chain.>
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
-pub struct IntelTdxReferenceValues {}
+pub struct AmdProductRootCertificates {
+    /// The product name, e.g. "Turin". VCEK certificates are matched to a product
+    /// line if the product name in the certificate contains this name.
+    #[prost(string, tag = "1")]
+    pub product_name: ::prost::alloc::string::String,
+    /// DER-encoded AMD Root Key (ARK) certificate.
+    #[prost(bytes = "vec", tag = "2")]
+    pub ark_certificate: ::prost::alloc::vec::Vec<u8>,
+    /// DER-encoded AMD SEV Key (ASK) certificate.
+    #[prost(bytes = "vec", tag = "3")]
+    pub ask_certificate: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct IntelTdxReferenceValues {
+    /// Minimum accepted security version numbers (SVNs) of the TDX module TCB
+    /// components. Must contain 16 bytes, which are compared component-wise with
+    /// the TEE_TCB_SVN field of the TD quote.
+    #[prost(bytes = "vec", tag = "1")]
+    pub min_tee_tcb_svn: ::prost::alloc::vec::Vec<u8>,
+    /// If true, will skip the check that the TD is not in debug mode.
+    #[prost(bool, tag = "2")]
+    pub allow_debug: bool,
+    /// Verifies the MRTD, i.e. the measurement of the initial contents of the TD,
+    /// which implicitly includes the firmware binary.
+    #[prost(message, optional, tag = "3")]
+    pub mr_td: ::core::option::Option<BinaryReferenceValue>,
+    /// Verifies the runtime measurement registers RTMR\[0..3\].
+    #[prost(message, optional, tag = "4")]
+    pub rtmr0: ::core::option::Option<BinaryReferenceValue>,
+    #[prost(message, optional, tag = "5")]
+    pub rtmr1: ::core::option::Option<BinaryReferenceValue>,
+    #[prost(message, optional, tag = "6")]
+    pub rtmr2: ::core::option::Option<BinaryReferenceValue>,
+    #[prost(message, optional, tag = "7")]
+    pub rtmr3: ::core::option::Option<BinaryReferenceValue>,
+    /// Bits of the TD attributes that must be equal to the corresponding bits in
+    /// `td_attributes`. The DEBUG bit is ignored, as it is covered by
+    /// `allow_debug`. No attributes are checked if the mask is zero.
+    #[prost(uint64, tag = "8")]
+    pub td_attributes_mask: u64,
+    #[prost(uint64, tag = "9")]
+    pub td_attributes: u64,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
 pub struct InsecureReferenceValues {}
@@ -840,6 +921,34 @@ pub struct RootLayerEndorsements {
     /// Endorsement of the Stage0 binary.
     #[prost(message, optional, tag = "2")]
     pub stage0: ::core::option::Option<TransparentReleaseEndorsement>,
+    /// Collateral needed to verify an Intel TDX quote. Required for Intel TDX
+    /// and ignored for other platforms.
+    #[prost(message, optional, tag = "3")]
+    pub intel_tdx_collateral: ::core::option::Option<IntelTdxCollateral>,
+}
+/// Collateral published by the Intel Provisioning Certification Service
+/// (PCS) which is needed to verify an Intel TDX quote, in addition to the PCK
+/// certificate chain embedded in the quote. See
+/// <https://api.portal.trustedservices.intel.com/content/documentation.html>
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct IntelTdxCollateral {
+    /// DER-encoded CRL issued by the Intel SGX Root CA.
+    #[prost(bytes = "vec", tag = "1")]
+    pub root_ca_crl: ::prost::alloc::vec::Vec<u8>,
+    /// DER-encoded CRL issued by the intermediate CA of the PCK certificate.
+    #[prost(bytes = "vec", tag = "2")]
+    pub pck_crl: ::prost::alloc::vec::Vec<u8>,
+    /// The signed TDX TCB info JSON document for the FMSPC of the platform.
+    #[prost(bytes = "vec", tag = "3")]
+    pub tcb_info: ::prost::alloc::vec::Vec<u8>,
+    /// The signed TDX Quoting Enclave identity JSON document.
+    #[prost(bytes = "vec", tag = "4")]
+    pub qe_identity: ::prost::alloc::vec::Vec<u8>,
+    /// PEM-encoded certificate chain of the TCB signing key which signs
+    /// `tcb_info` and `qe_identity`, ending with the Intel SGX Root CA.
+    #[prost(bytes = "vec", tag = "5")]
+    pub tcb_signing_cert_chain: ::prost::alloc::vec::Vec<u8>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
@@ -1199,6 +1308,41 @@ pub struct IntelTdxAttestationReport {
     /// The custom bytes that were passed to the report when it was requested.
     #[prost(bytes = "vec", tag = "1")]
     pub report_data: ::prost::alloc::vec::Vec<u8>,
+    /// The security version numbers of the TDX module TCB components.
+    #[prost(bytes = "vec", tag = "2")]
+    pub tee_tcb_svn: ::prost::alloc::vec::Vec<u8>,
+    /// The measurement of the TDX module.
+    #[prost(bytes = "vec", tag = "3")]
+    pub mr_seam: ::prost::alloc::vec::Vec<u8>,
+    /// The attributes of the TD, e.g. whether it was launched in debug mode.
+    #[prost(uint64, tag = "4")]
+    pub td_attributes: u64,
+    /// The extended features available mask (XFAM) of the TD.
+    #[prost(uint64, tag = "5")]
+    pub xfam: u64,
+    /// The measurement of the initial contents of the TD. This implicitly includes
+    /// the measurement of the firmware binary.
+    #[prost(bytes = "vec", tag = "6")]
+    pub mr_td: ::prost::alloc::vec::Vec<u8>,
+    /// Software-defined identifiers of the TD configuration and its owner.
+    #[prost(bytes = "vec", tag = "7")]
+    pub mr_config_id: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "8")]
+    pub mr_owner: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "9")]
+    pub mr_owner_config: ::prost::alloc::vec::Vec<u8>,
+    /// The runtime-extendable measurement registers RTMR\[0..3\].
+    #[prost(bytes = "vec", tag = "10")]
+    pub rtmr0: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "11")]
+    pub rtmr1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "12")]
+    pub rtmr2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "13")]
+    pub rtmr3: ::prost::alloc::vec::Vec<u8>,
+    /// Whether the TD was launched in debug mode.
+    #[prost(bool, tag = "14")]
+    pub debug: bool,
 }
 /// Values extracted from a fake attestation report when not running in a TEE.
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -1379,10 +1523,50 @@ pub struct AmdSevExpectedValues {
     /// If true, will skip the check that the TEE is not in debug mode.
     #[prost(bool, tag = "3")]
     pub allow_debug: bool,
+    /// DER-encoded certificate revocation lists issued by AMD Root Keys, used
+    /// to reject revoked ASK certificates.
+    #[prost(bytes = "vec", repeated, tag = "4")]
+    pub certificate_revocation_lists: ::prost::alloc::vec::Vec<
+        ::prost::alloc::vec::Vec<u8>,
+    >,
+    /// Root certificates of AMD product lines that are trusted in addition to the
+    /// built-in ones.
+    #[prost(message, repeated, tag = "5")]
+    pub additional_product_roots: ::prost::alloc::vec::Vec<AmdProductRootCertificates>,
+    /// Minimum accepted TCB versions for specific AMD product lines, keyed by
+    /// product line (e.g. "Milan"). Takes precedence over `min_tcb_version` if a
+    /// key equals the product line of the VCEK exactly. Keys must not be empty.
+    #[prost(btree_map = "string, message", tag = "6")]
+    pub product_min_tcb_versions: ::prost::alloc::collections::BTreeMap<
+        ::prost::alloc::string::String,
+        TcbVersion,
+    >,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
-pub struct IntelTdxExpectedValues {}
+pub struct IntelTdxExpectedValues {
+    #[prost(message, optional, tag = "1")]
+    pub mr_td_expected: ::core::option::Option<ExpectedDigests>,
+    #[prost(message, optional, tag = "2")]
+    pub rtmr0_expected: ::core::option::Option<ExpectedDigests>,
+    #[prost(message, optional, tag = "3")]
+    pub rtmr1_expected: ::core::option::Option<ExpectedDigests>,
+    #[prost(message, optional, tag = "4")]
+    pub rtmr2_expected: ::core::option::Option<ExpectedDigests>,
+    #[prost(message, optional, tag = "5")]
+    pub rtmr3_expected: ::core::option::Option<ExpectedDigests>,
+    /// Minimum accepted security version numbers of the TDX module TCB.
+    #[prost(bytes = "vec", tag = "6")]
+    pub min_tee_tcb_svn: ::prost::alloc::vec::Vec<u8>,
+    /// If true, will skip the check that the TD is not in debug mode.
+    #[prost(bool, tag = "7")]
+    pub allow_debug: bool,
+    /// Bits of the TD attributes that must match `td_attributes`.
+    #[prost(uint64, tag = "8")]
+    pub td_attributes_mask: u64,
+    #[prost(uint64, tag = "9")]
+    pub td_attributes: u64,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
 pub struct InsecureExpectedValues {}
@@ -12,6 +12,19 @@ pub struct GetGroupKeysResponse {
         super::super::key_provisioning::v1::GroupKeys,
     >,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct GetReferenceValuesResponse {
+    /// Additional reference values that the evidence of Follower enclaves must
+    /// match before the Leader releases group keys to them. The Leader always
+    /// requires Followers to match reference values derived from its own
+    /// evidence; these can only constrain the Followers further, and are rejected
+    /// if they skip any verification step or allow insecure platforms.
+    #[prost(message, optional, tag = "1")]
+    pub reference_values: ::core::option::Option<
+        super::super::attestation::v1::ReferenceValues,
+    >,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost_derive::Enumeration)]
 #[repr(i32)]
 pub enum KeyProvisioningRole {
@@ -73,6 +86,40 @@ pub struct SignResponse {
     #[prost(message, optional, tag = "1")]
     pub signature: ::core::option::Option<super::super::crypto::v1::Signature>,
 }
+/// Data sealed to the exact measurement of the enclave, including the container
+/// bundle and the application config.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct SealedData {
+    #[prost(bytes = "vec", tag = "1")]
+    pub nonce: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub ciphertext: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct SealRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub plaintext: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct SealResponse {
+    #[prost(message, optional, tag = "1")]
+    pub sealed_data: ::core::option::Option<SealedData>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct UnsealRequest {
+    #[prost(message, optional, tag = "1")]
+    pub sealed_data: ::core::option::Option<SealedData>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct UnsealResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub plaintext: ::prost::alloc::vec::Vec<u8>,
+}
 /// Choice between a key generated by the enclave instance and the key
 /// distributed to the enclave group with Key Provisioning.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost_derive::Enumeration)]
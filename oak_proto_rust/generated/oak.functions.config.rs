@@ -46,6 +46,24 @@ pub struct WasmtimeConfig {
     /// <https://docs.rs/wasmtime/latest/wasmtime/struct.Config.html#method.memory_init_cow>
     #[prost(bool, optional, tag = "7")]
     pub memory_init_cow: ::core::option::Option<bool>,
+    /// Amount of fuel available to each invocation of the module, including its
+    /// instantiation. Invocations that run out of fuel fail with
+    /// RESOURCE_EXHAUSTED. If not specified, fuel is not metered.
+    /// <https://docs.rs/wasmtime/latest/wasmtime/struct.Config.html#method.consume_fuel>
+    #[prost(uint64, optional, tag = "8")]
+    pub fuel_per_invocation: ::core::option::Option<u64>,
+    /// Wall-clock time each invocation of the module may run for, in
+    /// milliseconds. Invocations that run for longer fail with DEADLINE_EXCEEDED.
+    /// The deadline is enforced with a granularity of 10 milliseconds. If not
+    /// specified, invocations may run forever.
+    /// <https://docs.rs/wasmtime/latest/wasmtime/struct.Config.html#method.epoch_interruption>
+    #[prost(uint64, optional, tag = "9")]
+    pub epoch_deadline_ms: ::core::option::Option<u64>,
+    /// Maximum size of the linear memory of the module, in bytes. Invocations that
+    /// try to grow the memory beyond this size fail with RESOURCE_EXHAUSTED. If
+    /// not specified, the size is only bounded by the Wasm specification.
+    #[prost(uint64, optional, tag = "10")]
+    pub max_memory_bytes: ::core::option::Option<u64>,
 }
 /// Nested message and enum types in `WasmtimeConfig`.
 pub mod wasmtime_config {
@@ -96,6 +114,27 @@ pub mod wasmtime_config {
         pub max_memory_protection_keys: ::core::option::Option<u64>,
     }
 }
+/// Settings specific to the wasmi engine.
+///
+/// As with `WasmtimeConfig`, the fields are marked as `optional` so that we
+/// could detect their presence. If a field is left unspecified, the
+/// corresponding limit is not enforced.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct WasmiConfig {
+    /// Amount of fuel available to each invocation of the module, including its
+    /// instantiation. Invocations that run out of fuel fail with
+    /// RESOURCE_EXHAUSTED. As wasmi cannot interrupt execution based on
+    /// wall-clock time, this is the only way to bound the execution time of a
+    /// module. If not specified, fuel is not metered.
+    #[prost(uint64, optional, tag = "1")]
+    pub fuel_per_invocation: ::core::option::Option<u64>,
+    /// Maximum size of the linear memory of the module, in bytes. Invocations that
+    /// try to grow the memory beyond this size fail with RESOURCE_EXHAUSTED. If
+    /// not specified, the size is only bounded by the Wasm specification.
+    #[prost(uint64, optional, tag = "2")]
+    pub max_memory_bytes: ::core::option::Option<u64>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
 pub struct ApplicationConfig {
@@ -107,6 +146,11 @@ pub struct ApplicationConfig {
     /// if we're using the wasmi or native engines.
     #[prost(message, optional, tag = "4")]
     pub wasmtime_config: ::core::option::Option<WasmtimeConfig>,
+    /// Configuration for the wasmi engine, if that is used.
+    /// Currently only used when running on Restricted Kernel; this field is
+    /// ignored if we're using the wasmtime or native engines.
+    #[prost(message, optional, tag = "5")]
+    pub wasmi_config: ::core::option::Option<WasmiConfig>,
     /// Communication channel parameters.
     /// The default behaviour depends on the flavour of Oak Functions:
     ///    - when running on Restricted Kernel this setting is ignored completely as
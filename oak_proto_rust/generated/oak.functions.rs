@@ -3,8 +3,21 @@
 pub struct InitializeRequest {
     #[prost(bytes = "vec", tag = "1")]
     pub wasm_module: ::prost::alloc::vec::Vec<u8>,
+    /// Fixed size of the body of every response returned by the Wasm module, in
+    /// bytes. See `oak.functions.abi.ServerPolicy.constant_response_size_bytes`.
+    /// Zero disables padding.
     #[prost(uint32, tag = "2")]
     pub constant_response_size: u32,
+    /// Fixed amount of time it takes to return every response, in milliseconds.
+    /// See `oak.functions.abi.ServerPolicy.constant_processing_time_ms`. Zero
+    /// disables the delay.
+    #[prost(uint32, tag = "3")]
+    pub constant_processing_time_ms: u32,
+    /// Configuration of the application. Only used when running on Restricted
+    /// Kernel, as there is no other way to pass the configuration to the
+    /// enclave; Oak Containers fetch it from the orchestrator instead.
+    #[prost(message, optional, tag = "4")]
+    pub application_config: ::core::option::Option<config::ApplicationConfig>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
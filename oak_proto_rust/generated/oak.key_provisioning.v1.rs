@@ -7,6 +7,12 @@ pub struct GroupKeys {
     pub encrypted_encryption_private_key: ::core::option::Option<
         super::super::crypto::v1::EncryptedRequest,
     >,
+    /// Signing private key that was encrypted with HPKE using the encryption
+    /// public key provided in the endorsed evidence.
+    #[prost(message, optional, tag = "2")]
+    pub encrypted_signing_private_key: ::core::option::Option<
+        super::super::crypto::v1::EncryptedRequest,
+    >,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
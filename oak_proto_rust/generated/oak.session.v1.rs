@@ -190,13 +190,25 @@ pub struct PlaintextMessage {
     #[prost(bytes = "vec", tag = "1")]
     pub plaintext: ::prost::alloc::vec::Vec<u8>,
 }
+/// Signals that the sender has replaced the key it uses to encrypt messages in
+/// this direction. All subsequent encrypted messages from the sender are
+/// encrypted with the new key.
+/// <<http://www.noiseprotocol.org/noise.html#rekey>>
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost_derive::Message)]
+pub struct KeyUpdate {
+    /// Empty plaintext encrypted with the key that is being replaced. It
+    /// authenticates the position of the key update in the message stream.
+    #[prost(message, optional, tag = "1")]
+    pub confirmation: ::core::option::Option<EncryptedMessage>,
+}
 /// Request message for the Oak protocol attested secure session.
 /// This message is a wrapper containing different message types including:
 /// attestation, handshake and encrypted data exchange.
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
 pub struct SessionRequest {
-    #[prost(oneof = "session_request::Request", tags = "1, 2, 3")]
+    #[prost(oneof = "session_request::Request", tags = "1, 2, 3, 4")]
     pub request: ::core::option::Option<session_request::Request>,
 }
 /// Nested message and enum types in `SessionRequest`.
@@ -210,6 +222,8 @@ pub mod session_request {
         HandshakeRequest(super::HandshakeRequest),
         #[prost(message, tag = "3")]
         EncryptedMessage(super::EncryptedMessage),
+        #[prost(message, tag = "4")]
+        KeyUpdate(super::KeyUpdate),
     }
 }
 /// Wrapper around SessionRequest that is used in cases where it is necessary to
@@ -230,7 +244,7 @@ pub struct SessionRequestWithSessionId {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost_derive::Message)]
 pub struct SessionResponse {
-    #[prost(oneof = "session_response::Response", tags = "1, 2, 3")]
+    #[prost(oneof = "session_response::Response", tags = "1, 2, 3, 4")]
     pub response: ::core::option::Option<session_response::Response>,
 }
 /// Nested message and enum types in `SessionResponse`.
@@ -244,5 +258,7 @@ pub mod session_response {
         HandshakeResponse(super::HandshakeResponse),
         #[prost(message, tag = "3")]
         EncryptedMessage(super::EncryptedMessage),
+        #[prost(message, tag = "4")]
+        KeyUpdate(super::KeyUpdate),
     }
 }
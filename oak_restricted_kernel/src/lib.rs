@@ -53,6 +53,7 @@ mod logging;
 mod memory;
 mod mm;
 mod processes;
+mod random;
 #[cfg(feature = "serial_channel")]
 mod serial;
 pub mod shutdown;
@@ -60,6 +61,7 @@ pub mod shutdown;
 mod simpleio;
 mod snp;
 mod syscall;
+mod time;
 #[cfg(feature = "vsock_channel")]
 mod virtio;
 #[cfg(feature = "virtio_console_channel")]
@@ -99,6 +101,11 @@ use crate::{
     snp::{get_snp_page_addresses, init_snp_pages},
 };
 
+// Use the kernel RNG for crates that rely on `getrandom`, such as
+// `rand_core::OsRng`.
+#[cfg(target_os = "none")]
+getrandom::register_custom_getrandom!(random::getrandom);
+
 /// Allocator for physical memory frames in the system.
 /// We reserve enough room to handle up to 512 GiB of memory, for now.
 pub static FRAME_ALLOCATOR: Spinlock<PhysicalMemoryAllocator<4096>> =
@@ -295,6 +302,12 @@ pub fn start_kernel(info: &BootParams) -> ! {
     let heap_page_range = VMA_ALLOCATOR.lock().allocate(1 << 19).unwrap();
     memory::init_kernel_heap(heap_page_range).unwrap();
 
+    // Applications that don't need time can still run without a clock, so we only
+    // log the error here.
+    if let Err(err) = time::init(sev_status) {
+        log::warn!("failed to initialize the clock: {}", err);
+    }
+
     struct SensitiveDiceDataMemory {
         start_ptr: *mut u8,
         eventlog_ptr: *mut u8,
@@ -432,6 +445,10 @@ pub fn start_kernel(info: &BootParams) -> ! {
         (sensitive_dice_data.read_stage0_dice_data(), sensitive_dice_data.read_encoded_eventlog())
     };
 
+    // Everything that runs on top of the kernel expects to have a source of
+    // randomness, so there's no point in continuing without one.
+    random::init(&stage0_dice_data.layer_1_cdi.cdi).expect("failed to seed the RNG");
+
     // Okay. We've got page tables and a heap. Set up the "late" IDT, this time with
     // descriptors for user mode.
     let double_fault_stack = mm::allocate_stack();
@@ -0,0 +1,248 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Cryptographically secure random number generator for the kernel and the
+//! applications running on top of it.
+//!
+//! The generator is a ChaCha20 stream keyed with a seed that is derived from
+//! both hardware entropy (RDSEED, or RDRAND if RDSEED is not available) and
+//! the DICE compound device identifier. An attacker would need to know both
+//! inputs to predict the output.
+//!
+//! After every request the key is replaced with fresh output from the stream
+//! ("fast key erasure"), so compromising the kernel state doesn't reveal any
+//! previously generated bytes. Additional hardware entropy is mixed into the
+//! key periodically.
+
+use core::arch::{asm, x86_64::__cpuid_count};
+
+use hkdf::Hkdf;
+use oak_core::sync::OnceCell;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use sha2::Sha256;
+use spinning_top::Spinlock;
+use x86_64::instructions::random::RdRand;
+use zeroize::Zeroize;
+
+const KEY_SIZE: usize = 32;
+
+/// Number of bytes that we generate before mixing in new hardware entropy.
+const RESEED_INTERVAL: usize = 1 << 20;
+
+/// RDSEED may fail transiently if the entropy source is exhausted, so we retry
+/// a few times before giving up.
+const RDSEED_RETRIES: usize = 128;
+
+/// Intel recommends retrying RDRAND 10 times before assuming that the
+/// instruction is broken.
+const RDRAND_RETRIES: usize = 10;
+
+const SEED_INFO: &[u8] = b"Oak Restricted Kernel RNG seed";
+const RESEED_INFO: &[u8] = b"Oak Restricted Kernel RNG reseed";
+
+static RNG: OnceCell<Spinlock<Rng>> = OnceCell::new();
+
+struct Rng {
+    key: [u8; KEY_SIZE],
+    bytes_since_reseed: usize,
+}
+
+impl Rng {
+    fn new(hardware_entropy: &[u8], cdi: &[u8]) -> Self {
+        Self { key: derive_key(hardware_entropy, cdi, SEED_INFO), bytes_since_reseed: 0 }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if self.bytes_since_reseed >= RESEED_INTERVAL {
+            self.reseed();
+        }
+
+        let mut stream = Stream(ChaCha20Rng::from_seed(self.key));
+        stream.0.fill_bytes(&mut self.key);
+        stream.0.fill_bytes(dest);
+        self.bytes_since_reseed = self.bytes_since_reseed.saturating_add(dest.len());
+    }
+
+    fn reseed(&mut self) {
+        match hardware_entropy() {
+            Ok(mut entropy) => {
+                self.key = derive_key(&entropy, &self.key, RESEED_INFO);
+                self.bytes_since_reseed = 0;
+                entropy.zeroize();
+            }
+            // The current key is still secret, so failing to reseed isn't fatal. We'll
+            // try again on the next request.
+            Err(err) => log::warn!("failed to reseed the RNG: {}", err),
+        }
+    }
+}
+
+impl Drop for Rng {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// A ChaCha20 stream whose state, which includes the key, is zeroized on drop.
+struct Stream(ChaCha20Rng);
+
+impl Zeroize for Stream {
+    fn zeroize(&mut self) {
+        // Safety: `ChaCha20Rng` only holds integers and arrays of integers, so it
+        // has no pointers or drop glue and any bit pattern, including all zeros, is
+        // a valid value.
+        unsafe { zeroize::zeroize_flat_type(&mut self.0) };
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+fn derive_key(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; KEY_SIZE] {
+    let mut key = [0u8; KEY_SIZE];
+    let mut hkdf = Hkdf::<Sha256>::new(Some(salt), ikm);
+    hkdf.expand(info, &mut key).expect("key size is a valid HKDF output length");
+    // Safety: the HKDF state only holds the pseudorandom key and the SHA-256 state,
+    // which are arrays of integers, so any bit pattern is a valid value.
+    unsafe { zeroize::zeroize_flat_type(&mut hkdf) };
+    key
+}
+
+fn has_rdseed() -> bool {
+    // Safety: leaf 7 is supported on all CPUs that we can run on.
+    let features = unsafe { __cpuid_count(0x7, 0) };
+    features.ebx & (1 << 18) != 0
+}
+
+fn rdseed() -> Option<u64> {
+    let value: u64;
+    let success: u8;
+    // Safety: we've checked that RDSEED is supported, and it doesn't touch memory.
+    unsafe {
+        asm!(
+            "rdseed {value}",
+            "setc {success}",
+            value = out(reg) value,
+            success = out(reg_byte) success,
+            options(nomem, nostack),
+        );
+    }
+    (success != 0).then_some(value)
+}
+
+/// Collects entropy from the CPU's hardware random number generator.
+fn hardware_entropy() -> Result<[u8; KEY_SIZE], &'static str> {
+    let mut entropy = [0u8; KEY_SIZE];
+    let use_rdseed = has_rdseed();
+    let rdrand = RdRand::new();
+    if !use_rdseed && rdrand.is_none() {
+        return Err("neither RDSEED nor RDRAND is supported");
+    }
+
+    for chunk in entropy.chunks_exact_mut(core::mem::size_of::<u64>()) {
+        let seed = use_rdseed.then(|| (0..RDSEED_RETRIES).find_map(|_| rdseed())).flatten();
+        let value = seed
+            .or_else(|| {
+                rdrand.and_then(|rdrand| (0..RDRAND_RETRIES).find_map(|_| rdrand.get_u64()))
+            })
+            .ok_or("hardware random number generator failed")?;
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+    Ok(entropy)
+}
+
+/// Seeds the kernel RNG.
+///
+/// The `cdi` is mixed into the seed alongside hardware entropy, so that the
+/// output is unpredictable even if one of the sources is compromised.
+pub fn init(cdi: &[u8]) -> Result<(), &'static str> {
+    let mut entropy = hardware_entropy()?;
+    let rng = Rng::new(&entropy, cdi);
+    entropy.zeroize();
+    RNG.set(Spinlock::new(rng)).map_err(|_| "RNG already initialized")
+}
+
+/// Fills `dest` with random bytes.
+///
+/// Fails if the RNG has not been seeded yet.
+pub fn fill_bytes(dest: &mut [u8]) -> Result<(), &'static str> {
+    RNG.get().ok_or("RNG not initialized")?.lock().fill_bytes(dest);
+    Ok(())
+}
+
+/// Backend for the `getrandom` crate, so that crates like `rand_core::OsRng`
+/// work in the kernel.
+#[cfg(target_os = "none")]
+pub fn getrandom(dest: &mut [u8]) -> Result<(), getrandom::Error> {
+    fill_bytes(dest).map_err(|_| getrandom::Error::UNSUPPORTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_bytes_is_unique() {
+        let mut rng = Rng::new(&[1; KEY_SIZE], &[2; KEY_SIZE]);
+        let mut first = [0u8; 64];
+        let mut second = [0u8; 64];
+        rng.fill_bytes(&mut first);
+        rng.fill_bytes(&mut second);
+        assert_ne!(first, [0u8; 64]);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_seed_depends_on_both_inputs() {
+        let mut output = [[0u8; 32]; 3];
+        Rng::new(&[1; KEY_SIZE], &[2; KEY_SIZE]).fill_bytes(&mut output[0]);
+        Rng::new(&[3; KEY_SIZE], &[2; KEY_SIZE]).fill_bytes(&mut output[1]);
+        Rng::new(&[1; KEY_SIZE], &[3; KEY_SIZE]).fill_bytes(&mut output[2]);
+        assert_ne!(output[0], output[1]);
+        assert_ne!(output[0], output[2]);
+    }
+
+    #[test]
+    fn test_key_is_erased_after_use() {
+        let mut rng = Rng::new(&[1; KEY_SIZE], &[2; KEY_SIZE]);
+        let initial_key = rng.key;
+        rng.fill_bytes(&mut [0u8; 16]);
+        assert_ne!(rng.key, initial_key);
+    }
+
+    #[test]
+    fn test_stream_zeroize() {
+        let mut stream = Stream(ChaCha20Rng::from_seed([1; KEY_SIZE]));
+        stream.zeroize();
+        assert_eq!(stream.0.get_seed(), [0; KEY_SIZE]);
+    }
+
+    #[test]
+    fn test_reseed() {
+        let mut rng = Rng::new(&[1; KEY_SIZE], &[2; KEY_SIZE]);
+        rng.bytes_since_reseed = RESEED_INTERVAL;
+        let key = rng.key;
+        rng.reseed();
+        if hardware_entropy().is_ok() {
+            assert_ne!(rng.key, key);
+            assert_eq!(rng.bytes_since_reseed, 0);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use core::ffi::{c_int, c_ssize_t};
+
+use oak_restricted_kernel_interface::{
+    syscalls::{ClockId, Timespec},
+    Errno,
+};
+
+use crate::time;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+pub fn syscall_clock_gettime(clock_id: c_int, tp: *mut Timespec) -> c_ssize_t {
+    if tp.is_null() {
+        return Errno::EFAULT as isize;
+    }
+
+    let nanos = match ClockId::from_repr(clock_id) {
+        Some(ClockId::Realtime) => time::realtime_nanos(),
+        Some(ClockId::Monotonic) => time::monotonic_nanos(),
+        None => {
+            log::warn!("invalid clock ID passed to clock_gettime: {}", clock_id);
+            return Errno::EINVAL as isize;
+        }
+    };
+    let Some(nanos) = nanos else {
+        return Errno::EIO as isize;
+    };
+
+    // Safety: we should validate that the pointer is valid, as it comes from
+    // userspace and therefore is not to be trusted, but right now everything is
+    // in kernel space so there is nothing to check.
+    unsafe {
+        tp.write_unaligned(Timespec {
+            tv_sec: (nanos / NANOS_PER_SECOND) as i64,
+            tv_nsec: (nanos % NANOS_PER_SECOND) as i64,
+        })
+    };
+    0
+}
@@ -27,15 +27,64 @@ use oak_restricted_kernel_interface::{
 };
 use x86_64::{
     align_up,
-    structures::paging::{FrameAllocator, Page, PageSize, Size2MiB},
+    structures::paging::{
+        mapper::{FlagUpdateError, UnmapError},
+        page::PageRange,
+        FrameAllocator, FrameDeallocator, Page, PageSize, Size2MiB,
+    },
     VirtAddr,
 };
 
 use crate::{
-    mm::{Mapper, PageTableFlags},
+    mm::{Mapper, PageTableFlags, Translator},
     FRAME_ALLOCATOR, PAGE_TABLES,
 };
 
+/// Upper bound (exclusive) of the virtual addresses available to user space.
+///
+/// We assume virtual addresses are 48 bits, with the gap in the middle; the
+/// lower half belongs to user space.
+const USER_SPACE_END: u64 = 1 << 47;
+
+/// Returns the flags for user page table entries with the given protection.
+fn page_table_flags(prot: MmapProtection) -> PageTableFlags {
+    PageTableFlags::PRESENT
+        | PageTableFlags::USER_ACCESSIBLE
+        | PageTableFlags::ENCRYPTED
+        | if prot.contains(MmapProtection::PROT_EXEC) {
+            PageTableFlags::empty()
+        } else {
+            PageTableFlags::NO_EXECUTE
+        }
+        | if prot.contains(MmapProtection::PROT_WRITE) {
+            PageTableFlags::WRITABLE
+        } else {
+            PageTableFlags::empty()
+        }
+}
+
+/// Returns the range of 2 MiB pages covering `size` bytes starting from `addr`.
+///
+/// `addr` needs to be 2 MiB-aligned and the whole range needs to be in user
+/// space; `size` is rounded up to the closest 2 MiB boundary.
+fn user_pages(addr: VirtAddr, size: usize) -> Result<PageRange<Size2MiB>, Errno> {
+    if size == 0
+        || size as u64 >= USER_SPACE_END
+        || !addr.is_aligned(Size2MiB::SIZE)
+        || addr.as_u64() < Size2MiB::SIZE
+    {
+        log::warn!("invalid address {:?} or size {} for user pages", addr, size);
+        return Err(Errno::EINVAL);
+    }
+    let size = align_up(size as u64, Size2MiB::SIZE);
+    if addr.as_u64() + size >= USER_SPACE_END {
+        log::warn!("user pages at {:?} with size {} extend beyond user space", addr, size);
+        return Err(Errno::EINVAL);
+    }
+    let start = Page::containing_address(addr);
+    Ok(Page::range(start, start + size / Size2MiB::SIZE))
+}
+
 pub fn mmap(
     addr: Option<VirtAddr>,
     size: usize,
@@ -76,19 +125,7 @@ pub fn mmap(
     // Iterator that keeps allocating physical frames.
     let frames = repeat_with(|| FRAME_ALLOCATOR.lock().allocate_frame());
 
-    let pt_flags = PageTableFlags::PRESENT
-        | PageTableFlags::USER_ACCESSIBLE
-        | PageTableFlags::ENCRYPTED
-        | if prot.contains(MmapProtection::PROT_EXEC) {
-            PageTableFlags::empty()
-        } else {
-            PageTableFlags::NO_EXECUTE
-        }
-        | if prot.contains(MmapProtection::PROT_WRITE) {
-            PageTableFlags::WRITABLE
-        } else {
-            PageTableFlags::empty()
-        };
+    let pt_flags = page_table_flags(prot);
 
     let pages = {
         // This critical section is rather long...
@@ -195,3 +232,96 @@ pub fn syscall_mmap(
     mmap(Some(VirtAddr::from_ptr(addr)), size, prot, flags)
         .map_or_else(|err| err as isize, |ptr| ptr.as_ptr() as isize)
 }
+
+/// Removes the mappings for the pages covering the given range and returns
+/// the backing physical frames to the frame allocator.
+///
+/// Like on Linux, it's not an error if some of the pages in the range are not
+/// mapped.
+pub fn munmap(addr: VirtAddr, size: usize) -> Result<(), Errno> {
+    let pages = user_pages(addr, size)?;
+
+    let pt_guard = PAGE_TABLES.lock();
+    let pt = pt_guard.get().unwrap();
+    for page in pages {
+        // Safety: the page is in user space, so removing the mapping can't affect the
+        // kernel. It's up to the process to ensure it doesn't use the memory anymore.
+        match unsafe { pt.unmap(page) } {
+            Ok((frame, flush)) => {
+                flush.flush();
+                // Safety: the frame was only reachable through the mapping we've just
+                // removed, so nobody can be using it anymore.
+                unsafe { FRAME_ALLOCATOR.lock().deallocate_frame(frame) };
+            }
+            Err(UnmapError::PageNotMapped) => {}
+            Err(err) => {
+                log::warn!(
+                    "munmap: couldn't unmap page at address {}: {:?}",
+                    page.start_address().as_u64(),
+                    err
+                );
+                return Err(Errno::EINVAL);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Changes the protection of the pages covering the given range.
+///
+/// All of the pages in the range need to be mapped.
+pub fn mprotect(addr: VirtAddr, size: usize, prot: MmapProtection) -> Result<(), Errno> {
+    let pages = user_pages(addr, size)?;
+    let pt_flags = page_table_flags(prot);
+
+    let pt_guard = PAGE_TABLES.lock();
+    let pt = pt_guard.get().unwrap();
+    if pages.map(|page| pt.translate_virtual(page.start_address())).any(|addr| addr.is_none()) {
+        log::warn!("mprotect: range at {:?} contains unmapped pages", addr);
+        return Err(Errno::ENOMEM);
+    }
+    for page in pages {
+        // Safety: the page is in user space, so changing its flags can't affect the
+        // kernel.
+        unsafe { pt.update_flags(page, pt_flags) }
+            .map_err(|err| {
+                log::warn!(
+                    "mprotect: couldn't update flags for page at address {}: {:?}",
+                    page.start_address().as_u64(),
+                    err
+                );
+                match err {
+                    FlagUpdateError::PageNotMapped => Errno::ENOMEM,
+                    FlagUpdateError::ParentEntryHugePage => Errno::EINVAL,
+                }
+            })?
+            .flush();
+    }
+    Ok(())
+}
+
+/// Converts an address passed in by user space, rejecting non-canonical
+/// addresses.
+fn user_addr(addr: *const c_void) -> Result<VirtAddr, Errno> {
+    VirtAddr::try_new(addr as u64).map_err(|_| {
+        log::warn!("non-canonical address passed to syscall: {:?}", addr);
+        Errno::EINVAL
+    })
+}
+
+pub fn syscall_munmap(addr: *const c_void, size: c_size_t) -> isize {
+    user_addr(addr).and_then(|addr| munmap(addr, size)).map_or_else(|err| err as isize, |()| 0)
+}
+
+pub fn syscall_mprotect(addr: *const c_void, size: c_size_t, prot: usize) -> isize {
+    let prot = if let Some(prot) = MmapProtection::from_bits(prot as i32) {
+        prot
+    } else {
+        log::warn!("invalid protection flags passed to mprotect: {}", prot);
+        return Errno::EINVAL as isize;
+    };
+
+    user_addr(addr)
+        .and_then(|addr| mprotect(addr, size, prot))
+        .map_or_else(|err| err as isize, |()| 0)
+}
@@ -15,12 +15,14 @@
 //
 
 mod channel;
+mod clock;
 pub mod dice_data;
 pub mod event_log;
 mod fd;
 mod key;
 pub mod mmap;
 mod process;
+mod random;
 mod stdio;
 
 mod create_process;
@@ -33,7 +35,7 @@ use alloc::{boxed::Box, vec::Vec};
 use core::{arch::asm, ffi::c_void, mem::offset_of, ptr::addr_of_mut};
 
 use oak_channel::Channel;
-use oak_restricted_kernel_interface::{Errno, Syscall};
+use oak_restricted_kernel_interface::{syscalls::Timespec, Errno, Syscall};
 use x86_64::{
     registers::{
         control::Efer,
@@ -44,10 +46,12 @@ use x86_64::{
 };
 
 use self::{
+    clock::syscall_clock_gettime,
     create_process::syscall_unstable_create_proccess,
     fd::{syscall_fsync, syscall_read, syscall_write},
-    mmap::syscall_mmap,
+    mmap::{syscall_mmap, syscall_mprotect, syscall_munmap},
     process::syscall_exit,
+    random::syscall_getrandom,
     switch_process::syscall_unstable_switch_proccess,
 };
 use crate::mm;
@@ -92,7 +96,11 @@ extern "sysv64" fn syscall_handler(
         Some(Syscall::Mmap) => {
             syscall_mmap(arg1 as *const c_void, arg2, arg3, arg4, arg5 as i32, arg6)
         }
+        Some(Syscall::Mprotect) => syscall_mprotect(arg1 as *const c_void, arg2, arg3),
+        Some(Syscall::Munmap) => syscall_munmap(arg1 as *const c_void, arg2),
         Some(Syscall::Fsync) => syscall_fsync(arg1 as i32),
+        Some(Syscall::ClockGettime) => syscall_clock_gettime(arg1 as i32, arg2 as *mut Timespec),
+        Some(Syscall::GetRandom) => syscall_getrandom(arg1 as *mut c_void, arg2, arg3 as u32),
         Some(Syscall::UnstableCreateProcess) => {
             syscall_unstable_create_proccess(arg1 as *mut c_void, arg2)
         }
@@ -0,0 +1,53 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use core::ffi::{c_size_t, c_ssize_t, c_uint, c_void};
+
+use oak_restricted_kernel_interface::Errno;
+
+use crate::random;
+
+/// Upper bound on the number of bytes returned by a single call, to keep the
+/// time we spend holding the RNG lock bounded.
+const MAX_GETRANDOM_SIZE: usize = 1 << 16;
+
+pub fn syscall_getrandom(buf: *mut c_void, count: c_size_t, flags: c_uint) -> c_ssize_t {
+    if flags != 0 {
+        log::warn!("unsupported flags passed to getrandom: {:#x}", flags);
+        return Errno::EINVAL as isize;
+    }
+    if buf.is_null() && count > 0 {
+        return Errno::EFAULT as isize;
+    }
+
+    let count = count.min(MAX_GETRANDOM_SIZE);
+    if count == 0 {
+        return 0;
+    }
+
+    // Safety: we should validate that the pointer and count are valid, as these
+    // come from userspace and therefore are not to be trusted, but right now
+    // everything is in kernel space so there is nothing to check.
+    let data = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, count) };
+
+    match random::fill_bytes(data) {
+        Ok(()) => count as isize,
+        Err(err) => {
+            log::warn!("getrandom failed: {}", err);
+            Errno::EIO as isize
+        }
+    }
+}
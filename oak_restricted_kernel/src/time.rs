@@ -0,0 +1,465 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Timekeeping for the kernel and the applications running on top of it.
+//!
+//! Time is measured with the TSC. If SEV-SNP Secure TSC is enabled we read the
+//! TSC frequency from the `GUEST_TSC_FREQ` MSR, as in that case neither the
+//! counter nor its frequency can be manipulated by the hypervisor. Otherwise we
+//! calibrate the TSC against the PIT during boot.
+//!
+//! The wall clock time is anchored to the CMOS real-time clock, which is
+//! provided by the host. The RTC is periodically read again, and a new reading
+//! is only accepted if it is consistent with the time that has elapsed
+//! according to the TSC and doesn't move the wall clock backwards.
+
+use oak_core::{sync::OnceCell, timer::rdtsc};
+use oak_sev_guest::{
+    io::{IoPortFactory, PortFactoryWrapper, PortReader, PortWrapper, PortWriter},
+    msr::SevStatus,
+};
+use spinning_top::Spinlock;
+use x86_64::registers::model_specific::Msr;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// MSR that contains the TSC frequency of the guest when Secure TSC is enabled.
+///
+/// See section 15.36.18 of <https://www.amd.com/system/files/TechDocs/24593.pdf>.
+const GUEST_TSC_FREQ_MSR: u32 = 0xC001_0134;
+
+/// The TSC frequency, in MHz, is stored in the lower 18 bits of the MSR.
+const GUEST_TSC_FREQ_MHZ_MASK: u64 = 0x3_FFFF;
+
+const PIT_FREQUENCY_HZ: u64 = 1_193_182;
+const PIT_CHANNEL_2_PORT: u16 = 0x42;
+const PIT_COMMAND_PORT: u16 = 0x43;
+const PIT_GATE_PORT: u16 = 0x61;
+
+/// Channel 2, access mode lobyte/hibyte, mode 0 (interrupt on terminal count).
+const PIT_CHANNEL_2_ONE_SHOT: u8 = 0xB0;
+const PIT_GATE_ENABLE: u8 = 0x01;
+const PIT_SPEAKER_ENABLE: u8 = 0x02;
+const PIT_CHANNEL_2_OUTPUT: u8 = 0x20;
+
+/// We calibrate the TSC over 1/20th of a second (50 ms).
+const PIT_CALIBRATION_DIVISOR: u64 = 20;
+
+/// Maximum number of times we poll the PIT during calibration. Each poll is a
+/// port read, which takes at least tens of nanoseconds, so this allows for well
+/// over the 50 ms the countdown should take.
+const PIT_MAX_POLLS: u64 = 10_000_000;
+
+const CMOS_INDEX_PORT: u16 = 0x0070;
+const CMOS_DATA_PORT: u16 = 0x0071;
+const NMI_DISABLE_BIT: u8 = 0x80;
+
+const RTC_SECONDS: u8 = 0x00;
+const RTC_MINUTES: u8 = 0x02;
+const RTC_HOURS: u8 = 0x04;
+const RTC_DAY_OF_MONTH: u8 = 0x07;
+const RTC_MONTH: u8 = 0x08;
+const RTC_YEAR: u8 = 0x09;
+const RTC_STATUS_A: u8 = 0x0A;
+const RTC_STATUS_B: u8 = 0x0B;
+const RTC_STATUS_D: u8 = 0x0D;
+/// Century register, as advertised in the ACPI FADT by QEMU.
+const RTC_CENTURY: u8 = 0x32;
+
+const RTC_UPDATE_IN_PROGRESS: u8 = 0x80;
+const RTC_24_HOUR_MODE: u8 = 0x02;
+const RTC_BINARY_MODE: u8 = 0x04;
+const RTC_HOUR_PM: u8 = 0x80;
+
+/// Maximum number of times we poll status register A while an update is in
+/// progress. An update takes at most about 2 ms.
+const RTC_MAX_UPDATE_POLLS: u64 = 1_000_000;
+
+/// Maximum number of times we read the date and time while trying to get two
+/// consistent readings.
+const RTC_MAX_READ_ATTEMPTS: u32 = 10;
+
+/// How often we read the RTC again to correct the wall clock time.
+const RTC_REFRESH_INTERVAL_NANOS: u64 = 60 * NANOS_PER_SECOND;
+
+/// The RTC only has a resolution of one second.
+const RTC_RESOLUTION_NANOS: u64 = NANOS_PER_SECOND;
+
+/// Maximum drift between the RTC and the TSC we accept, as a fraction of the
+/// elapsed time (1%).
+const MAX_DRIFT_DIVISOR: u64 = 100;
+
+static CLOCK: OnceCell<Clock> = OnceCell::new();
+
+/// Raw date and time values, as stored in the RTC registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RtcDateTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+impl RtcDateTime {
+    /// Converts the raw register values to the number of seconds since the
+    /// Unix epoch, taking the data format in status register B into account.
+    fn to_unix_seconds(self, status_b: u8) -> Result<u64, &'static str> {
+        let decode = |value: u8| {
+            if status_b & RTC_BINARY_MODE != 0 {
+                value
+            } else {
+                (value >> 4) * 10 + (value & 0x0F)
+            }
+        };
+
+        let hour = if status_b & RTC_24_HOUR_MODE != 0 {
+            decode(self.hour)
+        } else {
+            // In 12-hour mode the highest bit of the hour indicates PM, and 12 o'clock is
+            // stored as 12.
+            let hour = decode(self.hour & !RTC_HOUR_PM) % 12;
+            if self.hour & RTC_HOUR_PM != 0 {
+                hour + 12
+            } else {
+                hour
+            }
+        };
+        let (second, minute, day, month) =
+            (decode(self.second), decode(self.minute), decode(self.day), decode(self.month));
+        // Not all RTCs implement the century register; assume the 21st century if it's
+        // not set.
+        let century = match decode(self.century) {
+            0 => 20,
+            century => century,
+        };
+        let year = century as u64 * 100 + decode(self.year) as u64;
+
+        if second > 59 || minute > 59 || hour > 23 || !(1..=31).contains(&day) {
+            return Err("invalid time in RTC");
+        }
+        if !(1..=12).contains(&month) || year < 1970 {
+            return Err("invalid date in RTC");
+        }
+
+        let days = days_since_epoch(year, month as u64, day as u64);
+        Ok(days * 86400 + hour as u64 * 3600 + minute as u64 * 60 + second as u64)
+    }
+}
+
+/// Returns the number of days between the Unix epoch and the given date in the
+/// proleptic Gregorian calendar.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // Treat March as the first month of the year, so that the leap day is at the
+    // end of the year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Reader for the CMOS real-time clock.
+struct Rtc {
+    index_port: PortWrapper<u8>,
+    data_port: PortWrapper<u8>,
+}
+
+impl Rtc {
+    /// # Safety
+    ///
+    /// The caller needs to guarantee that there are no other users of the CMOS
+    /// ports (0x70, 0x71) and that CMOS is actually available on those ports.
+    unsafe fn new(port_factory: &PortFactoryWrapper) -> Self {
+        Self {
+            index_port: port_factory.new_writer(CMOS_INDEX_PORT),
+            data_port: port_factory.new_reader(CMOS_DATA_PORT),
+        }
+    }
+
+    fn read_register(&mut self, index: u8) -> Result<u8, &'static str> {
+        // Safety: the caller of `new` guaranteed that the CMOS ports are exclusively
+        // available to us.
+        unsafe {
+            // NMIs are disabled while the register is selected, so that an NMI handler
+            // can't change the index in between.
+            self.index_port.try_write(index | NMI_DISABLE_BIT)?;
+            let value = self.data_port.try_read();
+            // Re-enable NMIs. Status register D is read-only, so leaving it selected is
+            // harmless.
+            self.index_port.try_write(RTC_STATUS_D)?;
+            value
+        }
+    }
+
+    fn read_date_time(&mut self) -> Result<RtcDateTime, &'static str> {
+        Ok(RtcDateTime {
+            second: self.read_register(RTC_SECONDS)?,
+            minute: self.read_register(RTC_MINUTES)?,
+            hour: self.read_register(RTC_HOURS)?,
+            day: self.read_register(RTC_DAY_OF_MONTH)?,
+            month: self.read_register(RTC_MONTH)?,
+            year: self.read_register(RTC_YEAR)?,
+            century: self.read_register(RTC_CENTURY)?,
+        })
+    }
+
+    /// Waits until the RTC isn't updating its registers.
+    fn wait_for_update(&mut self) -> Result<(), &'static str> {
+        for _ in 0..RTC_MAX_UPDATE_POLLS {
+            if self.read_register(RTC_STATUS_A)? & RTC_UPDATE_IN_PROGRESS == 0 {
+                return Ok(());
+            }
+            core::hint::spin_loop();
+        }
+        Err("RTC update didn't finish")
+    }
+
+    /// Returns the current time as nanoseconds since the Unix epoch.
+    fn unix_nanos(&mut self) -> Result<u64, &'static str> {
+        // The registers may be updated while we're reading them, so we keep reading
+        // until we get the same values twice in a row.
+        let mut date_time = None;
+        for _ in 0..RTC_MAX_READ_ATTEMPTS {
+            self.wait_for_update()?;
+            let first = self.read_date_time()?;
+            if first == self.read_date_time()? {
+                date_time = Some(first);
+                break;
+            }
+        }
+        let date_time = date_time.ok_or("RTC values kept changing while reading them")?;
+        let status_b = self.read_register(RTC_STATUS_B)?;
+        Ok(date_time.to_unix_seconds(status_b)? * NANOS_PER_SECOND)
+    }
+}
+
+/// Determines the frequency of the TSC, in Hz.
+fn tsc_frequency_hz(
+    sev_status: SevStatus,
+    port_factory: &PortFactoryWrapper,
+) -> Result<u64, &'static str> {
+    if sev_status.contains(SevStatus::SECURE_TSC_ENABLED) {
+        // Safety: the MSR is always available when Secure TSC is enabled, and reading
+        // it doesn't have any side effects.
+        let mhz = unsafe { Msr::new(GUEST_TSC_FREQ_MSR).read() } & GUEST_TSC_FREQ_MHZ_MASK;
+        if mhz == 0 {
+            return Err("invalid TSC frequency in GUEST_TSC_FREQ MSR");
+        }
+        return Ok(mhz * 1_000_000);
+    }
+
+    // Safety: the PIT and the speaker gate are always available on the platforms
+    // we support, and nothing else in the kernel uses them.
+    unsafe { calibrate_tsc(port_factory) }
+}
+
+/// Measures the TSC frequency by counting the ticks while the PIT counts down
+/// from a known value.
+///
+/// # Safety
+///
+/// The caller needs to guarantee that there are no other users of the PIT
+/// channel 2 and the speaker gate.
+unsafe fn calibrate_tsc(port_factory: &PortFactoryWrapper) -> Result<u64, &'static str> {
+    let mut gate: PortWrapper<u8> = port_factory.new_reader(PIT_GATE_PORT);
+    let mut command: PortWrapper<u8> = port_factory.new_writer(PIT_COMMAND_PORT);
+    let mut channel: PortWrapper<u8> = port_factory.new_writer(PIT_CHANNEL_2_PORT);
+
+    // Enable the gate of channel 2, but keep the speaker off.
+    let value = gate.try_read()?;
+    gate.try_write((value & !PIT_SPEAKER_ENABLE) | PIT_GATE_ENABLE)?;
+
+    let count = PIT_FREQUENCY_HZ / PIT_CALIBRATION_DIVISOR;
+    command.try_write(PIT_CHANNEL_2_ONE_SHOT)?;
+    channel.try_write(count as u8)?;
+    channel.try_write((count >> 8) as u8)?;
+
+    // The output of channel 2 goes high once the counter reaches zero.
+    let start = rdtsc();
+    let mut polls = 0;
+    while gate.try_read()? & PIT_CHANNEL_2_OUTPUT == 0 {
+        polls += 1;
+        if polls >= PIT_MAX_POLLS {
+            return Err("PIT countdown didn't finish during TSC calibration");
+        }
+        core::hint::spin_loop();
+    }
+    let ticks = rdtsc().saturating_sub(start);
+    if ticks == 0 {
+        return Err("TSC didn't advance during calibration");
+    }
+    Ok(ticks * PIT_CALIBRATION_DIVISOR)
+}
+
+/// The last accepted wall clock time.
+struct Anchor {
+    unix_nanos: u64,
+    tsc: u64,
+}
+
+struct Clock {
+    tsc_frequency_hz: u64,
+    boot_tsc: u64,
+    rtc: Spinlock<Rtc>,
+    anchor: Spinlock<Anchor>,
+}
+
+impl Clock {
+    fn tsc_to_nanos(&self, ticks: u64) -> u64 {
+        (ticks as u128 * NANOS_PER_SECOND as u128 / self.tsc_frequency_hz as u128) as u64
+    }
+
+    fn monotonic_nanos(&self) -> u64 {
+        self.tsc_to_nanos(rdtsc().saturating_sub(self.boot_tsc))
+    }
+
+    fn realtime_nanos(&self) -> u64 {
+        let mut anchor = self.anchor.lock();
+        let tsc = rdtsc();
+        let elapsed = self.tsc_to_nanos(tsc.saturating_sub(anchor.tsc));
+        let now = anchor.unix_nanos + elapsed;
+        if elapsed < RTC_REFRESH_INTERVAL_NANOS {
+            return now;
+        }
+
+        // The RTC only has a resolution of one second, so we only move the wall clock
+        // forward if the RTC is ahead of the TSC by more than that. We never move the
+        // clock backwards.
+        let max_drift = RTC_RESOLUTION_NANOS + elapsed / MAX_DRIFT_DIVISOR;
+        let now = match self.rtc.lock().unix_nanos() {
+            Ok(rtc_nanos) if rtc_nanos > now + max_drift || rtc_nanos + max_drift < now => {
+                log::warn!(
+                    "ignoring RTC time {} ns, as it's inconsistent with TSC time {} ns",
+                    rtc_nanos,
+                    now
+                );
+                now
+            }
+            Ok(rtc_nanos) if rtc_nanos > now + RTC_RESOLUTION_NANOS => rtc_nanos,
+            Ok(_) => now,
+            Err(err) => {
+                log::warn!("couldn't read the RTC: {}", err);
+                now
+            }
+        };
+        *anchor = Anchor { unix_nanos: now, tsc };
+        now
+    }
+}
+
+/// Initializes the kernel clock.
+///
+/// If initialization fails the clock will be unavailable, but the kernel can
+/// keep running.
+pub fn init(sev_status: SevStatus) -> Result<(), &'static str> {
+    let port_factory = if sev_status.contains(SevStatus::SEV_ES_ENABLED) {
+        crate::ghcb::get_ghcb_port_factory()
+    } else {
+        PortFactoryWrapper::new_raw()
+    };
+
+    let tsc_frequency_hz = tsc_frequency_hz(sev_status, &port_factory)?;
+    log::info!("TSC frequency: {} Hz", tsc_frequency_hz);
+
+    // Safety: CMOS is available on all platforms we support, and the kernel doesn't
+    // use it for anything else.
+    let mut rtc = unsafe { Rtc::new(&port_factory) };
+    let unix_nanos = rtc.unix_nanos()?;
+    let tsc = rdtsc();
+
+    CLOCK
+        .set(Clock {
+            tsc_frequency_hz,
+            boot_tsc: tsc,
+            rtc: Spinlock::new(rtc),
+            anchor: Spinlock::new(Anchor { unix_nanos, tsc }),
+        })
+        .map_err(|_| "clock already initialized")
+}
+
+/// Returns the number of nanoseconds since the Unix epoch, if the clock is
+/// available.
+pub fn realtime_nanos() -> Option<u64> {
+    CLOCK.get().map(Clock::realtime_nanos)
+}
+
+/// Returns the number of nanoseconds since the clock was initialized, if the
+/// clock is available.
+pub fn monotonic_nanos() -> Option<u64> {
+    CLOCK.get().map(Clock::monotonic_nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINARY_24_HOUR: u8 = RTC_BINARY_MODE | RTC_24_HOUR_MODE;
+
+    #[test]
+    fn days_since_epoch_known_dates() {
+        assert_eq!(days_since_epoch(1970, 1, 1), 0);
+        assert_eq!(days_since_epoch(2000, 3, 1), 11017);
+        assert_eq!(days_since_epoch(2024, 2, 29), 19782);
+    }
+
+    #[test]
+    fn rtc_binary_24_hour() {
+        let date_time =
+            RtcDateTime { second: 7, minute: 6, hour: 15, day: 1, month: 7, year: 24, century: 20 };
+        assert_eq!(date_time.to_unix_seconds(BINARY_24_HOUR), Ok(1719846367));
+    }
+
+    #[test]
+    fn rtc_bcd_12_hour() {
+        let date_time = RtcDateTime {
+            second: 0x07,
+            minute: 0x06,
+            hour: RTC_HOUR_PM | 0x03,
+            day: 0x01,
+            month: 0x07,
+            year: 0x24,
+            century: 0x20,
+        };
+        assert_eq!(date_time.to_unix_seconds(0), Ok(1719846367));
+    }
+
+    #[test]
+    fn rtc_missing_century() {
+        let date_time =
+            RtcDateTime { second: 7, minute: 6, hour: 15, day: 1, month: 7, year: 24, century: 0 };
+        assert_eq!(date_time.to_unix_seconds(BINARY_24_HOUR), Ok(1719846367));
+    }
+
+    #[test]
+    fn rtc_invalid_date() {
+        let date_time = RtcDateTime {
+            second: 7,
+            minute: 6,
+            hour: 15,
+            day: 1,
+            month: 13,
+            year: 24,
+            century: 20,
+        };
+        assert!(date_time.to_unix_seconds(BINARY_24_HOUR).is_err());
+    }
+}
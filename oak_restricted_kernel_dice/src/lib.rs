@@ -41,15 +41,22 @@ pub fn measure_digest_sha2_256(app_bytes: &[u8]) -> DigestSha2_256 {
     Sha256::digest(app_bytes).into()
 }
 
+/// Derives the sealing key for the application from the Layer 1 CDI.
+///
+/// Returns `None` if Stage 0 couldn't derive the CDI from a secret, in which
+/// case the application must not be given a sealing key.
 pub fn generate_derived_key(
     stage0_dice_data: &oak_dice::evidence::Stage0DiceData,
     app_digest: &DigestSha2_256,
-) -> DerivedKey {
+) -> Option<DerivedKey> {
+    if !stage0_dice_data.layer_1_cdi.is_available() {
+        return None;
+    }
     // Mix in the application digest when deriving CDI for Layer 2.
     let hkdf = Hkdf::<Sha256>::new(Some(app_digest), &stage0_dice_data.layer_1_cdi.cdi[..]);
     let mut derived_key = DerivedKey::default();
     hkdf.expand(b"CDI_Seal", &mut derived_key).expect("invalid length for derived key");
-    derived_key
+    Some(derived_key)
 }
 
 fn certificate_to_byte_array(cert: coset::CoseSign1) -> [u8; oak_dice::evidence::CERTIFICATE_SIZE] {
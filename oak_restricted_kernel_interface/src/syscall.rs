@@ -14,11 +14,11 @@
 // limitations under the License.
 //
 
-use core::ffi::{c_int, c_size_t, c_ssize_t, c_void};
+use core::ffi::{c_int, c_size_t, c_ssize_t, c_uint, c_void};
 
 use crate::{
     syscall,
-    syscalls::{MmapFlags, MmapProtection},
+    syscalls::{ClockId, MmapFlags, MmapProtection, Timespec},
     Errno, Syscall,
 };
 
@@ -110,6 +110,92 @@ pub fn mmap(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn sys_mprotect(addr: *const c_void, size: c_size_t, prot: c_int) -> c_ssize_t {
+    unsafe { syscall!(Syscall::Mprotect, addr, size, prot) }
+}
+
+/// Changes the protection of the memory mapped at `addr`.
+///
+/// # Safety
+///
+/// The caller needs to ensure that no existing references to the memory are
+/// used in ways that the new protection doesn't allow, e.g. writing through a
+/// `&mut [u8]` after `PROT_WRITE` has been removed.
+pub unsafe fn mprotect(
+    addr: *const c_void,
+    size: usize,
+    prot: MmapProtection,
+) -> Result<(), Errno> {
+    let ret = sys_mprotect(addr, size, prot.bits());
+
+    if ret < 0 {
+        Err(Errno::from_repr(ret)
+            .unwrap_or_else(|| panic!("unexpected error from mprotect syscall: {}", ret)))
+    } else {
+        Ok(())
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn sys_munmap(addr: *const c_void, size: c_size_t) -> c_ssize_t {
+    unsafe { syscall!(Syscall::Munmap, addr, size) }
+}
+
+/// Removes the mapping for the memory at `addr`, previously returned by
+/// [`mmap`].
+///
+/// # Safety
+///
+/// The memory must not be accessed after it has been unmapped; in particular,
+/// any slices returned by [`mmap`] for the range become dangling.
+pub unsafe fn munmap(addr: *const c_void, size: usize) -> Result<(), Errno> {
+    let ret = sys_munmap(addr, size);
+
+    if ret < 0 {
+        Err(Errno::from_repr(ret)
+            .unwrap_or_else(|| panic!("unexpected error from munmap syscall: {}", ret)))
+    } else {
+        Ok(())
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn sys_clock_gettime(clock_id: c_int, tp: *mut Timespec) -> c_ssize_t {
+    unsafe { syscall!(Syscall::ClockGettime, clock_id, tp) }
+}
+
+pub fn clock_gettime(clock_id: ClockId) -> Result<Timespec, Errno> {
+    let mut tp = Timespec::default();
+    let ret = sys_clock_gettime(clock_id as c_int, &mut tp);
+
+    if ret < 0 {
+        Err(Errno::from_repr(ret)
+            .unwrap_or_else(|| panic!("unexpected error from clock_gettime syscall: {}", ret)))
+    } else {
+        Ok(tp)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn sys_getrandom(buf: *mut c_void, count: c_size_t, flags: c_uint) -> c_ssize_t {
+    unsafe { syscall!(Syscall::GetRandom, buf, count, flags) }
+}
+
+/// Fills `buf` with random bytes from the kernel.
+///
+/// Returns the number of bytes written, which may be less than the size of the
+/// buffer.
+pub fn getrandom(buf: &mut [u8]) -> Result<usize, Errno> {
+    let ret = sys_getrandom(buf.as_mut_ptr() as *mut c_void, buf.len(), 0);
+    if ret < 0 {
+        Err(Errno::from_repr(ret)
+            .unwrap_or_else(|| panic!("unexpected error from getrandom syscall: {}", ret)))
+    } else {
+        Ok(ret as usize)
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn sys_exit(status: c_int) {
     unsafe { syscall!(Syscall::Exit, status) };
@@ -183,6 +269,26 @@ mod tests {
         assert!(fsync(fd).is_err());
     }
 
+    #[test]
+    fn test_clock_gettime() {
+        let realtime = clock_gettime(ClockId::Realtime).unwrap();
+        assert!(realtime.tv_sec > 0);
+        assert!((0..1_000_000_000).contains(&realtime.tv_nsec));
+
+        let first = clock_gettime(ClockId::Monotonic).unwrap();
+        let second = clock_gettime(ClockId::Monotonic).unwrap();
+        assert!((second.tv_sec, second.tv_nsec) >= (first.tv_sec, first.tv_nsec));
+    }
+
+    #[test]
+    fn test_getrandom() {
+        let mut first = [0u8; 32];
+        let mut second = [0u8; 32];
+        assert_eq!(Ok(32), getrandom(&mut first));
+        assert_eq!(Ok(32), getrandom(&mut second));
+        assert_ne!(first, second);
+    }
+
     #[test]
     fn test_mmap() {
         let mem = mmap(
@@ -196,6 +302,44 @@ mod tests {
         assert!(mem.is_ok());
     }
 
+    #[test]
+    fn test_mprotect() {
+        let mem = mmap(
+            None,
+            1024,
+            MmapProtection::PROT_READ | MmapProtection::PROT_WRITE,
+            MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+            -1,
+            0,
+        )
+        .unwrap();
+        mem[0] = 42;
+        assert!(unsafe {
+            mprotect(mem.as_ptr() as *const c_void, mem.len(), MmapProtection::PROT_READ)
+        }
+        .is_ok());
+        assert_eq!(mem[0], 42);
+    }
+
+    #[test]
+    fn test_munmap() {
+        let mem = mmap(
+            None,
+            1024,
+            MmapProtection::PROT_READ | MmapProtection::PROT_WRITE,
+            MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+            -1,
+            0,
+        )
+        .unwrap();
+        assert!(unsafe { munmap(mem.as_ptr() as *const c_void, mem.len()) }.is_ok());
+    }
+
+    #[test]
+    fn test_munmap_error() {
+        assert!(unsafe { munmap(0x1001 as *const c_void, 1024) }.is_err());
+    }
+
     #[test]
     fn test_mmap_error() {
         let mem = mmap(
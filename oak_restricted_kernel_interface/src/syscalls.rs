@@ -83,6 +83,40 @@ pub enum Syscall {
     ///   - We do not support PROT_NONE; PROT_READ is always implied.
     Mmap = 9,
 
+    /// Changes the protection of a memory mapping.
+    ///
+    /// Arguments:
+    ///   - arg0 (*const c_void): start address of the mapping
+    ///   - arg1 (c_size_t): size of the mapping
+    ///   - arg2 (c_int): new protection (PROT_EXEC, PROT_READ, PROT_WRITE)
+    ///
+    /// Returns:
+    ///   a value of <errno::Errno> on failure; 0, otherwise.
+    ///
+    /// Oak Restricted Kernel considerations:
+    ///   - as with mmap, we work on 2 MiB chunks: the address needs to be 2
+    ///     MiB-aligned, and the size will be rounded up to the closest 2 MiB
+    ///     boundary.
+    ///   - all of the pages in the range need to be mapped.
+    ///   - We do not support PROT_NONE; PROT_READ is always implied.
+    Mprotect = 10,
+
+    /// Removes a memory mapping and releases the memory backing it.
+    ///
+    /// Arguments:
+    ///   - arg0 (*const c_void): start address of the mapping
+    ///   - arg1 (c_size_t): size of the mapping
+    ///
+    /// Returns:
+    ///   a value of <errno::Errno> on failure; 0, otherwise.
+    ///
+    /// Oak Restricted Kernel considerations:
+    ///   - as with mmap, we work on 2 MiB chunks: the address needs to be 2
+    ///     MiB-aligned, and the size will be rounded up to the closest 2 MiB
+    ///     boundary.
+    ///   - it is not an error if the range contains pages that aren't mapped.
+    Munmap = 11,
+
     /// Terminates he calling process.
     /// Arguments:
     ///   - arg0 (c_int): error code
@@ -101,6 +135,41 @@ pub enum Syscall {
     ///   a value of <errno::Errno> on failure; 0, otherwise.
     Fsync = 74,
 
+    /// Retrieves the time of the specified clock.
+    ///
+    /// Arguments:
+    ///   - arg0 (c_int): clock ID (CLOCK_REALTIME, CLOCK_MONOTONIC)
+    ///   - arg1 (*mut Timespec): pointer to the structure to be filled
+    ///
+    /// Returns:
+    ///   a value of <errno::Errno> on failure; 0, otherwise.
+    ///
+    /// Oak Restricted Kernel considerations:
+    ///   - the clocks are based on the TSC, which is protected from the host
+    ///     only if SEV-SNP Secure TSC is enabled.
+    ///   - CLOCK_REALTIME is anchored to the wall clock time provided by the
+    ///     host, but it will never go backwards.
+    ///   - CLOCK_MONOTONIC counts the time since the kernel was booted.
+    ClockGettime = 228,
+
+    /// Fills a buffer with random bytes.
+    ///
+    /// Arguments:
+    ///   - arg0 (*mut c_void): pointer to the buffer to be filled
+    ///   - arg1 (c_size_t): size of the buffer
+    ///   - arg2 (c_uint): flags. No flags are supported; should be set to 0 by
+    ///     caller.
+    ///
+    /// Returns:
+    ///   a value of <errno::Errno> on failure; otherwise, the number of bytes
+    /// written.
+    ///
+    /// Oak Restricted Kernel considerations:
+    ///   - the bytes come from a kernel CSPRNG that is seeded from
+    ///     RDSEED/RDRAND and the DICE CDI at boot, so the call never blocks.
+    ///   - the call may return fewer bytes than requested for large buffers.
+    GetRandom = 318,
+
     /// Create new process from ELF file and starts it.
     ///
     /// Arguments:
@@ -151,3 +220,26 @@ bitflags! {
         const MAP_ANONYMOUS = 0x20;
     }
 }
+
+/// Clocks supported by the `ClockGettime` syscall.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, FromRepr, PartialEq, Eq)]
+pub enum ClockId {
+    /// Wall clock time, measured from the Unix epoch.
+    Realtime = 0,
+
+    /// Time since an unspecified point in the past, not affected by changes
+    /// to the wall clock time.
+    Monotonic = 1,
+}
+
+/// Time value filled in by the `ClockGettime` syscall.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timespec {
+    /// Whole seconds.
+    pub tv_sec: i64,
+
+    /// Nanoseconds, in the range 0..999_999_999.
+    pub tv_nsec: i64,
+}
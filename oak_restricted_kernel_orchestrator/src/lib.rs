@@ -36,7 +36,7 @@ use crate::alloc::string::ToString;
 
 pub struct AttestedApp {
     pub elf_binary: alloc::vec::Vec<u8>,
-    pub derived_key: oak_restricted_kernel_dice::DerivedKey,
+    pub derived_key: Option<oak_restricted_kernel_dice::DerivedKey>,
     pub dice_data: oak_dice::evidence::RestrictedKernelDiceData,
     pub event_log: EventLog,
 }
@@ -0,0 +1,80 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Access to the time provided by Restricted Kernel.
+
+use oak_restricted_kernel_interface::{
+    syscall::clock_gettime,
+    syscalls::{ClockId, Timespec},
+    Errno,
+};
+
+/// Clock that reads the wall clock time from Restricted Kernel.
+///
+/// The time is only protected from the host if the enclave runs with SEV-SNP
+/// Secure TSC enabled; see the `ClockGettime` syscall for details.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    _private: (),
+}
+
+impl SystemClock {
+    /// Creates a clock, failing if Restricted Kernel doesn't provide the time.
+    ///
+    /// The kernel clock is initialized once during boot, so once reading the
+    /// time has succeeded, later reads succeed as well.
+    pub fn new() -> Result<Self, Errno> {
+        clock_gettime(ClockId::Realtime)?;
+        clock_gettime(ClockId::Monotonic)?;
+        Ok(Self { _private: () })
+    }
+
+    /// Returns the number of milliseconds since the Unix epoch.
+    pub fn now_utc_millis(&self) -> Result<i64, Errno> {
+        clock_gettime(ClockId::Realtime).map(to_millis)
+    }
+
+    /// Returns the number of milliseconds since an unspecified point in the
+    /// past, which is guaranteed to never go backwards.
+    pub fn monotonic_millis(&self) -> Result<i64, Errno> {
+        clock_gettime(ClockId::Monotonic).map(to_millis)
+    }
+}
+
+fn to_millis(time: Timespec) -> i64 {
+    time.tv_sec * 1000 + time.tv_nsec / 1_000_000
+}
+
+/// Returns the wall clock time for the clock traits, which can't report errors.
+///
+/// `SystemClock::new` ensures that the time is available, so this doesn't fail
+/// in practice. If it did, the Unix epoch is returned, which fails validity
+/// checks closed instead of panicking.
+fn now_utc_millis_or_epoch(clock: &SystemClock) -> i64 {
+    clock.now_utc_millis().unwrap_or(0)
+}
+
+impl oak_session::clock::Clock for SystemClock {
+    fn get_current_time_ms(&self) -> i64 {
+        now_utc_millis_or_epoch(self)
+    }
+}
+
+impl oak_attestation_verification_types::util::Clock for SystemClock {
+    fn get_milliseconds_since_epoch(&self) -> i64 {
+        now_utc_millis_or_epoch(self)
+    }
+}
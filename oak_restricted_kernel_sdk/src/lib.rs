@@ -23,8 +23,10 @@ extern crate alloc;
 
 pub mod attestation;
 pub mod channel;
+pub mod clock;
 pub mod crypto;
 pub mod handler;
+pub mod random;
 #[cfg(any(feature = "testing", doc))]
 #[doc(cfg(feature = "testing"))]
 pub mod testing;
@@ -0,0 +1,73 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Access to the random number generator provided by Restricted Kernel.
+//!
+//! The kernel RNG is registered as the backend of the `getrandom` crate, so
+//! crates like `rand_core::OsRng`, `p256` and `hpke` can be used without any
+//! additional setup.
+
+#[cfg(target_os = "none")]
+use core::{
+    num::NonZeroU32,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use oak_restricted_kernel_interface::{syscall::getrandom, Errno};
+
+/// Fills `dest` with random bytes from the kernel RNG.
+pub fn fill_bytes(dest: &mut [u8]) -> Result<(), Errno> {
+    let mut remaining = dest;
+    while !remaining.is_empty() {
+        let count = getrandom(remaining)?;
+        if count == 0 {
+            return Err(Errno::EIO);
+        }
+        remaining = &mut remaining[count..];
+    }
+    Ok(())
+}
+
+/// Number of times `getrandom` has called into the kernel RNG.
+#[cfg(target_os = "none")]
+static BACKEND_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_os = "none")]
+fn getrandom_backend(dest: &mut [u8]) -> Result<(), getrandom::Error> {
+    BACKEND_CALLS.fetch_add(1, Ordering::SeqCst);
+    fill_bytes(dest).map_err(|err| {
+        let code = getrandom::Error::CUSTOM_START + (-(err as isize)) as u32;
+        NonZeroU32::new(code).expect("custom error codes are non-zero").into()
+    })
+}
+
+#[cfg(target_os = "none")]
+getrandom::register_custom_getrandom!(getrandom_backend);
+
+/// Checks that `getrandom` is backed by the kernel RNG.
+///
+/// Enabling the `rdrand` feature of `getrandom` anywhere in the dependency
+/// graph silently takes precedence over the custom backend registered above,
+/// so this is checked when an enclave app starts.
+#[cfg(target_os = "none")]
+pub fn check_getrandom_backend() -> Result<(), &'static str> {
+    let calls = BACKEND_CALLS.load(Ordering::SeqCst);
+    getrandom::getrandom(&mut [0u8; 1]).map_err(|_| "getrandom failed")?;
+    if BACKEND_CALLS.load(Ordering::SeqCst) == calls {
+        return Err("getrandom isn't backed by the kernel RNG");
+    }
+    Ok(())
+}
@@ -82,6 +82,8 @@ fn process_entry_fn(entry_fn: ItemFn) -> TokenStream {
                     oak_restricted_kernel_sdk::utils::log::LevelFilter::Warn
                 }
             );
+            oak_restricted_kernel_sdk::random::check_getrandom_backend()
+                .expect("failed to check the getrandom backend");
             oak_restricted_kernel_sdk::utils::log::info!("In main!");
             #entry_fn_name();
         }
@@ -25,7 +25,7 @@ use crate::{
         AttestationAggregator, AttestationType, AttestationVerifier, Attester,
         DefaultAttestationAggregator, Endorser,
     },
-    encryptors::OrderedChannelEncryptor,
+    encryptors::{OrderedChannelEncryptor, UnorderedChannelEncryptor},
     handshake::HandshakeType,
     key_extractor::{DefaultSigningKeyExtractor, KeyExtractor},
     session_binding::SessionBinder,
@@ -54,6 +54,12 @@ pub struct SessionConfigBuilder {
 
 pub trait EncryptorProvider: Send {
     fn provide_encryptor(&self, session_keys: SessionKeys) -> Result<Box<dyn Encryptor>, Error>;
+
+    /// Whether the provided encryptors support rekeying, which is required
+    /// for the thresholds in [`RekeyConfig`] to be set.
+    fn supports_rekey(&self) -> bool {
+        true
+    }
 }
 
 pub struct OrderedChannelEncryptorProvider;
@@ -65,6 +71,25 @@ impl EncryptorProvider for OrderedChannelEncryptorProvider {
     }
 }
 
+/// Provides an [`UnorderedChannelEncryptor`] for transports that may drop or
+/// reorder messages. Messages are accepted in any order as long as they are
+/// within `window_size` messages of the furthest message received so far, and
+/// replayed messages are rejected.
+pub struct UnorderedChannelEncryptorProvider {
+    pub window_size: u32,
+}
+
+impl EncryptorProvider for UnorderedChannelEncryptorProvider {
+    fn provide_encryptor(&self, session_keys: SessionKeys) -> Result<Box<dyn Encryptor>, Error> {
+        TryInto::<UnorderedChannelEncryptor>::try_into((session_keys, self.window_size))
+            .map(|v| Box::new(v) as Box<dyn Encryptor>)
+    }
+
+    fn supports_rekey(&self) -> bool {
+        false
+    }
+}
+
 impl SessionConfigBuilder {
     fn new(attestation_type: AttestationType, handshake_type: HandshakeType) -> Self {
         let attestation_provider_config = AttestationProviderConfig {
@@ -82,8 +107,10 @@ impl SessionConfigBuilder {
             session_binders: BTreeMap::new(),
         };
 
-        let encryptor_config =
-            EncryptorConfig { encryptor_provider: Box::new(OrderedChannelEncryptorProvider) };
+        let encryptor_config = EncryptorConfig {
+            encryptor_provider: Box::new(OrderedChannelEncryptorProvider),
+            rekey_config: RekeyConfig::default(),
+        };
 
         let binding_key_extractor = Box::new(DefaultSigningKeyExtractor);
 
@@ -156,6 +183,29 @@ impl SessionConfigBuilder {
         self
     }
 
+    /// Use an [`UnorderedChannelEncryptor`] that tolerates dropped and
+    /// reordered messages within `window_size`, e.g. for UDP-like transports.
+    ///
+    /// Rekeying is not supported with unordered encryption, so this can't be
+    /// combined with the rekey thresholds.
+    pub fn set_unordered_encryption(self, window_size: u32) -> Self {
+        self.set_encryption_provider(Box::new(UnorderedChannelEncryptorProvider { window_size }))
+    }
+
+    /// Rekey the outgoing direction of the session after `max_messages`
+    /// messages have been encrypted with the current key.
+    pub fn set_rekey_message_threshold(mut self, max_messages: u64) -> Self {
+        self.config.encryptor_config.rekey_config.max_messages = Some(max_messages);
+        self
+    }
+
+    /// Rekey the outgoing direction of the session after `max_bytes` bytes of
+    /// plaintext have been encrypted with the current key.
+    pub fn set_rekey_byte_threshold(mut self, max_bytes: u64) -> Self {
+        self.config.encryptor_config.rekey_config.max_bytes = Some(max_bytes);
+        self
+    }
+
     pub fn set_binding_key_extractor(
         mut self,
         binding_key_extractor: Box<dyn KeyExtractor>,
@@ -173,7 +223,18 @@ impl SessionConfigBuilder {
         self
     }
 
+    /// Builds the session config.
+    ///
+    /// Panics if rekey thresholds were set for an encryptor provider that
+    /// doesn't support rekeying.
     pub fn build(self) -> SessionConfig {
+        let rekey_config = &self.config.encryptor_config.rekey_config;
+        if rekey_config.max_messages.is_some() || rekey_config.max_bytes.is_some() {
+            assert!(
+                self.config.encryptor_config.encryptor_provider.supports_rekey(),
+                "rekey thresholds are not supported by the encryptor provider"
+            );
+        }
         self.config
     }
 }
@@ -202,4 +263,16 @@ pub struct HandshakerConfig {
 
 pub struct EncryptorConfig {
     pub encryptor_provider: Box<dyn EncryptorProvider>,
+    pub rekey_config: RekeyConfig,
+}
+
+/// Thresholds after which a peer replaces the key it uses to encrypt outgoing
+/// messages. The rekey is signalled in-band with a `KeyUpdate` message, so the
+/// peer switches to the new key at exactly the same position in the message
+/// stream. Unset thresholds never trigger a rekey. Rekeying relies on in-order
+/// delivery and is therefore only supported by the [`OrderedChannelEncryptor`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RekeyConfig {
+    pub max_messages: Option<u64>,
+    pub max_bytes: Option<u64>,
 }
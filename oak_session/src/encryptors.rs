@@ -33,16 +33,36 @@ pub struct OrderedChannelEncryptor {
 impl Encryptor for OrderedChannelEncryptor {
     fn encrypt(&mut self, plaintext: &Payload) -> anyhow::Result<Payload> {
         self.crypter
-            .encrypt(plaintext.message.as_slice())
+            .encrypt_with_nonce(plaintext.message.as_slice())
             .map(From::from)
             .map_err(|e| anyhow!("Encryption error: {e:#?}"))
     }
 
     fn decrypt(&mut self, ciphertext: &Payload) -> anyhow::Result<Payload> {
-        self.crypter
-            .decrypt(ciphertext.message.as_slice())
-            .map(From::from)
-            .map_err(|e| anyhow!("Encryption error: {e:#?}"))
+        // Nonces are optional for ordered channels, since both peers keep track
+        // of them, but if one is sent it must match the expected one.
+        match ciphertext.nonce.as_ref() {
+            Some(nonce) => self.crypter.decrypt_with_nonce(
+                nonce
+                    .as_slice()
+                    .try_into()
+                    .map_err(|e| anyhow!("Failed to extract nonce error: {e:#?}"))?,
+                ciphertext.message.as_slice(),
+            ),
+            None => self.crypter.decrypt(ciphertext.message.as_slice()),
+        }
+        .map(From::from)
+        .map_err(|e| anyhow!("Encryption error: {e:#?}"))
+    }
+
+    fn rekey_encryption(&mut self) -> anyhow::Result<()> {
+        self.crypter.rekey_write();
+        Ok(())
+    }
+
+    fn rekey_decryption(&mut self) -> anyhow::Result<()> {
+        self.crypter.rekey_read();
+        Ok(())
     }
 }
 
@@ -81,8 +101,8 @@ impl Encryptor for UnorderedChannelEncryptor {
         let nonce: [u8; NONCE_LEN] = ciphertext
             .nonce
             .as_ref()
-            .unwrap()
-            .clone()
+            .ok_or(anyhow!("unordered channel messages must contain a nonce"))?
+            .as_slice()
             .try_into()
             .map_err(|e| anyhow!("Failed to extract nonce error: {e:#?}"))?;
         self.crypter
@@ -23,7 +23,10 @@ use core::convert::TryInto;
 use anyhow::{anyhow, Context, Error};
 use oak_crypto::{
     identity_key::IdentityKeyHandle,
-    noise_handshake::{client::HandshakeInitiator, respond_kk, respond_nk, respond_nn, Response},
+    noise_handshake::{
+        client::HandshakeInitiator, respond_ik, respond_kk, respond_nk, respond_nn, respond_xx,
+        NoiseMessage, Response, XxResponder,
+    },
 };
 use oak_proto_rust::oak::{
     crypto::v1::SessionKeys,
@@ -41,6 +44,12 @@ pub enum HandshakeType {
     NoiseKN,
     NoiseNK,
     NoiseNN,
+    /// Mutual authentication where both static keys are transmitted encrypted
+    /// during the handshake, so no keys need to be shared in advance.
+    NoiseXX,
+    /// Mutual authentication where the responder's static key is known in
+    /// advance and the initiator's static key is transmitted encrypted.
+    NoiseIK,
 }
 
 /// Struct that represents the data extracted from a successfully executed Noise
@@ -52,6 +61,9 @@ pub struct HandshakeResult {
     pub handshake_hash: Vec<u8>,
     /// Bindings fo
     pub session_bindings: BTreeMap<String, SessionBinding>,
+    /// Static public key of the peer, if it was pre-shared or learned during
+    /// the handshake.
+    pub peer_static_public_key: Option<Vec<u8>>,
 }
 
 /// Trait that allows building a handshaker without passing any more data to it.
@@ -94,6 +106,11 @@ pub trait Handshaker: Send {
     /// handshake is not yet complete.
     fn get_handshake_hash(&self) -> Result<Vec<u8>, Error>;
 
+    /// Gets the static public key of the peer, if it was pre-shared or learned
+    /// during the handshake (e.g. for XX and IK). Returns an error if the
+    /// handshake is not yet complete.
+    fn get_peer_static_public_key(&self) -> Result<Option<Vec<u8>>, Error>;
+
     // Allows checking whether the handshake is complete without consuming the
     // produced results.
     fn is_handshake_complete(&self) -> bool;
@@ -101,6 +118,7 @@ pub trait Handshaker: Send {
 
 /// Client-side Handshaker that initiates the crypto handshake with the server.
 pub struct ClientHandshaker {
+    handshake_type: HandshakeType,
     handshake_initiator: HandshakeInitiator,
     session_binders: BTreeMap<String, Box<dyn SessionBinder>>,
     initial_message: Option<HandshakeRequest>,
@@ -132,26 +150,33 @@ impl ClientHandshaker {
                     .map_err(|error| anyhow!("invalid peer public key: {:?}", error))?,
             ),
             HandshakeType::NoiseNN => HandshakeInitiator::new_nn(),
+            HandshakeType::NoiseXX => HandshakeInitiator::new_xx(
+                handshaker_config
+                    .self_static_private_key
+                    .context("handshaker_config missing the self static private key")?,
+            ),
+            HandshakeType::NoiseIK => HandshakeInitiator::new_ik(
+                peer_static_public_key
+                    .context("handshaker_config missing the peer public key")?
+                    .as_slice()
+                    .try_into()
+                    .map_err(|error| anyhow!("invalid peer public key: {:?}", error))?,
+                handshaker_config
+                    .self_static_private_key
+                    .context("handshaker_config missing the self static private key")?,
+            ),
         };
         let initial_noise_message = handshake_initiator
             .build_initial_message()
             .map_err(|e| anyhow!("Error building initial message: {e:?}"))?;
         let initial_message = HandshakeRequest {
             r#handshake_type: Some(handshake_request::HandshakeType::NoiseHandshakeMessage(
-                NoiseHandshakeMessage {
-                    ephemeral_public_key: initial_noise_message.ephemeral_public_key,
-                    static_public_key: match handshake_type {
-                        HandshakeType::NoiseKK
-                        | HandshakeType::NoiseKN
-                        | HandshakeType::NoiseNK
-                        | HandshakeType::NoiseNN => vec![],
-                    },
-                    ciphertext: initial_noise_message.ciphertext,
-                },
+                initial_noise_message.into(),
             )),
             attestation_bindings: BTreeMap::new(),
         };
         Ok(Self {
+            handshake_type,
             handshake_initiator,
             session_binders: handshaker_config.session_binders,
             initial_message: Some(initial_message),
@@ -175,6 +200,15 @@ impl Handshaker for ClientHandshaker {
             .clone())
     }
 
+    fn get_peer_static_public_key(&self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self
+            .handshake_result
+            .as_ref()
+            .ok_or(anyhow!("handshake is not complete"))?
+            .peer_static_public_key
+            .clone())
+    }
+
     fn is_handshake_complete(&self) -> bool {
         self.handshake_result.is_some() && self.followup_message.is_none()
     }
@@ -199,18 +233,30 @@ impl ProtocolEngine<HandshakeResponse, HandshakeRequest> for ClientHandshaker {
         }
         match incoming_message.r#handshake_type.as_ref() {
             Some(handshake_response::HandshakeType::NoiseHandshakeMessage(noise_message)) => {
-                let handshake_result = self
-                    .handshake_initiator
-                    .process_response(&noise_message.into())
-                    .map_err(|e| anyhow!("Error processing response: {e:?}"))
-                    .map(|(handshake_hash, crypter)| HandshakeResult {
-                        session_keys: crypter.into(),
-                        handshake_hash: handshake_hash.to_vec(),
-                        session_bindings: incoming_message.attestation_bindings.clone(),
-                    })?;
-                if !self.session_binders.is_empty() {
+                let (handshake_hash, crypter, final_noise_message) = match self.handshake_type {
+                    HandshakeType::NoiseXX => self
+                        .handshake_initiator
+                        .process_xx_response(&noise_message.into())
+                        .map(|(final_message, handshake_hash, crypter)| {
+                            (handshake_hash, crypter, Some(final_message))
+                        }),
+                    _ => self
+                        .handshake_initiator
+                        .process_response(&noise_message.into())
+                        .map(|(handshake_hash, crypter)| (handshake_hash, crypter, None)),
+                }
+                .map_err(|e| anyhow!("Error processing response: {e:?}"))?;
+                let handshake_result = HandshakeResult {
+                    session_keys: crypter.into(),
+                    handshake_hash: handshake_hash.to_vec(),
+                    session_bindings: incoming_message.attestation_bindings.clone(),
+                    peer_static_public_key: self.handshake_initiator.peer_static_public_key(),
+                };
+                if !self.session_binders.is_empty() || final_noise_message.is_some() {
                     self.followup_message = Some(HandshakeRequest {
-                        r#handshake_type: None,
+                        r#handshake_type: final_noise_message.map(|message| {
+                            handshake_request::HandshakeType::NoiseHandshakeMessage(message.into())
+                        }),
                         attestation_bindings: self
                             .session_binders
                             .iter()
@@ -245,6 +291,8 @@ pub struct ServerHandshaker {
     session_binders: BTreeMap<String, Box<dyn SessionBinder>>,
     client_binding_expected: bool,
     noise_response: Option<Response>,
+    // Set while an XX handshake waits for the final message from the client.
+    xx_responder: Option<XxResponder>,
     handshake_response: Option<HandshakeResponse>,
     handshake_result: Option<HandshakeResult>,
 }
@@ -258,10 +306,34 @@ impl ServerHandshaker {
             session_binders: handshaker_config.session_binders,
             client_binding_expected,
             noise_response: None,
+            xx_responder: None,
             handshake_response: None,
             handshake_result: None,
         }
     }
+
+    fn create_handshake_response(
+        &self,
+        noise_message: &NoiseMessage,
+        handshake_hash: &[u8],
+    ) -> anyhow::Result<HandshakeResponse> {
+        Ok(HandshakeResponse {
+            r#handshake_type: Some(handshake_response::HandshakeType::NoiseHandshakeMessage(
+                NoiseHandshakeMessage {
+                    ephemeral_public_key: noise_message.ephemeral_public_key.clone(),
+                    static_public_key: noise_message.static_public_key.clone(),
+                    ciphertext: noise_message.ciphertext.clone(),
+                },
+            )),
+            attestation_bindings: self
+                .session_binders
+                .iter()
+                .map(|(id, binder)| {
+                    Ok((id.clone(), SessionBinding { binding: binder.bind(handshake_hash) }))
+                })
+                .collect::<Result<BTreeMap<String, SessionBinding>, Error>>()?,
+        })
+    }
 }
 
 impl Handshaker for ServerHandshaker {
@@ -278,6 +350,15 @@ impl Handshaker for ServerHandshaker {
             .clone())
     }
 
+    fn get_peer_static_public_key(&self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self
+            .handshake_result
+            .as_ref()
+            .ok_or(anyhow!("handshake is not complete"))?
+            .peer_static_public_key
+            .clone())
+    }
+
     fn is_handshake_complete(&self) -> bool {
         self.handshake_result.is_some()
     }
@@ -301,63 +382,85 @@ impl ProtocolEngine<HandshakeRequest, HandshakeResponse> for ServerHandshaker {
                 session_keys: noise_response.crypter.into(),
                 handshake_hash: noise_response.handshake_hash.to_vec(),
                 session_bindings: incoming_message.attestation_bindings.clone(),
+                peer_static_public_key: noise_response.initiator_static_public_key,
             });
-        } else {
-            let noise_response = match incoming_message.r#handshake_type.as_ref() {
+        } else if let Some(xx_responder) = self.xx_responder.take() {
+            let noise_message = match incoming_message.r#handshake_type.as_ref() {
                 Some(handshake_request::HandshakeType::NoiseHandshakeMessage(noise_message)) => {
-                    match self.handshake_type {
-                        HandshakeType::NoiseKN => core::unimplemented!(),
-                        HandshakeType::NoiseKK => respond_kk(
-                            self.self_identity_key
-                                .as_ref()
-                                .context("handshaker_config missing the self private key")?
-                                .as_ref(),
-                            self.peer_public_key
-                                .as_ref()
-                                .ok_or(anyhow!("Must provide public key for Kk"))?,
-                            &noise_message.into(),
-                        )
-                        .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
-                        HandshakeType::NoiseNK => respond_nk(
-                            self.self_identity_key
-                                .as_ref()
-                                .context("handshaker_config missing the self private key")?
-                                .as_ref(),
-                            &noise_message.into(),
-                        )
-                        .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
-                        HandshakeType::NoiseNN => respond_nn(&noise_message.into())
-                            .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
-                    }
+                    noise_message
                 }
                 None => return Err(anyhow!("Missing handshake_type")),
             };
-            self.handshake_response = Some(HandshakeResponse {
-                r#handshake_type: Some(handshake_response::HandshakeType::NoiseHandshakeMessage(
-                    NoiseHandshakeMessage {
-                        ephemeral_public_key: noise_response.response.ephemeral_public_key.clone(),
-                        static_public_key: match self.handshake_type {
-                            HandshakeType::NoiseKK
-                            | HandshakeType::NoiseKN
-                            | HandshakeType::NoiseNK
-                            | HandshakeType::NoiseNN => vec![],
-                        },
-                        ciphertext: noise_response.response.ciphertext.clone(),
-                    },
-                )),
-                attestation_bindings: self
-                    .session_binders
-                    .iter()
-                    .map(|(id, binder)| {
-                        Ok((
-                            id.clone(),
-                            SessionBinding {
-                                binding: binder.bind(noise_response.handshake_hash.as_slice()),
-                            },
-                        ))
-                    })
-                    .collect::<Result<BTreeMap<String, SessionBinding>, Error>>()?,
+            let handshake_hash = xx_responder.handshake_hash();
+            let (crypter, initiator_static_public_key) = xx_responder
+                .finish(&noise_message.into())
+                .map_err(|e| anyhow!("handshake finish failed: {e:?}"))?;
+            self.handshake_result = Some(HandshakeResult {
+                session_keys: crypter.into(),
+                handshake_hash: handshake_hash.to_vec(),
+                session_bindings: incoming_message.attestation_bindings.clone(),
+                peer_static_public_key: Some(initiator_static_public_key),
             });
+        } else {
+            let noise_message = match incoming_message.r#handshake_type.as_ref() {
+                Some(handshake_request::HandshakeType::NoiseHandshakeMessage(noise_message)) => {
+                    noise_message
+                }
+                None => return Err(anyhow!("Missing handshake_type")),
+            };
+            let noise_response = match self.handshake_type {
+                HandshakeType::NoiseKN => core::unimplemented!(),
+                HandshakeType::NoiseKK => respond_kk(
+                    self.self_identity_key
+                        .as_ref()
+                        .context("handshaker_config missing the self private key")?
+                        .as_ref(),
+                    self.peer_public_key
+                        .as_ref()
+                        .ok_or(anyhow!("Must provide public key for Kk"))?,
+                    &noise_message.into(),
+                )
+                .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
+                HandshakeType::NoiseNK => respond_nk(
+                    self.self_identity_key
+                        .as_ref()
+                        .context("handshaker_config missing the self private key")?
+                        .as_ref(),
+                    &noise_message.into(),
+                )
+                .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
+                HandshakeType::NoiseNN => respond_nn(&noise_message.into())
+                    .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
+                HandshakeType::NoiseIK => respond_ik(
+                    self.self_identity_key
+                        .as_ref()
+                        .context("handshaker_config missing the self private key")?
+                        .as_ref(),
+                    &noise_message.into(),
+                )
+                .map_err(|e| anyhow!("handshake response failed: {e:?}"))?,
+                HandshakeType::NoiseXX => {
+                    let (xx_responder, response) = respond_xx(
+                        self.self_identity_key
+                            .as_ref()
+                            .context("handshaker_config missing the self private key")?
+                            .as_ref(),
+                        &noise_message.into(),
+                    )
+                    .map_err(|e| anyhow!("handshake response failed: {e:?}"))?;
+                    // The handshake is only complete once the final message from the client
+                    // has been processed.
+                    self.handshake_response = Some(
+                        self.create_handshake_response(&response, &xx_responder.handshake_hash())?,
+                    );
+                    self.xx_responder = Some(xx_responder);
+                    return Ok(Some(()));
+                }
+            };
+            self.handshake_response = Some(self.create_handshake_response(
+                &noise_response.response,
+                &noise_response.handshake_hash,
+            )?);
             if self.client_binding_expected {
                 self.noise_response = Some(noise_response);
             } else {
@@ -365,6 +468,7 @@ impl ProtocolEngine<HandshakeRequest, HandshakeResponse> for ServerHandshaker {
                     session_keys: noise_response.crypter.into(),
                     handshake_hash: noise_response.handshake_hash.to_vec(),
                     session_bindings: BTreeMap::new(),
+                    peer_static_public_key: noise_response.initiator_static_public_key,
                 })
             }
         }
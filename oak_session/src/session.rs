@@ -21,14 +21,15 @@ use alloc::{
     boxed::Box,
     collections::{BTreeMap, VecDeque},
     string::String,
+    vec::Vec,
 };
 use core::mem;
 
 use anyhow::{anyhow, Context, Error, Ok};
 use oak_crypto::encryptor::Encryptor;
 use oak_proto_rust::oak::session::v1::{
-    session_request::Request, session_response::Response, EncryptedMessage, PlaintextMessage,
-    SessionBinding, SessionRequest, SessionResponse,
+    session_request::Request, session_response::Response, EncryptedMessage, KeyUpdate,
+    PlaintextMessage, SessionBinding, SessionRequest, SessionResponse,
 };
 
 use crate::{
@@ -36,7 +37,7 @@ use crate::{
         AttestationProvider, AttestationSuccess, AttestationType, ClientAttestationProvider,
         ServerAttestationProvider,
     },
-    config::{EncryptorProvider, SessionConfig},
+    config::{EncryptorProvider, RekeyConfig, SessionConfig},
     handshake::{
         ClientHandshaker, ClientHandshakerBuilder, Handshaker, HandshakerBuilder, ServerHandshaker,
         ServerHandshakerBuilder,
@@ -120,11 +121,41 @@ impl<AP: AttestationProvider, H: Handshaker> Step<AP, H> {
     }
 }
 
+/// Tracks the traffic encrypted with the current outgoing key and decides when
+/// the key needs to be replaced.
+struct RekeyCounter {
+    config: RekeyConfig,
+    messages: u64,
+    bytes: u64,
+}
+
+impl RekeyCounter {
+    fn new(config: RekeyConfig) -> Self {
+        Self { config, messages: 0, bytes: 0 }
+    }
+
+    fn is_due(&self) -> bool {
+        self.config.max_messages.is_some_and(|max_messages| self.messages >= max_messages)
+            || self.config.max_bytes.is_some_and(|max_bytes| self.bytes >= max_bytes)
+    }
+
+    fn record(&mut self, plaintext_len: usize) {
+        self.messages += 1;
+        self.bytes += plaintext_len as u64;
+    }
+
+    fn reset(&mut self) {
+        self.messages = 0;
+        self.bytes = 0;
+    }
+}
+
 /// Client-side secure attested session entrypoint.
 pub struct ClientSession {
     step: Step<ClientAttestationProvider, ClientHandshaker>,
     binding_key_extractor: Box<dyn KeyExtractor>,
     attestation_result: Option<AttestationSuccess>,
+    rekey_counter: RekeyCounter,
     outgoing_requests: VecDeque<SessionRequest>,
     incoming_responses: VecDeque<SessionResponse>,
 }
@@ -151,6 +182,7 @@ impl ClientSession {
             },
             binding_key_extractor: config.binding_key_extractor,
             attestation_result: None,
+            rekey_counter: RekeyCounter::new(config.encryptor_config.rekey_config),
             outgoing_requests: VecDeque::new(),
             incoming_responses: VecDeque::new(),
         })
@@ -168,10 +200,18 @@ impl Session for ClientSession {
                 Err(anyhow!("the session is not open"))
             }
             Step::Open(encryptor) => {
+                if self.rekey_counter.is_due() {
+                    let key_update = create_key_update(encryptor.as_mut())?;
+                    self.outgoing_requests.push_back(SessionRequest {
+                        request: Some(Request::KeyUpdate(key_update)),
+                    });
+                    self.rekey_counter.reset();
+                }
                 let encrypted_message: EncryptedMessage = encryptor
                     .encrypt(&plaintext.clone().into())
                     .map(From::from)
                     .context("couldn't encrypt the supplied plaintext")?;
+                self.rekey_counter.record(plaintext.plaintext.len());
                 self.outgoing_requests.push_back(SessionRequest {
                     request: Some(Request::EncryptedMessage(encrypted_message)),
                 });
@@ -185,25 +225,29 @@ impl Session for ClientSession {
             Step::Attestation { .. } | Step::Handshake { .. } | Step::Invalid => {
                 Err(anyhow!("the session is not open"))
             }
-            Step::Open(encryptor) => match self.incoming_responses.pop_front() {
-                Some(response) => {
-                    let encrypted_message = match response.response {
-                        Some(Response::EncryptedMessage(encrypted_message)) => encrypted_message,
+            Step::Open(encryptor) => {
+                while let Some(response) = self.incoming_responses.pop_front() {
+                    match response.response {
+                        Some(Response::EncryptedMessage(encrypted_message)) => {
+                            return Ok(Some(
+                                encryptor
+                                    .decrypt(&encrypted_message.into())
+                                    .map(From::from)
+                                    .context("couldn't decrypt the supplied plaintext")?,
+                            ));
+                        }
+                        Some(Response::KeyUpdate(key_update)) => {
+                            apply_key_update(encryptor.as_mut(), key_update)?
+                        }
                         _ => {
                             return Err(anyhow!(
                                 "unexpected content of SessionResponse: no encrypted message set"
                             ));
                         }
-                    };
-                    Ok(Some(
-                        encryptor
-                            .decrypt(&encrypted_message.into())
-                            .map(From::from)
-                            .context("couldn't decrypt the supplied plaintext")?,
-                    ))
+                    }
                 }
-                None => Ok(None),
-            },
+                Ok(None)
+            }
         }
     }
 }
@@ -279,7 +323,7 @@ impl ProtocolEngine<SessionResponse, SessionRequest> for ClientSession {
                 }
                 Ok(Some(()))
             }
-            (Some(Response::EncryptedMessage(_)), Step::Open(_)) => {
+            (Some(Response::EncryptedMessage(_) | Response::KeyUpdate(_)), Step::Open(_)) => {
                 self.incoming_responses.push_back(incoming_message.clone());
                 Ok(Some(()))
             }
@@ -294,6 +338,7 @@ pub struct ServerSession {
     binding_key_extractor: Box<dyn KeyExtractor>,
     // encryptor is initialized once the handshake is completed and the session becomes open
     attestation_result: Option<AttestationSuccess>,
+    rekey_counter: RekeyCounter,
     outgoing_responses: VecDeque<SessionResponse>,
     incoming_requests: VecDeque<SessionRequest>,
 }
@@ -328,6 +373,7 @@ impl ServerSession {
             },
             binding_key_extractor: config.binding_key_extractor,
             attestation_result: None,
+            rekey_counter: RekeyCounter::new(config.encryptor_config.rekey_config),
             outgoing_responses: VecDeque::new(),
             incoming_requests: VecDeque::new(),
         })
@@ -345,10 +391,18 @@ impl Session for ServerSession {
                 Err(anyhow!("the session is not open"))
             }
             Step::Open(encryptor) => {
+                if self.rekey_counter.is_due() {
+                    let key_update = create_key_update(encryptor.as_mut())?;
+                    self.outgoing_responses.push_back(SessionResponse {
+                        response: Some(Response::KeyUpdate(key_update)),
+                    });
+                    self.rekey_counter.reset();
+                }
                 let encrypted_message: EncryptedMessage = encryptor
                     .encrypt(&plaintext.clone().into())
                     .map(From::from)
                     .context("couldn't encrypt the supplied plaintext")?;
+                self.rekey_counter.record(plaintext.plaintext.len());
                 self.outgoing_responses.push_back(SessionResponse {
                     response: Some(Response::EncryptedMessage(encrypted_message)),
                 });
@@ -362,25 +416,29 @@ impl Session for ServerSession {
             Step::Attestation { .. } | Step::Handshake { .. } | Step::Invalid => {
                 Err(anyhow!("the session is not open"))
             }
-            Step::Open(encryptor) => match self.incoming_requests.pop_front() {
-                Some(request) => {
-                    let encrypted_message = match request.request {
-                        Some(Request::EncryptedMessage(encrypted_message)) => encrypted_message,
+            Step::Open(encryptor) => {
+                while let Some(request) = self.incoming_requests.pop_front() {
+                    match request.request {
+                        Some(Request::EncryptedMessage(encrypted_message)) => {
+                            return Ok(Some(
+                                encryptor
+                                    .decrypt(&encrypted_message.into())
+                                    .map(From::from)
+                                    .context("couldn't decrypt the supplied plaintext")?,
+                            ));
+                        }
+                        Some(Request::KeyUpdate(key_update)) => {
+                            apply_key_update(encryptor.as_mut(), key_update)?
+                        }
                         _ => {
                             return Err(anyhow!(
                                 "unexpected content of SessionRequest: no encrypted message set"
                             ));
                         }
-                    };
-                    Ok(Some(
-                        encryptor
-                            .decrypt(&encrypted_message.into())
-                            .map(From::from)
-                            .context("couldn't decrypt the supplied plaintext")?,
-                    ))
+                    }
                 }
-                None => Ok(None),
-            },
+                Ok(None)
+            }
         }
     }
 }
@@ -451,7 +509,7 @@ impl ProtocolEngine<SessionRequest, SessionResponse> for ServerSession {
                 }
                 Ok(Some(()))
             }
-            (Some(Request::EncryptedMessage(_)), Step::Open(_)) => {
+            (Some(Request::EncryptedMessage(_) | Request::KeyUpdate(_)), Step::Open(_)) => {
                 self.incoming_requests.push_back(incoming_message.clone());
                 Ok(Some(()))
             }
@@ -460,6 +518,32 @@ impl ProtocolEngine<SessionRequest, SessionResponse> for ServerSession {
     }
 }
 
+/// Encrypts an empty confirmation with the current outgoing key and then
+/// replaces that key.
+fn create_key_update(encryptor: &mut dyn Encryptor) -> Result<KeyUpdate, Error> {
+    let confirmation: EncryptedMessage = encryptor
+        .encrypt(&Vec::new().into())
+        .map(From::from)
+        .context("couldn't encrypt the key update confirmation")?;
+    encryptor.rekey_encryption().context("couldn't rekey the outgoing direction")?;
+    Ok(KeyUpdate { confirmation: Some(confirmation) })
+}
+
+/// Checks the key update confirmation with the current incoming key and then
+/// replaces that key.
+fn apply_key_update(encryptor: &mut dyn Encryptor, key_update: KeyUpdate) -> Result<(), Error> {
+    let confirmation = key_update
+        .confirmation
+        .ok_or(anyhow!("key update message doesn't contain a confirmation"))?;
+    let payload = encryptor
+        .decrypt(&confirmation.into())
+        .context("couldn't decrypt the key update confirmation")?;
+    if !payload.message.is_empty() {
+        return Err(anyhow!("key update confirmation is not empty"));
+    }
+    encryptor.rekey_decryption().context("couldn't rekey the incoming direction")
+}
+
 fn verify_session_binding(
     binding_key_extractor: &dyn KeyExtractor,
     attestation: &AttestationSuccess,
@@ -23,7 +23,10 @@ use oak_proto_rust::oak::{
     attestation::v1::{
         attestation_results, AttestationResults, Endorsements, Evidence, ExtractedEvidence,
     },
-    session::v1::{PlaintextMessage, SessionRequest, SessionResponse},
+    session::v1::{
+        session_request::Request, session_response::Response, PlaintextMessage, SessionRequest,
+        SessionResponse,
+    },
 };
 use p256::ecdsa::SigningKey;
 use rand_core::OsRng;
@@ -35,7 +38,7 @@ use crate::{
         ClientAttestationProvider, DefaultAttestationAggregator, Endorser, MockAttestationVerifier,
         MockAttester, MockEndorser, ServerAttestationProvider,
     },
-    config::{AttestationProviderConfig, HandshakerConfig, SessionConfig},
+    config::{AttestationProviderConfig, HandshakerConfig, SessionConfig, SessionConfigBuilder},
     encryptors::{OrderedChannelEncryptor, UnorderedChannelEncryptor},
     handshake::{ClientHandshaker, HandshakeType, Handshaker, ServerHandshaker},
     session_binding::SignatureBinderBuilder,
@@ -277,7 +280,71 @@ fn process_nn_handshake() {
     do_handshake(client_handshaker, server_handshaker);
 }
 
+#[test]
+fn process_xx_handshake() {
+    let client_identity_key: Box<dyn IdentityKeyHandle> = Box::new(IdentityKey::generate());
+    let server_identity_key: Box<dyn IdentityKeyHandle> = Box::new(IdentityKey::generate());
+    let client_public_key = client_identity_key.get_public_key().unwrap();
+    let server_public_key = server_identity_key.get_public_key().unwrap();
+    let mut client_handshaker = ClientHandshaker::create(HandshakerConfig {
+        handshake_type: HandshakeType::NoiseXX,
+        self_static_private_key: Some(client_identity_key),
+        peer_static_public_key: None,
+        session_binders: BTreeMap::new(),
+    })
+    .unwrap();
+    let mut server_handshaker = ServerHandshaker::new(
+        HandshakerConfig {
+            handshake_type: HandshakeType::NoiseXX,
+            self_static_private_key: Some(server_identity_key),
+            peer_static_public_key: None,
+            session_binders: BTreeMap::new(),
+        },
+        false,
+    );
+    exchange_handshake_messages(&mut client_handshaker, &mut server_handshaker);
+    assert_eq!(client_handshaker.get_peer_static_public_key().unwrap(), Some(server_public_key));
+    assert_eq!(server_handshaker.get_peer_static_public_key().unwrap(), Some(client_public_key));
+    verify_handshake_keys(client_handshaker, server_handshaker);
+}
+
+#[test]
+fn process_ik_handshake() {
+    let client_identity_key: Box<dyn IdentityKeyHandle> = Box::new(IdentityKey::generate());
+    let server_identity_key: Box<dyn IdentityKeyHandle> = Box::new(IdentityKey::generate());
+    let client_public_key = client_identity_key.get_public_key().unwrap();
+    let server_public_key = server_identity_key.get_public_key().unwrap();
+    let mut client_handshaker = ClientHandshaker::create(HandshakerConfig {
+        handshake_type: HandshakeType::NoiseIK,
+        self_static_private_key: Some(client_identity_key),
+        peer_static_public_key: Some(server_public_key.clone()),
+        session_binders: BTreeMap::new(),
+    })
+    .unwrap();
+    let mut server_handshaker = ServerHandshaker::new(
+        HandshakerConfig {
+            handshake_type: HandshakeType::NoiseIK,
+            self_static_private_key: Some(server_identity_key),
+            peer_static_public_key: None,
+            session_binders: BTreeMap::new(),
+        },
+        false,
+    );
+    exchange_handshake_messages(&mut client_handshaker, &mut server_handshaker);
+    assert_eq!(client_handshaker.get_peer_static_public_key().unwrap(), Some(server_public_key));
+    assert_eq!(server_handshaker.get_peer_static_public_key().unwrap(), Some(client_public_key));
+    verify_handshake_keys(client_handshaker, server_handshaker);
+}
+
 fn do_handshake(mut client_handshaker: ClientHandshaker, mut server_handshaker: ServerHandshaker) {
+    exchange_handshake_messages(&mut client_handshaker, &mut server_handshaker);
+    verify_handshake_keys(client_handshaker, server_handshaker);
+}
+
+fn exchange_handshake_messages(
+    client_handshaker: &mut ClientHandshaker,
+    server_handshaker: &mut ServerHandshaker,
+) {
     let request = client_handshaker.get_outgoing_message().unwrap().unwrap();
     server_handshaker
         .put_incoming_message(&request)
@@ -291,6 +358,11 @@ fn do_handshake(mut client_handshaker: ClientHandshaker, mut server_handshaker:
             .put_incoming_message(&followup)
             .expect("Failed to process the follow up from the client");
     }
+    assert!(client_handshaker.is_handshake_complete());
+    assert!(server_handshaker.is_handshake_complete());
+}
+
+fn verify_handshake_keys(client_handshaker: ClientHandshaker, server_handshaker: ServerHandshaker) {
     let session_keys_client = client_handshaker.take_session_keys().unwrap();
     let session_keys_server = server_handshaker.take_session_keys().unwrap();
     assert_eq!(session_keys_client.request_key, session_keys_server.response_key);
@@ -314,6 +386,23 @@ fn do_handshake(mut client_handshaker: ClientHandshaker, mut server_handshaker:
 
 #[test]
 fn session_succeeds_with_bidirectional_attestation() {
+    do_bidirectional_attestation_session(HandshakeType::NoiseNN, None, None);
+}
+
+#[test]
+fn session_xx_succeeds_with_bidirectional_attestation() {
+    do_bidirectional_attestation_session(
+        HandshakeType::NoiseXX,
+        Some(Box::new(IdentityKey::generate())),
+        Some(Box::new(IdentityKey::generate())),
+    );
+}
+
+fn do_bidirectional_attestation_session(
+    handshake_type: HandshakeType,
+    client_identity_key: Option<Box<dyn IdentityKeyHandle>>,
+    server_identity_key: Option<Box<dyn IdentityKeyHandle>>,
+) {
     let attester_id = "id".to_string();
     let binding_key_client = SigningKey::random(&mut OsRng);
     let verifying_key_client_vec: Vec<u8> =
@@ -351,38 +440,40 @@ fn session_succeeds_with_bidirectional_attestation() {
             ..Default::default()
         })
     });
-    let client_config =
-        SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
-            .add_self_attester(attester_id.clone(), Box::new(client_attester))
-            .add_self_endorser(attester_id.clone(), Box::new(client_endorser))
-            .add_peer_verifier(attester_id.clone(), Box::new(client_verifier))
-            .add_session_binder(
-                attester_id.clone(),
-                Box::new(
-                    SignatureBinderBuilder::default()
-                        .signer(Box::new(binding_key_client.clone()))
-                        .build()
-                        .unwrap(),
-                ),
-            )
-            .build();
-    let mut client_session = ClientSession::create(client_config).unwrap();
-    let server_config =
-        SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
-            .add_self_attester(attester_id.clone(), Box::new(server_attester))
-            .add_self_endorser(attester_id.clone(), Box::new(server_endorser))
-            .add_peer_verifier(attester_id.clone(), Box::new(server_verifier))
-            .add_session_binder(
-                attester_id.clone(),
-                Box::new(
-                    SignatureBinderBuilder::default()
-                        .signer(Box::new(binding_key_server.clone()))
-                        .build()
-                        .unwrap(),
-                ),
-            )
-            .build();
-    let mut server_session = ServerSession::create(server_config).unwrap();
+    let mut client_config = SessionConfig::builder(AttestationType::Bidirectional, handshake_type)
+        .add_self_attester(attester_id.clone(), Box::new(client_attester))
+        .add_self_endorser(attester_id.clone(), Box::new(client_endorser))
+        .add_peer_verifier(attester_id.clone(), Box::new(client_verifier))
+        .add_session_binder(
+            attester_id.clone(),
+            Box::new(
+                SignatureBinderBuilder::default()
+                    .signer(Box::new(binding_key_client.clone()))
+                    .build()
+                    .unwrap(),
+            ),
+        );
+    if let Some(client_identity_key) = client_identity_key {
+        client_config = client_config.set_self_private_key(client_identity_key);
+    }
+    let mut client_session = ClientSession::create(client_config.build()).unwrap();
+    let mut server_config = SessionConfig::builder(AttestationType::Bidirectional, handshake_type)
+        .add_self_attester(attester_id.clone(), Box::new(server_attester))
+        .add_self_endorser(attester_id.clone(), Box::new(server_endorser))
+        .add_peer_verifier(attester_id.clone(), Box::new(server_verifier))
+        .add_session_binder(
+            attester_id.clone(),
+            Box::new(
+                SignatureBinderBuilder::default()
+                    .signer(Box::new(binding_key_server.clone()))
+                    .build()
+                    .unwrap(),
+            ),
+        );
+    if let Some(server_identity_key) = server_identity_key {
+        server_config = server_config.set_self_private_key(server_identity_key);
+    }
+    let mut server_session = ServerSession::create(server_config.build()).unwrap();
     do_session_handshake(&mut client_session, &mut server_session);
 
     for message in test_messages() {
@@ -529,6 +620,59 @@ fn session_nk_succeeds() {
         verify_session_message(&mut server_session, &mut client_session, &message);
     }
 }
+#[test]
+fn session_xx_succeeds() {
+    let client_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseXX)
+        .set_self_private_key(Box::new(IdentityKey::generate()))
+        .build();
+    let mut client_session = ClientSession::create(client_config).unwrap();
+    let server_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseXX)
+        .set_self_private_key(Box::new(IdentityKey::generate()))
+        .build();
+    let mut server_session = ServerSession::create(server_config).unwrap();
+    do_session_handshake(&mut client_session, &mut server_session);
+
+    for message in test_messages() {
+        verify_session_message(&mut client_session, &mut server_session, &message);
+        verify_session_message(&mut server_session, &mut client_session, &message);
+    }
+}
+
+#[test]
+fn session_ik_succeeds() {
+    let server_identity_key = Box::new(IdentityKey::generate());
+    let client_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseIK)
+        .set_self_private_key(Box::new(IdentityKey::generate()))
+        .set_peer_static_public_key(server_identity_key.get_public_key().unwrap().as_slice())
+        .build();
+    let mut client_session = ClientSession::create(client_config).unwrap();
+    let server_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseIK)
+        .set_self_private_key(server_identity_key)
+        .build();
+    let mut server_session = ServerSession::create(server_config).unwrap();
+    do_session_handshake(&mut client_session, &mut server_session);
+
+    for message in test_messages() {
+        verify_session_message(&mut client_session, &mut server_session, &message);
+        verify_session_message(&mut server_session, &mut client_session, &message);
+    }
+}
+
+#[test]
+#[should_panic]
+fn session_ik_key_mismatch() {
+    let client_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseIK)
+        .set_self_private_key(Box::new(IdentityKey::generate()))
+        .set_peer_static_public_key(IdentityKey::generate().get_public_key().unwrap().as_slice())
+        .build();
+    let mut client_session = ClientSession::create(client_config).unwrap();
+    let server_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseIK)
+        .set_self_private_key(Box::new(IdentityKey::generate()))
+        .build();
+    let mut server_session = ServerSession::create(server_config).unwrap();
+    do_session_handshake(&mut client_session, &mut server_session);
+}
+
 #[test]
 #[should_panic]
 fn session_nk_key_mismatch() {
@@ -571,6 +715,99 @@ fn verify_session_message<I, O>(
     assert_eq!(message, &session2.read().unwrap().unwrap());
 }
 
+fn create_unattested_nn_sessions(
+    client_config: SessionConfigBuilder,
+    server_config: SessionConfigBuilder,
+) -> (ClientSession, ServerSession) {
+    let mut client_session = ClientSession::create(client_config.build()).unwrap();
+    let mut server_session = ServerSession::create(server_config.build()).unwrap();
+    do_session_handshake(&mut client_session, &mut server_session);
+    (client_session, server_session)
+}
+
+#[test]
+fn session_rekeys_after_message_threshold() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_rekey_message_threshold(2),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_rekey_message_threshold(1),
+    );
+
+    let mut client_key_updates = 0;
+    let mut server_key_updates = 0;
+    for _ in 0..3 {
+        for message in test_messages() {
+            client_session.write(&message).unwrap();
+            while let Some(request) = client_session.get_outgoing_message().unwrap() {
+                if matches!(request.request, Some(Request::KeyUpdate(_))) {
+                    client_key_updates += 1;
+                }
+                server_session.put_incoming_message(&request).unwrap();
+            }
+            assert_eq!(message, server_session.read().unwrap().unwrap());
+
+            server_session.write(&message).unwrap();
+            while let Some(response) = server_session.get_outgoing_message().unwrap() {
+                if matches!(response.response, Some(Response::KeyUpdate(_))) {
+                    server_key_updates += 1;
+                }
+                client_session.put_incoming_message(&response).unwrap();
+            }
+            assert_eq!(message, client_session.read().unwrap().unwrap());
+        }
+    }
+    // 9 messages were sent in each direction.
+    assert_eq!(client_key_updates, 4);
+    assert_eq!(server_key_updates, 8);
+}
+
+#[test]
+fn session_rekeys_after_byte_threshold() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_rekey_byte_threshold(6),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN),
+    );
+
+    // Every write is buffered before the server reads anything, so key updates
+    // must be applied in order with the surrounding messages.
+    let messages = test_messages();
+    for message in &messages {
+        client_session.write(message).unwrap();
+    }
+    let mut key_updates = 0;
+    while let Some(request) = client_session.get_outgoing_message().unwrap() {
+        if matches!(request.request, Some(Request::KeyUpdate(_))) {
+            key_updates += 1;
+        }
+        server_session.put_incoming_message(&request).unwrap();
+    }
+    assert_eq!(key_updates, 1);
+    for message in &messages {
+        assert_eq!(message, &server_session.read().unwrap().unwrap());
+    }
+    assert_eq!(server_session.read().unwrap(), None);
+}
+
+#[test]
+fn session_fails_with_dropped_key_update() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_rekey_message_threshold(1),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN),
+    );
+    let messages = test_messages();
+
+    verify_session_message(&mut client_session, &mut server_session, &messages[0]);
+    client_session.write(&messages[1]).unwrap();
+    let key_update = client_session.get_outgoing_message().unwrap().unwrap();
+    assert!(matches!(key_update.request, Some(Request::KeyUpdate(_))));
+    let request = client_session.get_outgoing_message().unwrap().unwrap();
+    server_session.put_incoming_message(&request).unwrap();
+    assert!(server_session.read().is_err());
+}
+
 #[test]
 fn test_session_sendable() {
     fn foo<T: Send>(_: T) {}
@@ -690,3 +927,139 @@ fn test_unordered_encryptor_window_size_3() {
         replica_2.decrypt(&clone_payload(&encrypted_payloads[5])).unwrap().message
     );
 }
+
+fn write_client_messages(
+    client_session: &mut ClientSession,
+    messages: &[PlaintextMessage],
+) -> Vec<SessionRequest> {
+    messages
+        .iter()
+        .map(|message| {
+            client_session.write(message).unwrap();
+            client_session.get_outgoing_message().unwrap().unwrap()
+        })
+        .collect()
+}
+
+fn ordered_test_messages(count: u8) -> Vec<PlaintextMessage> {
+    (0..count).map(|i| PlaintextMessage { plaintext: vec![i; 4] }).collect()
+}
+
+#[test]
+fn unordered_session_accepts_reordered_messages() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+    );
+    let messages = ordered_test_messages(4);
+    let requests = write_client_messages(&mut client_session, &messages);
+
+    for i in [3, 1, 0, 2] {
+        server_session.put_incoming_message(&requests[i]).unwrap();
+        assert_eq!(messages[i], server_session.read().unwrap().unwrap());
+    }
+
+    // The server -> client direction keeps working.
+    for message in test_messages() {
+        verify_session_message(&mut server_session, &mut client_session, &message);
+    }
+}
+
+#[test]
+fn unordered_session_tolerates_dropped_messages() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(2),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(2),
+    );
+    let messages = ordered_test_messages(6);
+    let requests = write_client_messages(&mut client_session, &messages);
+
+    // Messages 0, 2 and 3 are dropped.
+    for i in [1, 4, 5] {
+        server_session.put_incoming_message(&requests[i]).unwrap();
+        assert_eq!(messages[i], server_session.read().unwrap().unwrap());
+    }
+    // A late message outside of the window is rejected.
+    server_session.put_incoming_message(&requests[3]).unwrap();
+    assert!(server_session.read().is_err());
+}
+
+#[test]
+#[should_panic(expected = "rekey thresholds are not supported")]
+fn unordered_session_rejects_rekey_thresholds() {
+    SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+        .set_unordered_encryption(4)
+        .set_rekey_message_threshold(1)
+        .build();
+}
+
+#[test]
+fn unordered_session_rejects_replayed_messages() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+    );
+    let messages = ordered_test_messages(2);
+    let requests = write_client_messages(&mut client_session, &messages);
+
+    for i in [1, 0] {
+        server_session.put_incoming_message(&requests[i]).unwrap();
+        assert_eq!(messages[i], server_session.read().unwrap().unwrap());
+    }
+    for request in &requests {
+        server_session.put_incoming_message(request).unwrap();
+        assert!(server_session.read().is_err());
+    }
+}
+
+#[test]
+fn unordered_session_fails_without_nonce() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+    );
+    let mut request =
+        write_client_messages(&mut client_session, &ordered_test_messages(1)).pop().unwrap();
+    match request.request.as_mut() {
+        Some(Request::EncryptedMessage(encrypted_message)) => encrypted_message.nonce = None,
+        _ => panic!("expected an encrypted message"),
+    }
+
+    server_session.put_incoming_message(&request).unwrap();
+    assert!(server_session.read().is_err());
+}
+
+#[test]
+fn unordered_session_accepts_messages_from_ordered_peer() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
+            .set_unordered_encryption(4),
+    );
+
+    for message in test_messages() {
+        verify_session_message(&mut client_session, &mut server_session, &message);
+        verify_session_message(&mut server_session, &mut client_session, &message);
+    }
+}
+
+#[test]
+fn ordered_session_rejects_dropped_messages() {
+    let (mut client_session, mut server_session) = create_unattested_nn_sessions(
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN),
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN),
+    );
+    let messages = ordered_test_messages(2);
+    let requests = write_client_messages(&mut client_session, &messages);
+
+    server_session.put_incoming_message(&requests[1]).unwrap();
+    assert!(server_session.read().is_err());
+}
@@ -0,0 +1,162 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! This module contains structs and functions for interpreting the certificate
+//! table that the hypervisor returns in response to an Extended Guest Message
+//! request.
+//!
+//! See section 4.1.8.1 in <https://www.amd.com/system/files/TechDocs/56421-guest-hypervisor-communication-block-standardization.pdf>.
+
+use zerocopy::{FromBytes, FromZeroes};
+
+/// The GUID that identifies the AMD Root Key (ARK) certificate.
+pub const ARK_GUID: [u8; 16] =
+    guid(0xc0b406a4, 0xa803, 0x4952, [0x97, 0x43, 0x3f, 0xb6, 0x01, 0x4c, 0xd0, 0xae]);
+
+/// The GUID that identifies the AMD SEV Key (ASK) certificate.
+pub const ASK_GUID: [u8; 16] =
+    guid(0x4ab7b379, 0xbbac, 0x4fe4, [0xa0, 0x2f, 0x05, 0xae, 0xf3, 0x27, 0xc7, 0x82]);
+
+/// The GUID that identifies the Versioned Chip Endorsement Key (VCEK)
+/// certificate.
+pub const VCEK_GUID: [u8; 16] =
+    guid(0x63da758d, 0xe664, 0x4564, [0xad, 0xc5, 0xf4, 0xb9, 0x3b, 0xe8, 0xac, 0xcd]);
+
+/// The GUID that identifies the Versioned Loaded Endorsement Key (VLEK)
+/// certificate.
+pub const VLEK_GUID: [u8; 16] =
+    guid(0xa8074bc2, 0xa25a, 0x483e, [0xaa, 0xe6, 0x39, 0xc0, 0x45, 0xa0, 0xb8, 0xa1]);
+
+/// Encodes a GUID using the mixed-endian binary layout used in the certificate
+/// table.
+const fn guid(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> [u8; 16] {
+    let data1 = data1.to_le_bytes();
+    let data2 = data2.to_le_bytes();
+    let data3 = data3.to_le_bytes();
+    [
+        data1[0], data1[1], data1[2], data1[3], data2[0], data2[1], data3[0], data3[1], data4[0],
+        data4[1], data4[2], data4[3], data4[4], data4[5], data4[6], data4[7],
+    ]
+}
+
+/// An entry in the certificate table.
+///
+/// The table is terminated by an entry that consists only of zeroes.
+#[repr(C)]
+#[derive(Debug, FromZeroes, FromBytes)]
+pub struct CertificateTableEntry {
+    /// The GUID identifying the type of the certificate.
+    pub guid: [u8; 16],
+    /// The offset of the certificate from the start of the certificate buffer.
+    pub offset: u32,
+    /// The length of the certificate in bytes.
+    pub length: u32,
+}
+
+static_assertions::assert_eq_size!(CertificateTableEntry, [u8; 24]);
+
+impl CertificateTableEntry {
+    fn is_terminator(&self) -> bool {
+        self.guid == [0; 16] && self.offset == 0 && self.length == 0
+    }
+}
+
+/// Finds the certificate identified by `guid` in the certificate buffer that
+/// was populated by the hypervisor.
+///
+/// Returns `None` if the table does not contain an entry for the GUID.
+pub fn find_certificate<'a>(
+    buffer: &'a [u8],
+    guid: &[u8; 16],
+) -> Result<Option<&'a [u8]>, &'static str> {
+    let entry_size = core::mem::size_of::<CertificateTableEntry>();
+    let mut remaining = buffer;
+    loop {
+        let entry = CertificateTableEntry::read_from_prefix(remaining)
+            .ok_or("certificate table is not terminated")?;
+        if entry.is_terminator() {
+            return Ok(None);
+        }
+        if entry.guid == *guid {
+            let start = entry.offset as usize;
+            let end = start
+                .checked_add(entry.length as usize)
+                .ok_or("certificate length overflows the buffer")?;
+            return buffer
+                .get(start..end)
+                .map(Some)
+                .ok_or("certificate is outside of the certificate buffer");
+        }
+        remaining = &remaining[entry_size..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::AsBytes;
+
+    use super::*;
+
+    fn entry_bytes(guid: &[u8; 16], offset: u32, length: u32) -> [u8; 24] {
+        let mut bytes = [0u8; 24];
+        bytes[..16].copy_from_slice(guid);
+        bytes[16..20].copy_from_slice(offset.as_bytes());
+        bytes[20..].copy_from_slice(length.as_bytes());
+        bytes
+    }
+
+    fn test_buffer() -> [u8; 128] {
+        let mut buffer = [0u8; 128];
+        buffer[0..24].copy_from_slice(&entry_bytes(&ARK_GUID, 96, 4));
+        buffer[24..48].copy_from_slice(&entry_bytes(&VCEK_GUID, 100, 8));
+        // The third entry stays zeroed and terminates the table.
+        buffer[96..100].copy_from_slice(b"ark!");
+        buffer[100..108].copy_from_slice(b"vcek der");
+        buffer
+    }
+
+    #[test]
+    fn test_guid_encoding() {
+        assert_eq!(
+            VCEK_GUID,
+            [
+                0x8d, 0x75, 0xda, 0x63, 0x64, 0xe6, 0x64, 0x45, 0xad, 0xc5, 0xf4, 0xb9, 0x3b, 0xe8,
+                0xac, 0xcd
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_certificate() {
+        let buffer = test_buffer();
+        assert_eq!(find_certificate(&buffer, &VCEK_GUID), Ok(Some(&b"vcek der"[..])));
+        assert_eq!(find_certificate(&buffer, &ARK_GUID), Ok(Some(&b"ark!"[..])));
+        assert_eq!(find_certificate(&buffer, &ASK_GUID), Ok(None));
+    }
+
+    #[test]
+    fn test_find_certificate_out_of_bounds() {
+        let mut buffer = test_buffer();
+        buffer[24..48].copy_from_slice(&entry_bytes(&VCEK_GUID, 100, 64));
+        assert!(find_certificate(&buffer, &VCEK_GUID).is_err());
+    }
+
+    #[test]
+    fn test_find_certificate_unterminated_table() {
+        let buffer = entry_bytes(&ARK_GUID, 0, 0);
+        assert!(find_certificate(&buffer, &VCEK_GUID).is_err());
+    }
+}
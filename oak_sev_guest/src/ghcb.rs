@@ -85,6 +85,19 @@ const SW_EXIT_CODE_PAGE_STATE_CHANGE: u64 = 0x8000_0010;
 /// See table 6 in <https://www.amd.com/system/files/TechDocs/56421-guest-hypervisor-communication-block-standardization.pdf>.
 const SW_EXIT_CODE_GUEST_REQUEST: u64 = 0x8000_0011;
 
+///
+/// The value of the sw_exit_code field when doing an Extended Guest Message
+/// request.
+///
+/// See table 6 in <https://www.amd.com/system/files/TechDocs/56421-guest-hypervisor-communication-block-standardization.pdf>.
+const SW_EXIT_CODE_EXTENDED_GUEST_REQUEST: u64 = 0x8000_0012;
+
+/// The value of the sw_exit_info_2 field that indicates that the certificate
+/// buffer provided in an Extended Guest Message request was too small.
+///
+/// See section 4.1.8 in <https://www.amd.com/system/files/TechDocs/56421-guest-hypervisor-communication-block-standardization.pdf>.
+const EXTENDED_GUEST_REQUEST_INVALID_LENGTH: u64 = 1 << 32;
+
 /// Indicator bit that the address is a 16 bit number.
 ///
 /// See section 15.10.2 of <https://www.amd.com/system/files/TechDocs/24593.pdf> for more details.
@@ -591,6 +604,46 @@ where
         }
     }
 
+    /// Sends a guest request message to the Platform Secure Processor via the
+    /// Guest Message Protocol, and asks the hypervisor to fill the certificate
+    /// buffer with the certificate table of the host.
+    ///
+    /// The certificate buffer must consist of `certificates_pages` contiguous
+    /// 4KiB pages. If the buffer is too small to hold the certificates the
+    /// request is not forwarded to the Platform Secure Processor and the
+    /// number of required pages is returned instead, so that the same request
+    /// can be retried with a larger buffer.
+    ///
+    /// The memory containing the request, response and certificate data must
+    /// already be shared with the hypervisor.
+    ///
+    /// See section 4.1.8 in <https://www.amd.com/system/files/TechDocs/56421-guest-hypervisor-communication-block-standardization.pdf>.
+    pub fn do_extended_guest_message_request(
+        &mut self,
+        request_address: PhysAddr,
+        response_address: PhysAddr,
+        certificates_address: PhysAddr,
+        certificates_pages: usize,
+    ) -> Result<ExtendedGuestRequestStatus, &'static str> {
+        self.call(|ghcb| {
+            ghcb.sw_exit_code = SW_EXIT_CODE_EXTENDED_GUEST_REQUEST;
+            ghcb.sw_exit_info_1 = request_address.as_u64();
+            ghcb.sw_exit_info_2 = response_address.as_u64();
+            ghcb.rax = certificates_address.as_u64();
+            ghcb.rbx = certificates_pages as u64;
+            ghcb.valid_bitmap |= ValidBitmap::RAX | ValidBitmap::RBX;
+        })?;
+        let ghcb = self.ghcb.as_ref();
+        match ghcb.sw_exit_info_2 {
+            0 => Ok(ExtendedGuestRequestStatus::Success),
+            EXTENDED_GUEST_REQUEST_INVALID_LENGTH => {
+                Ok(ExtendedGuestRequestStatus::BufferTooSmall(ghcb.rbx as usize))
+            }
+            // For now we treat all other non-zero return values as unrecoverable errors.
+            _ => Err("extended guest message response indicates an error"),
+        }
+    }
+
     /// Performs a Page State Change operation on the given physical frame.
     ///
     /// See section 4.1.6 in <https://www.amd.com/system/files/TechDocs/56421-guest-hypervisor-communication-block-standardization.pdf>.
@@ -666,6 +719,16 @@ where
     }
 }
 
+/// The outcome of an Extended Guest Message request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtendedGuestRequestStatus {
+    /// The request was processed and the certificate buffer was populated.
+    Success,
+    /// The certificate buffer was too small. The value is the number of 4KiB
+    /// pages required to hold the certificates.
+    BufferTooSmall(usize),
+}
+
 /// Resets a byte slice to all zeroes.
 fn reset_slice(slice: &mut [u8]) {
     for byte in slice {
@@ -20,6 +20,7 @@ use core::{arch::asm, mem::size_of};
 use bitflags::bitflags;
 use strum::{Display, FromRepr};
 use x86_64::structures::paging::{PageSize, PhysFrame, Size2MiB, Size4KiB};
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
 
 /// The result from an instruction that indicates success.
 const SUCCESS: u64 = 0;
@@ -330,6 +331,12 @@ pub struct ExtensionBuffer {
     data: [u8; 48],
 }
 
+impl From<[u8; 48]> for ExtensionBuffer {
+    fn from(data: [u8; 48]) -> Self {
+        Self { data }
+    }
+}
+
 /// Error when extending an RTMR.
 ///
 /// These values are derived from the TDX Function Completion status structure.
@@ -359,23 +366,25 @@ pub fn extend_rtmr<T: Into<ExtensionBuffer>>(
     const LEAF: u64 = 2;
 
     let mut result: u64;
-    let buf_gpa = (buf.into().data).as_ptr() as usize as u64;
+    // The buffer must stay alive until the TDCALL has completed.
+    let buf: ExtensionBuffer = buf.into();
+    let buf_gpa = buf.data.as_ptr() as usize as u64;
     let rtmr_index = rtmr_index as u64;
 
     // The TDCALL leaf goes into RAX. RAX returns the result (0 is success). The
     // guest-physical address of the start of the extension buffer goes into RCX.
     // The RTMR index goes into RDX.
     //
-    // Safety: calling TDCALL here is safe since it does not alter memory and all
-    // the affected registers are specified, so no unspecified registers will be
-    // clobbered.
+    // Safety: calling TDCALL here is safe since it only reads the extension buffer
+    // and all the affected registers are specified, so no unspecified registers
+    // will be clobbered.
     unsafe {
         asm!(
             "tdcall",
             inout("rax") LEAF => result,
             in("rcx") buf_gpa,
             in("rdx") rtmr_index,
-            options(nomem, nostack),
+            options(readonly, nostack),
         );
     }
 
@@ -390,6 +399,157 @@ pub fn extend_rtmr<T: Into<ExtensionBuffer>>(
     Ok(())
 }
 
+/// The size of the user-supplied data that is included in a TD report.
+pub const REPORT_DATA_SIZE: usize = 64;
+
+/// The size of a SHA-384 measurement register.
+pub const MEASUREMENT_SIZE: usize = 48;
+
+/// The MAC-protected header of a TD report.
+///
+/// See section 3.6.6 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf)
+/// for more information.
+#[derive(Debug, Clone, AsBytes, FromZeroes, FromBytes)]
+#[repr(C)]
+pub struct ReportMac {
+    /// The type of the report. For TDX this is 0x81.
+    pub report_type: [u8; 4],
+    _reserved_1: [u8; 12],
+    /// The security version number of the CPU.
+    pub cpu_svn: [u8; 16],
+    /// SHA-384 digest of the TEE TCB information.
+    pub tee_tcb_info_hash: [u8; MEASUREMENT_SIZE],
+    /// SHA-384 digest of the TD information.
+    pub tee_info_hash: [u8; MEASUREMENT_SIZE],
+    /// The user-supplied data.
+    pub report_data: [u8; REPORT_DATA_SIZE],
+    _reserved_2: [u8; 32],
+    /// MAC over the report, which can only be verified on the same platform.
+    pub mac: [u8; 32],
+}
+
+static_assertions::assert_eq_size!(ReportMac, [u8; 256]);
+
+/// The measurements and attributes of a TD, as included in a TD report.
+///
+/// See section 3.6.9 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf)
+/// for more information.
+#[derive(Debug, Clone, AsBytes, FromZeroes, FromBytes)]
+#[repr(C)]
+pub struct TdInfoStruct {
+    /// The TD attributes.
+    pub attributes: u64,
+    /// The extended features that are available to the TD.
+    pub xfam: u64,
+    /// The measurement of the initial contents of the TD.
+    pub mr_td: [u8; MEASUREMENT_SIZE],
+    /// Software-defined ID for non-owner-defined configuration of the TD.
+    pub mr_config_id: [u8; MEASUREMENT_SIZE],
+    /// Software-defined ID for the TD's owner.
+    pub mr_owner: [u8; MEASUREMENT_SIZE],
+    /// Software-defined ID for owner-defined configuration of the TD.
+    pub mr_owner_config: [u8; MEASUREMENT_SIZE],
+    /// The run-time measurement registers.
+    pub rtmr: [[u8; MEASUREMENT_SIZE]; 4],
+    /// SHA-384 digest of the service TD bindings.
+    pub servtd_hash: [u8; MEASUREMENT_SIZE],
+    _reserved: [u8; 64],
+}
+
+static_assertions::assert_eq_size!(TdInfoStruct, [u8; 512]);
+
+/// A TD report, which can be converted to a remotely verifiable quote by the
+/// quoting enclave on the host.
+///
+/// See section 3.6.1 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf)
+/// for more information.
+#[derive(Debug, Clone, AsBytes, FromZeroes, FromBytes)]
+#[repr(C, align(1024))]
+pub struct TdReport {
+    /// The MAC-protected header.
+    pub report_mac: ReportMac,
+    /// Information about the TDX module.
+    pub tee_tcb_info: [u8; 239],
+    _reserved: [u8; 17],
+    /// The measurements and attributes of the TD.
+    pub td_info: TdInfoStruct,
+}
+
+static_assertions::assert_eq_size!(TdReport, [u8; 1024]);
+
+/// A buffer for the user-supplied data that is included in a TD report.
+///
+/// It must be 64-byte aligned.
+#[repr(C, align(64))]
+struct ReportDataBuffer {
+    data: [u8; REPORT_DATA_SIZE],
+}
+
+/// Error when requesting a TD report.
+///
+/// These values are derived from the TDX Function Completion status structure.
+/// See section 3.1.1 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf)
+/// for more information.
+#[derive(Debug, Display, FromRepr)]
+#[repr(u64)]
+pub enum GetTdReportError {
+    /// The supplied operand is invalid.
+    InvalidOperand = 0xC000010000000000,
+    /// Operation encountered a busy operand, indicated by the lower 32 bits of
+    /// the status. In many cases, this can be resolved by retrying the
+    /// operation.
+    OperandBusy = 0x8000020000000000,
+}
+
+/// Creates a TD report that includes the given report data, by calling the
+/// TDCALL[TDG.MR.REPORT] leaf.
+///
+/// The report reflects the current values of the RTMRs.
+///
+/// See section 5.4.5 of the [Intel® TDX Module v1.5 ABI Specification](https://cdrdv2.intel.com/v1/dl/getContent/817877?fileName=intel-tdx-module-1.5-abi-spec-348551004.pdf)
+/// for more information.
+pub fn get_td_report(report_data: [u8; REPORT_DATA_SIZE]) -> Result<TdReport, GetTdReportError> {
+    // The TDCALL leaf for TDG.MR.REPORT.
+    const LEAF: u64 = 4;
+    // The only report subtype that is currently defined.
+    const SUBTYPE: u64 = 0;
+
+    let mut result: u64;
+    let mut report = TdReport::new_zeroed();
+    let report_data = ReportDataBuffer { data: report_data };
+    let report_gpa = report.as_bytes_mut().as_mut_ptr() as usize as u64;
+    let report_data_gpa = report_data.data.as_ptr() as usize as u64;
+
+    // The TDCALL leaf goes into RAX. RAX returns the result (0 is success). The
+    // guest-physical address of the output buffer goes into RCX, the
+    // guest-physical address of the report data goes into RDX and the report
+    // subtype goes into R8.
+    //
+    // Safety: calling TDCALL here is safe since it only writes to the report
+    // buffer, which is large enough and correctly aligned, and all the affected
+    // registers are specified, so no unspecified registers will be clobbered.
+    unsafe {
+        asm!(
+            "tdcall",
+            inout("rax") LEAF => result,
+            in("rcx") report_gpa,
+            in("rdx") report_data_gpa,
+            in("r8") SUBTYPE,
+            options(nostack),
+        );
+    }
+
+    if result > 0 {
+        // According to the spec the result will either be 0 (Success) or one of the
+        // defined error values. The lower 32 bits can contain additional information
+        // that we ignore for now.
+        return Err(GetTdReportError::from_repr(result & 0xFFFFFFFF00000000)
+            .expect("TDCALL[TDG.MR.REPORT] returned an invalid result"));
+    }
+
+    Ok(report)
+}
+
 /// Splits a 64-bit little-endian unsigned integer into two 32-bit little-endian
 /// unsigned integers.
 ///
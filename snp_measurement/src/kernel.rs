@@ -0,0 +1,115 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::path::Path;
+
+use anyhow::Context;
+use log::info;
+use sha2::{Digest, Sha256};
+use zerocopy::FromBytes;
+
+/// The SHA2-256 digests of the kernel that Stage 0 measures.
+///
+/// These match the values reported by `oak_kernel_measurement` for the same
+/// bzImage file.
+pub struct KernelDigests {
+    pub image: [u8; 32],
+    pub setup_data: [u8; 32],
+}
+
+impl KernelDigests {
+    /// Loads the bzImage-format kernel from the supplied path and calculates
+    /// the digests of its kernel image and setup data.
+    pub fn load(kernel_path: &Path) -> anyhow::Result<Self> {
+        let bz_image = std::fs::read(kernel_path).context("couldn't load kernel bzImage")?;
+        let digests = Self::from_bz_image(&bz_image)?;
+        info!("Kernel Image Measurement: sha2-256:{}", hex::encode(digests.image));
+        info!("Kernel Setup Data Measurement: sha2-256:{}", hex::encode(digests.setup_data));
+        Ok(digests)
+    }
+
+    /// Splits a bzImage kernel file into the setup data and the kernel image
+    /// the same way the VMM does, and calculates their digests.
+    ///
+    /// This mirrors `Kernel::from_bz_image` in `oak_kernel_measurement`,
+    /// including the setup data header fields that the VMM and Stage 0
+    /// overwrite before the setup data is measured.
+    fn from_bz_image(bz_image: &[u8]) -> anyhow::Result<Self> {
+        anyhow::ensure!(bz_image.len() > 0x22C, "kernel bzImage is too small");
+        // The number of 512 byte sectors -1 is stored at offset 0x1F1.
+        let setup_sects = bz_image[0x1F1];
+        // For backwards compatibility, if setup_sects is 0 it should be set to 4.
+        let setup_sects = if setup_sects == 0 { 4 } else { setup_sects };
+        let setup_size = 512 * (setup_sects as usize + 1);
+        anyhow::ensure!(bz_image.len() >= setup_size, "kernel bzImage is truncated");
+
+        // The kernel image is just everything after the setup data.
+        let kernel_image = &bz_image[setup_size..];
+        let mut setup_data = bz_image[..setup_size].to_vec();
+        // The loader type will be set to `QEMU`.
+        setup_data[0x210] = 0xB0;
+        // The load flags will be updated to enable heap usage.
+        setup_data[0x211] |= 0x80;
+        // Set the default command_line location.
+        let default_cmd_line = 0x20000;
+        *u32::mut_from(&mut setup_data[0x228..0x22C]).expect("invalid slice for cmd_line") =
+            default_cmd_line;
+        // Set the offset to the end of the heap.
+        let default_setup = 0x10000;
+        *u16::mut_from(&mut setup_data[0x224..0x226]).expect("invalid slice for heap end ptr") =
+            (default_cmd_line - default_setup - 0x200) as u16;
+        // Stage 0 zeroes the location and size of the initial RAM disk before
+        // measuring, so that the measurement doesn't depend on them.
+        *u32::mut_from(&mut setup_data[0x218..0x21C]).expect("invalid slice for initrd location") =
+            0;
+        *u32::mut_from(&mut setup_data[0x21C..0x220]).expect("invalid slice for initrd size") = 0;
+
+        Ok(Self {
+            image: Sha256::digest(kernel_image).into(),
+            setup_data: Sha256::digest(&setup_data).into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bz_image() {
+        // With setup_sects set to 0 the setup data is 5 sectors long.
+        let mut bz_image = vec![0xFF; 512 * 5 + 64];
+        bz_image[0x1F1] = 0;
+
+        let digests = KernelDigests::from_bz_image(&bz_image).unwrap();
+
+        let mut setup_data = bz_image[..512 * 5].to_vec();
+        setup_data[0x210] = 0xB0;
+        setup_data[0x224..0x226].copy_from_slice(&0xFE00u16.to_le_bytes());
+        setup_data[0x218..0x220].fill(0);
+        setup_data[0x228..0x22C].copy_from_slice(&0x20000u32.to_le_bytes());
+        assert_eq!(digests.image, <[u8; 32]>::from(Sha256::digest(&bz_image[512 * 5..])));
+        assert_eq!(digests.setup_data, <[u8; 32]>::from(Sha256::digest(&setup_data)));
+    }
+
+    #[test]
+    fn test_from_bz_image_truncated_fails() {
+        let mut bz_image = vec![0; 1024];
+        bz_image[0x1F1] = 4;
+
+        assert!(KernelDigests::from_bz_image(&bz_image).is_err());
+    }
+}
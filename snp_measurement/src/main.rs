@@ -14,28 +14,51 @@
 // limitations under the License.
 //
 
+mod kernel;
 mod page;
+mod reference_values;
 mod stage0;
+mod tdx;
+#[cfg(test)]
+mod test_util;
 mod vmsa;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
-use clap::Parser;
-use log::trace;
+use clap::{Parser, ValueEnum};
+use log::{info, trace};
+use oak_proto_rust::oak::attestation::v1::{RootLayerReferenceValues, TcbVersion};
 use page::PageInfo;
+use prost::Message;
+use sha2::{Digest, Sha256};
 use x86_64::structures::paging::{PageSize, Size4KiB};
 
 use crate::{
-    stage0::load_stage0,
+    kernel::KernelDigests,
+    reference_values::{KernelLayerMeasurements, Stack},
+    stage0::{load_stage0, Stage0Info},
+    tdx::{Measurement, MrtdInfo, Stage0RtmrInputs},
     vmsa::{get_ap_vmsa, get_boot_vmsa, VMSA_ADDRESS},
 };
 
+/// The TEE platform for which to calculate the measurements.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Platform {
+    /// AMD SEV-SNP, which measures the initial memory into the launch digest.
+    SevSnp,
+    /// Intel TDX, which measures the initial memory into MRTD and the later
+    /// boot stages into the runtime measurement registers.
+    IntelTdx,
+}
+
 #[derive(Parser, Clone)]
-#[command(about = "Oak SEV-SNP Measurement Calculator")]
+#[command(about = "Oak SEV-SNP and TDX Measurement Calculator")]
 struct Cli {
     #[arg(long, help = "The location of the Stage 0 firmware ROM image")]
     stage0_rom: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Platform::SevSnp, help = "The TEE platform to calculate the measurements for")]
+    platform: Platform,
     #[arg(long, help = "Whether the firwmare is shadowed to support legacy boot")]
     legacy_boot: bool,
     #[arg(long, help = "The number of vCPUs available to the VM at boot", default_values_t = [1], value_delimiter = ',', num_args = 1..)]
@@ -45,6 +68,51 @@ struct Cli {
         help = "The dir to output the predicted attestation measurements as binary files to"
     )]
     attestation_measurements_output_dir: Option<PathBuf>,
+    #[arg(long, help = "The location of the kernel bzImage file")]
+    kernel: Option<PathBuf>,
+    #[arg(long, help = "The location of the initial RAM disk, if any")]
+    initrd: Option<PathBuf>,
+    #[arg(long, help = "The kernel command-line")]
+    kernel_cmdline: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_sha2_256_digest,
+        help = "The SHA2-256 digest of the ACPI table generation commands, as logged by Stage 0"
+    )]
+    acpi_digest: Option<[u8; 32]>,
+    #[arg(
+        long,
+        value_parser = parse_sha2_256_digest,
+        help = "The SHA2-256 digest of the E820 memory map, as logged by Stage 0"
+    )]
+    memory_map_digest: Option<[u8; 32]>,
+    #[arg(
+        long,
+        help = "Whether to skip verification of the Intel TDX RTMRs and the kernel layer measurements whose inputs were not supplied, instead of failing"
+    )]
+    allow_skip: bool,
+    #[arg(long, help = "The location to write the reference values to, as a binary proto")]
+    reference_values_output: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        help = "The Oak stack to write the reference values for. The kernel layer is only included if --kernel is set"
+    )]
+    stack: Option<Stack>,
+    #[arg(
+        long,
+        value_parser = parse_tcb_version,
+        help = "The minimum accepted AMD SEV-SNP TCB version, e.g. boot_loader=7,tee=0,snp=15,microcode=62"
+    )]
+    min_tcb_version: Option<TcbVersion>,
+    #[arg(
+        long,
+        value_parser = parse_tee_tcb_svn,
+        help = "The hex-encoded minimum accepted Intel TDX TEE TCB SVN"
+    )]
+    min_tee_tcb_svn: Option<[u8; 16]>,
+    #[arg(long, help = "Whether the reference values allow the TEE to be in debug mode")]
+    allow_debug: bool,
 }
 
 impl Cli {
@@ -57,8 +125,42 @@ fn main() -> anyhow::Result<()> {
     env_logger::init();
     let cli = Cli::parse();
 
+    if cli.reference_values_output.is_some() {
+        validate_reference_values_args(&cli)?;
+    }
+
     let stage0 = load_stage0(cli.stage0_path())?;
+    let kernel = cli.kernel.as_deref().map(KernelDigests::load).transpose()?;
+
+    let root_layer = match cli.platform {
+        Platform::SevSnp => {
+            let stage0_measurements = calculate_sev_snp_measurements(&cli, &stage0)?;
+            reference_values::amd_sev_root_layer(
+                &stage0_measurements,
+                cli.min_tcb_version.clone().unwrap_or_default(),
+                cli.allow_debug,
+            )
+        }
+        Platform::IntelTdx => {
+            let (mrtd, rtmrs) = calculate_tdx_measurements(&cli, &stage0, kernel.as_ref())?;
+            reference_values::intel_tdx_root_layer(
+                &mrtd,
+                &rtmrs,
+                cli.min_tee_tcb_svn.map(|svn| svn.to_vec()).unwrap_or_default(),
+                cli.allow_debug,
+            )
+        }
+    };
+
+    if let Some(path) = &cli.reference_values_output {
+        write_reference_values(&cli, path, root_layer, kernel.as_ref())?;
+    }
+
+    Ok(())
+}
 
+/// Calculates the launch digest for each of the vCPU counts.
+fn calculate_sev_snp_measurements(cli: &Cli, stage0: &Stage0Info) -> anyhow::Result<Vec<[u8; 48]>> {
     let mut base_page_info = PageInfo::new();
 
     // Add the Stage 0 firmware ROM image.
@@ -85,7 +187,8 @@ fn main() -> anyhow::Result<()> {
     let sev_es_reset_block = stage0.get_sev_es_reset_block();
     let ap_vmsa = get_ap_vmsa(&sev_es_reset_block);
     // Derive measurements for each vCPU counts specified.
-    for vcpu_count in cli.vcpu_count {
+    let mut measurements = Vec::with_capacity(cli.vcpu_count.len());
+    for &vcpu_count in &cli.vcpu_count {
         let mut page_info = base_page_info.clone();
         // Iterate through all vCPUs up to the specified count.
         for _ in 1..vcpu_count {
@@ -108,7 +211,232 @@ fn main() -> anyhow::Result<()> {
             std::fs::write(path, page_info.digest_cur)
                 .context("couldn't write attestation measurement")?;
         }
+        measurements.push(page_info.digest_cur);
+    }
+
+    Ok(measurements)
+}
+
+/// Calculates MRTD and predicts the RTMRs that can be predicted from the
+/// supplied inputs.
+fn calculate_tdx_measurements(
+    cli: &Cli,
+    stage0: &Stage0Info,
+    kernel: Option<&KernelDigests>,
+) -> anyhow::Result<(Measurement, Vec<Option<Measurement>>)> {
+    validate_rtmr_args(cli)?;
+
+    let mut mrtd_info = MrtdInfo::new();
+    mrtd_info.update_from_stage0(stage0);
+    let mrtd = mrtd_info.finalize();
+    println!("MRTD: {}", hex::encode(mrtd));
+
+    let inputs = Stage0RtmrInputs {
+        acpi_digest: cli.acpi_digest.unwrap_or_default(),
+        memory_map_digest: cli.memory_map_digest.unwrap_or_default(),
+        kernel_image_digest: kernel.map(|kernel| kernel.image).unwrap_or_default(),
+        kernel_setup_data_digest: kernel.map(|kernel| kernel.setup_data).unwrap_or_default(),
+        kernel_cmdline: cli.kernel_cmdline.clone().unwrap_or_default(),
+        ram_disk_digest: ram_disk_digest(cli)?.unwrap_or_default(),
+    };
+    // An RTMR can only be predicted if all of its inputs were supplied,
+    // otherwise it is skipped if --allow-skip was set. RTMR[3] is not used by
+    // Stage 0, so it is always zero.
+    let predictable = [
+        cli.acpi_digest.is_some() && cli.memory_map_digest.is_some(),
+        kernel.is_some(),
+        cli.kernel_cmdline.is_some() && cli.initrd.is_some(),
+        true,
+    ];
+    let rtmrs: Vec<Option<Measurement>> = inputs
+        .predict_rtmrs()
+        .into_iter()
+        .zip(predictable)
+        .map(|(rtmr, predictable)| predictable.then_some(rtmr))
+        .collect();
+    for (index, rtmr) in rtmrs.iter().enumerate() {
+        match rtmr {
+            Some(rtmr) => println!("RTMR{}: {}", index, hex::encode(rtmr)),
+            None => println!("RTMR{}: not predicted (missing inputs)", index),
+        }
     }
 
+    if let Some(dir) = cli.attestation_measurements_output_dir.clone() {
+        std::fs::write(dir.join("sha2_384_mrtd_with_stage0"), mrtd)
+            .context("couldn't write MRTD")?;
+        for (index, rtmr) in rtmrs.iter().enumerate() {
+            if let Some(rtmr) = rtmr {
+                std::fs::write(dir.join(format!("sha2_384_rtmr{}", index)), rtmr)
+                    .context("couldn't write RTMR")?;
+            }
+        }
+    }
+
+    Ok((mrtd, rtmrs))
+}
+
+/// Calculates the SHA2-256 digest of the initial RAM disk, if one was supplied.
+fn ram_disk_digest(cli: &Cli) -> anyhow::Result<Option<[u8; 32]>> {
+    cli.initrd
+        .as_ref()
+        .map(|path| {
+            let ram_disk = std::fs::read(path).context("couldn't load initial RAM disk")?;
+            Ok(Sha256::digest(ram_disk).into())
+        })
+        .transpose()
+}
+
+/// Checks that all the arguments needed to predict RTMR[0..3] were supplied,
+/// unless the caller explicitly allowed skipping them.
+fn validate_rtmr_args(cli: &Cli) -> anyhow::Result<()> {
+    let missing: Vec<&str> = [
+        ("--acpi-digest", cli.acpi_digest.is_none()),
+        ("--memory-map-digest", cli.memory_map_digest.is_none()),
+        ("--kernel", cli.kernel.is_none()),
+        ("--kernel-cmdline", cli.kernel_cmdline.is_none()),
+        ("--initrd", cli.initrd.is_none()),
+    ]
+    .into_iter()
+    .filter_map(|(arg, missing)| missing.then_some(arg))
+    .collect();
+    anyhow::ensure!(
+        cli.allow_skip || missing.is_empty(),
+        "{} required to predict the RTMRs, or pass --allow-skip to skip the RTMRs that can't be \
+         predicted",
+        missing.join(", ")
+    );
+    Ok(())
+}
+
+/// Checks that all the arguments needed to write the reference values were
+/// supplied, before spending time on calculating the measurements.
+fn validate_reference_values_args(cli: &Cli) -> anyhow::Result<()> {
+    match cli.platform {
+        Platform::SevSnp => anyhow::ensure!(
+            cli.min_tcb_version.is_some(),
+            "--min-tcb-version is required to write AMD SEV-SNP reference values"
+        ),
+        Platform::IntelTdx => anyhow::ensure!(
+            cli.min_tee_tcb_svn.is_some(),
+            "--min-tee-tcb-svn is required to write Intel TDX reference values"
+        ),
+    }
+    anyhow::ensure!(cli.stack.is_some(), "--stack is required to write reference values");
     Ok(())
 }
+
+/// Writes the reference values for the selected stack as a binary proto.
+///
+/// The kernel layer is only filled in if a kernel was supplied, otherwise only
+/// the root layer is set.
+fn write_reference_values(
+    cli: &Cli,
+    path: &Path,
+    root_layer: RootLayerReferenceValues,
+    kernel: Option<&KernelDigests>,
+) -> anyhow::Result<()> {
+    let stack = cli.stack.context("--stack is required to write reference values")?;
+    let kernel_layer = match kernel {
+        Some(kernel) => Some(reference_values::kernel_layer(
+            &KernelLayerMeasurements {
+                kernel_image_digest: kernel.image,
+                kernel_setup_data_digest: kernel.setup_data,
+                kernel_cmdline: cli.kernel_cmdline.clone(),
+                ram_disk_digest: ram_disk_digest(cli)?,
+                memory_map_digest: cli.memory_map_digest,
+                acpi_digest: cli.acpi_digest,
+            },
+            cli.allow_skip,
+        )?),
+        None => None,
+    };
+    let reference_values =
+        reference_values::stack_reference_values(stack, root_layer, kernel_layer);
+    info!("reference values for the layers above the root layer must be added separately");
+    std::fs::write(path, reference_values.encode_to_vec())
+        .context("couldn't write reference values")
+}
+
+/// Parses a hex-encoded SHA2-256 digest, optionally prefixed with `sha2-256:`.
+fn parse_sha2_256_digest(value: &str) -> anyhow::Result<[u8; 32]> {
+    let value = value.strip_prefix("sha2-256:").unwrap_or(value);
+    let mut digest = [0u8; 32];
+    hex::decode_to_slice(value, &mut digest).context("invalid SHA2-256 digest")?;
+    Ok(digest)
+}
+
+/// Parses a TCB version in the form `boot_loader=7,tee=0,snp=15,microcode=62`.
+///
+/// Components that are not specified default to zero.
+fn parse_tcb_version(value: &str) -> anyhow::Result<TcbVersion> {
+    let mut tcb_version = TcbVersion::default();
+    for component in value.split(',') {
+        let (name, svn) = component.split_once('=').context("invalid TCB version component")?;
+        let svn: u32 = svn.trim().parse().context("invalid TCB version SVN")?;
+        match name.trim() {
+            "boot_loader" => tcb_version.boot_loader = svn,
+            "tee" => tcb_version.tee = svn,
+            "snp" => tcb_version.snp = svn,
+            "microcode" => tcb_version.microcode = svn,
+            other => anyhow::bail!("unknown TCB version component: {}", other),
+        }
+    }
+    Ok(tcb_version)
+}
+
+/// Parses a hex-encoded 16-byte TDX TEE TCB SVN.
+fn parse_tee_tcb_svn(value: &str) -> anyhow::Result<[u8; 16]> {
+    let mut svn = [0u8; 16];
+    hex::decode_to_slice(value, &mut svn).context("invalid TEE TCB SVN")?;
+    Ok(svn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACPI_DIGEST: &str =
+        "--acpi-digest=0101010101010101010101010101010101010101010101010101010101010101";
+    const MEMORY_MAP_DIGEST: &str =
+        "--memory-map-digest=0202020202020202020202020202020202020202020202020202020202020202";
+    const KERNEL: &str = "--kernel=bzImage";
+    const KERNEL_CMDLINE: &str = "--kernel-cmdline=console=ttyS0";
+    const INITRD: &str = "--initrd=initrd.cpio";
+
+    fn tdx_cli(args: &[&str]) -> Cli {
+        Cli::parse_from(["snp_measurement", "--platform=intel-tdx"].iter().chain(args))
+    }
+
+    #[test]
+    fn test_validate_rtmr_args_with_all_digests() {
+        let cli = tdx_cli(&[ACPI_DIGEST, MEMORY_MAP_DIGEST, KERNEL, KERNEL_CMDLINE, INITRD]);
+        assert!(validate_rtmr_args(&cli).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rtmr_args_with_missing_digest_fails() {
+        let cli = tdx_cli(&[ACPI_DIGEST, KERNEL, KERNEL_CMDLINE, INITRD]);
+        let err = validate_rtmr_args(&cli).unwrap_err();
+        assert!(err.to_string().contains("--memory-map-digest"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_rtmr_args_with_missing_cmdline_and_initrd_fails() {
+        let cli = tdx_cli(&[ACPI_DIGEST, MEMORY_MAP_DIGEST, KERNEL]);
+        let err = validate_rtmr_args(&cli).unwrap_err();
+        assert!(err.to_string().contains("--kernel-cmdline, --initrd"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_rtmr_args_with_allow_skip() {
+        let cli = tdx_cli(&["--allow-skip"]);
+        assert!(validate_rtmr_args(&cli).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reference_values_args_without_stack_fails() {
+        let cli = tdx_cli(&["--min-tee-tcb-svn=00000000000000000000000000000000"]);
+        let err = validate_reference_values_args(&cli).unwrap_err();
+        assert!(err.to_string().contains("--stack"), "{err}");
+    }
+}
@@ -0,0 +1,354 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use clap::ValueEnum;
+use oak_proto_rust::oak::{
+    attestation::v1::{
+        binary_reference_value, kernel_binary_reference_value, reference_values,
+        text_reference_value, AmdSevReferenceValues, BinaryReferenceValue, Digests,
+        IntelTdxReferenceValues, KernelBinaryReferenceValue, KernelDigests,
+        KernelLayerReferenceValues, OakContainersReferenceValues,
+        OakRestrictedKernelReferenceValues, ReferenceValues, RootLayerReferenceValues,
+        SkipVerification, StringLiterals, TcbVersion, TextReferenceValue,
+    },
+    RawDigest,
+};
+
+use crate::tdx::Measurement;
+
+/// The Oak stack for which to build the full reference values.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Stack {
+    RestrictedKernel,
+    Containers,
+}
+
+/// The measurements of the kernel layer, as recorded by Stage 0.
+///
+/// All digests are SHA2-256 digests. The kernel image and setup data digests
+/// are calculated from the kernel bzImage, as `oak_kernel_measurement` does.
+/// Optional values that are not set can only be skipped during verification if
+/// explicitly allowed.
+pub struct KernelLayerMeasurements {
+    pub kernel_image_digest: [u8; 32],
+    pub kernel_setup_data_digest: [u8; 32],
+    pub kernel_cmdline: Option<String>,
+    pub ram_disk_digest: Option<[u8; 32]>,
+    pub memory_map_digest: Option<[u8; 32]>,
+    pub acpi_digest: Option<[u8; 32]>,
+}
+
+/// Builds the root layer reference values for AMD SEV-SNP.
+///
+/// Each of the Stage 0 measurements is accepted, so that the same reference
+/// values can be used for VMs with any of the measured vCPU counts.
+pub fn amd_sev_root_layer(
+    stage0_measurements: &[[u8; 48]],
+    min_tcb_version: TcbVersion,
+    allow_debug: bool,
+) -> RootLayerReferenceValues {
+    RootLayerReferenceValues {
+        amd_sev: Some(AmdSevReferenceValues {
+            min_tcb_version: Some(min_tcb_version),
+            allow_debug,
+            stage0: Some(digests_reference_value(
+                stage0_measurements
+                    .iter()
+                    .map(|measurement| RawDigest {
+                        sha2_384: measurement.to_vec(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Builds the root layer reference values for Intel TDX.
+///
+/// Registers that could not be predicted are skipped during verification.
+pub fn intel_tdx_root_layer(
+    mrtd: &Measurement,
+    rtmrs: &[Option<Measurement>],
+    min_tee_tcb_svn: Vec<u8>,
+    allow_debug: bool,
+) -> RootLayerReferenceValues {
+    let rtmr_reference_value = |index: usize| {
+        Some(match rtmrs[index] {
+            Some(rtmr) => sha2_384_reference_value(&rtmr),
+            None => skip_reference_value(),
+        })
+    };
+    RootLayerReferenceValues {
+        intel_tdx: Some(IntelTdxReferenceValues {
+            min_tee_tcb_svn,
+            allow_debug,
+            mr_td: Some(sha2_384_reference_value(mrtd)),
+            rtmr0: rtmr_reference_value(0),
+            rtmr1: rtmr_reference_value(1),
+            rtmr2: rtmr_reference_value(2),
+            rtmr3: rtmr_reference_value(3),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Builds the kernel layer reference values from the Stage 0 measurements.
+///
+/// Fails if any of the optional measurements is missing, unless `allow_skip`
+/// is set, in which case verification of the missing measurements is skipped.
+pub fn kernel_layer(
+    measurements: &KernelLayerMeasurements,
+    allow_skip: bool,
+) -> anyhow::Result<KernelLayerReferenceValues> {
+    let missing: Vec<&str> = [
+        ("kernel command-line", measurements.kernel_cmdline.is_none()),
+        ("initial RAM disk digest", measurements.ram_disk_digest.is_none()),
+        ("memory map digest", measurements.memory_map_digest.is_none()),
+        ("ACPI digest", measurements.acpi_digest.is_none()),
+    ]
+    .into_iter()
+    .filter_map(|(name, missing)| missing.then_some(name))
+    .collect();
+    anyhow::ensure!(
+        allow_skip || missing.is_empty(),
+        "missing kernel layer measurements: {}",
+        missing.join(", ")
+    );
+
+    let optional_digest = |digest: Option<[u8; 32]>| {
+        Some(match digest {
+            Some(digest) => sha2_256_reference_value(&digest),
+            None => skip_reference_value(),
+        })
+    };
+    Ok(KernelLayerReferenceValues {
+        kernel: Some(KernelBinaryReferenceValue {
+            r#type: Some(kernel_binary_reference_value::Type::Digests(KernelDigests {
+                image: Some(Digests {
+                    digests: vec![sha2_256_digest(&measurements.kernel_image_digest)],
+                }),
+                setup_data: Some(Digests {
+                    digests: vec![sha2_256_digest(&measurements.kernel_setup_data_digest)],
+                }),
+            })),
+        }),
+        kernel_cmd_line_text: Some(TextReferenceValue {
+            r#type: Some(match &measurements.kernel_cmdline {
+                Some(cmdline) => text_reference_value::Type::StringLiterals(StringLiterals {
+                    value: vec![cmdline.clone()],
+                }),
+                None => text_reference_value::Type::Skip(SkipVerification {}),
+            }),
+        }),
+        init_ram_fs: optional_digest(measurements.ram_disk_digest),
+        memory_map: optional_digest(measurements.memory_map_digest),
+        acpi: optional_digest(measurements.acpi_digest),
+        ..Default::default()
+    })
+}
+
+/// Combines the root and, if available, kernel layer reference values into
+/// the reference values for a stack.
+///
+/// The layers that are not supplied are left unset, so verification fails
+/// until reference values for them are added.
+pub fn stack_reference_values(
+    stack: Stack,
+    root_layer: RootLayerReferenceValues,
+    kernel_layer: Option<KernelLayerReferenceValues>,
+) -> ReferenceValues {
+    let r#type = match stack {
+        Stack::RestrictedKernel => {
+            reference_values::Type::OakRestrictedKernel(OakRestrictedKernelReferenceValues {
+                root_layer: Some(root_layer),
+                kernel_layer,
+                application_layer: None,
+            })
+        }
+        Stack::Containers => reference_values::Type::OakContainers(OakContainersReferenceValues {
+            root_layer: Some(root_layer),
+            kernel_layer,
+            system_layer: None,
+            container_layer: None,
+        }),
+    };
+    ReferenceValues { r#type: Some(r#type) }
+}
+
+fn skip_reference_value() -> BinaryReferenceValue {
+    BinaryReferenceValue { r#type: Some(binary_reference_value::Type::Skip(SkipVerification {})) }
+}
+
+fn digests_reference_value(digests: Vec<RawDigest>) -> BinaryReferenceValue {
+    BinaryReferenceValue {
+        r#type: Some(binary_reference_value::Type::Digests(Digests { digests })),
+    }
+}
+
+fn sha2_256_digest(digest: &[u8; 32]) -> RawDigest {
+    RawDigest { sha2_256: digest.to_vec(), ..Default::default() }
+}
+
+fn sha2_256_reference_value(digest: &[u8; 32]) -> BinaryReferenceValue {
+    digests_reference_value(vec![sha2_256_digest(digest)])
+}
+
+fn sha2_384_reference_value(digest: &Measurement) -> BinaryReferenceValue {
+    digests_reference_value(vec![RawDigest { sha2_384: digest.to_vec(), ..Default::default() }])
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::tdx::RTMR_COUNT;
+
+    fn kernel_layer_measurements() -> KernelLayerMeasurements {
+        KernelLayerMeasurements {
+            kernel_image_digest: [1; 32],
+            kernel_setup_data_digest: [2; 32],
+            kernel_cmdline: Some("console=ttyS0".to_string()),
+            ram_disk_digest: Some([3; 32]),
+            memory_map_digest: Some([4; 32]),
+            acpi_digest: Some([5; 32]),
+        }
+    }
+
+    fn is_skip(value: &Option<BinaryReferenceValue>) -> bool {
+        matches!(
+            value,
+            Some(BinaryReferenceValue { r#type: Some(binary_reference_value::Type::Skip(_)) })
+        )
+    }
+
+    #[test]
+    fn test_amd_sev_root_layer_accepts_each_measurement() {
+        let root_layer = amd_sev_root_layer(
+            &[[1; 48], [2; 48]],
+            TcbVersion { snp: 15, ..Default::default() },
+            false,
+        );
+
+        let amd_sev = root_layer.amd_sev.expect("no AMD SEV-SNP reference values");
+        assert_eq!(amd_sev.min_tcb_version.map(|tcb| tcb.snp), Some(15));
+        assert!(!amd_sev.allow_debug);
+        let Some(binary_reference_value::Type::Digests(digests)) =
+            amd_sev.stage0.and_then(|stage0| stage0.r#type)
+        else {
+            panic!("Stage 0 reference value isn't a list of digests");
+        };
+        let measurements: Vec<Vec<u8>> =
+            digests.digests.into_iter().map(|digest| digest.sha2_384).collect();
+        assert_eq!(measurements, vec![vec![1; 48], vec![2; 48]]);
+    }
+
+    #[test]
+    fn test_intel_tdx_root_layer_skips_unpredicted_rtmrs() {
+        let root_layer = intel_tdx_root_layer(
+            &[1; 48],
+            &[Some([2; 48]), None, Some([3; 48]), Some([0; 48])],
+            vec![3; 16],
+            false,
+        );
+
+        let intel_tdx = root_layer.intel_tdx.expect("no Intel TDX reference values");
+        assert_eq!(intel_tdx.min_tee_tcb_svn, vec![3; 16]);
+        assert_eq!(intel_tdx.mr_td, Some(sha2_384_reference_value(&[1; 48])));
+        assert_eq!(intel_tdx.rtmr0, Some(sha2_384_reference_value(&[2; 48])));
+        assert!(is_skip(&intel_tdx.rtmr1));
+        assert_eq!(intel_tdx.rtmr2, Some(sha2_384_reference_value(&[3; 48])));
+        assert_eq!(intel_tdx.rtmr3, Some(sha2_384_reference_value(&[0; 48])));
+    }
+
+    #[test]
+    fn test_kernel_layer() {
+        let kernel_layer = kernel_layer(&kernel_layer_measurements(), false).unwrap();
+
+        assert_eq!(
+            kernel_layer.kernel_cmd_line_text,
+            Some(TextReferenceValue {
+                r#type: Some(text_reference_value::Type::StringLiterals(StringLiterals {
+                    value: vec!["console=ttyS0".to_string()],
+                })),
+            })
+        );
+        assert_eq!(kernel_layer.init_ram_fs, Some(sha2_256_reference_value(&[3; 32])));
+        assert_eq!(kernel_layer.memory_map, Some(sha2_256_reference_value(&[4; 32])));
+        assert_eq!(kernel_layer.acpi, Some(sha2_256_reference_value(&[5; 32])));
+    }
+
+    #[test]
+    fn test_kernel_layer_with_missing_measurement_fails() {
+        let measurements =
+            KernelLayerMeasurements { acpi_digest: None, ..kernel_layer_measurements() };
+
+        let err = kernel_layer(&measurements, false).unwrap_err();
+        assert!(err.to_string().contains("ACPI digest"), "{err}");
+    }
+
+    #[test]
+    fn test_kernel_layer_with_allow_skip_skips_missing_measurements() {
+        let measurements = KernelLayerMeasurements {
+            kernel_cmdline: None,
+            ram_disk_digest: None,
+            ..kernel_layer_measurements()
+        };
+
+        let kernel_layer = kernel_layer(&measurements, true).unwrap();
+        assert_eq!(
+            kernel_layer.kernel_cmd_line_text,
+            Some(TextReferenceValue {
+                r#type: Some(text_reference_value::Type::Skip(SkipVerification {})),
+            })
+        );
+        assert!(is_skip(&kernel_layer.init_ram_fs));
+        assert_eq!(kernel_layer.acpi, Some(sha2_256_reference_value(&[5; 32])));
+    }
+
+    #[test]
+    fn test_stack_reference_values_round_trip() {
+        let root_layer =
+            intel_tdx_root_layer(&[1; 48], &[Some([2; 48]); RTMR_COUNT], vec![], false);
+        let kernel_layer = kernel_layer(&kernel_layer_measurements(), false).unwrap();
+
+        for stack in [Stack::RestrictedKernel, Stack::Containers] {
+            let reference_values =
+                stack_reference_values(stack, root_layer.clone(), Some(kernel_layer.clone()));
+            let decoded = ReferenceValues::decode(reference_values.encode_to_vec().as_slice())
+                .expect("couldn't decode reference values");
+            assert_eq!(decoded, reference_values);
+        }
+    }
+
+    #[test]
+    fn test_stack_reference_values_without_kernel_layer() {
+        let root_layer =
+            intel_tdx_root_layer(&[1; 48], &[Some([2; 48]); RTMR_COUNT], vec![], false);
+
+        let reference_values = stack_reference_values(Stack::Containers, root_layer.clone(), None);
+        let Some(reference_values::Type::OakContainers(containers)) = reference_values.r#type
+        else {
+            panic!("reference values aren't for Oak Containers");
+        };
+        assert_eq!(containers.root_layer, Some(root_layer));
+        assert_eq!(containers.kernel_layer, None);
+    }
+}
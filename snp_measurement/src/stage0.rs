@@ -88,12 +88,38 @@ const SEV_ES_RESET_GUID: u128 = u128::from_le_bytes([
     0xde, 0x71, 0xf7, 0x00, 0x7e, 0x1a, 0xcb, 0x4f, 0x89, 0x0e, 0x68, 0xc7, 0x7e, 0x2f, 0xb4, 0x4e,
 ]);
 
+/// The GUID identifying the TDX metadata offset GUID table entry.
+///
+/// This matches the TDX metadata offset GUID used in OVMF
+/// (e47a6535-984a-4798-865e-4685a7bf8ec2).
+///
+/// See <https://github.com/tianocore/edk2/blob/fff6d81270b57ee786ea18ad74f43149b9f03494/OvmfPkg/ResetVector/Ia16/ResetVectorVtf0.asm>.
+const TDX_METADATA_OFFSET_GUID: u128 = u128::from_le_bytes([
+    0x35, 0x65, 0x7a, 0xe4, 0x4a, 0x98, 0x98, 0x47, 0x86, 0x5e, 0x46, 0x85, 0xa7, 0xbf, 0x8e, 0xc2,
+]);
+
 /// The expected first 4 bytes of the SEV metadata section header.
 const SEV_SECTION_SIGNATURE: &[u8] = b"ASEV";
 
 /// The version of SEV metadata sections we expect to encounter.
 const SEV_METADATA_VERSION: u32 = 1;
 
+/// The size of the TDVF metadata descriptor.
+const TDVF_DESCRIPTOR_SIZE: usize = 16;
+
+/// The size of a TDVF metadata section entry.
+const TDVF_SECTION_SIZE: usize = 32;
+
+/// The expected first 4 bytes of the TDVF metadata descriptor.
+const TDVF_DESCRIPTOR_SIGNATURE: &[u8] = b"TDVF";
+
+/// The version of the TDVF metadata descriptor we expect to encounter.
+const TDVF_DESCRIPTOR_VERSION: u32 = 1;
+
+/// The TDVF section attribute indicating that the contents of the section must
+/// be measured into MRTD.
+const TDVF_SECTION_ATTRIBUTES_EXTENDMR: u32 = 1;
+
 /// The contents of the Stage 0 firmware ROM image and its associated metadata.
 pub struct Stage0Info {
     /// The bytes of the State 0 firmware ROM image.
@@ -172,6 +198,45 @@ impl Stage0Info {
         sev_es_reset_address.into()
     }
 
+    /// Gets the sections defined in the firmware TDVF metadata.
+    pub fn get_tdvf_sections(&self) -> Vec<TdvfSection> {
+        let tdx_metadata_content = *self
+            .parse_firmware_guid_table()
+            .get(&TDX_METADATA_OFFSET_GUID)
+            .expect("couldn't find TDX metadata offset entry in GUID table");
+        assert_eq!(
+            tdx_metadata_content.len(),
+            size_of::<u32>(),
+            "invalid length for TDX metadata offset entry"
+        );
+        // We expect the TDX metadata offset entry in the GUID table to contain only 4
+        // bytes that represent the 32-bit unsigned little-endian encoding of the
+        // reverse offset from the end of the firmware image to the start of the
+        // TDVF descriptor.
+        let mut tdx_metadata_offset: u32 = 0;
+        tdx_metadata_offset.as_bytes_mut().copy_from_slice(tdx_metadata_content);
+        let tdx_metadata_offset = tdx_metadata_offset as usize;
+        trace!("TDX metadata offset: {}", tdx_metadata_offset);
+        assert!(tdx_metadata_offset < self.bytes.len(), "invalid TDX metadata offset");
+        let descriptor_start = self.bytes.len() - tdx_metadata_offset;
+        let descriptor_end = descriptor_start + TDVF_DESCRIPTOR_SIZE;
+        let descriptor = TdvfDescriptor::parse(&self.bytes[descriptor_start..descriptor_end]);
+        trace!("TDVF section count: {}", descriptor.count);
+        let sections_end = descriptor_start + descriptor.length as usize;
+        assert!(sections_end <= self.bytes.len(), "invalid TDVF descriptor length");
+        let sections: Vec<TdvfSection> = self.bytes[descriptor_end..sections_end]
+            .chunks(TDVF_SECTION_SIZE)
+            .map(TdvfSection::parse)
+            .collect();
+        for section in &sections {
+            assert!(
+                section.data_offset + section.raw_size <= self.bytes.len(),
+                "TDVF section data outside of firmware image"
+            );
+        }
+        sections
+    }
+
     /// Parses the GUID table from the firmware image as a map.
     ///
     /// The GUID (represented as a u128) of each table entry is the key and the
@@ -221,7 +286,7 @@ impl Stage0Info {
         &self.bytes[content_start..content_end]
     }
 
-    fn new(bytes: Vec<u8>) -> Self {
+    pub fn new(bytes: Vec<u8>) -> Self {
         let size = bytes.len();
         let start_address = FIRMWARE_TOP - size;
         let legacy_size = size.min(LEGACY_MAX_SIZE);
@@ -305,6 +370,94 @@ impl From<SevMetadataPageType> for PageType {
     }
 }
 
+/// Information about a section specified in the firmware TDVF metadata.
+///
+/// See section 11 of the TDX Virtual Firmware Design Guide:
+/// <https://cdrdv2.intel.com/v1/dl/getContent/733585>
+pub struct TdvfSection {
+    /// The offset of the section's initial data in the firmware image.
+    pub data_offset: usize,
+    /// The size of the section's initial data in the firmware image.
+    pub raw_size: usize,
+    pub start_address: PhysAddr,
+    pub page_count: usize,
+    pub section_type: TdvfSectionType,
+    /// Whether the contents of the section are measured into MRTD.
+    pub extend_mr: bool,
+}
+
+impl TdvfSection {
+    fn parse(bytes: &[u8]) -> Self {
+        assert!(bytes.len() == TDVF_SECTION_SIZE);
+        let mut data_offset: u32 = 0;
+        data_offset.as_bytes_mut().copy_from_slice(&bytes[0..4]);
+        let mut raw_size: u32 = 0;
+        raw_size.as_bytes_mut().copy_from_slice(&bytes[4..8]);
+
+        let mut base: u64 = 0;
+        base.as_bytes_mut().copy_from_slice(&bytes[8..16]);
+        let start_address = PhysAddr::new(base);
+        assert_eq!(
+            start_address,
+            start_address.align_down(Size4KiB::SIZE),
+            "invalid TDVF section base address"
+        );
+
+        let mut size: u64 = 0;
+        size.as_bytes_mut().copy_from_slice(&bytes[16..24]);
+        assert_eq!(size % Size4KiB::SIZE, 0, "invalid TDVF section size");
+        assert!(raw_size as u64 <= size, "TDVF section data larger than section");
+        let page_count = (size / Size4KiB::SIZE) as usize;
+
+        let mut section_type: u32 = 0;
+        section_type.as_bytes_mut().copy_from_slice(&bytes[24..28]);
+        let mut attributes: u32 = 0;
+        attributes.as_bytes_mut().copy_from_slice(&bytes[28..32]);
+        trace!(
+            "TDVF section: offset: {}, raw_size: {}, base: {}, size: {}, type: {}, attributes: {}",
+            data_offset,
+            raw_size,
+            base,
+            size,
+            section_type,
+            attributes
+        );
+        let section_type =
+            TdvfSectionType::from_repr(section_type).expect("invalid TDVF section type");
+        assert_eq!(
+            attributes & !TDVF_SECTION_ATTRIBUTES_EXTENDMR,
+            0,
+            "unsupported TDVF section attributes"
+        );
+
+        Self {
+            data_offset: data_offset as usize,
+            raw_size: raw_size as usize,
+            start_address,
+            page_count,
+            section_type,
+            extend_mr: attributes & TDVF_SECTION_ATTRIBUTES_EXTENDMR != 0,
+        }
+    }
+}
+
+/// The section types used in the firmware TDVF metadata.
+///
+/// See section 11 of the TDX Virtual Firmware Design Guide:
+/// <https://cdrdv2.intel.com/v1/dl/getContent/733585>
+#[derive(Debug, FromRepr, PartialEq)]
+#[repr(u32)]
+pub enum TdvfSectionType {
+    /// The boot firmware volume.
+    Bfv = 0,
+    /// The configuration firmware volume.
+    Cfv = 1,
+    /// The TD hand-off block, which is populated by the VMM.
+    TdHob = 2,
+    /// Temporary memory used by the firmware.
+    TempMem = 3,
+}
+
 /// The instruction pointer and code segment base that will be set when a
 /// non-boot vCPU is reset.
 pub struct SevEsResetBlock {
@@ -379,3 +532,74 @@ impl SevMetadataHeader {
         Self { length, count }
     }
 }
+
+/// The TDVF metadata descriptor.
+///
+/// We validate the signature and version, but don't use it for anything else,
+/// so we don't need to store their values in the struct.
+///
+/// See section 11 of the TDX Virtual Firmware Design Guide:
+/// <https://cdrdv2.intel.com/v1/dl/getContent/733585>
+struct TdvfDescriptor {
+    length: u32,
+    count: u32,
+}
+
+impl TdvfDescriptor {
+    fn parse(bytes: &[u8]) -> Self {
+        assert!(bytes.len() == TDVF_DESCRIPTOR_SIZE);
+        let mut signature: [u8; 4] = [0; 4];
+        signature[..].copy_from_slice(&bytes[..4]);
+        assert_eq!(signature, TDVF_DESCRIPTOR_SIGNATURE, "invalid signature for TDVF descriptor");
+        let mut version: u32 = 0;
+        version.as_bytes_mut().copy_from_slice(&bytes[8..12]);
+        assert_eq!(version, TDVF_DESCRIPTOR_VERSION, "invalid version for TDVF descriptor");
+
+        let mut length: u32 = 0;
+        length.as_bytes_mut().copy_from_slice(&bytes[4..8]);
+        let mut count: u32 = 0;
+        count.as_bytes_mut().copy_from_slice(&bytes[12..16]);
+        trace!("TDVF descriptor: length:{}, count:{}", length, count);
+        assert_eq!(
+            length,
+            count * (TDVF_SECTION_SIZE as u32) + (TDVF_DESCRIPTOR_SIZE as u32),
+            "invalid length or count in TDVF descriptor"
+        );
+        Self { length, count }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{tdvf_firmware, tdvf_sections};
+
+    #[test]
+    fn test_get_tdvf_sections() {
+        let sections = tdvf_firmware().get_tdvf_sections();
+
+        let expected = tdvf_sections();
+        assert_eq!(sections.len(), expected.len());
+        for (section, expected) in sections.iter().zip(expected) {
+            assert_eq!(section.data_offset, expected.data_offset as usize);
+            assert_eq!(section.raw_size, expected.raw_size as usize);
+            assert_eq!(section.start_address, PhysAddr::new(expected.base));
+            assert_eq!(section.page_count as u64, expected.size / Size4KiB::SIZE);
+            assert_eq!(
+                section.section_type,
+                TdvfSectionType::from_repr(expected.section_type).unwrap()
+            );
+            assert_eq!(
+                section.extend_mr,
+                expected.attributes & TDVF_SECTION_ATTRIBUTES_EXTENDMR != 0
+            );
+        }
+    }
+
+    #[test]
+    fn test_tdvf_firmware_start_address() {
+        let firmware = tdvf_firmware();
+        assert_eq!(firmware.start_address, PhysAddr::new(0xffff_d000));
+        assert_eq!(firmware.get_tdvf_sections()[0].start_address, firmware.start_address);
+    }
+}
@@ -0,0 +1,235 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use log::trace;
+use sha2::{Digest, Sha384};
+use x86_64::{
+    structures::paging::{PageSize, Size4KiB},
+    PhysAddr,
+};
+
+use crate::stage0::{Stage0Info, TdvfSection};
+
+/// The size of a TDX measurement register.
+pub const MEASUREMENT_SIZE: usize = 48;
+
+/// The number of runtime measurement registers.
+pub const RTMR_COUNT: usize = 4;
+
+/// A TDX measurement register value.
+pub type Measurement = [u8; MEASUREMENT_SIZE];
+
+/// The size of the buffer that the TDX module hashes for each TDH.MEM.PAGE.ADD
+/// and TDH.MR.EXTEND operation, excluding the extended data.
+const MRTD_EXTENSION_BUFFER_SIZE: usize = 128;
+
+/// The size of the chunks in which TDH.MR.EXTEND measures page contents.
+const MR_EXTEND_CHUNK_SIZE: usize = 256;
+
+/// The operation identifier hashed into MRTD when a page is added to the TD.
+const PAGE_ADD_OPERATION: &[u8] = b"MEM.PAGE.ADD";
+
+/// The operation identifier hashed into MRTD when a chunk of a page is
+/// measured.
+const MR_EXTEND_OPERATION: &[u8] = b"MR.EXTEND";
+
+/// Replays the build-time measurement of a TD into MRTD.
+///
+/// The VMM adds each section listed in the TDVF metadata to the TD in the order
+/// in which the sections are listed, one page at a time. Each page is added
+/// with TDH.MEM.PAGE.ADD, which extends MRTD with the guest-physical address of
+/// the page. If the section has the EXTENDMR attribute, the page contents are
+/// then measured in 256 byte chunks using TDH.MR.EXTEND.
+///
+/// See section 12.4 of the Intel TDX Module Base Architecture Specification:
+/// <https://cdrdv2.intel.com/v1/dl/getContent/733575>
+pub struct MrtdInfo {
+    hasher: Sha384,
+}
+
+impl MrtdInfo {
+    pub fn new() -> Self {
+        Self { hasher: Sha384::new() }
+    }
+
+    /// Updates the measurement with all the TDVF sections in the firmware.
+    pub fn update_from_stage0(&mut self, stage0: &Stage0Info) {
+        for section in stage0.get_tdvf_sections() {
+            self.update_from_section(&section, stage0.rom_bytes());
+        }
+    }
+
+    /// Updates the measurement with the pages of a single TDVF section.
+    pub fn update_from_section(&mut self, section: &TdvfSection, rom: &[u8]) {
+        trace!(
+            "adding {:?} section at {:#x} with {} pages",
+            section.section_type,
+            section.start_address.as_u64(),
+            section.page_count
+        );
+        // The section data is padded with zeros up to the size of the section.
+        let mut contents = vec![0u8; section.page_count * Size4KiB::SIZE as usize];
+        contents[..section.raw_size]
+            .copy_from_slice(&rom[section.data_offset..section.data_offset + section.raw_size]);
+        for (page_number, page) in contents.chunks(Size4KiB::SIZE as usize).enumerate() {
+            let page_address = section.start_address + (page_number as u64) * Size4KiB::SIZE;
+            self.update_from_page_add(page_address);
+            if section.extend_mr {
+                for (chunk_number, chunk) in page.chunks(MR_EXTEND_CHUNK_SIZE).enumerate() {
+                    self.update_from_mr_extend(
+                        page_address + (chunk_number * MR_EXTEND_CHUNK_SIZE) as u64,
+                        chunk,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Gets the final value of MRTD.
+    pub fn finalize(self) -> Measurement {
+        self.hasher.finalize().into()
+    }
+
+    fn update_from_page_add(&mut self, address: PhysAddr) {
+        self.hasher.update(extension_buffer(PAGE_ADD_OPERATION, address));
+    }
+
+    fn update_from_mr_extend(&mut self, address: PhysAddr, chunk: &[u8]) {
+        assert_eq!(chunk.len(), MR_EXTEND_CHUNK_SIZE);
+        self.hasher.update(extension_buffer(MR_EXTEND_OPERATION, address));
+        self.hasher.update(chunk);
+    }
+}
+
+/// Builds the buffer that the TDX module hashes into MRTD for an operation.
+///
+/// The buffer consists of the operation identifier padded to 16 bytes and the
+/// little-endian guest-physical address, padded with zeros to 128 bytes.
+fn extension_buffer(operation: &[u8], address: PhysAddr) -> [u8; MRTD_EXTENSION_BUFFER_SIZE] {
+    let mut buffer = [0u8; MRTD_EXTENSION_BUFFER_SIZE];
+    buffer[..operation.len()].copy_from_slice(operation);
+    buffer[16..24].copy_from_slice(&address.as_u64().to_le_bytes());
+    buffer
+}
+
+/// The measurements that Stage 0 records in the runtime measurement registers.
+///
+/// All digests are SHA2-256 digests, matching the values that Stage 0 reports
+/// in its DICE event. Digests that are not available default to zeros, which
+/// is what Stage 0 uses when the corresponding component is not present.
+#[derive(Default)]
+pub struct Stage0RtmrInputs {
+    pub acpi_digest: [u8; 32],
+    pub memory_map_digest: [u8; 32],
+    pub kernel_image_digest: [u8; 32],
+    pub kernel_setup_data_digest: [u8; 32],
+    pub kernel_cmdline: String,
+    pub ram_disk_digest: [u8; 32],
+}
+
+impl Stage0RtmrInputs {
+    /// Predicts the values of RTMR[0..3] after Stage 0 has recorded its
+    /// measurements.
+    ///
+    /// This mirrors `record_measurements` in `stage0_bin_tdx`: each register
+    /// is extended with the SHA2-384 digest of each measurement, and RTMR[3]
+    /// is not used.
+    pub fn predict_rtmrs(&self) -> [Measurement; RTMR_COUNT] {
+        let mut rtmrs = [[0u8; MEASUREMENT_SIZE]; RTMR_COUNT];
+        extend_rtmr(&mut rtmrs[0], &self.acpi_digest);
+        extend_rtmr(&mut rtmrs[0], &self.memory_map_digest);
+        extend_rtmr(&mut rtmrs[1], &self.kernel_image_digest);
+        extend_rtmr(&mut rtmrs[1], &self.kernel_setup_data_digest);
+        extend_rtmr(&mut rtmrs[2], self.kernel_cmdline.as_bytes());
+        extend_rtmr(&mut rtmrs[2], &self.ram_disk_digest);
+        rtmrs
+    }
+}
+
+/// Replays TDG.MR.RTMR.EXTEND with the SHA2-384 digest of `data`.
+fn extend_rtmr(rtmr: &mut Measurement, data: &[u8]) {
+    let extension = Sha384::digest(data);
+    *rtmr = Sha384::new().chain_update(&rtmr[..]).chain_update(extension).finalize().into();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::tdvf_firmware;
+
+    /// MRTD of the test firmware image, calculated independently by following
+    /// section 12.4 of the Intel TDX Module Base Architecture Specification.
+    const TDVF_FIRMWARE_MRTD: &str = "bac746b2045276fd9ce6556e096a52dc8927ee4df4278c398b1b535e6809df47fdba2b781b49fcdd82ce17f1329ea8a8";
+
+    fn rtmr_inputs() -> Stage0RtmrInputs {
+        Stage0RtmrInputs {
+            acpi_digest: [1; 32],
+            memory_map_digest: [2; 32],
+            kernel_image_digest: [3; 32],
+            kernel_setup_data_digest: [4; 32],
+            kernel_cmdline: "console=ttyS0".to_string(),
+            ram_disk_digest: [5; 32],
+        }
+    }
+
+    #[test]
+    fn test_extension_buffer() {
+        let buffer = extension_buffer(PAGE_ADD_OPERATION, PhysAddr::new(0x1234_5678_9000));
+        let mut expected = [0u8; MRTD_EXTENSION_BUFFER_SIZE];
+        expected[..12].copy_from_slice(b"MEM.PAGE.ADD");
+        expected[16..24].copy_from_slice(&[0x00, 0x90, 0x78, 0x56, 0x34, 0x12, 0x00, 0x00]);
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_mrtd_of_page_add() {
+        let address = PhysAddr::new(0x80_0000);
+        let mut mrtd_info = MrtdInfo::new();
+        mrtd_info.update_from_page_add(address);
+        let expected: Measurement =
+            Sha384::digest(extension_buffer(PAGE_ADD_OPERATION, address)).into();
+        assert_eq!(mrtd_info.finalize(), expected);
+    }
+
+    #[test]
+    fn test_mrtd_of_firmware() {
+        let mut mrtd_info = MrtdInfo::new();
+        mrtd_info.update_from_stage0(&tdvf_firmware());
+        assert_eq!(hex::encode(mrtd_info.finalize()), TDVF_FIRMWARE_MRTD);
+    }
+
+    #[test]
+    fn test_predict_rtmrs() {
+        // Calculated by hand by extending each register, starting from zeros, with
+        // RTMR = SHA2-384(RTMR || SHA2-384(measurement)) for each of its
+        // measurements in order.
+        let rtmrs = rtmr_inputs().predict_rtmrs();
+
+        assert_eq!(
+            hex::encode(rtmrs[0]),
+            "2938b8220cf300df011c1f062ce2613681073b6f0e67944a33f6588c411b54d5303fe2f94b41c51a395d8943c6380837"
+        );
+        assert_eq!(
+            hex::encode(rtmrs[1]),
+            "d5e559a799b7ada7ab9651854bb855263b15fc53325d4a6abc7ab89e016f2dc724dc614a8c2cc7a846d0324685a36133"
+        );
+        assert_eq!(
+            hex::encode(rtmrs[2]),
+            "46b6be5a0ca7d7a083ee775fa8141b3707f634e7a5e118e31a10e5f01366b17dec92d25f9c10872368f0ecd894bb9d2d"
+        );
+        assert_eq!(rtmrs[3], [0u8; MEASUREMENT_SIZE]);
+    }
+}
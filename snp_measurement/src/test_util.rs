@@ -0,0 +1,122 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use x86_64::{
+    structures::paging::{PageSize, Size4KiB},
+    PhysAddr,
+};
+
+use crate::stage0::{Stage0Info, TdvfSectionType};
+
+/// The size of the test firmware image.
+pub const TDVF_FIRMWARE_SIZE: usize = 3 * Size4KiB::SIZE as usize;
+
+/// The offset of the TDVF metadata descriptor in the test firmware image.
+const TDVF_DESCRIPTOR_OFFSET: usize = 2 * Size4KiB::SIZE as usize;
+
+/// A section in the TDVF metadata of the test firmware image.
+pub struct TestTdvfSection {
+    pub data_offset: u32,
+    pub raw_size: u32,
+    pub base: u64,
+    pub size: u64,
+    pub section_type: u32,
+    pub attributes: u32,
+}
+
+/// Returns the sections in the TDVF metadata of the test firmware image.
+///
+/// The boot firmware volume covers the whole image, the configuration firmware
+/// volume is only partially initialized, and the other sections are not
+/// measured.
+pub fn tdvf_sections() -> Vec<TestTdvfSection> {
+    vec![
+        TestTdvfSection {
+            data_offset: 0,
+            raw_size: TDVF_FIRMWARE_SIZE as u32,
+            base: (PhysAddr::new(0x1_0000_0000) - TDVF_FIRMWARE_SIZE).as_u64(),
+            size: TDVF_FIRMWARE_SIZE as u64,
+            section_type: TdvfSectionType::Bfv as u32,
+            attributes: 1,
+        },
+        TestTdvfSection {
+            data_offset: Size4KiB::SIZE as u32,
+            raw_size: 0x100,
+            base: 0x90_0000,
+            size: Size4KiB::SIZE,
+            section_type: TdvfSectionType::Cfv as u32,
+            attributes: 1,
+        },
+        TestTdvfSection {
+            data_offset: 0,
+            raw_size: 0,
+            base: 0x80_0000,
+            size: 2 * Size4KiB::SIZE,
+            section_type: TdvfSectionType::TempMem as u32,
+            attributes: 0,
+        },
+        TestTdvfSection {
+            data_offset: 0,
+            raw_size: 0,
+            base: 0x80_2000,
+            size: Size4KiB::SIZE,
+            section_type: TdvfSectionType::TdHob as u32,
+            attributes: 0,
+        },
+    ]
+}
+
+/// Builds a firmware image that contains the TDVF metadata from
+/// [`tdvf_sections`], referenced from a GUID table at the end of the image.
+pub fn tdvf_firmware() -> Stage0Info {
+    let mut bytes: Vec<u8> = (0..TDVF_FIRMWARE_SIZE).map(|i| ((i * 7 + 3) % 251) as u8).collect();
+
+    let sections = tdvf_sections();
+    let mut descriptor = Vec::new();
+    descriptor.extend_from_slice(b"TDVF");
+    descriptor.extend_from_slice(&(16 + 32 * sections.len() as u32).to_le_bytes());
+    descriptor.extend_from_slice(&1u32.to_le_bytes());
+    descriptor.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+    for section in &sections {
+        descriptor.extend_from_slice(&section.data_offset.to_le_bytes());
+        descriptor.extend_from_slice(&section.raw_size.to_le_bytes());
+        descriptor.extend_from_slice(&section.base.to_le_bytes());
+        descriptor.extend_from_slice(&section.size.to_le_bytes());
+        descriptor.extend_from_slice(&section.section_type.to_le_bytes());
+        descriptor.extend_from_slice(&section.attributes.to_le_bytes());
+    }
+    bytes[TDVF_DESCRIPTOR_OFFSET..TDVF_DESCRIPTOR_OFFSET + descriptor.len()]
+        .copy_from_slice(&descriptor);
+
+    // The GUID table ends 32 bytes before the end of the image. It contains the
+    // TDX metadata offset entry followed by the footer.
+    let mut table = Vec::new();
+    table.extend_from_slice(&((TDVF_FIRMWARE_SIZE - TDVF_DESCRIPTOR_OFFSET) as u32).to_le_bytes());
+    table.extend_from_slice(&22u16.to_le_bytes());
+    table.extend_from_slice(&[
+        0x35, 0x65, 0x7a, 0xe4, 0x4a, 0x98, 0x98, 0x47, 0x86, 0x5e, 0x46, 0x85, 0xa7, 0xbf, 0x8e,
+        0xc2,
+    ]);
+    table.extend_from_slice(&((table.len() + 18) as u16).to_le_bytes());
+    table.extend_from_slice(&[
+        0xde, 0x82, 0xb5, 0x96, 0xb2, 0x1f, 0xf7, 0x45, 0xba, 0xea, 0xa3, 0x66, 0xc5, 0x5a, 0x08,
+        0x2d,
+    ]);
+    let table_end = TDVF_FIRMWARE_SIZE - 0x20;
+    bytes[table_end - table.len()..table_end].copy_from_slice(&table);
+
+    Stage0Info::new(bytes)
+}
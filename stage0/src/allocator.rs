@@ -30,6 +30,7 @@ use x86_64::{
     structures::paging::{Page, PageSize, Size4KiB},
     VirtAddr,
 };
+use zerocopy::FromZeroes;
 
 use crate::{
     paging::{share_page, unshare_page},
@@ -170,6 +171,19 @@ impl<T, A: Allocator, P: Platform> Shared<T, A, P> {
         Self { inner: Box::new_in(t, SharedAllocator::new(alloc)) }
     }
 
+    /// Allocates a zeroed `T` directly on a shared page.
+    ///
+    /// Unlike `new_in`, the value is never constructed on the stack, so this
+    /// is suitable for large buffers.
+    pub fn new_zeroed_in(alloc: A) -> Self
+    where
+        A: 'static,
+        T: FromZeroes,
+    {
+        // Safety: `T` implements `FromZeroes`, so all-zero bytes are a valid value.
+        Self { inner: unsafe { Box::new_zeroed_in(SharedAllocator::new(alloc)).assume_init() } }
+    }
+
     /// See `Box::from_raw_in` for documentation.
     ///
     /// # Safety
@@ -16,12 +16,14 @@
 
 pub mod mmio;
 
-use core::arch::x86_64::{CpuidResult, __cpuid};
+use alloc::vec::Vec;
+use core::arch::x86_64::{__cpuid, CpuidResult};
 
 pub use mmio::*;
 use oak_attestation::dice::DiceAttester;
 use oak_dice::evidence::TeePlatform;
 use oak_linux_boot_params::BootE820Entry;
+use oak_proto_rust::oak::attestation::v1::Stage0Measurements;
 use oak_stage0_dice::DerivedKey;
 use x86_64::{
     registers::model_specific::Msr,
@@ -84,6 +86,10 @@ impl crate::Platform for Base {
 
     fn populate_zero_page(_zero_page: &mut ZeroPage) {}
 
+    fn record_measurements(_measurements: &Stage0Measurements) -> Result<(), &'static str> {
+        Ok(())
+    }
+
     fn get_attester() -> Result<Self::Attester, &'static str> {
         oak_stage0_dice::generate_initial_dice_data(
             oak_stage0_dice::mock_attestation_report,
@@ -97,6 +103,10 @@ impl crate::Platform for Base {
         oak_stage0_dice::mock_derived_key()
     }
 
+    fn get_tee_certificate() -> Option<Vec<u8>> {
+        None
+    }
+
     fn change_page_state(_page: Page<Size4KiB>, _state: PageAssignment) {}
 
     fn revalidate_page(_page: Page<Size4KiB>) {}
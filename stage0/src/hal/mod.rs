@@ -16,12 +16,14 @@
 
 pub mod base;
 
+use alloc::vec::Vec;
 use core::arch::x86_64::CpuidResult;
 
 pub use base::Base;
 use oak_attestation::attester::{Attester, Serializable};
 use oak_dice::evidence::TeePlatform;
 use oak_linux_boot_params::BootE820Entry;
+use oak_proto_rust::oak::attestation::v1::Stage0Measurements;
 use oak_sev_guest::io::{IoPortFactory, PortReader, PortWriter};
 use oak_stage0_dice::DerivedKey;
 use x86_64::{
@@ -127,6 +129,14 @@ pub trait Platform {
     /// Example: locations of the SEV Secrets and CPUID pages.
     fn populate_zero_page(zero_page: &mut ZeroPage);
 
+    /// Records the measurements of the next boot stage in platform-specific
+    /// measurement registers, e.g. the RTMRs under Intel TDX.
+    ///
+    /// This gets executed before `get_derived_key()` and `get_attester()`, so
+    /// that the derived key and the attestation report reflect the
+    /// measurements.
+    fn record_measurements(measurements: &Stage0Measurements) -> Result<(), &'static str>;
+
     /// Returns an attester that can be used to extend the attestation
     /// information with an event log entry and serialize the attestation data
     /// so that it can be passed to the next boot stage.
@@ -140,8 +150,19 @@ pub trait Platform {
     /// We use this key as the unique device secret for deriving compound
     /// devices identifiers for each layer, and eventually a sealing key in
     /// the last layer.
+    ///
+    /// Platforms that can't derive a key from a secret must return an error
+    /// rather than a key that can be computed outside the VM, so that the
+    /// next layers refuse to seal data.
     fn get_derived_key() -> Result<DerivedKey, &'static str>;
 
+    /// Returns the DER-encoded certificate for the key that signed the
+    /// attestation report, e.g. the AMD VCEK certificate.
+    ///
+    /// The certificate is only available if the host provided it when the
+    /// attester was created, so this must be called after `get_attester()`.
+    fn get_tee_certificate() -> Option<Vec<u8>>;
+
     /// Ask for the page state to be changed by the hypervisor.
     fn change_page_state(page: Page<Size4KiB>, state: PageAssignment);
 
@@ -22,13 +22,16 @@
 extern crate alloc;
 
 use alloc::{boxed::Box, format, vec::Vec};
-use core::panic::PanicInfo;
+use core::{
+    alloc::{Allocator, Layout},
+    panic::PanicInfo,
+};
 
 use linked_list_allocator::LockedHeap;
 use oak_attestation::attester::{Attester, Serializable};
 use oak_dice::evidence::{
     DICE_DATA_CMDLINE_PARAM, DICE_DATA_LENGTH_CMDLINE_PARAM, EVENTLOG_CMDLINE_PARAM,
-    STAGE0_DICE_PROTO_MAGIC,
+    STAGE0_DICE_PROTO_MAGIC, TEE_CERTIFICATE_CMDLINE_PARAM,
 };
 use oak_linux_boot_params::{BootE820Entry, E820EntryType};
 use oak_proto_rust::oak::attestation::v1::DiceData;
@@ -190,13 +193,23 @@ pub fn rust64_start<P: hal::Platform>() -> ! {
         kernel_cmdline: cmdline.clone(),
     };
 
-    // Use the root derived key as the UDS (unique device secret) for deriving
-    // sealing keys.
-    let mut uds: DerivedKey = P::get_derived_key().expect("couldn't get derived key");
+    P::record_measurements(&stage0_event_proto).expect("couldn't record measurements");
 
-    let mut cdi = derive_sealing_cdi(&uds, &stage0_event_proto);
-    // Zero out the UDS.
-    uds.zeroize();
+    // Use the root derived key as the UDS (unique device secret) for deriving
+    // sealing keys. If the platform has no secret to derive it from, the CDI
+    // for the next layer is left zeroed so that it refuses to seal data.
+    let mut cdi: DerivedKey = match P::get_derived_key() {
+        Ok(mut uds) => {
+            let cdi = derive_sealing_cdi(&uds, &stage0_event_proto);
+            // Zero out the UDS.
+            uds.zeroize();
+            cdi
+        }
+        Err(err) => {
+            log::warn!("couldn't get derived key, sealing is not available: {}", err);
+            DerivedKey::default()
+        }
+    };
 
     // Generate Stage0 Event Log data.
     let stage0_event = oak_stage0_dice::encode_stage0_event(stage0_event_proto);
@@ -296,9 +309,27 @@ pub fn rust64_start<P: hal::Platform>() -> ! {
     sensitive_attestation_data_length += PAGE_SIZE;
 
     // Append the DICE data address to the kernel command-line.
-    let extra = format!(
+    let mut extra = format!(
         "--{DICE_DATA_CMDLINE_PARAM}={attestation_data:p} --{EVENTLOG_CMDLINE_PARAM}={event_log_data:p} --{DICE_DATA_LENGTH_CMDLINE_PARAM}={sensitive_attestation_data_length}"
     );
+
+    // Write the TEE certificate to memory if the host provided one. The
+    // certificate is not sensitive, so it is not included in the length of data
+    // that must be zeroed by the next stage.
+    if let Some(tee_certificate_data) =
+        P::get_tee_certificate().and_then(|certificate| write_tee_certificate(&certificate))
+    {
+        // Reserve memory containing the TEE certificate.
+        zero_page.insert_e820_entry(BootE820Entry::new(
+            tee_certificate_data.as_ptr() as usize,
+            PAGE_SIZE,
+            E820EntryType::RESERVED,
+        ));
+        extra.push_str(&format!(
+            " --{TEE_CERTIFICATE_CMDLINE_PARAM}={:p}",
+            tee_certificate_data.as_ptr()
+        ));
+    }
     let cmdline = if cmdline.is_empty() {
         extra
     } else if cmdline.contains("--") {
@@ -325,6 +356,34 @@ pub fn rust64_start<P: hal::Platform>() -> ! {
     }
 }
 
+/// Writes the TEE certificate, prefixed by its little-endian length, to the
+/// start of a newly allocated page. The page is never freed, so it can be
+/// reserved for the next stage.
+///
+/// Returns `None` if the certificate doesn't fit in a page or the page can't
+/// be allocated; the certificate is optional, so booting continues without it.
+fn write_tee_certificate(tee_certificate: &[u8]) -> Option<&'static [u8]> {
+    // The 8 bytes are reserved for the size of the certificate.
+    if tee_certificate.len() > PAGE_SIZE - 8 {
+        log::warn!(
+            "TEE certificate is too large ({} bytes), not passing it on",
+            tee_certificate.len()
+        );
+        return None;
+    }
+    let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).ok()?;
+    let Ok(page) = BOOT_ALLOC.allocate_zeroed(layout) else {
+        log::warn!("couldn't allocate memory for the TEE certificate, not passing it on");
+        return None;
+    };
+    // Safety: the allocation is a fresh, zeroed, page-sized block of memory and the
+    // boot allocator never deallocates, so it is valid for the 'static lifetime.
+    let page: &'static mut [u8] = unsafe { &mut *page.as_ptr() };
+    page[..8].copy_from_slice(tee_certificate.len().to_le_bytes().as_slice());
+    page[8..8 + tee_certificate.len()].copy_from_slice(tee_certificate);
+    Some(page)
+}
+
 /// Common panic routine for the Stage0 binaries. This needs to be wrapped in a
 /// panic_handler function in individual binary crates.
 pub fn panic(info: &PanicInfo) -> ! {
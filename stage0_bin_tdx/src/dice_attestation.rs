@@ -0,0 +1,88 @@
+//
+// Copyright 2024 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use oak_attestation::dice::DiceAttester;
+use oak_dice::evidence::TeePlatform;
+use oak_proto_rust::oak::attestation::v1::Stage0Measurements;
+use oak_stage0_dice::DerivedKey;
+use oak_tdx_guest::tdcall::{
+    extend_rtmr, get_td_report, RtmrIndex, TdReport, MEASUREMENT_SIZE, REPORT_DATA_SIZE,
+};
+use sha2::{Digest, Sha384};
+
+/// Extends the RTMRs with the measurements of the next boot stage.
+///
+/// The register assignment follows the convention used by TDVF:
+///   - RTMR[0] holds the firmware configuration: the ACPI tables and the memory
+///     map.
+///   - RTMR[1] holds the OS loader: the kernel image and its setup data.
+///   - RTMR[2] holds the OS configuration: the kernel command-line and the
+///     initial RAM disk.
+///
+/// Each register is extended with the SHA2-384 digest of the measurement, or
+/// of the command-line itself.
+pub fn record_measurements(measurements: &Stage0Measurements) -> Result<(), &'static str> {
+    let extensions: [(RtmrIndex, &[u8]); 6] = [
+        (RtmrIndex::Rtmr0, &measurements.acpi_digest),
+        (RtmrIndex::Rtmr0, &measurements.memory_map_digest),
+        (RtmrIndex::Rtmr1, &measurements.kernel_measurement),
+        (RtmrIndex::Rtmr1, &measurements.setup_data_digest),
+        (RtmrIndex::Rtmr2, measurements.kernel_cmdline.as_bytes()),
+        (RtmrIndex::Rtmr2, &measurements.ram_disk_digest),
+    ];
+    for (rtmr_index, data) in extensions {
+        let digest: [u8; MEASUREMENT_SIZE] = Sha384::digest(data).into();
+        extend_rtmr(rtmr_index, digest).map_err(|err| {
+            log::error!("couldn't extend RTMR: {}", err);
+            "couldn't extend RTMR"
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns an attester whose root layer evidence contains a TD report.
+///
+/// A TD report can only be verified on the same platform, so it must be
+/// upgraded to a quote with `TDG.VP.VMCALL<GetQuote>` before the evidence is
+/// sent to a remote verifier, which Stage 1 of Oak Containers does with
+/// `oak_attestation_verification::extract::upgrade_td_report_to_quote`. Stage 0
+/// can't do this itself, since a quote with its certification data doesn't
+/// fit in the fixed-size DICE data passed to the next layer.
+pub fn get_attester() -> Result<DiceAttester, &'static str> {
+    oak_stage0_dice::generate_initial_dice_data(get_attestation, TeePlatform::IntelTdx)?
+        .try_into()
+        .map_err(|_| "couldn't convert initial DICE evidence to an attester")
+}
+
+fn get_attestation(report_data: [u8; REPORT_DATA_SIZE]) -> Result<TdReport, &'static str> {
+    get_td_report(report_data).map_err(|err| {
+        log::error!("couldn't get TD report: {}", err);
+        "couldn't get TD report"
+    })
+}
+
+/// Intel TDX does not provide a hardware key derivation function, and the TD
+/// report only contains measurements that anyone can compute, so there is no
+/// secret to derive a key from.
+///
+/// Fails so that data isn't sealed with a key that isn't secret. Sealing on
+/// Intel TDX requires a secret provisioned from outside the TD, e.g. by a key
+/// broker after attestation.
+pub fn get_derived_key() -> Result<DerivedKey, &'static str> {
+    // TODO: b/360488668 - derive the sealing key from a secret provisioned
+    // after attestation, e.g. by a key broker.
+    Err("Intel TDX doesn't provide a secret to derive keys from")
+}
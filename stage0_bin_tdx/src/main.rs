@@ -20,7 +20,7 @@
 
 extern crate alloc;
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use core::{
     mem::{size_of, MaybeUninit},
     ops::{Index, IndexMut},
@@ -56,6 +56,7 @@ use zerocopy::{AsBytes, FromBytes};
 use zeroize::Zeroize;
 
 mod asm;
+mod dice_attestation;
 
 mod counters {
     use core::sync::atomic::AtomicUsize;
@@ -319,31 +320,50 @@ fn init_tdx_serial_port() {
 
 use oak_stage0::hal::PortFactory;
 
-struct Mmio {}
-impl<S: x86_64::structures::paging::page::PageSize> oak_stage0::hal::Mmio<S> for Mmio {
+/// MMIO access through the VMM, using TDG.VP.VMCALL<#VE.RequestMMIO>.
+///
+/// The VMM emulates the access, so the memory doesn't need to be mapped.
+struct Mmio<S: PageSize> {
+    base_address: PhysAddr,
+    phantom: core::marker::PhantomData<S>,
+}
+
+impl<S: PageSize> Mmio<S> {
+    fn address(&self, offset: usize) -> *const u32 {
+        let offset = offset * size_of::<u32>();
+        if offset >= S::SIZE as usize {
+            panic!("invalid MMIO access: offset would access beyond memory boundary");
+        }
+        (self.base_address + offset as u64).as_u64() as *const u32
+    }
+}
+
+impl<S: PageSize> oak_stage0::hal::Mmio<S> for Mmio<S> {
     fn read_u32(&self, offset: usize) -> u32 {
-        mmio_read_u32(offset as *const u32).unwrap()
+        mmio_read_u32(self.address(offset)).unwrap()
     }
     unsafe fn write_u32(&mut self, offset: usize, val: u32) {
-        mmio_write_u32(offset as *mut u32, val).unwrap()
+        mmio_write_u32(self.address(offset), val).unwrap()
     }
 }
 
 struct Tdx {}
 
 impl oak_stage0::Platform for Tdx {
-    type Mmio<S: x86_64::structures::paging::page::PageSize> = Mmio;
+    type Mmio<S: x86_64::structures::paging::page::PageSize> = Mmio<S>;
     type Attester = oak_attestation::dice::DiceAttester;
 
     fn cpuid(leaf: u32) -> core::arch::x86_64::CpuidResult {
         call_cpuid(leaf, 0).unwrap()
     }
 
-    unsafe fn mmio<S>(_: x86_64::addr::PhysAddr) -> <Self as oak_stage0::Platform>::Mmio<S>
+    unsafe fn mmio<S>(
+        base_address: x86_64::addr::PhysAddr,
+    ) -> <Self as oak_stage0::Platform>::Mmio<S>
     where
         S: x86_64::structures::paging::page::PageSize,
     {
-        todo!()
+        Mmio { base_address, phantom: core::marker::PhantomData }
     }
 
     fn port_factory() -> PortFactory {
@@ -613,13 +633,19 @@ impl oak_stage0::Platform for Tdx {
         info!("populate_zero_page start");
         info!("populate_zero_page completed");
     }
+    fn record_measurements(
+        measurements: &oak_proto_rust::oak::attestation::v1::Stage0Measurements,
+    ) -> Result<(), &'static str> {
+        dice_attestation::record_measurements(measurements)
+    }
     fn get_attester() -> Result<Self::Attester, &'static str> {
-        // TODO: b/367564134 - impl TDX attester using RTMRs.
-        oak_stage0::hal::base::Base::get_attester()
+        dice_attestation::get_attester()
     }
     fn get_derived_key() -> Result<[u8; 32], &'static str> {
-        // TODO: b/360488668 - impl get_derived_key
-        Ok([0; 32])
+        dice_attestation::get_derived_key()
+    }
+    fn get_tee_certificate() -> Option<Vec<u8>> {
+        None
     }
     fn change_page_state(
         page: x86_64::structures::paging::Page,
@@ -94,8 +94,13 @@ pub fn dice_data_proto_to_stage0_dice_data(
 
 /// Generates the initial DICE data that can be used as the starting state for a
 /// DICE attester.
+///
+/// The attestation report can be any platform-specific report that includes
+/// the given report data, e.g. an AMD SEV-SNP attestation report or an Intel
+/// TDX TD report.
 pub fn generate_initial_dice_data<
-    F: FnOnce([u8; REPORT_DATA_SIZE]) -> Result<AttestationReport, &'static str>,
+    R: AsBytes,
+    F: FnOnce([u8; REPORT_DATA_SIZE]) -> Result<R, &'static str>,
 >(
     get_attestation: F,
     tee_platform: TeePlatform,
@@ -128,6 +133,7 @@ pub fn generate_initial_dice_data<
             remote_attestation_report: report_bytes.to_vec(),
             eca_public_key: stage0_eca_verifying_key.to_vec(),
             platform: tee_platform as i32,
+            tee_certificate: Vec::new(),
         }),
         layers: Vec::new(),
         application_keys: None,
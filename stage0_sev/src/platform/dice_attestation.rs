@@ -14,9 +14,13 @@
 // limitations under the License.
 //
 
+use alloc::vec::Vec;
+
 use oak_attestation::dice::DiceAttester;
 use oak_sev_guest::{
+    certificates::{find_certificate, VCEK_GUID},
     crypto::GuestMessageEncryptor,
+    ghcb::ExtendedGuestRequestStatus,
     guest::{
         AttestationRequest, AttestationResponse, GuestFieldFlags, GuestMessage, KeyRequest,
         KeyResponse, Message, ReportStatus,
@@ -27,7 +31,10 @@ use oak_sev_snp_attestation_report::{AttestationReport, REPORT_DATA_SIZE};
 use oak_stage0::{allocator::Shared, hal::Platform};
 use oak_stage0_dice::DerivedKey;
 use spinning_top::Spinlock;
-use x86_64::{PhysAddr, VirtAddr};
+use x86_64::{
+    structures::paging::{PageSize, Size4KiB},
+    PhysAddr, VirtAddr,
+};
 use zerocopy::{AsBytes, FromBytes};
 use zeroize::Zeroize;
 
@@ -37,6 +44,18 @@ use super::{GHCB_WRAPPER, SEV_SECRETS};
 /// message protocol.
 static GUEST_MESSAGE_ENCRYPTOR: Spinlock<Option<GuestMessageEncryptor>> = Spinlock::new(None);
 
+/// The number of pages reserved for the certificate table that the host
+/// returns in response to an extended guest request. This is enough to hold
+/// the ARK, ASK and VCEK certificates.
+const CERTIFICATES_PAGES: usize = 4;
+
+/// Buffer that receives the certificate table from the host.
+type CertificatesBuffer = [u8; CERTIFICATES_PAGES * Size4KiB::SIZE as usize];
+
+/// The DER-encoded VCEK certificate, if the host provided one alongside the
+/// attestation report.
+static TEE_CERTIFICATE: Spinlock<Option<Vec<u8>>> = Spinlock::new(None);
+
 /// Initializes the Guest Message encryptor using VMPCK0.
 pub fn init_guest_message_encryptor() -> Result<(), &'static str> {
     // Safety: `SecretsPage` implements `FromBytes` which ensures that it has no
@@ -54,11 +73,19 @@ pub fn init_guest_message_encryptor() -> Result<(), &'static str> {
 }
 
 /// Sends a request to the Secure Processor using the Guest Message Protocol.
+///
+/// If a certificates buffer is provided, an extended guest request is used so
+/// that the host fills the buffer with its certificate table. If the host does
+/// not support extended guest requests, or the buffer is too small, the same
+/// message is resent as a regular guest request and the buffer is left
+/// untouched. The message must not be re-encrypted, as that would advance the
+/// message sequence number.
 fn send_guest_message_request<
     Request: AsBytes + FromBytes + Message,
     Response: AsBytes + FromBytes + Message,
 >(
     request: Request,
+    certificates: Option<&mut CertificatesBuffer>,
 ) -> Result<Response, &'static str> {
     let mut guard = GUEST_MESSAGE_ENCRYPTOR.lock();
     let encryptor = guard.as_mut().ok_or("guest message encryptor is not initialized")?;
@@ -70,10 +97,29 @@ fn send_guest_message_request<
     let request_address = PhysAddr::new(VirtAddr::from_ptr(request_message.as_ref()).as_u64());
     let response_address = PhysAddr::new(VirtAddr::from_ptr(response_message.as_ref()).as_u64());
 
-    GHCB_WRAPPER
-        .get()
-        .ok_or("GHCB not initialized")?
-        .do_guest_message_request(request_address, response_address)?;
+    let mut ghcb = GHCB_WRAPPER.get().ok_or("GHCB not initialized")?;
+    let extended_request_status = certificates.map(|certificates| {
+        let certificates_address = PhysAddr::new(VirtAddr::from_ptr(certificates).as_u64());
+        ghcb.do_extended_guest_message_request(
+            request_address,
+            response_address,
+            certificates_address,
+            CERTIFICATES_PAGES,
+        )
+    });
+    match extended_request_status {
+        Some(Ok(ExtendedGuestRequestStatus::Success)) => {}
+        Some(Ok(ExtendedGuestRequestStatus::BufferTooSmall(pages))) => {
+            log::warn!("host certificates need {} pages, ignoring them", pages);
+            ghcb.do_guest_message_request(request_address, response_address)?;
+        }
+        Some(Err(err)) => {
+            log::warn!("extended guest request failed: {}, retrying without certificates", err);
+            ghcb.do_guest_message_request(request_address, response_address)?;
+        }
+        None => ghcb.do_guest_message_request(request_address, response_address)?,
+    }
+    drop(ghcb);
     response_message.validate()?;
     encryptor.decrypt_message::<Response>(response_message.as_ref())
 }
@@ -91,11 +137,22 @@ fn get_attestation(report_data: [u8; REPORT_DATA_SIZE]) -> Result<AttestationRep
     if super::sev_status().contains(SevStatus::SNP_ACTIVE) {
         let mut report_request = AttestationRequest::new();
         report_request.report_data = report_data;
-        let attestation_response: AttestationResponse = send_guest_message_request(report_request)?;
+        let mut certificates = Shared::<CertificatesBuffer, _, super::Sev>::new_zeroed_in(
+            &oak_stage0::SHORT_TERM_ALLOC,
+        );
+        let attestation_response: AttestationResponse =
+            send_guest_message_request(report_request, Some(certificates.as_mut()))?;
         attestation_response.validate()?;
         if attestation_response.get_status() != Some(ReportStatus::Success) {
             return Err("report request failed due to invalid parameters");
         }
+        // An all-zero buffer is an empty certificate table, so we don't need to
+        // distinguish between hosts that did and did not populate it.
+        match find_certificate(certificates.as_ref(), &VCEK_GUID) {
+            Ok(Some(vcek)) => *TEE_CERTIFICATE.lock() = Some(vcek.to_vec()),
+            Ok(None) => log::warn!("host did not provide a VCEK certificate"),
+            Err(err) => log::warn!("invalid certificate table: {}", err),
+        }
         Ok(attestation_response.report)
     } else {
         oak_stage0_dice::mock_attestation_report(report_data)
@@ -107,9 +164,13 @@ pub fn get_derived_key() -> Result<DerivedKey, &'static str> {
         let mut key_request = KeyRequest::new();
         let selected_fields = GuestFieldFlags::MEASUREMENT | GuestFieldFlags::GUEST_POLICY;
         key_request.guest_field_select = selected_fields.bits();
-        let key_response: KeyResponse = send_guest_message_request(key_request)?;
+        let key_response: KeyResponse = send_guest_message_request(key_request, None)?;
         Ok(key_response.derived_key)
     } else {
         oak_stage0::hal::Base::get_derived_key()
     }
 }
+
+pub fn get_tee_certificate() -> Option<Vec<u8>> {
+    TEE_CERTIFICATE.lock().clone()
+}
@@ -18,13 +18,14 @@ mod accept_memory;
 mod dice_attestation;
 mod mmio;
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use core::{arch::x86_64::CpuidResult, mem::MaybeUninit};
 
 use oak_attestation::dice::DiceAttester;
 use oak_core::sync::OnceCell;
 use oak_dice::evidence::TeePlatform;
 use oak_linux_boot_params::BootE820Entry;
+use oak_proto_rust::oak::attestation::v1::Stage0Measurements;
 use oak_sev_guest::{
     ap_jump_table::ApJumpTable, cpuid::CpuidInput, ghcb::GhcbProtocol, msr::SevStatus,
 };
@@ -294,6 +295,12 @@ impl Platform for Sev {
         }
     }
 
+    fn record_measurements(_measurements: &Stage0Measurements) -> Result<(), &'static str> {
+        // SEV-SNP has no run-time measurement registers. The measurements are
+        // covered by the Stage 0 event in the DICE evidence instead.
+        Ok(())
+    }
+
     fn get_attester() -> Result<Self::Attester, &'static str> {
         dice_attestation::get_attester()
     }
@@ -302,6 +309,10 @@ impl Platform for Sev {
         dice_attestation::get_derived_key()
     }
 
+    fn get_tee_certificate() -> Option<Vec<u8>> {
+        dice_attestation::get_tee_certificate()
+    }
+
     fn change_page_state(
         page: x86_64::structures::paging::Page<x86_64::structures::paging::Size4KiB>,
         state: PageAssignment,